@@ -0,0 +1,687 @@
+//! [`AccountClient`]: a [`Client`] bound to one account.
+//!
+//! Almost every endpoint is account-scoped. Instead of passing the same
+//! [`AccountId`] to each call, bind it once with [`Client::for_account`] and
+//! use the per-domain sub-APIs:
+//!
+//! ```no_run
+//! # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+//! use oanda_rs::models::{CandlestickGranularity, MarketOrderRequest, PricingComponent};
+//!
+//! let account = client
+//!     .for_account("101-004-1234567-001")
+//!     .price_component(PricingComponent::BID.with_ask())
+//!     .tag_prefix("mean-revert/");
+//!
+//! let candles = account
+//!     .candles()
+//!     .get("EUR_USD")
+//!     .granularity(CandlestickGranularity::H1)
+//!     .send()
+//!     .await?;
+//! account.orders().create(MarketOrderRequest::new("EUR_USD", 100)).await?;
+//! let open = account.trades().open().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The handle is cheap to clone and shares the client's connection pool
+//! and rate limiter. Its sub-APIs return the same builders and responses as
+//! the corresponding [`Client`] methods.
+//!
+//! To trade accounts that belong to several OANDA users, register them on
+//! a [`MultiAccountClient`], which routes each account to its own token and
+//! environment while sharing one pool and one set of rate limiters.
+
+use std::collections::{BTreeMap, HashMap};
+
+use futures_util::future::try_join_all;
+use rust_decimal::Decimal;
+
+use crate::client::{Client, Environment};
+use crate::endpoints::accounts::{
+    AccountChangesRequest, AccountInstrumentsRequest, AccountResponse, AccountSummaryResponse,
+    ConfigureAccountRequest,
+};
+use crate::endpoints::instruments::{CandlesRequest, LatestCandlesRequest};
+use crate::endpoints::orders::{
+    CancelOrderResponse, CreateOrderIdempotentRequest, CreateOrderResponse, ListOrdersRequest,
+    ListOrdersResponse, OrderResponse, ReplaceOrderResponse, SetOrderClientExtensionsRequest,
+};
+use crate::endpoints::positions::{ClosePositionRequest, ListPositionsResponse, PositionResponse};
+use crate::endpoints::pricing::{PricesRequest, PricingStreamRequest};
+use crate::endpoints::trades::{
+    CloseTradeRequest, ListTradesRequest, ListTradesResponse, SetTradeClientExtensionsResponse,
+    SetTradeDependentOrdersRequest, TradeResponse,
+};
+use crate::endpoints::transactions::{
+    ListTransactionsRequest, TransactionResponse, TransactionStreamRequest,
+    TransactionsIdRangeRequest, TransactionsResponse,
+};
+use crate::error::Error;
+use crate::models::{
+    AccountId, AccountUnits, CandleSpecification, ClientExtensions, Currency, InstrumentName,
+    OrderRequest, OrderSpecifier, PricingComponent, Trade, TradeSpecifier, TransactionId,
+};
+
+impl Client {
+    /// Binds this client to an account; see [`AccountClient`].
+    ///
+    /// (`Client::account` is the account details endpoint.)
+    pub fn for_account(&self, account_id: impl Into<AccountId>) -> AccountClient {
+        AccountClient {
+            client: self.clone(),
+            account_id: account_id.into(),
+            price_component: None,
+            tag_prefix: None,
+        }
+    }
+}
+
+/// A [`Client`] bound to one account, with optional per-account defaults.
+///
+/// Created by [`Client::for_account`]; see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct AccountClient {
+    client: Client,
+    account_id: AccountId,
+    price_component: Option<PricingComponent>,
+    tag_prefix: Option<String>,
+}
+
+impl AccountClient {
+    /// The price component requested by [`candles()`](Self::candles) when
+    /// a request does not choose one (OANDA's own default is mid).
+    pub fn price_component(mut self, price: PricingComponent) -> Self {
+        self.price_component = Some(price);
+        self
+    }
+
+    /// A prefix prepended to the client-extension tags this handle writes,
+    /// so that a strategy's orders and trades can be told apart (e.g. with
+    /// [`Paginated::tag`](crate::endpoints::paging::Paginated::tag)).
+    ///
+    /// It applies to orders created or replaced through
+    /// [`orders()`](Self::orders) (both the order's and the trade's client
+    /// extensions) and to the client extensions set on orders and trades.
+    /// Tags that already start with the prefix are left unchanged; the
+    /// client extension ID and comment are never touched.
+    pub fn tag_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.tag_prefix = Some(prefix.into());
+        self
+    }
+
+    /// The account this handle is bound to.
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    /// The underlying client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// See [`Client::account`].
+    pub async fn details(&self) -> Result<AccountResponse, Error> {
+        self.client.account(self.account_id.clone()).await
+    }
+
+    /// See [`Client::account_summary`].
+    pub async fn summary(&self) -> Result<AccountSummaryResponse, Error> {
+        self.client.account_summary(self.account_id.clone()).await
+    }
+
+    /// See [`Client::account_instruments`].
+    pub fn instruments(&self) -> AccountInstrumentsRequest {
+        self.client.account_instruments(self.account_id.clone())
+    }
+
+    /// See [`Client::configure_account`].
+    pub fn configure(&self) -> ConfigureAccountRequest {
+        self.client.configure_account(self.account_id.clone())
+    }
+
+    /// See [`Client::account_changes`].
+    pub fn changes(&self) -> AccountChangesRequest {
+        self.client.account_changes(self.account_id.clone())
+    }
+
+    /// Order endpoints of the account.
+    pub fn orders(&self) -> AccountOrders<'_> {
+        AccountOrders { account: self }
+    }
+
+    /// Trade endpoints of the account.
+    pub fn trades(&self) -> AccountTrades<'_> {
+        AccountTrades { account: self }
+    }
+
+    /// Position endpoints of the account.
+    pub fn positions(&self) -> AccountPositions<'_> {
+        AccountPositions { account: self }
+    }
+
+    /// Pricing endpoints of the account.
+    pub fn pricing(&self) -> AccountPricing<'_> {
+        AccountPricing { account: self }
+    }
+
+    /// Transaction endpoints of the account.
+    pub fn transactions(&self) -> AccountTransactions<'_> {
+        AccountTransactions { account: self }
+    }
+
+    /// Account-scoped candle endpoints.
+    pub fn candles(&self) -> AccountCandles<'_> {
+        AccountCandles { account: self }
+    }
+
+    fn id(&self) -> AccountId {
+        self.account_id.clone()
+    }
+
+    fn prefix_order(&self, order: impl Into<OrderRequest>) -> OrderRequest {
+        let mut order = order.into();
+        if let Some(prefix) = &self.tag_prefix {
+            order.prefix_tags(prefix);
+        }
+        order
+    }
+}
+
+/// Order endpoints of an [`AccountClient`].
+#[derive(Debug, Clone, Copy)]
+pub struct AccountOrders<'a> {
+    account: &'a AccountClient,
+}
+
+impl AccountOrders<'_> {
+    /// See [`Client::create_order`].
+    pub async fn create(
+        &self,
+        order: impl Into<OrderRequest>,
+    ) -> Result<CreateOrderResponse, Error> {
+        let order = self.account.prefix_order(order);
+        self.account
+            .client
+            .create_order(self.account.id(), order)
+            .await
+    }
+
+    /// See [`Client::create_order_idempotent`].
+    pub fn create_idempotent(
+        &self,
+        order: impl Into<OrderRequest>,
+        client_id: impl Into<String>,
+    ) -> CreateOrderIdempotentRequest {
+        let order = self.account.prefix_order(order);
+        self.account
+            .client
+            .create_order_idempotent(self.account.id(), order, client_id)
+    }
+
+    /// See [`Client::list_orders`].
+    pub fn list(&self) -> ListOrdersRequest {
+        self.account.client.list_orders(self.account.id())
+    }
+
+    /// See [`Client::list_pending_orders`].
+    pub async fn pending(&self) -> Result<ListOrdersResponse, Error> {
+        self.account
+            .client
+            .list_pending_orders(self.account.id())
+            .await
+    }
+
+    /// See [`Client::order`].
+    pub async fn get(&self, order: impl Into<OrderSpecifier>) -> Result<OrderResponse, Error> {
+        self.account.client.order(self.account.id(), order).await
+    }
+
+    /// See [`Client::replace_order`].
+    pub async fn replace(
+        &self,
+        order: impl Into<OrderSpecifier>,
+        replacement: impl Into<OrderRequest>,
+    ) -> Result<ReplaceOrderResponse, Error> {
+        let replacement = self.account.prefix_order(replacement);
+        self.account
+            .client
+            .replace_order(self.account.id(), order, replacement)
+            .await
+    }
+
+    /// See [`Client::cancel_order`].
+    pub async fn cancel(
+        &self,
+        order: impl Into<OrderSpecifier>,
+    ) -> Result<CancelOrderResponse, Error> {
+        self.account
+            .client
+            .cancel_order(self.account.id(), order)
+            .await
+    }
+
+    /// See [`Client::set_order_client_extensions`].
+    pub fn set_client_extensions(
+        &self,
+        order: impl Into<OrderSpecifier>,
+    ) -> SetOrderClientExtensionsRequest {
+        self.account
+            .client
+            .set_order_client_extensions(self.account.id(), order)
+            .tag_prefix(self.account.tag_prefix.clone())
+    }
+}
+
+/// Trade endpoints of an [`AccountClient`].
+#[derive(Debug, Clone, Copy)]
+pub struct AccountTrades<'a> {
+    account: &'a AccountClient,
+}
+
+impl AccountTrades<'_> {
+    /// See [`Client::list_trades`].
+    pub fn list(&self) -> ListTradesRequest {
+        self.account.client.list_trades(self.account.id())
+    }
+
+    /// See [`Client::list_open_trades`].
+    pub async fn open(&self) -> Result<ListTradesResponse, Error> {
+        self.account
+            .client
+            .list_open_trades(self.account.id())
+            .await
+    }
+
+    /// See [`Client::trade`].
+    pub async fn get(&self, trade: impl Into<TradeSpecifier>) -> Result<TradeResponse, Error> {
+        self.account.client.trade(self.account.id(), trade).await
+    }
+
+    /// See [`Client::close_trade`].
+    pub fn close(&self, trade: impl Into<TradeSpecifier>) -> CloseTradeRequest {
+        self.account.client.close_trade(self.account.id(), trade)
+    }
+
+    /// See [`Client::set_trade_client_extensions`].
+    pub async fn set_client_extensions(
+        &self,
+        trade: impl Into<TradeSpecifier>,
+        mut extensions: ClientExtensions,
+    ) -> Result<SetTradeClientExtensionsResponse, Error> {
+        if let Some(prefix) = &self.account.tag_prefix {
+            extensions.prefix_tag(prefix);
+        }
+        self.account
+            .client
+            .set_trade_client_extensions(self.account.id(), trade, extensions)
+            .await
+    }
+
+    /// See [`Client::set_trade_dependent_orders`].
+    pub fn set_dependent_orders(
+        &self,
+        trade: impl Into<TradeSpecifier>,
+    ) -> SetTradeDependentOrdersRequest {
+        self.account
+            .client
+            .set_trade_dependent_orders(self.account.id(), trade)
+    }
+}
+
+/// Position endpoints of an [`AccountClient`].
+#[derive(Debug, Clone, Copy)]
+pub struct AccountPositions<'a> {
+    account: &'a AccountClient,
+}
+
+impl AccountPositions<'_> {
+    /// See [`Client::list_positions`].
+    pub async fn list(&self) -> Result<ListPositionsResponse, Error> {
+        self.account.client.list_positions(self.account.id()).await
+    }
+
+    /// See [`Client::list_open_positions`].
+    pub async fn open(&self) -> Result<ListPositionsResponse, Error> {
+        self.account
+            .client
+            .list_open_positions(self.account.id())
+            .await
+    }
+
+    /// See [`Client::position`].
+    pub async fn get(
+        &self,
+        instrument: impl Into<InstrumentName>,
+    ) -> Result<PositionResponse, Error> {
+        self.account
+            .client
+            .position(self.account.id(), instrument)
+            .await
+    }
+
+    /// See [`Client::close_position`].
+    pub fn close(&self, instrument: impl Into<InstrumentName>) -> ClosePositionRequest {
+        self.account
+            .client
+            .close_position(self.account.id(), instrument)
+    }
+}
+
+/// Pricing endpoints of an [`AccountClient`].
+#[derive(Debug, Clone, Copy)]
+pub struct AccountPricing<'a> {
+    account: &'a AccountClient,
+}
+
+impl AccountPricing<'_> {
+    /// See [`Client::prices`].
+    pub fn prices<I>(&self, instruments: I) -> PricesRequest
+    where
+        I: IntoIterator,
+        I::Item: Into<InstrumentName>,
+    {
+        self.account.client.prices(self.account.id(), instruments)
+    }
+
+    /// See [`Client::pricing_stream`].
+    pub fn stream<I>(&self, instruments: I) -> PricingStreamRequest
+    where
+        I: IntoIterator,
+        I::Item: Into<InstrumentName>,
+    {
+        self.account
+            .client
+            .pricing_stream(self.account.id(), instruments)
+    }
+}
+
+/// Transaction endpoints of an [`AccountClient`].
+#[derive(Debug, Clone, Copy)]
+pub struct AccountTransactions<'a> {
+    account: &'a AccountClient,
+}
+
+impl AccountTransactions<'_> {
+    /// See [`Client::list_transactions`].
+    pub fn list(&self) -> ListTransactionsRequest {
+        self.account.client.list_transactions(self.account.id())
+    }
+
+    /// See [`Client::transaction`].
+    pub async fn get(
+        &self,
+        transaction_id: impl Into<TransactionId>,
+    ) -> Result<TransactionResponse, Error> {
+        self.account
+            .client
+            .transaction(self.account.id(), transaction_id)
+            .await
+    }
+
+    /// See [`Client::transactions_id_range`].
+    pub fn id_range(
+        &self,
+        from: impl Into<TransactionId>,
+        to: impl Into<TransactionId>,
+    ) -> TransactionsIdRangeRequest {
+        self.account
+            .client
+            .transactions_id_range(self.account.id(), from, to)
+    }
+
+    /// See [`Client::transactions_since_id`].
+    pub async fn since_id(
+        &self,
+        id: impl Into<TransactionId>,
+    ) -> Result<TransactionsResponse, Error> {
+        self.account
+            .client
+            .transactions_since_id(self.account.id(), id)
+            .await
+    }
+
+    /// See [`Client::transaction_stream`].
+    pub fn stream(&self) -> TransactionStreamRequest {
+        self.account.client.transaction_stream(self.account.id())
+    }
+}
+
+/// Account-scoped candle endpoints of an [`AccountClient`], requesting its
+/// default [price component](AccountClient::price_component).
+#[derive(Debug, Clone, Copy)]
+pub struct AccountCandles<'a> {
+    account: &'a AccountClient,
+}
+
+impl AccountCandles<'_> {
+    /// See [`Client::account_candles`]; the request's
+    /// [`price`](CandlesRequest::price) overrides the default.
+    pub fn get(&self, instrument: impl Into<InstrumentName>) -> CandlesRequest {
+        let request = self
+            .account
+            .client
+            .account_candles(self.account.id(), instrument);
+        match self.account.price_component {
+            Some(price) => request.price(price),
+            None => request,
+        }
+    }
+
+    /// See [`Client::latest_candles`]; specifications without a price
+    /// component get the default.
+    pub fn latest(
+        &self,
+        specifications: impl IntoIterator<Item = CandleSpecification>,
+    ) -> LatestCandlesRequest {
+        let default = self.account.price_component;
+        let specifications = specifications.into_iter().map(|mut spec| {
+            spec.price = spec.price.or(default);
+            spec
+        });
+        self.account
+            .client
+            .latest_candles(self.account.id(), specifications)
+    }
+}
+
+/// A set of accounts, each routed to its own credentials, sharing one
+/// connection pool and one set of rate limiters.
+///
+/// Accounts are registered with the credentials of the client passed to
+/// [`new`](Self::new) ([`account`](Self::account)) or with their own
+/// token and environment ([`account_with_credentials`](Self::account_with_credentials),
+/// see [`Client::with_credentials`]).
+///
+/// ```no_run
+/// # async fn run() -> Result<(), oanda_rs::Error> {
+/// use oanda_rs::account::MultiAccountClient;
+/// use oanda_rs::{Client, Environment};
+///
+/// let client = Client::new(Environment::Live, "token-of-user-a");
+/// let accounts = MultiAccountClient::new(client)
+///     .account("001-001-1111111-001")
+///     .account("001-001-1111111-002")
+///     .account_with_credentials("001-001-2222222-001", Environment::Live, "token-of-user-b")?;
+///
+/// let open = accounts.list_all_open_trades().await?;
+/// let nav = accounts.total_nav("USD").await?;
+/// accounts.for_account("001-001-2222222-001")?.trades().open().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MultiAccountClient {
+    client: Client,
+    accounts: BTreeMap<AccountId, Client>,
+}
+
+impl MultiAccountClient {
+    /// An empty set of accounts sharing `client`'s connection pool and rate
+    /// limiters.
+    pub fn new(client: Client) -> Self {
+        MultiAccountClient {
+            client,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Registers an account accessible with the credentials of the client
+    /// passed to [`new`](Self::new).
+    pub fn account(mut self, account_id: impl Into<AccountId>) -> Self {
+        self.accounts.insert(account_id.into(), self.client.clone());
+        self
+    }
+
+    /// Registers an account accessible with its own token, in its own
+    /// environment.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] when the token is empty.
+    pub fn account_with_credentials(
+        mut self,
+        account_id: impl Into<AccountId>,
+        environment: Environment,
+        token: impl Into<String>,
+    ) -> Result<Self, Error> {
+        let client = self.client.with_credentials(environment, token)?;
+        self.accounts.insert(account_id.into(), client);
+        Ok(self)
+    }
+
+    /// The registered accounts, in ID order.
+    pub fn accounts(&self) -> impl Iterator<Item = &AccountId> {
+        self.accounts.keys()
+    }
+
+    /// The client carrying the credentials of an account, if registered.
+    pub fn client(&self, account_id: &AccountId) -> Option<&Client> {
+        self.accounts.get(account_id)
+    }
+
+    /// A handle bound to a registered account.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] when the account is not registered.
+    pub fn for_account(&self, account_id: impl Into<AccountId>) -> Result<AccountClient, Error> {
+        let account_id = account_id.into();
+        match self.accounts.get(&account_id) {
+            Some(client) => Ok(client.for_account(account_id)),
+            None => Err(Error::Config(format!(
+                "no credentials registered for account {account_id}"
+            ))),
+        }
+    }
+
+    /// The open trades of every registered account, fetched concurrently,
+    /// in account order.
+    pub async fn list_all_open_trades(&self) -> Result<Vec<(AccountId, Trade)>, Error> {
+        let responses = try_join_all(
+            self.accounts
+                .iter()
+                .map(|(id, client)| client.list_open_trades(id.clone())),
+        )
+        .await?;
+        Ok(self
+            .accounts
+            .keys()
+            .zip(responses)
+            .flat_map(|(id, response)| {
+                response
+                    .trades
+                    .into_iter()
+                    .map(move |trade| (id.clone(), trade))
+            })
+            .collect())
+    }
+
+    /// The combined NAV of every registered account, in `currency`.
+    ///
+    /// NAVs in another home currency are converted with the home conversion
+    /// factors OANDA quotes for an instrument of that account involving
+    /// `currency` (one extra instruments and pricing request per home
+    /// currency).
+    ///
+    /// # Errors
+    ///
+    /// Besides request failures, returns [`Error::Config`] when an account
+    /// summary lacks its NAV or currency, or when no instrument of the
+    /// account converts between the two currencies.
+    pub async fn total_nav(&self, currency: impl Into<Currency>) -> Result<AccountUnits, Error> {
+        let currency = currency.into();
+        let summaries = try_join_all(
+            self.accounts
+                .iter()
+                .map(|(id, client)| client.account_summary(id.clone())),
+        )
+        .await?;
+        // Factor converting `currency` into each home currency.
+        let mut factors: HashMap<Currency, Decimal> = HashMap::new();
+        let mut total = Decimal::ZERO;
+        for ((id, client), summary) in self.accounts.iter().zip(summaries) {
+            let (Some(nav), Some(home)) = (summary.account.nav, summary.account.currency) else {
+                return Err(Error::Config(format!(
+                    "the summary of account {id} has no NAV or currency"
+                )));
+            };
+            if home == currency {
+                total += nav.value();
+                continue;
+            }
+            let factor = match factors.get(&home) {
+                Some(factor) => *factor,
+                None => {
+                    let factor = conversion_factor(client, id, &currency).await?;
+                    factors.insert(home, factor);
+                    factor
+                }
+            };
+            total += nav.value() / factor;
+        }
+        Ok(AccountUnits(total))
+    }
+}
+
+/// The factor converting amounts in `currency` into the home currency of an
+/// account, read from the home conversions of an instrument involving
+/// `currency`.
+async fn conversion_factor(
+    client: &Client,
+    account_id: &AccountId,
+    currency: &Currency,
+) -> Result<Decimal, Error> {
+    let instruments = client
+        .account_instruments(account_id.clone())
+        .send()
+        .await?;
+    let instrument = instruments.instruments.into_iter().find_map(|instrument| {
+        let name = instrument.name?;
+        name.as_str()
+            .split('_')
+            .any(|part| part == currency.as_str())
+            .then_some(name)
+    });
+    if let Some(instrument) = instrument {
+        let prices = client
+            .prices(account_id.clone(), [instrument])
+            .include_home_conversions(true)
+            .send()
+            .await?;
+        let factor = prices
+            .home_conversions
+            .iter()
+            .find(|conversion| conversion.currency.as_ref() == Some(currency))
+            .and_then(|conversion| conversion.position_value)
+            .map(|factor| factor.value())
+            .filter(|factor| !factor.is_zero());
+        if let Some(factor) = factor {
+            return Ok(factor);
+        }
+    }
+    Err(Error::Config(format!(
+        "no instrument of account {account_id} converts {currency} into its home currency"
+    )))
+}
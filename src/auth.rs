@@ -0,0 +1,346 @@
+//! Where the client's bearer token comes from.
+//!
+//! A [`Client`](crate::Client) asks its [`TokenProvider`] for the token on
+//! every REST request and every stream (re)connect. When OANDA answers HTTP
+//! 401, the client asks the provider to [refresh](TokenProvider::refresh)
+//! the token and retries once, so a rotated token takes effect without
+//! rebuilding the client; managed streams pick it up on their next
+//! reconnect.
+//!
+//! ```no_run
+//! use oanda_rs::auth::FileToken;
+//! use oanda_rs::{Client, Environment};
+//!
+//! let client = Client::builder()
+//!     .environment(Environment::Practice)
+//!     .token_provider(FileToken::new("/run/secrets/oanda-token"))
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! Tokens are held in [`Token`]s, whose memory is zeroized on drop.
+
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use futures_core::future::BoxFuture;
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+
+/// A bearer token. Its `Debug` output is redacted and its memory is
+/// zeroized when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Token(Zeroizing<String>);
+
+impl Token {
+    /// Wraps a token, trimming surrounding whitespace (such as the
+    /// trailing newline of a token file).
+    pub fn new(token: impl Into<String>) -> Self {
+        let token = Zeroizing::new(token.into());
+        let trimmed = token.trim();
+        if trimmed.len() == token.len() {
+            Token(token)
+        } else {
+            Token(Zeroizing::new(trimmed.to_owned()))
+        }
+    }
+
+    /// The token itself.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the token is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Token(<redacted>)")
+    }
+}
+
+impl From<String> for Token {
+    fn from(token: String) -> Self {
+        Token::new(token)
+    }
+}
+
+impl From<&str> for Token {
+    fn from(token: &str) -> Self {
+        Token::new(token)
+    }
+}
+
+/// A source of the bearer token, read for every request.
+///
+/// [`token`](Self::token) is called for every REST request and stream
+/// connect, so implementations should answer from a cache.
+/// [`refresh`](Self::refresh) is called once after an HTTP 401, before the
+/// request is retried. Both run on the async executor, so implementations
+/// must not block.
+pub trait TokenProvider: fmt::Debug + Send + Sync {
+    /// The current token.
+    fn token(&self) -> BoxFuture<'_, Result<Token, Error>>;
+
+    /// A fresh token, after `rejected` was answered with HTTP 401. Defaults
+    /// to [`token`](Self::token).
+    ///
+    /// Concurrent requests rejected with the same token each call this, so
+    /// a provider should only fetch a new token while its current one is
+    /// still `rejected`.
+    fn refresh(&self, rejected: Token) -> BoxFuture<'_, Result<Token, Error>> {
+        let _ = rejected;
+        self.token()
+    }
+}
+
+/// A token that never changes; what
+/// [`ClientBuilder::token`](crate::ClientBuilder::token) uses.
+#[derive(Debug, Clone)]
+pub struct StaticToken(Token);
+
+impl StaticToken {
+    /// A provider always answering `token`.
+    pub fn new(token: impl Into<Token>) -> Self {
+        StaticToken(token.into())
+    }
+}
+
+impl TokenProvider for StaticToken {
+    fn token(&self) -> BoxFuture<'_, Result<Token, Error>> {
+        Box::pin(std::future::ready(Ok(self.0.clone())))
+    }
+}
+
+/// A token read from an environment variable on every request.
+#[derive(Debug, Clone)]
+pub struct EnvToken {
+    var: String,
+}
+
+impl EnvToken {
+    /// A provider reading the variable `var`.
+    pub fn new(var: impl Into<String>) -> Self {
+        EnvToken { var: var.into() }
+    }
+
+    fn read(&self) -> Result<Token, Error> {
+        std::env::var(&self.var)
+            .ok()
+            .map(Token::new)
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| Error::Config(format!("environment variable {} is not set", self.var)))
+    }
+}
+
+impl TokenProvider for EnvToken {
+    fn token(&self) -> BoxFuture<'_, Result<Token, Error>> {
+        Box::pin(std::future::ready(self.read()))
+    }
+}
+
+/// A token read from a file, re-read whenever its modification time
+/// changes (and on [`refresh`](TokenProvider::refresh) of the token it
+/// holds).
+///
+/// Surrounding whitespace is trimmed. The file is checked with one
+/// `stat` call per request, made on Tokio's blocking thread pool.
+#[derive(Debug)]
+pub struct FileToken {
+    path: PathBuf,
+    cached: Arc<Mutex<Option<FileCache>>>,
+}
+
+/// The token last read from a [`FileToken`]'s file, with the file's
+/// modification time when it was read.
+type FileCache = (Option<SystemTime>, Token);
+
+impl FileToken {
+    /// A provider reading the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileToken {
+            path: path.into(),
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn read(&self, rejected: Option<Token>) -> BoxFuture<'static, Result<Token, Error>> {
+        let path = self.path.clone();
+        let cached = Arc::clone(&self.cached);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || read_token_file(&path, &cached, rejected))
+                .await
+                .map_err(|e| Error::Config(format!("failed to read token file: {e}")))?
+        })
+    }
+}
+
+/// Reads the token file unless it is unchanged since it was cached and the
+/// cached token is not the `rejected` one.
+fn read_token_file(
+    path: &Path,
+    cached: &Mutex<Option<FileCache>>,
+    rejected: Option<Token>,
+) -> Result<Token, Error> {
+    let error = |e: std::io::Error| {
+        Error::Config(format!("failed to read token file {}: {e}", path.display()))
+    };
+    let modified = std::fs::metadata(path).map_err(error)?.modified().ok();
+    let mut cached = cached.lock().expect("token cache poisoned");
+    if let Some((seen, token)) = cached.as_ref() {
+        if modified.is_some() && *seen == modified && rejected.as_ref() != Some(token) {
+            return Ok(token.clone());
+        }
+    }
+    let token = Token::new(Zeroizing::new(std::fs::read_to_string(path).map_err(error)?).as_str());
+    if token.is_empty() {
+        return Err(Error::Config(format!(
+            "token file {} is empty",
+            path.display()
+        )));
+    }
+    *cached = Some((modified, token.clone()));
+    Ok(token)
+}
+
+impl TokenProvider for FileToken {
+    fn token(&self) -> BoxFuture<'_, Result<Token, Error>> {
+        self.read(None)
+    }
+
+    fn refresh(&self, rejected: Token) -> BoxFuture<'_, Result<Token, Error>> {
+        self.read(Some(rejected))
+    }
+}
+
+type Fetch = dyn Fn() -> BoxFuture<'static, Result<Token, Error>> + Send + Sync;
+
+/// A token fetched by an async callback (e.g. from a secrets manager),
+/// cached until OANDA rejects it.
+///
+/// ```no_run
+/// use oanda_rs::auth::{CallbackToken, Token};
+///
+/// let provider = CallbackToken::new(|| async {
+///     // e.g. ask a vault for the current token
+///     Ok(Token::new("my-token"))
+/// });
+/// ```
+pub struct CallbackToken {
+    fetch: Arc<Fetch>,
+    cached: tokio::sync::Mutex<Option<Token>>,
+}
+
+impl CallbackToken {
+    /// A provider calling `fetch` for the first request and after an
+    /// HTTP 401 rejects the token it last returned.
+    pub fn new<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Token, Error>> + Send + 'static,
+    {
+        CallbackToken {
+            fetch: Arc::new(move || Box::pin(fetch())),
+            cached: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    async fn get(&self, rejected: Option<Token>) -> Result<Token, Error> {
+        let mut cached = self.cached.lock().await;
+        match cached.as_ref() {
+            // Another request may already have replaced the rejected token.
+            Some(token) if rejected.as_ref() != Some(token) => Ok(token.clone()),
+            _ => {
+                let token = (self.fetch)().await?;
+                *cached = Some(token.clone());
+                Ok(token)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CallbackToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackToken").finish_non_exhaustive()
+    }
+}
+
+impl TokenProvider for CallbackToken {
+    fn token(&self) -> BoxFuture<'_, Result<Token, Error>> {
+        Box::pin(self.get(None))
+    }
+
+    fn refresh(&self, rejected: Token) -> BoxFuture<'_, Result<Token, Error>> {
+        Box::pin(self.get(Some(rejected)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_trimmed_and_redacted() {
+        let token = Token::new(" secret\n");
+        assert_eq!(token.expose(), "secret");
+        assert!(!format!("{token:?}").contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn file_token_rereads_on_change_and_refresh() {
+        let path = std::env::temp_dir().join(format!("oanda-token-{}", std::process::id()));
+        std::fs::write(&path, "first\n").unwrap();
+        let provider = FileToken::new(&path);
+        assert_eq!(provider.token().await.unwrap().expose(), "first");
+        std::fs::write(&path, "second").unwrap();
+        assert_eq!(
+            provider
+                .refresh(Token::new("first"))
+                .await
+                .unwrap()
+                .expose(),
+            "second"
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(provider.token().await, Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn callback_token_is_cached_until_refreshed() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let provider = CallbackToken::new(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(Token::new(format!("token-{n}"))) }
+        });
+        assert_eq!(provider.token().await.unwrap().expose(), "token-0");
+        assert_eq!(provider.token().await.unwrap().expose(), "token-0");
+        assert_eq!(
+            provider
+                .refresh(Token::new("token-0"))
+                .await
+                .unwrap()
+                .expose(),
+            "token-1"
+        );
+        // A request rejected with the old token gets the new one, unfetched.
+        assert_eq!(
+            provider
+                .refresh(Token::new("token-0"))
+                .await
+                .unwrap()
+                .expose(),
+            "token-1"
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
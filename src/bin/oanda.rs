@@ -0,0 +1,531 @@
+//! `oanda`: a command-line client for quick account inspection and
+//! emergency flattening. Requires the `cli` feature:
+//!
+//! ```sh
+//! cargo install oanda-rs --features cli
+//! OANDA_TOKEN=... oanda summary
+//! oanda candles EUR_USD --granularity H1 --count 24 --format csv
+//! oanda close-position --all
+//! ```
+//!
+//! The client is configured like
+//! [`ClientBuilder::from_env`](oanda_rs::ClientBuilder::from_env), from
+//! `OANDA_*` environment variables, or like
+//! [`ClientBuilder::from_config`](oanda_rs::ClientBuilder::from_config)
+//! from the file named by `--config` (or `OANDA_CONFIG`):
+//!
+//! ```json
+//! {"token_file": "/run/secrets/oanda-token", "environment": "practice", "account_id": "101-004-1234567-001"}
+//! ```
+//!
+//! `--environment`, `--rest-url`/`--stream-url` and `--account` override
+//! the configured values.
+//!
+//! REST responses are printed as pretty JSON; streams and transaction
+//! listings as NDJSON, one object per line.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures_util::StreamExt;
+use oanda_rs::export::csv::write_candles;
+use oanda_rs::models::transaction::TransactionFilter;
+use oanda_rs::models::{
+    AccountId, CandlestickGranularity, DecimalNumber, InstrumentCandles, InstrumentName,
+    LimitOrderRequest, MarketOrderRequest, OrderRequest, PriceValue, PricingComponent,
+    StopLossDetails, TakeProfitDetails, TradeStateFilter,
+};
+use oanda_rs::{Client, ClientBuilder, Environment, Error};
+use serde::Serialize;
+
+// `Error` is large, and these functions only ever pass it up to `main`.
+type Result<T, E = Box<Error>> = std::result::Result<T, E>;
+
+#[derive(Parser)]
+#[command(
+    name = "oanda",
+    version,
+    about = "Command-line client for the OANDA v20 API"
+)]
+struct Cli {
+    #[command(flatten)]
+    connection: Connection,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct Connection {
+    /// A config file read instead of the `OANDA_*` environment variables.
+    #[arg(long, env = "OANDA_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    /// Overrides the configured environment.
+    #[arg(long, global = true, conflicts_with = "rest_url")]
+    environment: Option<EnvironmentArg>,
+    /// Overrides the REST base URL (with `--stream-url`), e.g. for a proxy.
+    #[arg(long, global = true, requires = "stream_url")]
+    rest_url: Option<reqwest::Url>,
+    /// Overrides the streaming base URL (with `--rest-url`).
+    #[arg(long, global = true, requires = "rest_url")]
+    stream_url: Option<reqwest::Url>,
+    /// Overrides the configured account (defaults to the token's only
+    /// account).
+    #[arg(long, global = true)]
+    account: Option<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EnvironmentArg {
+    Practice,
+    Live,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists the accounts the token can access.
+    Accounts,
+    /// Shows the account summary.
+    Summary,
+    /// Lists the instruments the account can trade.
+    Instruments {
+        /// Only these instruments.
+        instruments: Vec<String>,
+    },
+    /// Fetches candles for an instrument.
+    Candles {
+        instrument: String,
+        #[arg(long, default_value = "H1")]
+        granularity: String,
+        /// Price components, e.g. `M`, `BA` or `BMA`.
+        #[arg(long, default_value = "M")]
+        price: String,
+        #[arg(long)]
+        count: Option<u32>,
+        /// RFC 3339 or UNIX time.
+        #[arg(long)]
+        from: Option<String>,
+        /// RFC 3339 or UNIX time.
+        #[arg(long)]
+        to: Option<String>,
+        #[arg(long, value_enum, default_value = "json")]
+        format: Format,
+    },
+    /// Shows current prices.
+    Prices {
+        #[arg(required = true)]
+        instruments: Vec<String>,
+    },
+    /// Streams prices or transactions as NDJSON until interrupted.
+    Stream {
+        #[command(subcommand)]
+        stream: StreamCommand,
+    },
+    /// Places an order.
+    Order {
+        #[command(subcommand)]
+        order: OrderCommand,
+    },
+    /// Lists trades (open ones by default).
+    Trades {
+        /// Include closed trades.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Closes a trade, fully or partially.
+    CloseTrade {
+        trade: String,
+        /// Units to close (default: all).
+        #[arg(long)]
+        units: Option<String>,
+    },
+    /// Closes positions: both sides of the given instruments, or of every
+    /// open position with `--all`.
+    ClosePosition {
+        #[arg(required_unless_present = "all")]
+        instruments: Vec<String>,
+        #[arg(long, conflicts_with = "instruments")]
+        all: bool,
+    },
+    /// Lists transactions in a time range as NDJSON.
+    Transactions {
+        /// RFC 3339 or UNIX time.
+        #[arg(long)]
+        from: Option<String>,
+        /// RFC 3339 or UNIX time.
+        #[arg(long)]
+        to: Option<String>,
+        /// Only these transaction types, e.g. `ORDER_FILL`.
+        #[arg(long = "type")]
+        types: Vec<String>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Subcommand)]
+enum StreamCommand {
+    /// The pricing stream.
+    Prices {
+        #[arg(required = true)]
+        instruments: Vec<String>,
+    },
+    /// The transaction stream.
+    Transactions,
+}
+
+#[derive(Subcommand)]
+enum OrderCommand {
+    /// A market order; negative units sell.
+    Market {
+        instrument: String,
+        #[arg(allow_negative_numbers = true)]
+        units: DecimalNumber,
+        #[command(flatten)]
+        exits: Exits,
+    },
+    /// A limit order; negative units sell.
+    Limit {
+        instrument: String,
+        #[arg(allow_negative_numbers = true)]
+        units: DecimalNumber,
+        price: PriceValue,
+        #[command(flatten)]
+        exits: Exits,
+    },
+}
+
+#[derive(Args)]
+struct Exits {
+    /// Stop-loss price for the resulting trade.
+    #[arg(long)]
+    stop_loss: Option<PriceValue>,
+    /// Take-profit price for the resulting trade.
+    #[arg(long)]
+    take_profit: Option<PriceValue>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(code) => code,
+        Err(e) => {
+            print_error(format_args!("{e}"), &e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints an error to stderr, with the response body of an API error.
+fn print_error(message: std::fmt::Arguments<'_>, e: &Error) {
+    eprintln!("error: {message}");
+    if let Error::Api { body, .. } = e {
+        if let Ok(body) = serde_json::to_string_pretty(body) {
+            eprintln!("{body}");
+        }
+    }
+}
+
+/// A connected client and the account to act on, resolved lazily.
+struct Session {
+    client: Client,
+    account: Option<AccountId>,
+}
+
+impl Session {
+    fn connect(connection: Connection) -> Result<Session> {
+        let mut builder = match &connection.config {
+            Some(path) => ClientBuilder::from_config(path)?,
+            None => ClientBuilder::from_env()?,
+        };
+        match (
+            connection.environment,
+            connection.rest_url,
+            connection.stream_url,
+        ) {
+            (_, Some(rest), Some(stream)) => {
+                builder = builder.environment(Environment::Custom { rest, stream });
+            }
+            (Some(EnvironmentArg::Live), ..) => builder = builder.environment(Environment::Live),
+            (Some(EnvironmentArg::Practice), ..) => {
+                builder = builder.environment(Environment::Practice);
+            }
+            _ => {}
+        }
+        if let Some(account) = connection.account {
+            builder = builder.default_account(account);
+        }
+        let client = builder.build()?;
+        Ok(Session {
+            account: client.default_account().cloned(),
+            client,
+        })
+    }
+
+    /// The configured account, or the token's only account.
+    async fn account(&self) -> Result<AccountId> {
+        if let Some(account) = &self.account {
+            return Ok(account.clone());
+        }
+        let accounts = self.client.list_accounts().await?.accounts;
+        match accounts.as_slice() {
+            [only] => only
+                .id
+                .clone()
+                .ok_or_else(|| Error::Config("the account has no ID".into()).into()),
+            _ => Err(Error::Config(format!(
+                "the token can access {} accounts; pass --account or set OANDA_ACCOUNT_ID",
+                accounts.len()
+            ))
+            .into()),
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<ExitCode> {
+    let session = Session::connect(cli.connection)?;
+    let client = &session.client;
+    match cli.command {
+        Command::Accounts => print_json(&client.list_accounts().await?),
+        Command::Summary => print_json(&client.account_summary(session.account().await?).await?),
+        Command::Instruments { instruments } => {
+            let mut request = client.account_instruments(session.account().await?);
+            if !instruments.is_empty() {
+                request = request.instruments(instruments);
+            }
+            print_json(&request.send().await?)
+        }
+        Command::Candles {
+            instrument,
+            granularity,
+            price,
+            count,
+            from,
+            to,
+            format,
+        } => {
+            let price: PricingComponent = price
+                .parse()
+                .map_err(|e| Error::Config(format!("invalid --price: {e}")))?;
+            let mut request = client
+                .candles(instrument)
+                .granularity(CandlestickGranularity::from(granularity.as_str()))
+                .price(price);
+            if let Some(count) = count {
+                request = request.count(count);
+            }
+            if let Some(from) = from {
+                request = request.from(from);
+            }
+            if let Some(to) = to {
+                request = request.to(to);
+            }
+            let candles = request.send().await?;
+            match format {
+                Format::Json => print_json(&candles),
+                Format::Csv => print_candles_csv(&candles),
+            }
+        }
+        Command::Prices { instruments } => print_json(
+            &client
+                .prices(session.account().await?, instruments)
+                .send()
+                .await?,
+        ),
+        Command::Stream { stream } => match stream {
+            StreamCommand::Prices { instruments } => {
+                let mut stream = client
+                    .pricing_stream(session.account().await?, instruments)
+                    .send()
+                    .await?;
+                while let Some(item) = stream.next().await {
+                    print_line(&item?)?;
+                }
+                Ok(())
+            }
+            StreamCommand::Transactions => {
+                let mut stream = client
+                    .transaction_stream(session.account().await?)
+                    .send()
+                    .await?;
+                while let Some(item) = stream.next().await {
+                    print_line(&item?)?;
+                }
+                Ok(())
+            }
+        },
+        Command::Order { order } => {
+            let order: OrderRequest = match order {
+                OrderCommand::Market {
+                    instrument,
+                    units,
+                    exits,
+                } => {
+                    let mut order = MarketOrderRequest::new(instrument, units);
+                    if let Some(price) = exits.stop_loss {
+                        order = order.stop_loss_on_fill(StopLossDetails::at_price(price));
+                    }
+                    if let Some(price) = exits.take_profit {
+                        order = order.take_profit_on_fill(TakeProfitDetails::at_price(price));
+                    }
+                    order.into()
+                }
+                OrderCommand::Limit {
+                    instrument,
+                    units,
+                    price,
+                    exits,
+                } => {
+                    let mut order = LimitOrderRequest::new(instrument, units, price);
+                    if let Some(price) = exits.stop_loss {
+                        order = order.stop_loss_on_fill(StopLossDetails::at_price(price));
+                    }
+                    if let Some(price) = exits.take_profit {
+                        order = order.take_profit_on_fill(TakeProfitDetails::at_price(price));
+                    }
+                    order.into()
+                }
+            };
+            print_json(&client.create_order(session.account().await?, order).await?)
+        }
+        Command::Trades { all } => {
+            let account = session.account().await?;
+            if all {
+                print_json(
+                    &client
+                        .list_trades(account)
+                        .state(TradeStateFilter::All)
+                        .send()
+                        .await?,
+                )
+            } else {
+                print_json(&client.list_open_trades(account).await?)
+            }
+        }
+        Command::CloseTrade { trade, units } => {
+            let mut request = client.close_trade(session.account().await?, trade);
+            if let Some(units) = units {
+                request = request.units(units);
+            }
+            print_json(&request.send().await?)
+        }
+        Command::ClosePosition { instruments, all } => {
+            // every position is attempted even when an earlier one fails
+            let mut failed = false;
+            let account = session.account().await?;
+            let positions = client.list_open_positions(account.clone()).await?.positions;
+            let wanted: Vec<InstrumentName> = instruments.into_iter().map(Into::into).collect();
+            for position in positions {
+                let Some(instrument) = position.instrument else {
+                    continue;
+                };
+                if !all && !wanted.contains(&instrument) {
+                    continue;
+                }
+                // closing a side without units is rejected, so only name
+                // the sides that are open
+                let open = |side: Option<oanda_rs::models::PositionSide>| {
+                    if side
+                        .and_then(|side| side.units)
+                        .is_some_and(|units| !units.value().is_zero())
+                    {
+                        "ALL"
+                    } else {
+                        "NONE"
+                    }
+                };
+                let closed = client
+                    .close_position(account.clone(), instrument.clone())
+                    .long_units(open(position.long))
+                    .short_units(open(position.short))
+                    .send()
+                    .await;
+                match closed {
+                    Ok(response) => print_json(&response)?,
+                    Err(e) => {
+                        print_error(format_args!("cannot close {instrument}: {e}"), &e);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                return Ok(ExitCode::FAILURE);
+            }
+            Ok(())
+        }
+        Command::Transactions { from, to, types } => {
+            let account = session.account().await?;
+            let mut request = client.list_transactions(account.clone());
+            if let Some(from) = from {
+                request = request.from(from);
+            }
+            if let Some(to) = to {
+                request = request.to(to);
+            }
+            if !types.is_empty() {
+                request = request.types(
+                    types
+                        .iter()
+                        .map(|t| TransactionFilter::from(t.as_str()))
+                        .collect::<Vec<_>>(),
+                );
+            }
+            let listing = request.send().await?;
+            for page in &listing.pages {
+                let Some((from, to)) = page_range(page) else {
+                    return Err(Error::Stream(format!("unexpected page URL {page}")).into());
+                };
+                let mut range = client.transactions_id_range(account.clone(), from, to);
+                if !types.is_empty() {
+                    range = range.types(types.iter().map(|t| TransactionFilter::from(t.as_str())));
+                }
+                for transaction in range.send().await?.transactions {
+                    print_line(&transaction)?;
+                }
+            }
+            Ok(())
+        }
+    }?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The `from` and `to` transaction IDs of an `idrange` page URL.
+fn page_range(page: &str) -> Option<(String, String)> {
+    let url = reqwest::Url::parse(page).ok()?;
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    Some((param("from")?, param("to")?))
+}
+
+fn print_json(value: &impl Serialize) -> Result<()> {
+    let text = serde_json::to_string_pretty(value).expect("responses serialize to JSON");
+    write_stdout(format_args!("{text}\n"))
+}
+
+fn print_line(value: &impl Serialize) -> Result<()> {
+    let text = serde_json::to_string(value).expect("responses serialize to JSON");
+    write_stdout(format_args!("{text}\n"))
+}
+
+fn print_candles_csv(candles: &InstrumentCandles) -> Result<()> {
+    write_candles(std::io::stdout().lock(), &candles.candles)
+        .map_err(|e| Error::Stream(format!("cannot write to stdout: {e}")).into())
+}
+
+/// Writes to stdout, flushing so piped NDJSON arrives line by line.
+fn write_stdout(text: std::fmt::Arguments<'_>) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_fmt(text)
+        .and_then(|()| stdout.flush())
+        .map_err(|e| Error::Stream(format!("cannot write to stdout: {e}")).into())
+}
@@ -0,0 +1,922 @@
+//! A blocking (synchronous) client, for code that does not run an async
+//! runtime. Requires the `blocking` feature.
+//!
+//! [`Client`] mirrors the async [`Client`](crate::Client) method for
+//! method: operations without optional parameters return their response
+//! directly, and operations with optional parameters return a builder
+//! with the same setters whose `send()` blocks. The two streams become
+//! iterators.
+//!
+//! Every call is driven on a current-thread tokio runtime owned by the
+//! client and shared by its clones, so the async client's rate limiter
+//! and connection pool behave exactly as they do there. The blocking
+//! client must not be used from within an async runtime: `send()` would
+//! panic.
+//!
+//! ```no_run
+//! use oanda_rs::Environment;
+//! use oanda_rs::blocking::Client;
+//! use oanda_rs::models::CandlestickGranularity;
+//!
+//! # fn run() -> Result<(), oanda_rs::Error> {
+//! let client = Client::new(Environment::Practice, std::env::var("OANDA_TOKEN").unwrap());
+//! let candles = client
+//!     .candles("EUR_USD")
+//!     .granularity(CandlestickGranularity::D)
+//!     .count(30)
+//!     .send()?;
+//! println!("{} candles", candles.candles.len());
+//!
+//! for item in client.pricing_stream("101-004-1234567-001", ["EUR_USD"]).send()? {
+//!     println!("{:?}", item?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use tokio::runtime::Runtime;
+
+use crate::client::Environment;
+use crate::endpoints::accounts::{
+    AccountChangesResponse, AccountInstrumentsResponse, AccountResponse, AccountSummaryResponse,
+    ConfigureAccountResponse, ListAccountsResponse,
+};
+use crate::endpoints::instruments::{
+    LatestCandlesResponse, OrderBookResponse, PositionBookResponse,
+};
+use crate::endpoints::orders::{
+    CancelOrderResponse, CreateOrderResponse, IdempotentOrderResponse, ListOrdersResponse,
+    OrderResponse, ReplaceOrderResponse, SetOrderClientExtensionsResponse,
+};
+use crate::endpoints::positions::{ClosePositionResponse, ListPositionsResponse, PositionResponse};
+use crate::endpoints::pricing::PricesResponse;
+use crate::endpoints::trades::{
+    CloseTradeResponse, ListTradesResponse, SetTradeClientExtensionsResponse,
+    SetTradeDependentOrdersResponse, TradeResponse,
+};
+use crate::endpoints::transactions::{
+    ListTransactionsResponse, TransactionResponse, TransactionsResponse,
+};
+use crate::error::Error;
+use crate::models::transaction::{TransactionFilter, TransactionStreamItem};
+use crate::models::{
+    AccountId, CandleSpecification, CandlestickGranularity, ClientExtensions, DateTime,
+    DecimalNumber, InstrumentCandles, InstrumentName, Order, OrderId, OrderRequest, OrderSpecifier,
+    OrderStateFilter, PriceStreamItem, PricingComponent, StopLossDetails, TakeProfitDetails, Trade,
+    TradeId, TradeSpecifier, TradeStateFilter, TrailingStopLossDetails, TransactionId,
+    WeeklyAlignment,
+};
+use crate::streaming::StreamStats;
+
+/// A blocking OANDA client; see the [module docs](self).
+///
+/// Cheap to clone: clones share the async client and the runtime.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Creates a blocking client for `environment` authenticating with the
+    /// given personal access token, using default settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the TLS backend or the runtime cannot be initialized. Use
+    /// [`Client::from_client`] to handle the latter.
+    pub fn new(environment: Environment, token: impl Into<String>) -> Client {
+        Client::from_client(crate::Client::new(environment, token))
+            .expect("the blocking runtime can be started")
+    }
+
+    /// Wraps an async client, typically one configured with
+    /// [`Client::builder`](crate::Client::builder).
+    ///
+    /// Fails with [`Error::Config`] if the runtime cannot be started.
+    pub fn from_client(client: crate::Client) -> Result<Client, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Config(format!("cannot start the blocking runtime: {e}")))?;
+        Ok(Client {
+            inner: client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The async client this client drives.
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    fn wrap<B>(&self, inner: B) -> Request<B> {
+        Request {
+            inner,
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    /// See [`crate::Client::account`].
+    pub fn account(&self, account_id: impl Into<AccountId>) -> Result<AccountResponse, Error> {
+        self.block_on(self.inner.account(account_id))
+    }
+
+    /// See [`crate::Client::account_with_meta`].
+    pub fn account_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<AccountResponse>, Error> {
+        self.block_on(self.inner.account_with_meta(account_id))
+    }
+
+    /// See [`crate::Client::account_changes`].
+    pub fn account_changes(&self, account_id: impl Into<AccountId>) -> AccountChangesRequest {
+        self.wrap(self.inner.account_changes(account_id)).into()
+    }
+
+    /// See [`crate::Client::list_accounts`].
+    pub fn list_accounts(&self) -> Result<ListAccountsResponse, Error> {
+        self.block_on(self.inner.list_accounts())
+    }
+
+    /// See [`crate::Client::list_accounts_with_meta`].
+    pub fn list_accounts_with_meta(&self) -> Result<crate::Response<ListAccountsResponse>, Error> {
+        self.block_on(self.inner.list_accounts_with_meta())
+    }
+
+    /// See [`crate::Client::account_summary`].
+    pub fn account_summary(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<AccountSummaryResponse, Error> {
+        self.block_on(self.inner.account_summary(account_id))
+    }
+
+    /// See [`crate::Client::account_summary_with_meta`].
+    pub fn account_summary_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<AccountSummaryResponse>, Error> {
+        self.block_on(self.inner.account_summary_with_meta(account_id))
+    }
+
+    /// See [`crate::Client::account_instruments`].
+    pub fn account_instruments(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> AccountInstrumentsRequest {
+        self.wrap(self.inner.account_instruments(account_id)).into()
+    }
+
+    /// See [`crate::Client::configure_account`].
+    pub fn configure_account(&self, account_id: impl Into<AccountId>) -> ConfigureAccountRequest {
+        self.wrap(self.inner.configure_account(account_id)).into()
+    }
+
+    /// See [`crate::Client::candles`].
+    pub fn candles(&self, instrument: impl Into<InstrumentName>) -> CandlesRequest {
+        self.wrap(self.inner.candles(instrument)).into()
+    }
+
+    /// See [`crate::Client::account_candles`].
+    pub fn account_candles(
+        &self,
+        account_id: impl Into<AccountId>,
+        instrument: impl Into<InstrumentName>,
+    ) -> CandlesRequest {
+        self.wrap(self.inner.account_candles(account_id, instrument))
+            .into()
+    }
+
+    /// See [`crate::Client::latest_candles`].
+    pub fn latest_candles(
+        &self,
+        account_id: impl Into<AccountId>,
+        specifications: impl IntoIterator<Item = CandleSpecification>,
+    ) -> LatestCandlesRequest {
+        self.wrap(self.inner.latest_candles(account_id, specifications))
+            .into()
+    }
+
+    /// See [`crate::Client::instrument_order_book`].
+    pub fn instrument_order_book(&self, instrument: impl Into<InstrumentName>) -> OrderBookRequest {
+        self.wrap(self.inner.instrument_order_book(instrument))
+            .into()
+    }
+
+    /// See [`crate::Client::instrument_position_book`].
+    pub fn instrument_position_book(
+        &self,
+        instrument: impl Into<InstrumentName>,
+    ) -> PositionBookRequest {
+        self.wrap(self.inner.instrument_position_book(instrument))
+            .into()
+    }
+
+    /// See [`crate::Client::create_order`].
+    pub fn create_order(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderRequest>,
+    ) -> Result<CreateOrderResponse, Error> {
+        self.block_on(self.inner.create_order(account_id, order))
+    }
+
+    /// See [`crate::Client::create_order_with_meta`].
+    pub fn create_order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderRequest>,
+    ) -> Result<crate::Response<CreateOrderResponse>, Error> {
+        self.block_on(self.inner.create_order_with_meta(account_id, order))
+    }
+
+    /// See [`crate::Client::create_order_idempotent`].
+    pub fn create_order_idempotent(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderRequest>,
+        client_id: impl Into<String>,
+    ) -> CreateOrderIdempotentRequest {
+        self.wrap(
+            self.inner
+                .create_order_idempotent(account_id, order, client_id),
+        )
+        .into()
+    }
+
+    /// See [`crate::Client::list_orders`].
+    pub fn list_orders(&self, account_id: impl Into<AccountId>) -> ListOrdersRequest {
+        self.wrap(self.inner.list_orders(account_id)).into()
+    }
+
+    /// See [`crate::Client::list_pending_orders`].
+    pub fn list_pending_orders(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<ListOrdersResponse, Error> {
+        self.block_on(self.inner.list_pending_orders(account_id))
+    }
+
+    /// See [`crate::Client::list_pending_orders_with_meta`].
+    pub fn list_pending_orders_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListOrdersResponse>, Error> {
+        self.block_on(self.inner.list_pending_orders_with_meta(account_id))
+    }
+
+    /// See [`crate::Client::order`].
+    pub fn order(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+    ) -> Result<OrderResponse, Error> {
+        self.block_on(self.inner.order(account_id, order))
+    }
+
+    /// See [`crate::Client::order_with_meta`].
+    pub fn order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+    ) -> Result<crate::Response<OrderResponse>, Error> {
+        self.block_on(self.inner.order_with_meta(account_id, order))
+    }
+
+    /// See [`crate::Client::replace_order`].
+    pub fn replace_order(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+        replacement: impl Into<OrderRequest>,
+    ) -> Result<ReplaceOrderResponse, Error> {
+        self.block_on(self.inner.replace_order(account_id, order, replacement))
+    }
+
+    /// See [`crate::Client::replace_order_with_meta`].
+    pub fn replace_order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+        replacement: impl Into<OrderRequest>,
+    ) -> Result<crate::Response<ReplaceOrderResponse>, Error> {
+        self.block_on(
+            self.inner
+                .replace_order_with_meta(account_id, order, replacement),
+        )
+    }
+
+    /// See [`crate::Client::cancel_order`].
+    pub fn cancel_order(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+    ) -> Result<CancelOrderResponse, Error> {
+        self.block_on(self.inner.cancel_order(account_id, order))
+    }
+
+    /// See [`crate::Client::cancel_order_with_meta`].
+    pub fn cancel_order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+    ) -> Result<crate::Response<CancelOrderResponse>, Error> {
+        self.block_on(self.inner.cancel_order_with_meta(account_id, order))
+    }
+
+    /// See [`crate::Client::set_order_client_extensions`].
+    pub fn set_order_client_extensions(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+    ) -> SetOrderClientExtensionsRequest {
+        self.wrap(self.inner.set_order_client_extensions(account_id, order))
+            .into()
+    }
+
+    /// See [`crate::Client::list_positions`].
+    pub fn list_positions(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<ListPositionsResponse, Error> {
+        self.block_on(self.inner.list_positions(account_id))
+    }
+
+    /// See [`crate::Client::list_positions_with_meta`].
+    pub fn list_positions_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListPositionsResponse>, Error> {
+        self.block_on(self.inner.list_positions_with_meta(account_id))
+    }
+
+    /// See [`crate::Client::list_open_positions`].
+    pub fn list_open_positions(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<ListPositionsResponse, Error> {
+        self.block_on(self.inner.list_open_positions(account_id))
+    }
+
+    /// See [`crate::Client::list_open_positions_with_meta`].
+    pub fn list_open_positions_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListPositionsResponse>, Error> {
+        self.block_on(self.inner.list_open_positions_with_meta(account_id))
+    }
+
+    /// See [`crate::Client::position`].
+    pub fn position(
+        &self,
+        account_id: impl Into<AccountId>,
+        instrument: impl Into<InstrumentName>,
+    ) -> Result<PositionResponse, Error> {
+        self.block_on(self.inner.position(account_id, instrument))
+    }
+
+    /// See [`crate::Client::position_with_meta`].
+    pub fn position_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        instrument: impl Into<InstrumentName>,
+    ) -> Result<crate::Response<PositionResponse>, Error> {
+        self.block_on(self.inner.position_with_meta(account_id, instrument))
+    }
+
+    /// See [`crate::Client::close_position`].
+    pub fn close_position(
+        &self,
+        account_id: impl Into<AccountId>,
+        instrument: impl Into<InstrumentName>,
+    ) -> ClosePositionRequest {
+        self.wrap(self.inner.close_position(account_id, instrument))
+            .into()
+    }
+
+    /// See [`crate::Client::prices`].
+    pub fn prices<I>(&self, account_id: impl Into<AccountId>, instruments: I) -> PricesRequest
+    where
+        I: IntoIterator,
+        I::Item: Into<InstrumentName>,
+    {
+        self.wrap(self.inner.prices(account_id, instruments)).into()
+    }
+
+    /// See [`crate::Client::pricing_stream`]. The stream is an iterator
+    /// here.
+    pub fn pricing_stream<I>(
+        &self,
+        account_id: impl Into<AccountId>,
+        instruments: I,
+    ) -> PricingStreamRequest
+    where
+        I: IntoIterator,
+        I::Item: Into<InstrumentName>,
+    {
+        self.wrap(self.inner.pricing_stream(account_id, instruments))
+            .into()
+    }
+
+    /// See [`crate::Client::list_trades`].
+    pub fn list_trades(&self, account_id: impl Into<AccountId>) -> ListTradesRequest {
+        self.wrap(self.inner.list_trades(account_id)).into()
+    }
+
+    /// See [`crate::Client::list_open_trades`].
+    pub fn list_open_trades(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<ListTradesResponse, Error> {
+        self.block_on(self.inner.list_open_trades(account_id))
+    }
+
+    /// See [`crate::Client::list_open_trades_with_meta`].
+    pub fn list_open_trades_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListTradesResponse>, Error> {
+        self.block_on(self.inner.list_open_trades_with_meta(account_id))
+    }
+
+    /// See [`crate::Client::trade`].
+    pub fn trade(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+    ) -> Result<TradeResponse, Error> {
+        self.block_on(self.inner.trade(account_id, trade))
+    }
+
+    /// See [`crate::Client::trade_with_meta`].
+    pub fn trade_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+    ) -> Result<crate::Response<TradeResponse>, Error> {
+        self.block_on(self.inner.trade_with_meta(account_id, trade))
+    }
+
+    /// See [`crate::Client::close_trade`].
+    pub fn close_trade(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+    ) -> CloseTradeRequest {
+        self.wrap(self.inner.close_trade(account_id, trade)).into()
+    }
+
+    /// See [`crate::Client::set_trade_client_extensions`].
+    pub fn set_trade_client_extensions(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+        extensions: ClientExtensions,
+    ) -> Result<SetTradeClientExtensionsResponse, Error> {
+        self.block_on(
+            self.inner
+                .set_trade_client_extensions(account_id, trade, extensions),
+        )
+    }
+
+    /// See [`crate::Client::set_trade_client_extensions_with_meta`].
+    pub fn set_trade_client_extensions_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+        extensions: ClientExtensions,
+    ) -> Result<crate::Response<SetTradeClientExtensionsResponse>, Error> {
+        self.block_on(
+            self.inner
+                .set_trade_client_extensions_with_meta(account_id, trade, extensions),
+        )
+    }
+
+    /// See [`crate::Client::set_trade_dependent_orders`].
+    pub fn set_trade_dependent_orders(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+    ) -> SetTradeDependentOrdersRequest {
+        self.wrap(self.inner.set_trade_dependent_orders(account_id, trade))
+            .into()
+    }
+
+    /// See [`crate::Client::list_transactions`].
+    pub fn list_transactions(&self, account_id: impl Into<AccountId>) -> ListTransactionsRequest {
+        self.wrap(self.inner.list_transactions(account_id)).into()
+    }
+
+    /// See [`crate::Client::transaction`].
+    pub fn transaction(
+        &self,
+        account_id: impl Into<AccountId>,
+        transaction_id: impl Into<TransactionId>,
+    ) -> Result<TransactionResponse, Error> {
+        self.block_on(self.inner.transaction(account_id, transaction_id))
+    }
+
+    /// See [`crate::Client::transaction_with_meta`].
+    pub fn transaction_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        transaction_id: impl Into<TransactionId>,
+    ) -> Result<crate::Response<TransactionResponse>, Error> {
+        self.block_on(self.inner.transaction_with_meta(account_id, transaction_id))
+    }
+
+    /// See [`crate::Client::transactions_id_range`].
+    pub fn transactions_id_range(
+        &self,
+        account_id: impl Into<AccountId>,
+        from: impl Into<TransactionId>,
+        to: impl Into<TransactionId>,
+    ) -> TransactionsIdRangeRequest {
+        self.wrap(self.inner.transactions_id_range(account_id, from, to))
+            .into()
+    }
+
+    /// See [`crate::Client::transaction_stream`]. The stream is an
+    /// iterator here.
+    pub fn transaction_stream(&self, account_id: impl Into<AccountId>) -> TransactionStreamRequest {
+        self.wrap(self.inner.transaction_stream(account_id)).into()
+    }
+
+    /// See [`crate::Client::transactions_since_id`].
+    pub fn transactions_since_id(
+        &self,
+        account_id: impl Into<AccountId>,
+        id: impl Into<TransactionId>,
+    ) -> Result<TransactionsResponse, Error> {
+        self.block_on(self.inner.transactions_since_id(account_id, id))
+    }
+
+    /// See [`crate::Client::transactions_since_id_with_meta`].
+    pub fn transactions_since_id_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        id: impl Into<TransactionId>,
+    ) -> Result<crate::Response<TransactionsResponse>, Error> {
+        self.block_on(self.inner.transactions_since_id_with_meta(account_id, id))
+    }
+}
+
+/// An async builder paired with the runtime that will drive it.
+struct Request<B> {
+    inner: B,
+    runtime: Arc<Runtime>,
+}
+
+/// Generates a blocking builder wrapping the async builder of the same
+/// name, forwarding its setters and blocking in `send()`.
+macro_rules! blocking_request {
+    (
+        $module:ident::$name:ident $(-> $response:ty)?;
+        $( fn $setter:ident($($arg:ident: $ty:ty),*); )*
+    ) => {
+        #[doc = concat!(
+            "Blocking counterpart of [`", stringify!($name), "`](crate::endpoints::",
+            stringify!($module), "::", stringify!($name), ")."
+        )]
+        #[derive(Debug)]
+        pub struct $name {
+            inner: crate::endpoints::$module::$name,
+            runtime: Arc<Runtime>,
+        }
+
+        impl From<Request<crate::endpoints::$module::$name>> for $name {
+            fn from(request: Request<crate::endpoints::$module::$name>) -> Self {
+                $name {
+                    inner: request.inner,
+                    runtime: request.runtime,
+                }
+            }
+        }
+
+        impl $name {
+            $(
+                #[doc = concat!(
+                    "See [`", stringify!($name), "::", stringify!($setter),
+                    "`](crate::endpoints::", stringify!($module), "::", stringify!($name),
+                    "::", stringify!($setter), ")."
+                )]
+                pub fn $setter(mut self, $($arg: $ty),*) -> Self {
+                    self.inner = self.inner.$setter($($arg),*);
+                    self
+                }
+            )*
+
+            $(
+                /// Sends the request, blocking until the response arrives.
+                pub fn send(self) -> Result<$response, Error> {
+                    self.runtime.block_on(self.inner.send())
+                }
+
+                /// Like [`send`](Self::send), but also returns the
+                /// response's [metadata](crate::Response).
+                pub fn send_with_meta(self) -> Result<crate::Response<$response>, Error> {
+                    self.runtime.block_on(self.inner.send_with_meta())
+                }
+            )?
+        }
+    };
+}
+
+blocking_request! {
+    accounts::AccountChangesRequest -> AccountChangesResponse;
+    fn since_transaction_id(id: impl Into<TransactionId>);
+}
+
+blocking_request! {
+    accounts::AccountInstrumentsRequest -> AccountInstrumentsResponse;
+    fn instruments(instruments: impl IntoIterator<Item = impl Into<InstrumentName>>);
+}
+
+blocking_request! {
+    accounts::ConfigureAccountRequest -> ConfigureAccountResponse;
+    fn alias(alias: impl Into<String>);
+    fn margin_rate(margin_rate: impl Into<DecimalNumber>);
+}
+
+blocking_request! {
+    instruments::CandlesRequest -> InstrumentCandles;
+    fn price(price: PricingComponent);
+    fn granularity(granularity: CandlestickGranularity);
+    fn count(count: u32);
+    fn from(from: impl Into<DateTime>);
+    fn to(to: impl Into<DateTime>);
+    fn smooth(smooth: bool);
+    fn include_first(include_first: bool);
+    fn daily_alignment(hour: u8);
+    fn alignment_timezone(timezone: impl Into<String>);
+    fn weekly_alignment(alignment: WeeklyAlignment);
+    fn units(units: impl Into<DecimalNumber>);
+}
+
+blocking_request! {
+    instruments::LatestCandlesRequest -> LatestCandlesResponse;
+    fn units(units: impl Into<DecimalNumber>);
+    fn smooth(smooth: bool);
+    fn daily_alignment(hour: u8);
+    fn alignment_timezone(timezone: impl Into<String>);
+    fn weekly_alignment(alignment: WeeklyAlignment);
+}
+
+blocking_request! {
+    instruments::OrderBookRequest -> OrderBookResponse;
+    fn time(time: impl Into<DateTime>);
+}
+
+blocking_request! {
+    instruments::PositionBookRequest -> PositionBookResponse;
+    fn time(time: impl Into<DateTime>);
+}
+
+blocking_request! {
+    orders::CreateOrderIdempotentRequest;
+    fn max_attempts(attempts: u32);
+    fn lookback(transactions: u32);
+    fn settle_delay(delay: Duration);
+}
+
+impl CreateOrderIdempotentRequest {
+    /// Sends the request, blocking until the order is known to exist or
+    /// the attempts are exhausted.
+    pub fn send(self) -> Result<IdempotentOrderResponse, Error> {
+        self.runtime.block_on(self.inner.send())
+    }
+}
+
+blocking_request! {
+    orders::ListOrdersRequest -> ListOrdersResponse;
+    fn ids(ids: impl IntoIterator<Item = impl Into<OrderId>>);
+    fn state(state: OrderStateFilter);
+    fn instrument(instrument: impl Into<InstrumentName>);
+    fn count(count: u32);
+    fn before_id(before_id: impl Into<OrderId>);
+}
+
+impl ListOrdersRequest {
+    /// See [`ListOrdersRequest::into_stream`](crate::endpoints::orders::ListOrdersRequest::into_stream);
+    /// each page is fetched as the iterator reaches it.
+    pub fn into_stream(self) -> Paginated<Order> {
+        Paginated {
+            inner: self.inner.into_stream(),
+            runtime: self.runtime,
+        }
+    }
+}
+
+blocking_request! {
+    orders::SetOrderClientExtensionsRequest -> SetOrderClientExtensionsResponse;
+    fn client_extensions(extensions: ClientExtensions);
+    fn trade_client_extensions(extensions: ClientExtensions);
+}
+
+blocking_request! {
+    positions::ClosePositionRequest -> ClosePositionResponse;
+    fn long_units(units: impl Into<String>);
+    fn long_client_extensions(extensions: ClientExtensions);
+    fn short_units(units: impl Into<String>);
+    fn short_client_extensions(extensions: ClientExtensions);
+}
+
+blocking_request! {
+    pricing::PricesRequest -> PricesResponse;
+    fn since(since: impl Into<DateTime>);
+    fn include_home_conversions(include: bool);
+}
+
+blocking_request! {
+    pricing::PricingStreamRequest;
+    fn snapshot(snapshot: bool);
+    fn auto_reconnect(enabled: bool);
+    fn heartbeat_timeout(timeout: Duration);
+    fn backoff(initial: Duration, max: Duration);
+    fn backoff_reset_after(stable: Duration);
+    fn max_reconnect_attempts(attempts: u32);
+}
+
+impl PricingStreamRequest {
+    /// Opens the stream, blocking until the connection is established.
+    pub fn send(self) -> Result<PricingStream, Error> {
+        let inner = self.runtime.block_on(self.inner.send())?;
+        Ok(PricingStream {
+            inner,
+            runtime: self.runtime,
+        })
+    }
+}
+
+blocking_request! {
+    trades::ListTradesRequest -> ListTradesResponse;
+    fn ids(ids: impl IntoIterator<Item = impl Into<TradeId>>);
+    fn state(state: TradeStateFilter);
+    fn instrument(instrument: impl Into<InstrumentName>);
+    fn count(count: u32);
+    fn before_id(before_id: impl Into<TradeId>);
+}
+
+impl ListTradesRequest {
+    /// See [`ListTradesRequest::into_stream`](crate::endpoints::trades::ListTradesRequest::into_stream);
+    /// each page is fetched as the iterator reaches it.
+    pub fn into_stream(self) -> Paginated<Trade> {
+        Paginated {
+            inner: self.inner.into_stream(),
+            runtime: self.runtime,
+        }
+    }
+}
+
+blocking_request! {
+    trades::CloseTradeRequest -> CloseTradeResponse;
+    fn units(units: impl Into<String>);
+}
+
+blocking_request! {
+    trades::SetTradeDependentOrdersRequest -> SetTradeDependentOrdersResponse;
+    fn take_profit(details: TakeProfitDetails);
+    fn cancel_take_profit();
+    fn stop_loss(details: StopLossDetails);
+    fn cancel_stop_loss();
+    fn trailing_stop_loss(details: TrailingStopLossDetails);
+    fn cancel_trailing_stop_loss();
+}
+
+blocking_request! {
+    transactions::ListTransactionsRequest -> ListTransactionsResponse;
+    fn from(from: impl Into<DateTime>);
+    fn to(to: impl Into<DateTime>);
+    fn page_size(page_size: u32);
+    fn types(types: impl IntoIterator<Item = TransactionFilter>);
+}
+
+blocking_request! {
+    transactions::TransactionsIdRangeRequest -> TransactionsResponse;
+    fn types(types: impl IntoIterator<Item = TransactionFilter>);
+}
+
+blocking_request! {
+    transactions::TransactionStreamRequest;
+    fn auto_reconnect(enabled: bool);
+    fn heartbeat_timeout(timeout: Duration);
+    fn backoff(initial: Duration, max: Duration);
+    fn backoff_reset_after(stable: Duration);
+    fn max_reconnect_attempts(attempts: u32);
+}
+
+impl TransactionStreamRequest {
+    /// Opens the stream, blocking until the connection is established.
+    pub fn send(self) -> Result<TransactionStream, Error> {
+        let inner = self.runtime.block_on(self.inner.send())?;
+        Ok(TransactionStream {
+            inner,
+            runtime: self.runtime,
+        })
+    }
+}
+
+/// A paged list as a blocking iterator; see
+/// [`endpoints::paging::Paginated`](crate::endpoints::paging::Paginated).
+#[derive(Debug)]
+pub struct Paginated<T> {
+    inner: crate::endpoints::paging::Paginated<T>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Paginated<T> {
+    /// See [`Paginated::tag`](crate::endpoints::paging::Paginated::tag).
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.inner = self.inner.tag(tag);
+        self
+    }
+
+    /// See [`Paginated::between`](crate::endpoints::paging::Paginated::between).
+    pub fn between(
+        mut self,
+        from: impl Into<DateTime>,
+        to: impl Into<DateTime>,
+    ) -> Result<Self, Error> {
+        self.inner = self.inner.between(from, to)?;
+        Ok(self)
+    }
+}
+
+impl<T> Iterator for Paginated<T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.inner.next())
+    }
+}
+
+/// A pricing stream as a blocking iterator; see
+/// [`streaming::PricingStream`](crate::streaming::PricingStream) for its
+/// reconnection behaviour.
+#[derive(Debug)]
+pub struct PricingStream {
+    inner: crate::streaming::PricingStream,
+    runtime: Arc<Runtime>,
+}
+
+impl PricingStream {
+    /// See [`streaming::PricingStream::record`](crate::streaming::PricingStream::record).
+    pub fn record(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.inner = self.inner.record(writer);
+        self
+    }
+
+    /// A snapshot of the stream's connection statistics.
+    pub fn stats(&self) -> StreamStats {
+        self.inner.stats()
+    }
+}
+
+impl Iterator for PricingStream {
+    type Item = Result<PriceStreamItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.inner.next())
+    }
+}
+
+/// A transaction stream as a blocking iterator; see
+/// [`streaming::TransactionStream`](crate::streaming::TransactionStream)
+/// for its reconnection and back-fill behaviour.
+#[derive(Debug)]
+pub struct TransactionStream {
+    inner: crate::streaming::TransactionStream,
+    runtime: Arc<Runtime>,
+}
+
+impl TransactionStream {
+    /// See [`streaming::TransactionStream::record`](crate::streaming::TransactionStream::record).
+    pub fn record(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.inner = self.inner.record(writer);
+        self
+    }
+
+    /// A snapshot of the stream's connection statistics.
+    pub fn stats(&self) -> StreamStats {
+        self.inner.stats()
+    }
+}
+
+impl Iterator for TransactionStream {
+    type Item = Result<TransactionStreamItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.inner.next())
+    }
+}
@@ -0,0 +1,353 @@
+//! The [`Broker`] trait: the account, order, trade, position, pricing and
+//! transaction operations a strategy needs, independent of the backend.
+//!
+//! [`Client`] implements it against the OANDA API. Strategy code written
+//! against `impl Broker` (or a `B: Broker` parameter) can run unchanged
+//! against a paper-trading engine, a recorder or a test double:
+//!
+//! ```no_run
+//! use oanda_rs::broker::Broker;
+//! use oanda_rs::prelude::*;
+//!
+//! async fn flatten(broker: &impl Broker, account: &AccountId) -> Result<(), Error> {
+//!     for trade in broker.list_open_trades(account).await?.trades {
+//!         if let Some(id) = trade.id {
+//!             broker.close_trade(account, id.into(), None).await?;
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The trait uses `async fn`-style methods returning `Send` futures, so it
+//! is meant for static dispatch (generics) rather than `dyn Broker`.
+//! Operations with optional parameters take them as plain arguments; use
+//! the inherent [`Client`] builders for anything beyond them.
+
+use std::future::Future;
+
+use futures_core::Stream;
+
+use crate::client::Client;
+use crate::endpoints::accounts::{
+    AccountInstrumentsResponse, AccountResponse, AccountSummaryResponse,
+};
+use crate::endpoints::orders::{
+    CancelOrderResponse, CreateOrderResponse, ListOrdersResponse, OrderResponse,
+    ReplaceOrderResponse,
+};
+use crate::endpoints::positions::{ClosePositionResponse, ListPositionsResponse, PositionResponse};
+use crate::endpoints::pricing::PricesResponse;
+use crate::endpoints::trades::{CloseTradeResponse, ListTradesResponse, TradeResponse};
+use crate::endpoints::transactions::{TransactionResponse, TransactionsResponse};
+use crate::error::Error;
+use crate::models::transaction::TransactionStreamItem;
+use crate::models::{
+    AccountId, DecimalNumber, InstrumentName, OrderRequest, OrderSpecifier, PriceStreamItem,
+    TradeSpecifier, TransactionId,
+};
+use crate::streaming::{PricingStream, TransactionStream};
+
+/// The trading operations of an OANDA-like backend.
+///
+/// See the [module docs](self) for an example.
+pub trait Broker: Send + Sync {
+    /// The stream returned by [`pricing_stream`](Broker::pricing_stream).
+    type PriceStream: Stream<Item = Result<PriceStreamItem, Error>> + Send + Unpin;
+
+    /// The stream returned by
+    /// [`transaction_stream`](Broker::transaction_stream).
+    type TransactionStream: Stream<Item = Result<TransactionStreamItem, Error>> + Send + Unpin;
+
+    /// The full details of an account, including its open trades, orders
+    /// and positions.
+    fn account(
+        &self,
+        account_id: &AccountId,
+    ) -> impl Future<Output = Result<AccountResponse, Error>> + Send;
+
+    /// The summary of an account.
+    fn account_summary(
+        &self,
+        account_id: &AccountId,
+    ) -> impl Future<Output = Result<AccountSummaryResponse, Error>> + Send;
+
+    /// The instruments an account can trade; all of them when
+    /// `instruments` is empty.
+    fn account_instruments(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> impl Future<Output = Result<AccountInstrumentsResponse, Error>> + Send;
+
+    /// Submits an order.
+    fn create_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderRequest,
+    ) -> impl Future<Output = Result<CreateOrderResponse, Error>> + Send;
+
+    /// The account's pending orders.
+    fn list_pending_orders(
+        &self,
+        account_id: &AccountId,
+    ) -> impl Future<Output = Result<ListOrdersResponse, Error>> + Send;
+
+    /// The details of one order.
+    fn order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+    ) -> impl Future<Output = Result<OrderResponse, Error>> + Send;
+
+    /// Replaces a pending order with a new one.
+    fn replace_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+        replacement: OrderRequest,
+    ) -> impl Future<Output = Result<ReplaceOrderResponse, Error>> + Send;
+
+    /// Cancels a pending order.
+    fn cancel_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+    ) -> impl Future<Output = Result<CancelOrderResponse, Error>> + Send;
+
+    /// The account's open trades.
+    fn list_open_trades(
+        &self,
+        account_id: &AccountId,
+    ) -> impl Future<Output = Result<ListTradesResponse, Error>> + Send;
+
+    /// The details of one trade.
+    fn trade(
+        &self,
+        account_id: &AccountId,
+        trade: TradeSpecifier,
+    ) -> impl Future<Output = Result<TradeResponse, Error>> + Send;
+
+    /// Closes `units` of a trade, or all of it when `None`.
+    fn close_trade(
+        &self,
+        account_id: &AccountId,
+        trade: TradeSpecifier,
+        units: Option<DecimalNumber>,
+    ) -> impl Future<Output = Result<CloseTradeResponse, Error>> + Send;
+
+    /// The account's open positions.
+    fn list_open_positions(
+        &self,
+        account_id: &AccountId,
+    ) -> impl Future<Output = Result<ListPositionsResponse, Error>> + Send;
+
+    /// The account's position in one instrument.
+    fn position(
+        &self,
+        account_id: &AccountId,
+        instrument: InstrumentName,
+    ) -> impl Future<Output = Result<PositionResponse, Error>> + Send;
+
+    /// Closes both sides of the account's position in an instrument.
+    fn close_position(
+        &self,
+        account_id: &AccountId,
+        instrument: InstrumentName,
+    ) -> impl Future<Output = Result<ClosePositionResponse, Error>> + Send;
+
+    /// The current prices of `instruments`.
+    fn prices(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> impl Future<Output = Result<PricesResponse, Error>> + Send;
+
+    /// Opens a price stream for `instruments`.
+    fn pricing_stream(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> impl Future<Output = Result<Self::PriceStream, Error>> + Send;
+
+    /// The details of one transaction.
+    fn transaction(
+        &self,
+        account_id: &AccountId,
+        transaction_id: TransactionId,
+    ) -> impl Future<Output = Result<TransactionResponse, Error>> + Send;
+
+    /// The transactions after `transaction_id`.
+    fn transactions_since_id(
+        &self,
+        account_id: &AccountId,
+        transaction_id: TransactionId,
+    ) -> impl Future<Output = Result<TransactionsResponse, Error>> + Send;
+
+    /// Opens the account's transaction stream.
+    fn transaction_stream(
+        &self,
+        account_id: &AccountId,
+    ) -> impl Future<Output = Result<Self::TransactionStream, Error>> + Send;
+}
+
+impl Broker for Client {
+    type PriceStream = PricingStream;
+    type TransactionStream = TransactionStream;
+
+    async fn account(&self, account_id: &AccountId) -> Result<AccountResponse, Error> {
+        Client::account(self, account_id.clone()).await
+    }
+
+    async fn account_summary(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<AccountSummaryResponse, Error> {
+        Client::account_summary(self, account_id.clone()).await
+    }
+
+    async fn account_instruments(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> Result<AccountInstrumentsResponse, Error> {
+        let request = Client::account_instruments(self, account_id.clone());
+        if instruments.is_empty() {
+            request.send().await
+        } else {
+            request.instruments(instruments).send().await
+        }
+    }
+
+    async fn create_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderRequest,
+    ) -> Result<CreateOrderResponse, Error> {
+        Client::create_order(self, account_id.clone(), order).await
+    }
+
+    async fn list_pending_orders(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<ListOrdersResponse, Error> {
+        Client::list_pending_orders(self, account_id.clone()).await
+    }
+
+    async fn order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+    ) -> Result<OrderResponse, Error> {
+        Client::order(self, account_id.clone(), order).await
+    }
+
+    async fn replace_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+        replacement: OrderRequest,
+    ) -> Result<ReplaceOrderResponse, Error> {
+        Client::replace_order(self, account_id.clone(), order, replacement).await
+    }
+
+    async fn cancel_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+    ) -> Result<CancelOrderResponse, Error> {
+        Client::cancel_order(self, account_id.clone(), order).await
+    }
+
+    async fn list_open_trades(&self, account_id: &AccountId) -> Result<ListTradesResponse, Error> {
+        Client::list_open_trades(self, account_id.clone()).await
+    }
+
+    async fn trade(
+        &self,
+        account_id: &AccountId,
+        trade: TradeSpecifier,
+    ) -> Result<TradeResponse, Error> {
+        Client::trade(self, account_id.clone(), trade).await
+    }
+
+    async fn close_trade(
+        &self,
+        account_id: &AccountId,
+        trade: TradeSpecifier,
+        units: Option<DecimalNumber>,
+    ) -> Result<CloseTradeResponse, Error> {
+        let request = Client::close_trade(self, account_id.clone(), trade);
+        match units {
+            Some(units) => request.units(units.to_string()).send().await,
+            None => request.send().await,
+        }
+    }
+
+    async fn list_open_positions(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<ListPositionsResponse, Error> {
+        Client::list_open_positions(self, account_id.clone()).await
+    }
+
+    async fn position(
+        &self,
+        account_id: &AccountId,
+        instrument: InstrumentName,
+    ) -> Result<PositionResponse, Error> {
+        Client::position(self, account_id.clone(), instrument).await
+    }
+
+    async fn close_position(
+        &self,
+        account_id: &AccountId,
+        instrument: InstrumentName,
+    ) -> Result<ClosePositionResponse, Error> {
+        Client::close_position(self, account_id.clone(), instrument)
+            .send()
+            .await
+    }
+
+    async fn prices(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> Result<PricesResponse, Error> {
+        Client::prices(self, account_id.clone(), instruments)
+            .send()
+            .await
+    }
+
+    async fn pricing_stream(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> Result<PricingStream, Error> {
+        Client::pricing_stream(self, account_id.clone(), instruments)
+            .send()
+            .await
+    }
+
+    async fn transaction(
+        &self,
+        account_id: &AccountId,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionResponse, Error> {
+        Client::transaction(self, account_id.clone(), transaction_id).await
+    }
+
+    async fn transactions_since_id(
+        &self,
+        account_id: &AccountId,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionsResponse, Error> {
+        Client::transactions_since_id(self, account_id.clone(), transaction_id).await
+    }
+
+    async fn transaction_stream(&self, account_id: &AccountId) -> Result<TransactionStream, Error> {
+        Client::transaction_stream(self, account_id.clone())
+            .send()
+            .await
+    }
+}
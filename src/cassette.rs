@@ -0,0 +1,224 @@
+//! HTTP record/replay ("cassette") support for REST requests.
+//!
+//! A cassette is an NDJSON file with one request/response exchange per
+//! line:
+//!
+//! ```json
+//! {"method":"GET","path":"/v3/accounts/101-004-1234567-001/summary","query":null,
+//!  "request":{"headers":{"authorization":"Bearer <redacted>"},"body":null},
+//!  "response":{"status":200,"headers":{"requestid":"24644537"},"body":{"account":{}}}}
+//! ```
+//!
+//! Bodies are stored as JSON when they are JSON objects or arrays and as
+//! strings otherwise; the bearer token is never written.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+
+/// How a client should use a cassette; set on the builder.
+#[derive(Debug, Clone)]
+pub(crate) enum CassetteMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// An open cassette, shared by all clones of a client.
+#[derive(Debug)]
+pub(crate) enum Cassette {
+    Record(Mutex<BufWriter<File>>),
+    Replay(Mutex<Vec<Replayable>>),
+}
+
+#[derive(Debug)]
+pub(crate) struct Replayable {
+    exchange: Exchange,
+    used: bool,
+}
+
+/// One recorded request and its response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    method: String,
+    path: String,
+    query: Option<String>,
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    headers: Map<String, Value>,
+    body: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Map<String, Value>,
+    body: Value,
+}
+
+/// A response as the transport decodes it: status, headers and body text.
+pub(crate) struct RawResponse {
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: String,
+}
+
+impl Cassette {
+    /// Opens the cassette: truncates the file when recording, loads it
+    /// when replaying.
+    pub(crate) fn open(mode: &CassetteMode) -> Result<Cassette, Error> {
+        match mode {
+            CassetteMode::Record(path) => {
+                let file = File::create(path).map_err(|e| io_error(path, e))?;
+                Ok(Cassette::Record(Mutex::new(BufWriter::new(file))))
+            }
+            CassetteMode::Replay(path) => {
+                let file = File::open(path).map_err(|e| io_error(path, e))?;
+                let mut exchanges = Vec::new();
+                for (number, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line.map_err(|e| io_error(path, e))?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let exchange = serde_json::from_str(&line).map_err(|e| {
+                        Error::Config(format!(
+                            "invalid cassette entry at {}:{}: {e}",
+                            path.display(),
+                            number + 1
+                        ))
+                    })?;
+                    exchanges.push(Replayable {
+                        exchange,
+                        used: false,
+                    });
+                }
+                Ok(Cassette::Replay(Mutex::new(exchanges)))
+            }
+        }
+    }
+
+    /// Answers a request from the cassette, using each recorded exchange
+    /// once, in the order they were recorded.
+    pub(crate) fn replay(&self, request: &Request) -> Option<Result<RawResponse, Error>> {
+        let Cassette::Replay(exchanges) = self else {
+            return None;
+        };
+        let mut exchanges = exchanges.lock().unwrap_or_else(PoisonError::into_inner);
+        let url = request.url();
+        let query = sorted_query(url.query());
+        let found = exchanges.iter_mut().find(|r| {
+            !r.used
+                && r.exchange.method == request.method().as_str()
+                && r.exchange.path == url.path()
+                && sorted_query(r.exchange.query.as_deref()) == query
+        });
+        let Some(replayable) = found else {
+            return Some(Err(Error::Config(format!(
+                "the cassette has no unused recording of {} {}",
+                request.method(),
+                url.path()
+            ))));
+        };
+        replayable.used = true;
+        let response = &replayable.exchange.response;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &response.headers {
+            if let (Ok(name), Some(Ok(value))) = (
+                HeaderName::try_from(name.as_str()),
+                value.as_str().map(HeaderValue::from_str),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        Some(Ok(RawResponse {
+            status: StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK),
+            headers,
+            body: match &response.body {
+                Value::String(text) => text.clone(),
+                Value::Null => String::new(),
+                json => json.to_string(),
+            },
+        }))
+    }
+
+    /// Appends an exchange to a recording cassette.
+    pub(crate) fn record(&self, request: &Request, response: &RawResponse) -> Result<(), Error> {
+        let Cassette::Record(writer) = self else {
+            return Ok(());
+        };
+        let mut request_headers = headers_json(request.headers());
+        if request_headers.contains_key(AUTHORIZATION.as_str()) {
+            request_headers.insert(AUTHORIZATION.as_str().into(), "Bearer <redacted>".into());
+        }
+        let request_body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map_or(Value::Null, |bytes| {
+                body_json(&String::from_utf8_lossy(bytes))
+            });
+        let exchange = Exchange {
+            method: request.method().as_str().to_owned(),
+            path: request.url().path().to_owned(),
+            query: request.url().query().map(str::to_owned),
+            request: RecordedRequest {
+                headers: request_headers,
+                body: request_body,
+            },
+            response: RecordedResponse {
+                status: response.status.as_u16(),
+                headers: headers_json(&response.headers),
+                body: body_json(&response.body),
+            },
+        };
+        let line = serde_json::to_string(&exchange).expect("exchanges serialize to JSON");
+        let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writeln!(writer, "{line}")
+            .and_then(|()| writer.flush())
+            .map_err(|e| Error::Config(format!("failed to write to the cassette: {e}")))
+    }
+}
+
+fn headers_json(headers: &HeaderMap) -> Map<String, Value> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.as_str().to_owned(), value.to_str().ok()?.into())))
+        .collect()
+}
+
+fn body_json(text: &str) -> Value {
+    if text.is_empty() {
+        return Value::Null;
+    }
+    match serde_json::from_str(text) {
+        Ok(json @ (Value::Object(_) | Value::Array(_))) => json,
+        _ => Value::String(text.to_owned()),
+    }
+}
+
+/// Query pairs in a canonical order, so parameter order does not matter.
+fn sorted_query(query: Option<&str>) -> Vec<(String, String)> {
+    let Some(query) = query else {
+        return Vec::new();
+    };
+    // reuse the URL parser's form decoding
+    let mut url = reqwest::Url::parse("http://cassette.invalid/").expect("static URL parses");
+    url.set_query(Some(query));
+    let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    pairs.sort();
+    pairs
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> Error {
+    Error::Config(format!("cannot open cassette {}: {error}", path.display()))
+}
@@ -1,19 +1,28 @@
 //! The OANDA API client.
 
 use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use reqwest::Url;
 
+use crate::auth::{StaticToken, Token, TokenProvider};
+use crate::cassette::{Cassette, CassetteMode};
 use crate::error::Error;
-use crate::models::AcceptDatetimeFormat;
+use crate::guard::{Guard, Guards};
+use crate::models::{AcceptDatetimeFormat, AccountId};
 use crate::rate_limit::RateLimiter;
+use crate::streaming::StreamConfig;
 
 /// Default REST rate limit (requests/second). OANDA rejects above 120/s per
 /// IP; the default keeps comfortable headroom.
 const DEFAULT_REST_RATE_LIMIT: u32 = 100;
 /// OANDA allows at most 2 new connections per second per IP.
 const CONNECTIONS_PER_SECOND: u32 = 2;
+/// Delay before the first retry of a failed GET request.
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
 
 /// The OANDA environment (host pair) a [`Client`] talks to.
 #[derive(Debug, Clone)]
@@ -26,7 +35,11 @@ pub enum Environment {
     /// `api-fxtrade.oanda.com` / `stream-fxtrade.oanda.com`.
     Live,
     /// Custom host pair, mainly for tests and proxies. Both URLs are used
-    /// as-is (the `/v3` prefix is **not** appended).
+    /// as-is (the `/v3` prefix is **not** appended). [`Guards`], when
+    /// installed, treat it as live unless the REST host is the practice
+    /// host or a loopback address.
+    ///
+    /// [`Guards`]: crate::guard::Guards
     Custom {
         /// Base URL for REST requests.
         rest: Url,
@@ -51,16 +64,50 @@ impl Environment {
             Environment::Custom { stream, .. } => stream.clone(),
         }
     }
+
+    /// Whether requests may reach OANDA's live REST host. Fails closed: a
+    /// custom REST host counts as live (it may be a proxy) unless it is the
+    /// practice host or a loopback address.
+    fn is_live(&self) -> bool {
+        match self {
+            Environment::Practice => false,
+            Environment::Live => true,
+            Environment::Custom { rest, .. } => match rest.host_str() {
+                Some("api-fxpractice.oanda.com" | "localhost") => false,
+                Some(host) => !host
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<IpAddr>()
+                    .is_ok_and(|ip| ip.is_loopback()),
+                None => true,
+            },
+        }
+    }
 }
 
 pub(crate) struct Inner {
     pub(crate) http: reqwest::Client,
     pub(crate) rest_base: Url,
     pub(crate) stream_base: Url,
-    pub(crate) token: String,
+    pub(crate) token: Arc<dyn TokenProvider>,
     pub(crate) datetime_format: AcceptDatetimeFormat,
-    pub(crate) rest_limiter: Option<RateLimiter>,
-    pub(crate) conn_limiter: Option<RateLimiter>,
+    pub(crate) rest_limiter: Option<Arc<RateLimiter>>,
+    pub(crate) conn_limiter: Option<Arc<RateLimiter>>,
+    pub(crate) cassette: Option<Arc<Cassette>>,
+    pub(crate) default_account: Option<AccountId>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) stream_config: StreamConfig,
+    /// Whether the REST host is OANDA's live one.
+    pub(crate) live: bool,
+    pub(crate) guard: Option<Arc<Guard>>,
+}
+
+/// How GET requests are retried after transient failures.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RetryPolicy {
+    pub(crate) max_retries: u32,
+    pub(crate) backoff: Duration,
 }
 
 /// An asynchronous OANDA v20 API client.
@@ -91,6 +138,8 @@ impl fmt::Debug for Client {
             .field("stream_base", &self.inner.stream_base.as_str())
             .field("token", &"<redacted>")
             .field("datetime_format", &self.inner.datetime_format)
+            .field("live", &self.inner.live)
+            .field("guards", &self.inner.guard)
             .finish()
     }
 }
@@ -124,6 +173,60 @@ impl Client {
     pub fn datetime_format(&self) -> AcceptDatetimeFormat {
         self.inner.datetime_format
     }
+
+    /// The account configured with [`ClientBuilder::default_account`], if
+    /// any.
+    pub fn default_account(&self) -> Option<&AccountId> {
+        self.inner.default_account.as_ref()
+    }
+
+    /// The [`Guards`] checked before orders are sent, if any. Without
+    /// them, nothing is checked.
+    pub fn guards(&self) -> Option<&Guards> {
+        self.inner.guard.as_deref().map(Guard::guards)
+    }
+
+    /// A client for other credentials that shares this client's connection
+    /// pool, rate limiters, cassette, datetime format, timeouts, retry
+    /// policy, stream defaults and guards (but not its default account).
+    /// The guards' per-minute order limit counts the orders of both.
+    ///
+    /// OANDA's rate limits apply per IP, not per token, so clients for
+    /// several users should be derived from one another rather than built
+    /// separately. See also
+    /// [`MultiAccountClient`](crate::account::MultiAccountClient).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] when the token is empty.
+    pub fn with_credentials(
+        &self,
+        environment: Environment,
+        token: impl Into<String>,
+    ) -> Result<Client, Error> {
+        let token = Token::new(token);
+        if token.is_empty() {
+            return Err(Error::Config("a non-empty API token is required".into()));
+        }
+        Ok(Client {
+            inner: Arc::new(Inner {
+                http: self.inner.http.clone(),
+                rest_base: environment.rest_base(),
+                stream_base: environment.stream_base(),
+                token: Arc::new(StaticToken::new(token)),
+                datetime_format: self.inner.datetime_format,
+                rest_limiter: self.inner.rest_limiter.clone(),
+                conn_limiter: self.inner.conn_limiter.clone(),
+                cassette: self.inner.cassette.clone(),
+                default_account: None,
+                timeout: self.inner.timeout,
+                retry: self.inner.retry,
+                stream_config: self.inner.stream_config.clone(),
+                live: environment.is_live(),
+                guard: self.inner.guard.clone(),
+            }),
+        })
+    }
 }
 
 /// Configures and builds a [`Client`].
@@ -143,12 +246,20 @@ impl Client {
 #[derive(Debug)]
 pub struct ClientBuilder {
     environment: Environment,
-    token: Option<String>,
+    token: Option<Token>,
+    token_provider: Option<Arc<dyn TokenProvider>>,
     datetime_format: AcceptDatetimeFormat,
     http: Option<reqwest::Client>,
     user_agent: String,
     rest_rate_limit: u32,
     rate_limiting: bool,
+    cassette: Option<CassetteMode>,
+    default_account: Option<AccountId>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    retry: RetryPolicy,
+    stream_config: StreamConfig,
+    guards: Option<Guards>,
 }
 
 impl Default for ClientBuilder {
@@ -156,11 +267,22 @@ impl Default for ClientBuilder {
         ClientBuilder {
             environment: Environment::Practice,
             token: None,
+            token_provider: None,
             datetime_format: AcceptDatetimeFormat::Rfc3339,
             http: None,
             user_agent: concat!("oanda-rs/", env!("CARGO_PKG_VERSION")).to_owned(),
             rest_rate_limit: DEFAULT_REST_RATE_LIMIT,
             rate_limiting: true,
+            cassette: None,
+            default_account: None,
+            timeout: None,
+            connect_timeout: None,
+            retry: RetryPolicy {
+                max_retries: 0,
+                backoff: DEFAULT_RETRY_BACKOFF,
+            },
+            stream_config: StreamConfig::default(),
+            guards: None,
         }
     }
 }
@@ -173,9 +295,18 @@ impl ClientBuilder {
     }
 
     /// Sets the personal access token used as the bearer token on every
-    /// request. Required.
+    /// request. Required unless a [`token_provider`](Self::token_provider)
+    /// is set.
     pub fn token(mut self, token: impl Into<String>) -> Self {
-        self.token = Some(token.into());
+        self.token = Some(Token::new(token));
+        self
+    }
+
+    /// Reads the bearer token from `provider` for every request instead of
+    /// using a fixed [`token`](Self::token), so that it can be rotated
+    /// without rebuilding the client. See the [`auth`](crate::auth) module.
+    pub fn token_provider(mut self, provider: impl TokenProvider + 'static) -> Self {
+        self.token_provider = Some(Arc::new(provider));
         self
     }
 
@@ -220,15 +351,122 @@ impl ClientBuilder {
         self
     }
 
+    /// The account the application works with, available through
+    /// [`Client::default_account`]. Endpoints still take the account ID
+    /// explicitly.
+    pub fn default_account(mut self, account_id: impl Into<AccountId>) -> Self {
+        self.default_account = Some(account_id.into());
+        self
+    }
+
+    /// Fails REST requests that take longer than `timeout` (including
+    /// reading the body) with a transport error. Unset by default. Streams
+    /// are not affected; they detect stale connections by heartbeat.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Limits how long connecting to OANDA may take, for REST requests and
+    /// streams alike. Unset by default. Ignored when an
+    /// [`http_client`](Self::http_client) is supplied.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Retries GET requests up to `retries` times after a
+    /// [retryable](crate::ErrorKind::is_retryable) failure (a transport
+    /// error, a 5xx or a 429), waiting with exponential backoff in between.
+    /// Defaults to 0. Requests that change state are never retried; see
+    /// [`Client::create_order_idempotent`] for orders.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.retry.max_retries = retries;
+        self
+    }
+
+    /// The delay before the first retry, doubled for each further one.
+    /// Defaults to 500ms.
+    pub fn retry_backoff(mut self, initial: Duration) -> Self {
+        self.retry.backoff = initial;
+        self
+    }
+
+    /// Whether streams reconnect automatically by default (enabled unless
+    /// changed). Each stream request can override this and the other
+    /// stream defaults below.
+    pub fn stream_auto_reconnect(mut self, enabled: bool) -> Self {
+        self.stream_config.auto_reconnect = enabled;
+        self
+    }
+
+    /// The default heartbeat timeout of streams (10s unless changed).
+    pub fn stream_heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.stream_config.heartbeat_timeout = timeout;
+        self
+    }
+
+    /// The default reconnect backoff of streams (1s → 5 minutes unless
+    /// changed).
+    pub fn stream_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.stream_config.backoff_initial = initial;
+        self.stream_config.backoff_max = max;
+        self
+    }
+
+    /// The default limit of consecutive failed reconnect attempts of
+    /// streams (unlimited unless changed).
+    pub fn stream_max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.stream_config.max_reconnect_attempts = Some(attempts);
+        self
+    }
+
+    /// Checks orders, replacements and closes against `guards` before
+    /// sending them; see the [`guard`](crate::guard) module.
+    ///
+    /// Unset by default, and a client without guards is unrestricted: it
+    /// sends any order to any environment, the live one included.
+    pub fn guards(mut self, guards: Guards) -> Self {
+        self.guards = Some(guards);
+        self
+    }
+
+    /// Records every REST request and its response to `path` (truncating
+    /// it), one JSON object per line, for later [replay](Self::replay_from).
+    ///
+    /// The bearer token is redacted; response headers such as `RequestID`
+    /// are kept. Streaming endpoints are not recorded. A failure to write
+    /// the cassette does not fail the request; it is logged with the
+    /// `tracing` feature.
+    pub fn record_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cassette = Some(CassetteMode::Record(path.into()));
+        self
+    }
+
+    /// Answers REST requests from a cassette written by
+    /// [`record_to`](Self::record_to) instead of the network.
+    ///
+    /// Requests are matched on method, path and query parameters (in any
+    /// order), and each recording answers one request, in the order they
+    /// were recorded. A request without an unused recording fails with
+    /// [`Error::Config`]. Rate limiting does not apply to replayed
+    /// requests.
+    pub fn replay_from(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cassette = Some(CassetteMode::Replay(path.into()));
+        self
+    }
+
     /// Builds the [`Client`].
     ///
     /// # Errors
     ///
-    /// Returns [`Error::Config`] when no token was provided, the token is
-    /// empty, or the HTTP client cannot be constructed.
+    /// Returns [`Error::Config`] when neither a token nor a token provider
+    /// was provided, the token is empty, the HTTP client cannot be
+    /// constructed, or the cassette file cannot be opened or parsed.
     pub fn build(self) -> Result<Client, Error> {
-        let token = match self.token {
-            Some(t) if !t.trim().is_empty() => t,
+        let token: Arc<dyn TokenProvider> = match (self.token_provider, self.token) {
+            (Some(provider), _) => provider,
+            (None, Some(token)) if !token.is_empty() => Arc::new(StaticToken::new(token)),
             _ => return Err(Error::Config("a non-empty API token is required".into())),
         };
         if self.rate_limiting && self.rest_rate_limit == 0 {
@@ -238,19 +476,29 @@ impl ClientBuilder {
         }
         let http = match self.http {
             Some(http) => http,
-            None => reqwest::Client::builder()
-                .user_agent(&self.user_agent)
-                .build()
-                .map_err(|e| Error::Config(format!("failed to build HTTP client: {e}")))?,
+            None => {
+                let mut http = reqwest::Client::builder().user_agent(&self.user_agent);
+                if let Some(timeout) = self.connect_timeout {
+                    http = http.connect_timeout(timeout);
+                }
+                http.build()
+                    .map_err(|e| Error::Config(format!("failed to build HTTP client: {e}")))?
+            }
         };
         let (rest_limiter, conn_limiter) = if self.rate_limiting {
             (
-                Some(RateLimiter::per_second(self.rest_rate_limit)),
-                Some(RateLimiter::per_second(CONNECTIONS_PER_SECOND)),
+                Some(Arc::new(RateLimiter::per_second(self.rest_rate_limit))),
+                Some(Arc::new(RateLimiter::per_second(CONNECTIONS_PER_SECOND))),
             )
         } else {
             (None, None)
         };
+        let cassette = self
+            .cassette
+            .as_ref()
+            .map(Cassette::open)
+            .transpose()?
+            .map(Arc::new);
         Ok(Client {
             inner: Arc::new(Inner {
                 http,
@@ -260,6 +508,13 @@ impl ClientBuilder {
                 datetime_format: self.datetime_format,
                 rest_limiter,
                 conn_limiter,
+                cassette,
+                default_account: self.default_account,
+                timeout: self.timeout,
+                retry: self.retry,
+                stream_config: self.stream_config,
+                live: self.environment.is_live(),
+                guard: self.guards.map(|guards| Arc::new(Guard::new(guards))),
             }),
         })
     }
@@ -355,6 +610,25 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn derived_clients_share_rate_limiters() {
+        let client = Client::new(Environment::Practice, "a");
+        let other = client.with_credentials(Environment::Live, "b").unwrap();
+        assert_eq!(other.inner.token.token().await.unwrap().expose(), "b");
+        assert_eq!(
+            other.inner.rest_base.as_str(),
+            "https://api-fxtrade.oanda.com/v3"
+        );
+        assert!(Arc::ptr_eq(
+            client.inner.rest_limiter.as_ref().unwrap(),
+            other.inner.rest_limiter.as_ref().unwrap()
+        ));
+        assert!(matches!(
+            client.with_credentials(Environment::Live, " "),
+            Err(Error::Config(_))
+        ));
+    }
+
     #[test]
     fn custom_environment_uses_given_urls() {
         let env = Environment::Custom {
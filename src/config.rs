@@ -0,0 +1,481 @@
+//! [`ClientBuilder`] configuration from environment variables and config
+//! files.
+
+use std::path::Path;
+use std::time::Duration;
+
+use reqwest::Url;
+use serde_json::{Map, Value};
+
+use crate::auth::FileToken;
+use crate::client::{ClientBuilder, Environment};
+use crate::error::Error;
+use crate::models::AcceptDatetimeFormat;
+use crate::streaming::StreamConfig;
+
+/// Every configuration key; environment variables are the key in upper
+/// case, prefixed with `OANDA_`.
+const KEYS: &[&str] = &[
+    "environment",
+    "rest_url",
+    "stream_url",
+    "token",
+    "token_file",
+    "account_id",
+    "datetime_format",
+    "rest_rate_limit",
+    "rate_limiting",
+    "timeout",
+    "connect_timeout",
+    "max_retries",
+    "retry_backoff",
+    "stream_auto_reconnect",
+    "stream_heartbeat_timeout",
+    "stream_backoff_initial",
+    "stream_backoff_max",
+    "stream_max_reconnect_attempts",
+];
+
+impl ClientBuilder {
+    /// A builder configured from `OANDA_*` environment variables.
+    ///
+    /// | Variable | Value |
+    /// |---|---|
+    /// | `OANDA_TOKEN` or `OANDA_TOKEN_FILE` | the token, or a file holding it (re-read when it changes, see [`FileToken`]); one is required |
+    /// | `OANDA_ENVIRONMENT` | `practice` (default), `live` or `custom` |
+    /// | `OANDA_REST_URL`, `OANDA_STREAM_URL` | base URLs; both required for (and implying) `custom` |
+    /// | `OANDA_ACCOUNT_ID` | the [default account](ClientBuilder::default_account) |
+    /// | `OANDA_DATETIME_FORMAT` | `RFC3339` or `UNIX` |
+    /// | `OANDA_REST_RATE_LIMIT` | requests per second |
+    /// | `OANDA_RATE_LIMITING` | `true` or `false` |
+    /// | `OANDA_TIMEOUT`, `OANDA_CONNECT_TIMEOUT` | seconds |
+    /// | `OANDA_MAX_RETRIES` | retries of failed GET requests |
+    /// | `OANDA_RETRY_BACKOFF` | seconds |
+    /// | `OANDA_STREAM_AUTO_RECONNECT` | `true` or `false` |
+    /// | `OANDA_STREAM_HEARTBEAT_TIMEOUT` | seconds |
+    /// | `OANDA_STREAM_BACKOFF_INITIAL`, `OANDA_STREAM_BACKOFF_MAX` | seconds |
+    /// | `OANDA_STREAM_MAX_RECONNECT_ATTEMPTS` | attempts |
+    ///
+    /// Unset variables keep the builder defaults, and the builder can be
+    /// adjusted further before [`build`](Self::build).
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), oanda_rs::Error> {
+    /// let client = oanda_rs::ClientBuilder::from_env()?.build()?;
+    /// let account = client.default_account();
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] naming every missing or invalid variable.
+    pub fn from_env() -> Result<ClientBuilder, Error> {
+        let mut values = Map::new();
+        let mut problems = Vec::new();
+        for key in KEYS {
+            let name = env_name(key);
+            match std::env::var(&name) {
+                Ok(value) => {
+                    values.insert((*key).to_owned(), Value::String(value));
+                }
+                Err(std::env::VarError::NotPresent) => {}
+                Err(std::env::VarError::NotUnicode(_)) => {
+                    problems.push(format!("`{name}` is not valid UTF-8"));
+                }
+            }
+        }
+        Settings {
+            values,
+            env: true,
+            problems,
+        }
+        .into_builder("the environment")
+    }
+
+    /// A builder configured from a TOML (`.toml`, with the `toml` feature)
+    /// or JSON (`.json`) file.
+    ///
+    /// The file holds the keys of [`from_env`](Self::from_env) in lower
+    /// case without the `OANDA_` prefix, at the top level; numbers and
+    /// booleans may be written natively or as strings:
+    ///
+    /// ```toml
+    /// environment = "live"
+    /// token_file = "/run/secrets/oanda-token"
+    /// account_id = "001-001-1234567-001"
+    /// rest_rate_limit = 50
+    /// timeout = 10
+    /// max_retries = 2
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] when the file cannot be read or parsed, and
+    /// naming every missing, invalid or unknown key.
+    pub fn from_config(path: impl AsRef<Path>) -> Result<ClientBuilder, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("cannot read {}: {e}", path.display())))?;
+        let parsed = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str::<Value>(&text).map_err(|e| e.to_string()),
+            #[cfg(feature = "toml")]
+            Some("toml") => toml::from_str::<Value>(&text).map_err(|e| e.to_string()),
+            #[cfg(not(feature = "toml"))]
+            Some("toml") => {
+                return Err(Error::Config(format!(
+                    "cannot read {}: TOML config files need the `toml` feature",
+                    path.display()
+                )));
+            }
+            _ => {
+                return Err(Error::Config(format!(
+                    "cannot read {}: expected a .toml or .json file",
+                    path.display()
+                )));
+            }
+        };
+        let values = match parsed {
+            Ok(Value::Object(values)) => values,
+            Ok(_) => {
+                return Err(Error::Config(format!(
+                    "invalid config file {}: expected a table of keys",
+                    path.display()
+                )));
+            }
+            Err(e) => {
+                return Err(Error::Config(format!(
+                    "invalid config file {}: {e}",
+                    path.display()
+                )));
+            }
+        };
+        let problems = values
+            .keys()
+            .filter(|key| !KEYS.contains(&key.as_str()))
+            .map(|key| format!("unknown key `{key}`"))
+            .collect();
+        Settings {
+            values,
+            env: false,
+            problems,
+        }
+        .into_builder(&path.display().to_string())
+    }
+}
+
+fn env_name(key: &str) -> String {
+    format!("OANDA_{}", key.to_ascii_uppercase())
+}
+
+/// Raw configuration values, validated key by key so that every problem is
+/// reported at once.
+struct Settings {
+    values: Map<String, Value>,
+    /// Whether the values come from environment variables (for naming keys
+    /// in messages).
+    env: bool,
+    problems: Vec<String>,
+}
+
+impl Settings {
+    fn into_builder(mut self, source: &str) -> Result<ClientBuilder, Error> {
+        let mut builder = ClientBuilder::default();
+
+        let environment =
+            self.parse(
+                "environment",
+                "`practice`, `live` or `custom`",
+                |v| match str_of(v)?.to_ascii_lowercase().as_str() {
+                    "practice" => Some(Some(Environment::Practice)),
+                    "live" => Some(Some(Environment::Live)),
+                    "custom" => Some(None),
+                    _ => None,
+                },
+            );
+        let rest = self.parse("rest_url", "a URL", url_of);
+        let stream = self.parse("stream_url", "a URL", url_of);
+        let custom = match environment {
+            Some(Some(environment)) => {
+                for key in ["rest_url", "stream_url"] {
+                    if self.values.contains_key(key) {
+                        let (name, environment) = (self.name(key), self.name("environment"));
+                        self.problems
+                            .push(format!("`{name}` needs `{environment}` = custom"));
+                    }
+                }
+                builder = builder.environment(environment);
+                false
+            }
+            Some(None) => true,
+            // Implied by a URL.
+            None => self.values.contains_key("rest_url") || self.values.contains_key("stream_url"),
+        };
+        if custom {
+            if let (Some(rest), Some(stream)) = (rest, stream) {
+                builder = builder.environment(Environment::Custom { rest, stream });
+            } else {
+                for key in ["rest_url", "stream_url"] {
+                    // A present but invalid URL is already reported.
+                    if !self.values.contains_key(key) {
+                        self.missing(key, "for a custom environment");
+                    }
+                }
+            }
+        }
+
+        let token = self.parse("token", "a non-empty string", |v| {
+            str_of(v)
+                .filter(|s| !s.trim().is_empty())
+                .map(str::to_owned)
+        });
+        let token_file = self.parse("token_file", "a path", |v| str_of(v).map(str::to_owned));
+        let has_token = self.values.contains_key("token");
+        let has_token_file = self.values.contains_key("token_file");
+        match (token, token_file) {
+            (Some(_), Some(_)) => {
+                let (token, file) = (self.name("token"), self.name("token_file"));
+                self.problems
+                    .push(format!("set only one of `{token}` and `{file}`"));
+            }
+            (Some(token), None) => builder = builder.token(token),
+            (None, Some(path)) => builder = builder.token_provider(FileToken::new(path)),
+            (None, None) if !has_token && !has_token_file => {
+                let file = self.name("token_file");
+                self.missing("token", &format!("(or `{file}`)"));
+            }
+            (None, None) => {}
+        }
+
+        if let Some(account) =
+            self.parse("account_id", "a string", |v| str_of(v).map(str::to_owned))
+        {
+            builder = builder.default_account(account);
+        }
+        if let Some(format) = self.parse(
+            "datetime_format",
+            "`RFC3339` or `UNIX`",
+            |v| match str_of(v)?.to_ascii_uppercase().as_str() {
+                "RFC3339" => Some(AcceptDatetimeFormat::Rfc3339),
+                "UNIX" => Some(AcceptDatetimeFormat::Unix),
+                _ => None,
+            },
+        ) {
+            builder = builder.datetime_format(format);
+        }
+        if let Some(limit) = self.parse("rest_rate_limit", "a positive integer", |v| {
+            u32_of(v).filter(|n| *n > 0)
+        }) {
+            builder = builder.rest_rate_limit(limit);
+        }
+        if let Some(enabled) = self.parse("rate_limiting", "a boolean", bool_of) {
+            builder = builder.rate_limiting(enabled);
+        }
+        if let Some(timeout) = self.parse("timeout", "seconds", secs_of) {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.parse("connect_timeout", "seconds", secs_of) {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(retries) = self.parse("max_retries", "a non-negative integer", u32_of) {
+            builder = builder.max_retries(retries);
+        }
+        if let Some(backoff) = self.parse("retry_backoff", "seconds", secs_of) {
+            builder = builder.retry_backoff(backoff);
+        }
+        if let Some(enabled) = self.parse("stream_auto_reconnect", "a boolean", bool_of) {
+            builder = builder.stream_auto_reconnect(enabled);
+        }
+        if let Some(timeout) = self.parse("stream_heartbeat_timeout", "seconds", secs_of) {
+            builder = builder.stream_heartbeat_timeout(timeout);
+        }
+        let initial = self.parse("stream_backoff_initial", "seconds", secs_of);
+        let max = self.parse("stream_backoff_max", "seconds", secs_of);
+        if initial.is_some() || max.is_some() {
+            let defaults = StreamConfig::default();
+            builder = builder.stream_backoff(
+                initial.unwrap_or(defaults.backoff_initial),
+                max.unwrap_or(defaults.backoff_max),
+            );
+        }
+        if let Some(attempts) = self.parse(
+            "stream_max_reconnect_attempts",
+            "a non-negative integer",
+            u32_of,
+        ) {
+            builder = builder.stream_max_reconnect_attempts(attempts);
+        }
+
+        if self.problems.is_empty() {
+            Ok(builder)
+        } else {
+            Err(Error::Config(format!(
+                "invalid client configuration from {source}: {}",
+                self.problems.join("; ")
+            )))
+        }
+    }
+
+    /// How `key` is spelled in this source.
+    fn name(&self, key: &str) -> String {
+        if self.env {
+            env_name(key)
+        } else {
+            key.to_owned()
+        }
+    }
+
+    fn missing(&mut self, key: &str, context: &str) {
+        let name = self.name(key);
+        self.problems.push(format!("missing `{name}` {context}"));
+    }
+
+    /// The value of `key`, if set, converted by `parse`; records a problem
+    /// when it does not convert.
+    fn parse<T>(
+        &mut self,
+        key: &str,
+        expected: &str,
+        parse: impl FnOnce(&Value) -> Option<T>,
+    ) -> Option<T> {
+        let value = self.values.get(key)?;
+        let parsed = parse(value);
+        if parsed.is_none() {
+            let name = self.name(key);
+            self.problems
+                .push(format!("`{name}` must be {expected}, got {value}"));
+        }
+        parsed
+    }
+}
+
+fn str_of(value: &Value) -> Option<&str> {
+    value.as_str()
+}
+
+fn url_of(value: &Value) -> Option<Url> {
+    str_of(value)?.parse().ok()
+}
+
+fn u32_of(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn bool_of(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Some(true),
+            "false" | "0" | "no" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn secs_of(value: &Value) -> Option<Duration> {
+    let secs = match value {
+        Value::Number(n) => n.as_f64()?,
+        Value::String(s) => s.trim().parse().ok()?,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(values: Value) -> Settings {
+        let Value::Object(values) = values else {
+            unreachable!()
+        };
+        Settings {
+            values,
+            env: false,
+            problems: Vec::new(),
+        }
+    }
+
+    fn problems(values: Value) -> String {
+        match settings(values).into_builder("test") {
+            Err(Error::Config(message)) => message,
+            other => panic!("expected a config error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_native_and_string_values() {
+        let client = settings(serde_json::json!({
+            "token": "t",
+            "environment": "LIVE",
+            "account_id": "001-001-1234567-001",
+            "rest_rate_limit": "50",
+            "timeout": 2.5,
+            "max_retries": 3,
+            "rate_limiting": "true",
+        }))
+        .into_builder("test")
+        .unwrap()
+        .build()
+        .unwrap();
+        assert_eq!(
+            client.default_account().map(|id| id.as_str()),
+            Some("001-001-1234567-001")
+        );
+        assert_eq!(client.inner.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(client.inner.retry.max_retries, 3);
+    }
+
+    #[test]
+    fn lists_every_problem() {
+        let message = problems(serde_json::json!({
+            "environment": "staging",
+            "rest_rate_limit": 0,
+            "timeout": "soon",
+        }));
+        for expected in [
+            "`environment` must be `practice`, `live` or `custom`, got \"staging\"",
+            "`rest_rate_limit` must be a positive integer, got 0",
+            "`timeout` must be seconds, got \"soon\"",
+            "missing `token` (or `token_file`)",
+        ] {
+            assert!(
+                message.contains(expected),
+                "{expected:?} not in {message:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn custom_environment_needs_both_urls() {
+        let message = problems(serde_json::json!({
+            "token": "t",
+            "rest_url": "http://127.0.0.1:1",
+        }));
+        assert!(message.contains("missing `stream_url` for a custom environment"));
+        assert!(!message.contains("rest_url"));
+
+        let message = problems(serde_json::json!({
+            "token": "t",
+            "environment": "live",
+            "rest_url": "http://127.0.0.1:1",
+        }));
+        assert!(message.contains("`rest_url` needs `environment` = custom"));
+    }
+
+    #[test]
+    fn env_names_are_prefixed() {
+        let mut settings = settings(serde_json::json!({"max_retries": "many"}));
+        settings.env = true;
+        let Err(Error::Config(message)) = settings.into_builder("the environment") else {
+            panic!("expected a config error");
+        };
+        assert!(message.contains("`OANDA_MAX_RETRIES` must be"));
+        assert!(message.contains("missing `OANDA_TOKEN` (or `OANDA_TOKEN_FILE`)"));
+    }
+}
@@ -1,11 +1,14 @@
 //! Account endpoints: listing, summaries, tradeable instruments,
 //! configuration, and change polling.
 
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 
 use crate::client::Client;
 use crate::error::Error;
-use crate::models::transaction::{ClientConfigureRejectTransaction, ClientConfigureTransaction};
+use crate::models::transaction::{
+    ClientConfigureRejectTransaction, ClientConfigureTransaction, TransactionRejectReason,
+};
 use crate::models::{
     Account, AccountChanges, AccountChangesState, AccountId, AccountProperties, AccountSummary,
     DecimalNumber, Instrument, InstrumentName, TransactionId,
@@ -20,8 +23,19 @@ impl Client {
         &self,
         account_id: impl Into<AccountId>,
     ) -> Result<AccountResponse, Error> {
+        self.account_with_meta(account_id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`account`](Self::account), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn account_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<AccountResponse>, Error> {
         let account_id = account_id.into();
-        self.execute(self.get(&["accounts", account_id.as_str()]))
+        self.execute_with_meta(self.get(&["accounts", account_id.as_str()]))
             .await
     }
 
@@ -57,7 +71,17 @@ impl Client {
     /// # }
     /// ```
     pub async fn list_accounts(&self) -> Result<ListAccountsResponse, Error> {
-        self.execute(self.get(&["accounts"])).await
+        self.list_accounts_with_meta()
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`list_accounts`](Self::list_accounts), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn list_accounts_with_meta(
+        &self,
+    ) -> Result<crate::Response<ListAccountsResponse>, Error> {
+        self.execute_with_meta(self.get(&["accounts"])).await
     }
 
     /// Get a summary for a single account.
@@ -78,8 +102,19 @@ impl Client {
         &self,
         account_id: impl Into<AccountId>,
     ) -> Result<AccountSummaryResponse, Error> {
+        self.account_summary_with_meta(account_id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`account_summary`](Self::account_summary), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn account_summary_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<AccountSummaryResponse>, Error> {
         let account_id = account_id.into();
-        self.execute(self.get(&["accounts", account_id.as_str(), "summary"]))
+        self.execute_with_meta(self.get(&["accounts", account_id.as_str(), "summary"]))
             .await
     }
 
@@ -186,13 +221,23 @@ impl AccountChangesRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<AccountChangesResponse, Error> {
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<AccountChangesResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
         let mut request = self
             .client
             .get(&["accounts", self.account_id.as_str(), "changes"]);
         if let Some(id) = &self.since_transaction_id {
             request = request.query(&[("sinceTransactionID", id.as_str())]);
         }
-        self.client.execute(request).await
+        request
     }
 }
 
@@ -258,13 +303,25 @@ impl AccountInstrumentsRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<AccountInstrumentsResponse, Error> {
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(
+        self,
+    ) -> Result<crate::Response<AccountInstrumentsResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
         let mut request = self
             .client
             .get(&["accounts", self.account_id.as_str(), "instruments"]);
         if let Some(instruments) = &self.instruments {
             request = request.query(&[("instruments", join_names(instruments))]);
         }
-        self.client.execute(request).await
+        request
     }
 }
 
@@ -311,11 +368,19 @@ impl ConfigureAccountRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<ConfigureAccountResponse, Error> {
-        let request = self
-            .client
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<ConfigureAccountResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .patch(&["accounts", self.account_id.as_str(), "configuration"])
-            .json(&self.body);
-        self.client.execute(request).await
+            .json(&self.body)
     }
 }
 
@@ -351,6 +416,16 @@ pub struct ConfigureAccountRejectBody {
     pub last_transaction_id: Option<TransactionId>,
 }
 
+impl ConfigureAccountRejectBody {
+    /// Why OANDA rejected the request, when the reject transaction says.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        self.client_configure_reject_transaction
+            .as_ref()?
+            .reject_reason
+            .as_ref()
+    }
+}
+
 /// Joins instrument names into OANDA's comma-separated list format.
 pub(crate) fn join_names(instruments: &[InstrumentName]) -> String {
     instruments
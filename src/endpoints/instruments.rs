@@ -1,5 +1,6 @@
 //! Instrument endpoints: candlestick data and order/position books.
 
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 
 use crate::client::Client;
@@ -208,6 +209,16 @@ impl CandlesRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<InstrumentCandles, Error> {
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the response's
+    /// [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<InstrumentCandles>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
         let request = match &self.account_id {
             Some(account_id) => self.client.get(&[
                 "accounts",
@@ -220,7 +231,7 @@ impl CandlesRequest {
                 .client
                 .get(&["instruments", self.instrument.as_str(), "candles"]),
         };
-        self.client.execute(request.query(&self.params)).await
+        request.query(&self.params)
     }
 }
 
@@ -270,18 +281,26 @@ impl LatestCandlesRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<LatestCandlesResponse, Error> {
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the response's
+    /// [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<LatestCandlesResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
         let specs = self
             .specifications
             .iter()
             .map(ToString::to_string)
             .collect::<Vec<_>>()
             .join(",");
-        let request = self
-            .client
+        self.client
             .get(&["accounts", self.account_id.as_str(), "candles", "latest"])
             .query(&[("candleSpecifications", specs)])
-            .query(&self.params);
-        self.client.execute(request).await
+            .query(&self.params)
     }
 }
 
@@ -316,16 +335,22 @@ impl OrderBookRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<OrderBookResponse, Error> {
+        Ok(self.send_with_meta().await?.value)
+    }
+
+    /// Like [`send`](Self::send), but also returns the response's
+    /// [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<OrderBookResponse>, Error> {
         let mut request = self
             .client
             .get(&["instruments", self.instrument.as_str(), "orderBook"]);
         if let Some(time) = &self.time {
             request = request.query(&[("time", time.as_str())]);
         }
-        let (mut response, headers): (OrderBookResponse, _) =
-            self.client.execute_with_headers(request).await?;
-        response.link = crate::transport::header_str(&headers, "Link").map(str::to_owned);
-        Ok(response)
+        let response: crate::Response<OrderBookResponse> =
+            self.client.execute_with_meta(request).await?;
+        let link = crate::transport::header_str(&response.headers, "Link").map(str::to_owned);
+        Ok(response.map(|value| OrderBookResponse { link, ..value }))
     }
 }
 
@@ -360,16 +385,22 @@ impl PositionBookRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<PositionBookResponse, Error> {
+        Ok(self.send_with_meta().await?.value)
+    }
+
+    /// Like [`send`](Self::send), but also returns the response's
+    /// [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<PositionBookResponse>, Error> {
         let mut request =
             self.client
                 .get(&["instruments", self.instrument.as_str(), "positionBook"]);
         if let Some(time) = &self.time {
             request = request.query(&[("time", time.as_str())]);
         }
-        let (mut response, headers): (PositionBookResponse, _) =
-            self.client.execute_with_headers(request).await?;
-        response.link = crate::transport::header_str(&headers, "Link").map(str::to_owned);
-        Ok(response)
+        let response: crate::Response<PositionBookResponse> =
+            self.client.execute_with_meta(request).await?;
+        let link = crate::transport::header_str(&response.headers, "Link").map(str::to_owned);
+        Ok(response.map(|value| PositionBookResponse { link, ..value }))
     }
 }
 
@@ -9,6 +9,7 @@
 pub mod accounts;
 pub mod instruments;
 pub mod orders;
+pub mod paging;
 pub mod positions;
 pub mod pricing;
 pub mod trades;
@@ -1,13 +1,17 @@
 //! Order endpoints: creating, listing, replacing and cancelling orders.
 
+use std::time::Duration;
+
+use reqwest::{RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
 
 use crate::client::Client;
+use crate::endpoints::paging::{Fields, Paginated};
 use crate::error::Error;
 use crate::models::transaction::{
     OrderCancelRejectTransaction, OrderCancelTransaction,
     OrderClientExtensionsModifyRejectTransaction, OrderClientExtensionsModifyTransaction,
-    OrderFillTransaction, Transaction,
+    OrderFillTransaction, Transaction, TransactionRejectReason,
 };
 use crate::models::{
     AccountId, ClientExtensions, InstrumentName, Order, OrderId, OrderRequest, OrderSpecifier,
@@ -24,6 +28,8 @@ impl Client {
     /// Rejections (HTTP 400/404) carry an `orderRejectTransaction`; recover
     /// it with
     /// [`ApiErrorBody::details::<CreateOrderRejectBody>`](crate::ApiErrorBody::details).
+    /// An order refused by the client's [guards](crate::guard) fails with
+    /// [`Error::Guard`] without being sent.
     ///
     /// # Examples
     ///
@@ -49,16 +55,89 @@ impl Client {
         account_id: impl Into<AccountId>,
         order: impl Into<OrderRequest>,
     ) -> Result<CreateOrderResponse, Error> {
+        self.create_order_with_meta(account_id, order)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`create_order`](Self::create_order), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn create_order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderRequest>,
+    ) -> Result<crate::Response<CreateOrderResponse>, Error> {
         let account_id = account_id.into();
+        let order = order.into();
+        self.guard_order(&account_id, &order).await?;
         let request = self
             .post(&["accounts", account_id.as_str(), "orders"])
-            .json(&OrderRequestBody {
-                order: order.into(),
-            });
-        let (mut response, headers): (CreateOrderResponse, _) =
-            self.execute_with_headers(request).await?;
-        response.location = crate::transport::header_str(&headers, "Location").map(str::to_owned);
-        Ok(response)
+            .json(&OrderRequestBody { order });
+        let response: crate::Response<CreateOrderResponse> =
+            self.execute_with_meta(request).await?;
+        let location = response.location.clone();
+        Ok(response.map(|value| CreateOrderResponse { location, ..value }))
+    }
+
+    /// Create an order that can safely be retried after an ambiguous
+    /// failure.
+    ///
+    /// The order is tagged with `client_id` as its
+    /// [`ClientExtensions::id`]. When a submission fails without a definite
+    /// answer from OANDA — a transport error such as a timeout, an HTTP
+    /// 5xx, or a success response whose body could not be read — the order
+    /// is first looked up by its client ID and, failing that, among the
+    /// account's most recent transactions. It is only resubmitted when
+    /// neither finds it; a resubmission rejected with
+    /// `CLIENT_ORDER_ID_ALREADY_EXISTS` is resolved the same way.
+    ///
+    /// The client ID must be unique per order: reusing the ID of an
+    /// earlier order makes the lookup find that order instead.
+    ///
+    /// `POST /v3/accounts/{accountID}/orders`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), oanda_rs::Error> {
+    /// # let client = oanda_rs::Client::new(oanda_rs::Environment::Practice, "token");
+    /// use oanda_rs::models::MarketOrderRequest;
+    ///
+    /// let result = client
+    ///     .create_order_idempotent(
+    ///         "101-004-1234567-001",
+    ///         MarketOrderRequest::new("EUR_USD", 100),
+    ///         "entry-2024-06-14-001",
+    ///     )
+    ///     .send()
+    ///     .await?;
+    /// if result.recovered {
+    ///     println!("order was already placed: {:?}", result.response.order_create_transaction.id());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_order_idempotent(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderRequest>,
+        client_id: impl Into<String>,
+    ) -> CreateOrderIdempotentRequest {
+        let client_id = client_id.into();
+        let mut order = order.into();
+        let extensions = order
+            .client_extensions_mut()
+            .get_or_insert_with(ClientExtensions::new);
+        extensions.id = Some(client_id.clone());
+        CreateOrderIdempotentRequest {
+            client: self.clone(),
+            account_id: account_id.into(),
+            order,
+            client_id,
+            max_attempts: 3,
+            lookback: 100,
+            settle_delay: Duration::from_secs(1),
+        }
     }
 
     /// Get a list of orders for an account.
@@ -79,8 +158,19 @@ impl Client {
         &self,
         account_id: impl Into<AccountId>,
     ) -> Result<ListOrdersResponse, Error> {
+        self.list_pending_orders_with_meta(account_id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`list_pending_orders`](Self::list_pending_orders), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn list_pending_orders_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListOrdersResponse>, Error> {
         let account_id = account_id.into();
-        self.execute(self.get(&["accounts", account_id.as_str(), "pendingOrders"]))
+        self.execute_with_meta(self.get(&["accounts", account_id.as_str(), "pendingOrders"]))
             .await
     }
 
@@ -92,10 +182,27 @@ impl Client {
         account_id: impl Into<AccountId>,
         order: impl Into<OrderSpecifier>,
     ) -> Result<OrderResponse, Error> {
+        self.order_with_meta(account_id, order)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`order`](Self::order), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+    ) -> Result<crate::Response<OrderResponse>, Error> {
         let account_id = account_id.into();
         let order = order.into();
-        self.execute(self.get(&["accounts", account_id.as_str(), "orders", order.as_str()]))
-            .await
+        self.execute_with_meta(self.get(&[
+            "accounts",
+            account_id.as_str(),
+            "orders",
+            order.as_str(),
+        ]))
+        .await
     }
 
     /// Replace an order in an account by simultaneously cancelling it and
@@ -108,17 +215,30 @@ impl Client {
         order: impl Into<OrderSpecifier>,
         replacement: impl Into<OrderRequest>,
     ) -> Result<ReplaceOrderResponse, Error> {
+        self.replace_order_with_meta(account_id, order, replacement)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`replace_order`](Self::replace_order), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn replace_order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+        replacement: impl Into<OrderRequest>,
+    ) -> Result<crate::Response<ReplaceOrderResponse>, Error> {
         let account_id = account_id.into();
         let order = order.into();
+        let replacement = replacement.into();
+        self.guard_order(&account_id, &replacement).await?;
         let request = self
             .put(&["accounts", account_id.as_str(), "orders", order.as_str()])
-            .json(&OrderRequestBody {
-                order: replacement.into(),
-            });
-        let (mut response, headers): (ReplaceOrderResponse, _) =
-            self.execute_with_headers(request).await?;
-        response.location = crate::transport::header_str(&headers, "Location").map(str::to_owned);
-        Ok(response)
+            .json(&OrderRequestBody { order: replacement });
+        let response: crate::Response<ReplaceOrderResponse> =
+            self.execute_with_meta(request).await?;
+        let location = response.location.clone();
+        Ok(response.map(|value| ReplaceOrderResponse { location, ..value }))
     }
 
     /// Cancel a pending order in an account.
@@ -135,9 +255,21 @@ impl Client {
         account_id: impl Into<AccountId>,
         order: impl Into<OrderSpecifier>,
     ) -> Result<CancelOrderResponse, Error> {
+        self.cancel_order_with_meta(account_id, order)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`cancel_order`](Self::cancel_order), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn cancel_order_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        order: impl Into<OrderSpecifier>,
+    ) -> Result<crate::Response<CancelOrderResponse>, Error> {
         let account_id = account_id.into();
         let order = order.into();
-        self.execute(self.put(&[
+        self.execute_with_meta(self.put(&[
             "accounts",
             account_id.as_str(),
             "orders",
@@ -166,6 +298,7 @@ impl Client {
                 client_extensions: None,
                 trade_client_extensions: None,
             },
+            tag_prefix: None,
         }
     }
 }
@@ -248,6 +381,255 @@ pub struct CreateOrderRejectBody {
     pub last_transaction_id: Option<TransactionId>,
 }
 
+impl CreateOrderRejectBody {
+    /// Why OANDA rejected the request, when the reject transaction says.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        self.order_reject_transaction.as_ref()?.reject_reason()
+    }
+}
+
+/// Builder for [`Client::create_order_idempotent`].
+#[derive(Debug)]
+pub struct CreateOrderIdempotentRequest {
+    client: Client,
+    account_id: AccountId,
+    order: OrderRequest,
+    client_id: String,
+    max_attempts: u32,
+    lookback: u32,
+    settle_delay: Duration,
+}
+
+impl CreateOrderIdempotentRequest {
+    /// The maximum number of submissions (default 3, minimum 1).
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// How many of the account's most recent transactions are searched
+    /// when the lookup by client ID finds nothing (default 100).
+    pub fn lookback(mut self, transactions: u32) -> Self {
+        self.lookback = transactions;
+        self
+    }
+
+    /// How long to wait after an ambiguous failure before looking the
+    /// order up, giving a submission still in flight time to land
+    /// (default 1s).
+    pub fn settle_delay(mut self, delay: Duration) -> Self {
+        self.settle_delay = delay;
+        self
+    }
+
+    /// Performs the submission, looking the order up and resubmitting as
+    /// needed.
+    ///
+    /// # Errors
+    ///
+    /// Definite failures (e.g. an HTTP 400 rejection) are returned at
+    /// once. The last ambiguous failure is returned once `max_attempts`
+    /// submissions were made without finding the order. A failing lookup
+    /// is returned as is: the order is never resubmitted while its fate is
+    /// unknown.
+    pub async fn send(self) -> Result<IdempotentOrderResponse, Error> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let error = match self
+                .client
+                .create_order(self.account_id.clone(), self.order.clone())
+                .await
+            {
+                Ok(response) => {
+                    return Ok(IdempotentOrderResponse {
+                        response,
+                        recovered: false,
+                        attempts,
+                    });
+                }
+                Err(error) => error,
+            };
+            let duplicate = is_duplicate_client_id(&error);
+            if !duplicate && !is_ambiguous(&error) {
+                return Err(error);
+            }
+            tokio::time::sleep(self.settle_delay).await;
+            if let Some(response) = self.find_created().await? {
+                return Ok(IdempotentOrderResponse {
+                    response,
+                    recovered: true,
+                    attempts,
+                });
+            }
+            if duplicate || attempts >= self.max_attempts {
+                return Err(error);
+            }
+        }
+    }
+
+    /// Looks for the order by client ID, then among the most recent
+    /// transactions.
+    async fn find_created(&self) -> Result<Option<CreateOrderResponse>, Error> {
+        let specifier = OrderSpecifier::from_client_id(&self.client_id);
+        match self.client.order(self.account_id.clone(), specifier).await {
+            Ok(found) => return self.rebuild(&found.order).await,
+            Err(error) if error.status() == Some(StatusCode::NOT_FOUND) => {}
+            Err(error) => return Err(error),
+        }
+
+        let summary = self.client.account_summary(self.account_id.clone()).await?;
+        let Some(last) = summary
+            .last_transaction_id
+            .and_then(|id| id.as_str().parse::<u64>().ok())
+        else {
+            return Ok(None);
+        };
+        let from = last
+            .saturating_sub(u64::from(self.lookback.saturating_sub(1)))
+            .max(1);
+        let recent = self
+            .client
+            .transactions_id_range(self.account_id.clone(), from.to_string(), last.to_string())
+            .send()
+            .await?;
+        Ok(response_from_transactions(
+            &self.client_id,
+            recent.transactions,
+            recent.last_transaction_id,
+        ))
+    }
+
+    /// Rebuilds the create response of an order found by client ID from
+    /// its creating, filling and cancelling transactions.
+    async fn rebuild(&self, order: &Order) -> Result<Option<CreateOrderResponse>, Error> {
+        let Some(id) = order.id() else {
+            return Ok(None);
+        };
+        let created = self
+            .client
+            .transaction(self.account_id.clone(), id.0.clone())
+            .await?;
+        let mut related_transaction_ids = vec![TransactionId(id.0.clone())];
+        let mut last_transaction_id = created.last_transaction_id;
+        let mut order_fill_transaction = None;
+        let mut order_cancel_transaction = None;
+        for id in [
+            order.filling_transaction_id(),
+            order.cancelling_transaction_id(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let response = self
+                .client
+                .transaction(self.account_id.clone(), id.clone())
+                .await?;
+            related_transaction_ids.push(id.clone());
+            last_transaction_id = response.last_transaction_id.or(last_transaction_id);
+            match response.transaction {
+                Transaction::OrderFill(fill) => order_fill_transaction = Some(fill),
+                Transaction::OrderCancel(cancel) => order_cancel_transaction = Some(cancel),
+                _ => {}
+            }
+        }
+        Ok(Some(CreateOrderResponse {
+            order_create_transaction: created.transaction,
+            order_fill_transaction,
+            order_cancel_transaction,
+            order_reissue_transaction: None,
+            order_reissue_reject_transaction: None,
+            related_transaction_ids,
+            last_transaction_id,
+            location: None,
+        }))
+    }
+}
+
+/// Result of [`Client::create_order_idempotent`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct IdempotentOrderResponse {
+    /// The create response. When `recovered`, it is rebuilt from the
+    /// order's transactions (and `location` is unset).
+    pub response: CreateOrderResponse,
+    /// Whether the order was found after an ambiguous failure instead of
+    /// being confirmed by a successful submission.
+    pub recovered: bool,
+    /// How many submissions were made.
+    pub attempts: u32,
+}
+
+/// Whether a failed submission leaves open whether OANDA created the
+/// order.
+fn is_ambiguous(error: &Error) -> bool {
+    match error {
+        Error::Transport(_) | Error::Decode { .. } => true,
+        Error::Api { status, .. } => status.is_server_error(),
+        _ => false,
+    }
+}
+
+/// Whether OANDA rejected the order because its client ID is taken.
+fn is_duplicate_client_id(error: &Error) -> bool {
+    error.reject_reason() == Some(TransactionRejectReason::ClientOrderIdAlreadyExists)
+}
+
+/// The client ID of an order-creating transaction.
+fn created_client_id(transaction: &Transaction) -> Option<&str> {
+    let extensions = match transaction {
+        Transaction::MarketOrder(t) => &t.client_extensions,
+        Transaction::FixedPriceOrder(t) => &t.client_extensions,
+        Transaction::LimitOrder(t) => &t.client_extensions,
+        Transaction::StopOrder(t) => &t.client_extensions,
+        Transaction::MarketIfTouchedOrder(t) => &t.client_extensions,
+        Transaction::TakeProfitOrder(t) => &t.client_extensions,
+        Transaction::StopLossOrder(t) => &t.client_extensions,
+        Transaction::TrailingStopLossOrder(t) => &t.client_extensions,
+        _ => return None,
+    };
+    extensions.as_ref()?.id.as_deref()
+}
+
+/// Rebuilds a create response from a window of transactions containing
+/// the order's creation.
+fn response_from_transactions(
+    client_id: &str,
+    transactions: Vec<Transaction>,
+    last_transaction_id: Option<TransactionId>,
+) -> Option<CreateOrderResponse> {
+    let created = transactions
+        .iter()
+        .position(|tx| created_client_id(tx) == Some(client_id))?;
+    let order_id = transactions[created].id()?.0.clone();
+    let mut related_transaction_ids = vec![TransactionId(order_id.clone())];
+    let mut order_fill_transaction = None;
+    let mut order_cancel_transaction = None;
+    for tx in &transactions[created + 1..] {
+        let is_order = |id: &Option<OrderId>| id.as_ref().is_some_and(|id| id.0 == order_id);
+        match tx {
+            Transaction::OrderFill(fill) if is_order(&fill.order_id) => {
+                order_fill_transaction = Some(fill.clone());
+            }
+            Transaction::OrderCancel(cancel) if is_order(&cancel.order_id) => {
+                order_cancel_transaction = Some(cancel.clone());
+            }
+            _ => continue,
+        }
+        related_transaction_ids.extend(tx.id().cloned());
+    }
+    Some(CreateOrderResponse {
+        order_create_transaction: transactions[created].clone(),
+        order_fill_transaction,
+        order_cancel_transaction,
+        order_reissue_transaction: None,
+        order_reissue_reject_transaction: None,
+        related_transaction_ids,
+        last_transaction_id,
+        location: None,
+    })
+}
+
 /// Builder for [`Client::list_orders`].
 #[derive(Debug)]
 pub struct ListOrdersRequest {
@@ -300,11 +682,45 @@ impl ListOrdersRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<ListOrdersResponse, Error> {
-        let request = self
-            .client
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<ListOrdersResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    /// Streams every matching order, newest first, paging backwards with
+    /// `before_id` until the history is exhausted; `count` sets the page
+    /// size (500 when unset). See [`Paginated`] for client-side filters.
+    pub fn into_stream(self) -> Paginated<Order> {
+        let fields = Fields {
+            id: |order: &Order| order.id().map(OrderId::as_str),
+            tag: |order: &Order| order.client_extensions()?.tag.as_deref(),
+            time: Order::create_time,
+        };
+        let ListOrdersRequest {
+            client,
+            account_id,
+            params,
+        } = self;
+        Paginated::new(params, fields, move |params| {
+            let request = client
+                .get(&["accounts", account_id.as_str(), "orders"])
+                .query(&params);
+            let client = client.clone();
+            async move {
+                let response: ListOrdersResponse = client.execute(request).await?;
+                Ok(response.orders)
+            }
+        })
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .get(&["accounts", self.account_id.as_str(), "orders"])
-            .query(&self.params);
-        self.client.execute(request).await
+            .query(&self.params)
     }
 }
 
@@ -435,6 +851,16 @@ pub struct CancelOrderRejectBody {
     pub last_transaction_id: Option<TransactionId>,
 }
 
+impl CancelOrderRejectBody {
+    /// Why OANDA rejected the request, when the reject transaction says.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        self.order_cancel_reject_transaction
+            .as_ref()?
+            .reject_reason
+            .as_ref()
+    }
+}
+
 /// Builder for [`Client::set_order_client_extensions`].
 #[derive(Debug)]
 pub struct SetOrderClientExtensionsRequest {
@@ -442,6 +868,7 @@ pub struct SetOrderClientExtensionsRequest {
     account_id: AccountId,
     order: OrderSpecifier,
     body: SetOrderClientExtensionsBody,
+    tag_prefix: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -457,22 +884,46 @@ struct SetOrderClientExtensionsBody {
 
 impl SetOrderClientExtensionsRequest {
     /// The client extensions to update for the order.
-    pub fn client_extensions(mut self, extensions: ClientExtensions) -> Self {
+    pub fn client_extensions(mut self, mut extensions: ClientExtensions) -> Self {
+        if let Some(prefix) = &self.tag_prefix {
+            extensions.prefix_tag(prefix);
+        }
         self.body.client_extensions = Some(extensions);
         self
     }
 
     /// The client extensions to update for the trade created when the
     /// order fills.
-    pub fn trade_client_extensions(mut self, extensions: ClientExtensions) -> Self {
+    pub fn trade_client_extensions(mut self, mut extensions: ClientExtensions) -> Self {
+        if let Some(prefix) = &self.tag_prefix {
+            extensions.prefix_tag(prefix);
+        }
         self.body.trade_client_extensions = Some(extensions);
         self
     }
 
+    /// Prefixes the tags of the extensions set afterwards (see
+    /// [`AccountClient::tag_prefix`](crate::account::AccountClient::tag_prefix)).
+    pub(crate) fn tag_prefix(mut self, prefix: Option<String>) -> Self {
+        self.tag_prefix = prefix;
+        self
+    }
+
     /// Performs the request.
     pub async fn send(self) -> Result<SetOrderClientExtensionsResponse, Error> {
-        let request = self
-            .client
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(
+        self,
+    ) -> Result<crate::Response<SetOrderClientExtensionsResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .put(&[
                 "accounts",
                 self.account_id.as_str(),
@@ -480,8 +931,7 @@ impl SetOrderClientExtensionsRequest {
                 self.order.as_str(),
                 "clientExtensions",
             ])
-            .json(&self.body);
-        self.client.execute(request).await
+            .json(&self.body)
     }
 }
 
@@ -531,3 +981,13 @@ pub struct SetOrderClientExtensionsRejectBody {
     #[serde(rename = "lastTransactionID", skip_serializing_if = "Option::is_none")]
     pub last_transaction_id: Option<TransactionId>,
 }
+
+impl SetOrderClientExtensionsRejectBody {
+    /// Why OANDA rejected the request, when the reject transaction says.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        self.order_client_extensions_modify_reject_transaction
+            .as_ref()?
+            .reject_reason
+            .as_ref()
+    }
+}
@@ -0,0 +1,240 @@
+//! Walking the full history of list endpoints that page backwards by ID.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use chrono::Utc;
+use futures_core::Stream;
+use futures_core::stream::BoxStream;
+use futures_util::StreamExt;
+
+use crate::error::Error;
+use crate::models::DateTime;
+
+/// Page size used when the builder did not set a `count`: the maximum
+/// OANDA allows.
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Query parameters of a list request; `count` and `beforeID` are
+/// rewritten for each page.
+type Params = Vec<(&'static str, String)>;
+
+/// A stream over every item of a list endpoint, newest first, fetching
+/// further pages (through the client's rate limiter) as it is polled.
+///
+/// Returned by
+/// [`ListTradesRequest::into_stream`](crate::endpoints::trades::ListTradesRequest::into_stream)
+/// and
+/// [`ListOrdersRequest::into_stream`](crate::endpoints::orders::ListOrdersRequest::into_stream).
+/// Each page asks for the builder's `count` items (500 when unset) with a
+/// `beforeID` just below the oldest item seen so far; the stream ends after
+/// a short or empty page. A request failure is yielded once and ends the
+/// stream.
+///
+/// The filters below are applied client-side, on top of the builder's
+/// server-side filters.
+///
+/// ```no_run
+/// # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+/// use futures_util::TryStreamExt;
+/// use oanda_rs::models::TradeStateFilter;
+///
+/// let trades: Vec<_> = client
+///     .list_trades("101-004-1234567-001")
+///     .state(TradeStateFilter::Closed)
+///     .into_stream()
+///     .tag("breakout")
+///     .between("2024-06-01T00:00:00Z", "2024-07-01T00:00:00Z")?
+///     .try_collect()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Paginated<T> {
+    pages: BoxStream<'static, Result<Vec<T>, Error>>,
+    buffer: std::vec::IntoIter<T>,
+    done: bool,
+    tag: Option<String>,
+    from: Option<chrono::DateTime<Utc>>,
+    to: Option<chrono::DateTime<Utc>>,
+    tag_of: fn(&T) -> Option<&str>,
+    time_of: fn(&T) -> Option<&DateTime>,
+}
+
+impl<T> std::fmt::Debug for Paginated<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Paginated")
+            .field("done", &self.done)
+            .field("tag", &self.tag)
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Accessors the stream needs on the items it pages through.
+pub(crate) struct Fields<T> {
+    pub(crate) id: fn(&T) -> Option<&str>,
+    pub(crate) tag: fn(&T) -> Option<&str>,
+    pub(crate) time: fn(&T) -> Option<&DateTime>,
+}
+
+impl<T: Send + 'static> Paginated<T> {
+    /// Pages through `fetch`, which performs the list request with the
+    /// given query parameters.
+    pub(crate) fn new<F, Fut>(params: Params, fields: Fields<T>, fetch: F) -> Self
+    where
+        F: FnMut(Params) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Vec<T>, Error>> + Send + 'static,
+    {
+        let mut base = Vec::with_capacity(params.len());
+        let mut page_size = MAX_PAGE_SIZE;
+        let mut before = None;
+        for (key, value) in params {
+            match key {
+                "count" => page_size = value.parse().unwrap_or(MAX_PAGE_SIZE),
+                "beforeID" => before = Some(value),
+                _ => base.push((key, value)),
+            }
+        }
+        let cursor = Cursor {
+            fetch,
+            base,
+            page_size: page_size.max(1),
+            before,
+            skip_before: false,
+            exhausted: false,
+        };
+        let id_of = fields.id;
+        let pages = futures_util::stream::try_unfold(cursor, move |mut cursor| async move {
+            if cursor.exhausted {
+                return Ok(None);
+            }
+            let mut params = cursor.base.clone();
+            params.push(("count", cursor.page_size.to_string()));
+            if let Some(before) = &cursor.before {
+                params.push(("beforeID", before.clone()));
+            }
+            let mut page = (cursor.fetch)(params).await?;
+            cursor.exhausted = page.len() < cursor.page_size as usize;
+            // `beforeID` is inclusive: drop the item already yielded
+            if cursor.skip_before && page.first().and_then(id_of) == cursor.before.as_deref() {
+                page.remove(0);
+            }
+            let Some(oldest) = page.last().and_then(id_of) else {
+                return Ok(None);
+            };
+            cursor.before = Some(oldest.to_owned());
+            cursor.skip_before = true;
+            Ok(Some((page, cursor)))
+        });
+        Paginated {
+            pages: pages.boxed(),
+            buffer: Vec::new().into_iter(),
+            done: false,
+            tag: None,
+            from: None,
+            to: None,
+            tag_of: fields.tag,
+            time_of: fields.time,
+        }
+    }
+}
+
+impl<T> Paginated<T> {
+    /// Only yields items whose client extensions carry this tag.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Only yields items created (trades: opened) from `from` (inclusive)
+    /// to `to` (exclusive). Paging stops at the first item older than
+    /// `from`.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Config`] if either bound is neither an RFC 3339 nor a UNIX
+    /// time.
+    pub fn between(
+        mut self,
+        from: impl Into<DateTime>,
+        to: impl Into<DateTime>,
+    ) -> Result<Self, Error> {
+        let parse = |time: DateTime| {
+            time.to_utc()
+                .ok_or_else(|| Error::Config(format!("invalid time bound {:?}", time.as_str())))
+        };
+        self.from = Some(parse(from.into())?);
+        self.to = Some(parse(to.into())?);
+        Ok(self)
+    }
+
+    /// Whether `item` passes the filters; `None` when it is older than the
+    /// window, so that nothing after it can match either.
+    fn accepts(&self, item: &T) -> Option<bool> {
+        if self.from.is_some() || self.to.is_some() {
+            let Some(time) = (self.time_of)(item).and_then(DateTime::to_utc) else {
+                return Some(false);
+            };
+            if self.from.is_some_and(|from| time < from) {
+                return None;
+            }
+            if self.to.is_some_and(|to| time >= to) {
+                return Some(false);
+            }
+        }
+        Some(match &self.tag {
+            Some(tag) => (self.tag_of)(item) == Some(tag.as_str()),
+            None => true,
+        })
+    }
+}
+
+// Items are only buffered, never pinned.
+impl<T> Unpin for Paginated<T> {}
+
+impl<T> Stream for Paginated<T> {
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if let Some(item) = this.buffer.next() {
+                match this.accepts(&item) {
+                    Some(true) => return Poll::Ready(Some(Ok(item))),
+                    Some(false) => continue,
+                    None => {
+                        this.done = true;
+                        continue;
+                    }
+                }
+            }
+            match this.pages.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(page))) => this.buffer = page.into_iter(),
+                Poll::Ready(Some(Err(error))) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(error)));
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Paging state threaded through the page stream.
+struct Cursor<F> {
+    fetch: F,
+    base: Params,
+    page_size: u32,
+    /// `beforeID` of the next page.
+    before: Option<String>,
+    /// Whether `before` is the ID of an item already yielded.
+    skip_before: bool,
+    exhausted: bool,
+}
@@ -1,12 +1,13 @@
 //! Position endpoints: listing and closing positions.
 
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 
 use crate::client::Client;
 use crate::error::Error;
 use crate::models::transaction::{
     MarketOrderRejectTransaction, MarketOrderTransaction, OrderCancelTransaction,
-    OrderFillTransaction,
+    OrderFillTransaction, TransactionRejectReason,
 };
 use crate::models::{AccountId, ClientExtensions, InstrumentName, Position, TransactionId};
 
@@ -20,8 +21,19 @@ impl Client {
         &self,
         account_id: impl Into<AccountId>,
     ) -> Result<ListPositionsResponse, Error> {
+        self.list_positions_with_meta(account_id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`list_positions`](Self::list_positions), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn list_positions_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListPositionsResponse>, Error> {
         let account_id = account_id.into();
-        self.execute(self.get(&["accounts", account_id.as_str(), "positions"]))
+        self.execute_with_meta(self.get(&["accounts", account_id.as_str(), "positions"]))
             .await
     }
 
@@ -33,8 +45,19 @@ impl Client {
         &self,
         account_id: impl Into<AccountId>,
     ) -> Result<ListPositionsResponse, Error> {
+        self.list_open_positions_with_meta(account_id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`list_open_positions`](Self::list_open_positions), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn list_open_positions_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListPositionsResponse>, Error> {
         let account_id = account_id.into();
-        self.execute(self.get(&["accounts", account_id.as_str(), "openPositions"]))
+        self.execute_with_meta(self.get(&["accounts", account_id.as_str(), "openPositions"]))
             .await
     }
 
@@ -46,9 +69,21 @@ impl Client {
         account_id: impl Into<AccountId>,
         instrument: impl Into<InstrumentName>,
     ) -> Result<PositionResponse, Error> {
+        self.position_with_meta(account_id, instrument)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`position`](Self::position), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn position_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        instrument: impl Into<InstrumentName>,
+    ) -> Result<crate::Response<PositionResponse>, Error> {
         let account_id = account_id.into();
         let instrument = instrument.into();
-        self.execute(self.get(&[
+        self.execute_with_meta(self.get(&[
             "accounts",
             account_id.as_str(),
             "positions",
@@ -181,8 +216,19 @@ impl ClosePositionRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<ClosePositionResponse, Error> {
-        let request = self
-            .client
+        self.client.guard_close(Some(&self.instrument))?;
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<ClosePositionResponse>, Error> {
+        self.client.guard_close(Some(&self.instrument))?;
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .put(&[
                 "accounts",
                 self.account_id.as_str(),
@@ -190,8 +236,7 @@ impl ClosePositionRequest {
                 self.instrument.as_str(),
                 "close",
             ])
-            .json(&self.body);
-        self.client.execute(request).await
+            .json(&self.body)
     }
 }
 
@@ -276,3 +321,14 @@ pub struct ClosePositionRejectBody {
     #[serde(rename = "lastTransactionID", skip_serializing_if = "Option::is_none")]
     pub last_transaction_id: Option<TransactionId>,
 }
+
+impl ClosePositionRejectBody {
+    /// Why OANDA rejected the request: the reason of the long side's
+    /// reject transaction, or else the short side's.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        self.long_order_reject_transaction
+            .iter()
+            .chain(&self.short_order_reject_transaction)
+            .find_map(|t| t.reject_reason.as_ref())
+    }
+}
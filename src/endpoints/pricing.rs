@@ -1,5 +1,6 @@
 //! Pricing endpoints: current prices and the pricing stream.
 
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 
 use crate::client::Client;
@@ -94,7 +95,7 @@ impl Client {
             account_id: account_id.into(),
             instruments: instruments.into_iter().map(Into::into).collect(),
             snapshot: None,
-            config: StreamConfig::default(),
+            config: self.inner.stream_config.clone(),
         }
     }
 }
@@ -125,15 +126,23 @@ impl PricesRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<PricesResponse, Error> {
-        let request = self
-            .client
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the response's
+    /// [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<PricesResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .get(&["accounts", self.account_id.as_str(), "pricing"])
             .query(&[(
                 "instruments",
                 super::accounts::join_names(&self.instruments),
             )])
-            .query(&self.params);
-        self.client.execute(request).await
+            .query(&self.params)
     }
 }
 
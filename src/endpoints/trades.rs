@@ -1,8 +1,10 @@
 //! Trade endpoints: listing, closing, and modifying open trades.
 
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 
 use crate::client::Client;
+use crate::endpoints::paging::{Fields, Paginated};
 use crate::error::Error;
 use crate::models::transaction::{
     MarketOrderRejectTransaction, MarketOrderTransaction, OrderCancelRejectTransaction,
@@ -10,6 +12,7 @@ use crate::models::transaction::{
     StopLossOrderTransaction, TakeProfitOrderRejectTransaction, TakeProfitOrderTransaction,
     TradeClientExtensionsModifyRejectTransaction, TradeClientExtensionsModifyTransaction,
     TrailingStopLossOrderRejectTransaction, TrailingStopLossOrderTransaction,
+    TransactionRejectReason,
 };
 use crate::models::{
     AccountId, ClientExtensions, InstrumentName, StopLossDetails, TakeProfitDetails, Trade,
@@ -35,8 +38,19 @@ impl Client {
         &self,
         account_id: impl Into<AccountId>,
     ) -> Result<ListTradesResponse, Error> {
+        self.list_open_trades_with_meta(account_id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`list_open_trades`](Self::list_open_trades), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn list_open_trades_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> Result<crate::Response<ListTradesResponse>, Error> {
         let account_id = account_id.into();
-        self.execute(self.get(&["accounts", account_id.as_str(), "openTrades"]))
+        self.execute_with_meta(self.get(&["accounts", account_id.as_str(), "openTrades"]))
             .await
     }
 
@@ -48,10 +62,27 @@ impl Client {
         account_id: impl Into<AccountId>,
         trade: impl Into<TradeSpecifier>,
     ) -> Result<TradeResponse, Error> {
+        self.trade_with_meta(account_id, trade)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`trade`](Self::trade), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn trade_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+    ) -> Result<crate::Response<TradeResponse>, Error> {
         let account_id = account_id.into();
         let trade = trade.into();
-        self.execute(self.get(&["accounts", account_id.as_str(), "trades", trade.as_str()]))
-            .await
+        self.execute_with_meta(self.get(&[
+            "accounts",
+            account_id.as_str(),
+            "trades",
+            trade.as_str(),
+        ]))
+        .await
     }
 
     /// Close (partially or fully) a specific open trade in an account.
@@ -99,6 +130,19 @@ impl Client {
         trade: impl Into<TradeSpecifier>,
         extensions: ClientExtensions,
     ) -> Result<SetTradeClientExtensionsResponse, Error> {
+        self.set_trade_client_extensions_with_meta(account_id, trade, extensions)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`set_trade_client_extensions`](Self::set_trade_client_extensions), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn set_trade_client_extensions_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        trade: impl Into<TradeSpecifier>,
+        extensions: ClientExtensions,
+    ) -> Result<crate::Response<SetTradeClientExtensionsResponse>, Error> {
         let account_id = account_id.into();
         let trade = trade.into();
         let request = self
@@ -112,7 +156,7 @@ impl Client {
             .json(&SetTradeClientExtensionsBody {
                 client_extensions: extensions,
             });
-        self.execute(request).await
+        self.execute_with_meta(request).await
     }
 
     /// Create, replace and cancel the dependent orders (take-profit,
@@ -193,11 +237,45 @@ impl ListTradesRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<ListTradesResponse, Error> {
-        let request = self
-            .client
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<ListTradesResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    /// Streams every matching trade, newest first, paging backwards with
+    /// `before_id` until the history is exhausted; `count` sets the page
+    /// size (500 when unset). See [`Paginated`] for client-side filters.
+    pub fn into_stream(self) -> Paginated<Trade> {
+        let fields = Fields {
+            id: |trade: &Trade| trade.id.as_ref().map(TradeId::as_str),
+            tag: |trade: &Trade| trade.client_extensions.as_ref()?.tag.as_deref(),
+            time: |trade: &Trade| trade.open_time.as_ref(),
+        };
+        let ListTradesRequest {
+            client,
+            account_id,
+            params,
+        } = self;
+        Paginated::new(params, fields, move |params| {
+            let request = client
+                .get(&["accounts", account_id.as_str(), "trades"])
+                .query(&params);
+            let client = client.clone();
+            async move {
+                let response: ListTradesResponse = client.execute(request).await?;
+                Ok(response.trades)
+            }
+        })
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .get(&["accounts", self.account_id.as_str(), "trades"])
-            .query(&self.params);
-        self.client.execute(request).await
+            .query(&self.params)
     }
 }
 
@@ -235,9 +313,9 @@ pub struct CloseTradeRequest {
 }
 
 #[derive(Debug, Serialize)]
-struct CloseTradeBody {
+struct CloseTradeBody<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
-    units: Option<String>,
+    units: Option<&'a str>,
 }
 
 impl CloseTradeRequest {
@@ -250,8 +328,19 @@ impl CloseTradeRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<CloseTradeResponse, Error> {
-        let request = self
-            .client
+        self.client.guard_close(None)?;
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<CloseTradeResponse>, Error> {
+        self.client.guard_close(None)?;
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .put(&[
                 "accounts",
                 self.account_id.as_str(),
@@ -259,8 +348,9 @@ impl CloseTradeRequest {
                 self.trade.as_str(),
                 "close",
             ])
-            .json(&CloseTradeBody { units: self.units });
-        self.client.execute(request).await
+            .json(&CloseTradeBody {
+                units: self.units.as_deref(),
+            })
     }
 }
 
@@ -323,6 +413,16 @@ pub struct CloseTradeRejectBody {
     pub last_transaction_id: Option<TransactionId>,
 }
 
+impl CloseTradeRejectBody {
+    /// Why OANDA rejected the request, when the reject transaction says.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        self.order_reject_transaction
+            .as_ref()?
+            .reject_reason
+            .as_ref()
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct SetTradeClientExtensionsBody {
     #[serde(rename = "clientExtensions")]
@@ -376,6 +476,16 @@ pub struct SetTradeClientExtensionsRejectBody {
     pub last_transaction_id: Option<TransactionId>,
 }
 
+impl SetTradeClientExtensionsRejectBody {
+    /// Why OANDA rejected the request, when the reject transaction says.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        self.trade_client_extensions_modify_reject_transaction
+            .as_ref()?
+            .reject_reason
+            .as_ref()
+    }
+}
+
 /// Builder for [`Client::set_trade_dependent_orders`].
 #[derive(Debug)]
 pub struct SetTradeDependentOrdersRequest {
@@ -434,8 +544,19 @@ impl SetTradeDependentOrdersRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<SetTradeDependentOrdersResponse, Error> {
-        let request = self
-            .client
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(
+        self,
+    ) -> Result<crate::Response<SetTradeDependentOrdersResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .put(&[
                 "accounts",
                 self.account_id.as_str(),
@@ -443,8 +564,7 @@ impl SetTradeDependentOrdersRequest {
                 self.trade.as_str(),
                 "orders",
             ])
-            .json(&self.body);
-        self.client.execute(request).await
+            .json(&self.body)
     }
 }
 
@@ -576,3 +696,33 @@ pub struct SetTradeDependentOrdersRejectBody {
     #[serde(rename = "lastTransactionID", skip_serializing_if = "Option::is_none")]
     pub last_transaction_id: Option<TransactionId>,
 }
+
+impl SetTradeDependentOrdersRejectBody {
+    /// Why OANDA rejected the request: the reason of the first reject
+    /// transaction present, in the order the fields are declared.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        [
+            self.take_profit_order_cancel_reject_transaction
+                .as_ref()
+                .and_then(|t| t.reject_reason.as_ref()),
+            self.take_profit_order_reject_transaction
+                .as_ref()
+                .and_then(|t| t.reject_reason.as_ref()),
+            self.stop_loss_order_cancel_reject_transaction
+                .as_ref()
+                .and_then(|t| t.reject_reason.as_ref()),
+            self.stop_loss_order_reject_transaction
+                .as_ref()
+                .and_then(|t| t.reject_reason.as_ref()),
+            self.trailing_stop_loss_order_cancel_reject_transaction
+                .as_ref()
+                .and_then(|t| t.reject_reason.as_ref()),
+            self.trailing_stop_loss_order_reject_transaction
+                .as_ref()
+                .and_then(|t| t.reject_reason.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+    }
+}
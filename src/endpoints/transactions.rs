@@ -1,6 +1,7 @@
 //! Transaction endpoints: paging, lookup, and range queries over an
 //! account's transaction history.
 
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 
 use crate::client::Client;
@@ -34,9 +35,21 @@ impl Client {
         account_id: impl Into<AccountId>,
         transaction_id: impl Into<TransactionId>,
     ) -> Result<TransactionResponse, Error> {
+        self.transaction_with_meta(account_id, transaction_id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`transaction`](Self::transaction), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn transaction_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        transaction_id: impl Into<TransactionId>,
+    ) -> Result<crate::Response<TransactionResponse>, Error> {
         let account_id = account_id.into();
         let transaction_id = transaction_id.into();
-        self.execute(self.get(&[
+        self.execute_with_meta(self.get(&[
             "accounts",
             account_id.as_str(),
             "transactions",
@@ -103,7 +116,7 @@ impl Client {
         TransactionStreamRequest {
             client: self.clone(),
             account_id: account_id.into(),
-            config: StreamConfig::default(),
+            config: self.inner.stream_config.clone(),
         }
     }
 
@@ -119,12 +132,24 @@ impl Client {
         account_id: impl Into<AccountId>,
         id: impl Into<TransactionId>,
     ) -> Result<TransactionsResponse, Error> {
+        self.transactions_since_id_with_meta(account_id, id)
+            .await
+            .map(|response| response.value)
+    }
+
+    /// Like [`transactions_since_id`](Self::transactions_since_id), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn transactions_since_id_with_meta(
+        &self,
+        account_id: impl Into<AccountId>,
+        id: impl Into<TransactionId>,
+    ) -> Result<crate::Response<TransactionsResponse>, Error> {
         let account_id = account_id.into();
         let id = id.into();
         let request = self
             .get(&["accounts", account_id.as_str(), "transactions", "sinceid"])
             .query(&[("id", id.as_str())]);
-        self.execute(request).await
+        self.execute_with_meta(request).await
     }
 }
 
@@ -198,11 +223,19 @@ impl ListTransactionsRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<ListTransactionsResponse, Error> {
-        let request = self
-            .client
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<ListTransactionsResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
+        self.client
             .get(&["accounts", self.account_id.as_str(), "transactions"])
-            .query(&self.params);
-        self.client.execute(request).await
+            .query(&self.params)
     }
 }
 
@@ -268,6 +301,16 @@ impl TransactionsIdRangeRequest {
 
     /// Performs the request.
     pub async fn send(self) -> Result<TransactionsResponse, Error> {
+        self.client.execute(self.build()).await
+    }
+
+    /// Like [`send`](Self::send), but also returns the
+    /// response's [metadata](crate::Response).
+    pub async fn send_with_meta(self) -> Result<crate::Response<TransactionsResponse>, Error> {
+        self.client.execute_with_meta(self.build()).await
+    }
+
+    fn build(&self) -> RequestBuilder {
         let mut request = self
             .client
             .get(&[
@@ -285,7 +328,7 @@ impl TransactionsIdRangeRequest {
                 .join(",");
             request = request.query(&[("type", joined)]);
         }
-        self.client.execute(request).await
+        request
     }
 }
 
@@ -4,6 +4,9 @@ use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use crate::guard::GuardViolation;
+use crate::models::transaction::TransactionRejectReason;
+
 /// The unified error type returned by every SDK operation.
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -43,6 +46,16 @@ pub enum Error {
     /// The client was configured with invalid values.
     #[error("invalid client configuration: {0}")]
     Config(String),
+
+    /// An order could not be composed from the given parameters (e.g. a
+    /// take-profit on the wrong side of the entry); nothing was sent.
+    #[error("invalid order: {0}")]
+    InvalidOrder(String),
+
+    /// The client's [`Guards`](crate::guard::Guards) refused the request;
+    /// nothing was sent.
+    #[error("refused by guards: {0}")]
+    Guard(#[from] GuardViolation),
 }
 
 impl Error {
@@ -63,6 +76,41 @@ impl Error {
         }
     }
 
+    /// Classifies the error for retry decisions; see [`ErrorKind`].
+    ///
+    /// ```no_run
+    /// # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+    /// use oanda_rs::ErrorKind;
+    ///
+    /// let accounts = loop {
+    ///     match client.list_accounts().await {
+    ///         Err(e) if e.kind().is_retryable() => {
+    ///             tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    ///         }
+    ///         Err(e) if e.kind() == ErrorKind::Unauthorized => panic!("check the token"),
+    ///         other => break other?,
+    ///     }
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Api { status, .. } => ErrorKind::from_status(*status),
+            Error::Transport(e) => match e.status() {
+                Some(status) => ErrorKind::from_status(status),
+                None if e.is_builder() => ErrorKind::Config,
+                None if e.is_decode() => ErrorKind::Decode,
+                // timeouts, refused or reset connections, interrupted bodies
+                None => ErrorKind::Transient,
+            },
+            Error::Decode { .. } => ErrorKind::Decode,
+            Error::Stream(_) => ErrorKind::Transient,
+            Error::Config(_) => ErrorKind::Config,
+            Error::InvalidOrder(_) | Error::Guard(_) => ErrorKind::Rejected,
+        }
+    }
+
     /// Whether this error was caused by OANDA's rate limiting (HTTP 429).
     ///
     /// OANDA allows 120 REST requests per second and 2 new connections per
@@ -73,6 +121,77 @@ impl Error {
     pub fn is_rate_limited(&self) -> bool {
         self.status() == Some(StatusCode::TOO_MANY_REQUESTS)
     }
+
+    /// Why OANDA rejected the request, for API errors that carry a reject
+    /// reason (see [`ApiErrorBody::reason`]).
+    ///
+    /// ```no_run
+    /// # async fn run(client: oanda_rs::Client, account: oanda_rs::models::AccountId,
+    /// #     order: oanda_rs::models::OrderRequest) -> Result<(), oanda_rs::Error> {
+    /// match client.create_order(account, order).await {
+    ///     Err(e) if e.reject_reason().is_some_and(|r| r.is_insufficient_margin()) => {
+    ///         // shrink the order and try again
+    ///     }
+    ///     other => {
+    ///         other?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reject_reason(&self) -> Option<TransactionRejectReason> {
+        match self {
+            Error::Api { body, .. } => body.reason(),
+            _ => None,
+        }
+    }
+}
+
+/// A coarse classification of an [`Error`], returned by [`Error::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A temporary failure: a timeout, a refused or dropped connection, a
+    /// server error (HTTP 5xx or 408) or a broken stream. Retrying with
+    /// backoff may succeed.
+    Transient,
+    /// OANDA is rate limiting requests (HTTP 429); retry after a delay.
+    RateLimited,
+    /// The token is invalid or lacks access to the account (HTTP 401 or
+    /// 403).
+    Unauthorized,
+    /// The account, order, trade or other resource does not exist (HTTP
+    /// 404).
+    NotFound,
+    /// OANDA rejected the request (any other HTTP 4xx), or the SDK refused
+    /// to send it; it must be corrected before being sent again. See
+    /// [`Error::reject_reason`].
+    Rejected,
+    /// A response body could not be decoded.
+    Decode,
+    /// The client is misconfigured.
+    Config,
+}
+
+impl ErrorKind {
+    /// Whether sending the same request again may succeed:
+    /// [`Transient`](ErrorKind::Transient) and
+    /// [`RateLimited`](ErrorKind::RateLimited) errors.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, ErrorKind::Transient | ErrorKind::RateLimited)
+    }
+
+    pub(crate) fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => ErrorKind::RateLimited,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::Unauthorized,
+            StatusCode::NOT_FOUND => ErrorKind::NotFound,
+            StatusCode::REQUEST_TIMEOUT => ErrorKind::Transient,
+            status if status.is_client_error() => ErrorKind::Rejected,
+            // 5xx, and anything unexpected that reached the error path
+            _ => ErrorKind::Transient,
+        }
+    }
 }
 
 /// The JSON body OANDA returns for error responses.
@@ -93,7 +212,7 @@ pub struct ApiErrorBody {
     pub error_code: Option<String>,
     /// The reason the request was rejected, when provided.
     #[serde(rename = "rejectReason", skip_serializing_if = "Option::is_none")]
-    pub reject_reason: Option<String>,
+    pub reject_reason: Option<TransactionRejectReason>,
     /// Any additional top-level fields of the error body.
     #[serde(flatten)]
     pub extra: serde_json::Map<String, serde_json::Value>,
@@ -110,6 +229,28 @@ impl ApiErrorBody {
         }
     }
 
+    /// The reason the request was rejected.
+    ///
+    /// Uses `rejectReason` when present, then the `rejectReason` of a reject
+    /// transaction carried in the body (e.g. `orderRejectTransaction`), then
+    /// `errorCode` when it names a known [`TransactionRejectReason`].
+    pub fn reason(&self) -> Option<TransactionRejectReason> {
+        if let Some(reason) = &self.reject_reason {
+            return Some(reason.clone());
+        }
+        self.extra
+            .iter()
+            .filter(|(key, _)| key.ends_with("RejectTransaction"))
+            .find_map(|(_, tx)| tx.get("rejectReason")?.as_str())
+            .map(TransactionRejectReason::from)
+            .or_else(|| {
+                self.error_code
+                    .as_deref()
+                    .map(TransactionRejectReason::from)
+                    .filter(|reason| !matches!(reason, TransactionRejectReason::Other(_)))
+            })
+    }
+
     /// Attempts to decode the full error body into a typed view.
     ///
     /// Useful for order endpoints, whose 400/404 responses carry reject
@@ -162,6 +303,10 @@ mod tests {
             Error::Config("bad".into()).to_string(),
             "invalid client configuration: bad"
         );
+        assert_eq!(
+            Error::InvalidOrder("no entry".into()).to_string(),
+            "invalid order: no entry"
+        );
     }
 
     #[test]
@@ -174,6 +319,97 @@ mod tests {
         assert_eq!(Error::Config("x".into()).request_id(), None);
     }
 
+    #[test]
+    fn kind_classifies_statuses_and_variants() {
+        assert_eq!(api_error(429).kind(), ErrorKind::RateLimited);
+        assert_eq!(api_error(401).kind(), ErrorKind::Unauthorized);
+        assert_eq!(api_error(403).kind(), ErrorKind::Unauthorized);
+        assert_eq!(api_error(404).kind(), ErrorKind::NotFound);
+        assert_eq!(api_error(400).kind(), ErrorKind::Rejected);
+        assert_eq!(api_error(408).kind(), ErrorKind::Transient);
+        assert_eq!(api_error(503).kind(), ErrorKind::Transient);
+        assert_eq!(Error::Stream("gap".into()).kind(), ErrorKind::Transient);
+        assert_eq!(Error::Config("x".into()).kind(), ErrorKind::Config);
+        assert_eq!(Error::InvalidOrder("x".into()).kind(), ErrorKind::Rejected);
+        assert_eq!(
+            Error::Guard(GuardViolation::LiveTradingNotAllowed).kind(),
+            ErrorKind::Rejected
+        );
+        let decode = Error::Decode {
+            source: serde_json::from_str::<u8>("x").unwrap_err(),
+            body: "x".into(),
+        };
+        assert_eq!(decode.kind(), ErrorKind::Decode);
+        assert!(api_error(429).kind().is_retryable());
+        assert!(api_error(502).kind().is_retryable());
+        assert!(!api_error(400).kind().is_retryable());
+        assert!(!decode.kind().is_retryable());
+    }
+
+    #[tokio::test]
+    async fn kind_treats_connection_failures_as_transient() {
+        // nothing listens on port 9 of the loopback interface
+        let error = reqwest::Client::new()
+            .get("http://127.0.0.1:9/")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(Error::Transport(error).kind(), ErrorKind::Transient);
+        let error = reqwest::Client::new()
+            .get("not a url")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(Error::Transport(error).kind(), ErrorKind::Config);
+    }
+
+    #[test]
+    fn reason_falls_back_to_reject_transaction_and_error_code() {
+        let body = |code: Option<&str>, extra: serde_json::Value| ApiErrorBody {
+            error_message: "rejected".into(),
+            error_code: code.map(Into::into),
+            reject_reason: None,
+            extra: serde_json::from_value(extra).unwrap(),
+        };
+        let nested = body(
+            Some("SOMETHING_ELSE"),
+            serde_json::json!({
+                "orderRejectTransaction": {
+                    "type": "LIMIT_ORDER_REJECT",
+                    "rejectReason": "PRICE_PRECISION_EXCEEDED"
+                }
+            }),
+        );
+        assert_eq!(
+            nested.reason(),
+            Some(TransactionRejectReason::PricePrecisionExceeded)
+        );
+        let coded = body(Some("INSUFFICIENT_MARGIN"), serde_json::json!({}));
+        assert_eq!(
+            coded.reason(),
+            Some(TransactionRejectReason::InsufficientMargin)
+        );
+        // error codes that are not reject reasons are not mistaken for one
+        assert_eq!(body(Some("CODE"), serde_json::json!({})).reason(), None);
+        assert_eq!(api_error(400).reject_reason(), None);
+        assert_eq!(Error::Stream("x".into()).reject_reason(), None);
+    }
+
+    #[test]
+    fn reject_reason_classification() {
+        use TransactionRejectReason as R;
+        assert!(R::InsufficientMargin.is_insufficient_margin());
+        assert!(!R::InsufficientMargin.is_client_side_fixable());
+        assert!(R::InstrumentNotTradeable.is_market_halted());
+        assert!(R::StopLossOrderGuaranteedHaltedCreateViolation.is_market_halted());
+        assert!(R::TakeProfitOnFillPricePrecisionExceeded.is_price_precision());
+        assert!(!R::UnitsPrecisionExceeded.is_price_precision());
+        assert!(R::UnitsPrecisionExceeded.is_client_side_fixable());
+        assert!(R::ClientOrderIdAlreadyExists.is_client_side_fixable());
+        assert!(!R::AccountLocked.is_client_side_fixable());
+        assert!(!R::from("NEW_REASON").is_client_side_fixable());
+    }
+
     #[test]
     fn details_decodes_extra_fields() {
         #[derive(serde::Deserialize)]
@@ -186,7 +422,7 @@ mod tests {
         let body = ApiErrorBody {
             error_message: "rejected".into(),
             error_code: None,
-            reject_reason: Some("REASON".into()),
+            reject_reason: Some(TransactionRejectReason::InsufficientMargin),
             extra,
         };
         let view: View = body.details().unwrap();
@@ -0,0 +1,250 @@
+//! Arrow record batches and Parquet files from the [`export`](super) rows.
+//!
+//! Decimal columns are `Decimal128(38, 10)` ([`DECIMAL_PRECISION`],
+//! [`DECIMAL_SCALE`]) and times are `Timestamp(Nanosecond, "UTC")`, so
+//! prices reach the dataset exactly as OANDA sent them, with no float
+//! conversion on the way. Every batch of a kind shares one schema
+//! ([`candle_schema`], [`tick_schema`]), so batches fetched page by page can
+//! be appended to the same file.
+//!
+//! ```no_run
+//! use oanda_rs::export::arrow::{candle_schema, candles_record_batch, write_parquet};
+//! use oanda_rs::models::{CandlestickGranularity, PricingComponent};
+//!
+//! # async fn run(client: oanda_rs::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut batches = Vec::new();
+//! for month in 1..=12 {
+//!     let candles = client
+//!         .candles("EUR_USD")
+//!         .granularity(CandlestickGranularity::M1)
+//!         .price(PricingComponent::BID.with_ask())
+//!         .from(format!("2023-{month:02}-01T00:00:00Z"))
+//!         .count(5000)
+//!         .send()
+//!         .await?;
+//!     batches.push(candles_record_batch(&candles.candles)?);
+//! }
+//! write_parquet(std::fs::File::create("eur_usd_m1_2023.parquet")?, candle_schema(), batches)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Decimal128Array, Int64Array, StringArray, TimestampNanosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use rust_decimal::Decimal;
+
+use super::{CandleRow, TickRow};
+use crate::models::DateTime;
+
+pub use arrow_array::RecordBatch;
+pub use arrow_schema::{ArrowError, SchemaRef};
+pub use parquet::errors::ParquetError;
+
+/// Precision of every decimal column.
+pub const DECIMAL_PRECISION: u8 = 38;
+
+/// Scale (digits after the decimal point) of every decimal column.
+///
+/// Values with more significant decimals than this are rejected rather than
+/// rounded.
+pub const DECIMAL_SCALE: i8 = 10;
+
+/// The schema of [`candles_record_batch`]: [`CandleRow::COLUMNS`], all
+/// nullable.
+pub fn candle_schema() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("time", timestamp_type(), true),
+        Field::new("complete", DataType::Boolean, true),
+        Field::new("volume", DataType::Int64, true),
+    ];
+    fields.extend(
+        CandleRow::COLUMNS[3..]
+            .iter()
+            .map(|name| Field::new(*name, decimal_type(), true)),
+    );
+    Arc::new(Schema::new(fields))
+}
+
+/// The schema of [`ticks_record_batch`]: [`TickRow::COLUMNS`], all
+/// nullable.
+pub fn tick_schema() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("time", timestamp_type(), true),
+        Field::new("instrument", DataType::Utf8, true),
+    ];
+    fields.extend(
+        TickRow::COLUMNS[2..8]
+            .iter()
+            .map(|name| Field::new(*name, decimal_type(), true)),
+    );
+    fields.push(Field::new("tradeable", DataType::Boolean, true));
+    Arc::new(Schema::new(fields))
+}
+
+/// Converts candlesticks into a record batch with [`candle_schema`].
+///
+/// Fails when a time cannot be parsed or a price does not fit the decimal
+/// columns.
+pub fn candles_record_batch<I>(candles: I) -> Result<RecordBatch, ArrowError>
+where
+    I: IntoIterator,
+    I::Item: Into<CandleRow>,
+{
+    let rows: Vec<CandleRow> = candles.into_iter().map(Into::into).collect();
+    let price = |pick: fn(&CandleRow) -> Option<Decimal>| decimals(rows.iter().map(pick));
+    RecordBatch::try_new(
+        candle_schema(),
+        vec![
+            timestamps(rows.iter().map(|r| r.time.as_ref()))?,
+            Arc::new(BooleanArray::from_iter(rows.iter().map(|r| r.complete))),
+            Arc::new(Int64Array::from_iter(rows.iter().map(|r| r.volume))),
+            price(|r| r.bid_o.map(|p| p.0))?,
+            price(|r| r.bid_h.map(|p| p.0))?,
+            price(|r| r.bid_l.map(|p| p.0))?,
+            price(|r| r.bid_c.map(|p| p.0))?,
+            price(|r| r.mid_o.map(|p| p.0))?,
+            price(|r| r.mid_h.map(|p| p.0))?,
+            price(|r| r.mid_l.map(|p| p.0))?,
+            price(|r| r.mid_c.map(|p| p.0))?,
+            price(|r| r.ask_o.map(|p| p.0))?,
+            price(|r| r.ask_h.map(|p| p.0))?,
+            price(|r| r.ask_l.map(|p| p.0))?,
+            price(|r| r.ask_c.map(|p| p.0))?,
+        ],
+    )
+}
+
+/// Converts prices, e.g. the [`ClientPrice`](crate::models::ClientPrice)s
+/// of a recorded pricing stream, into a record batch with [`tick_schema`].
+///
+/// Fails when a time cannot be parsed or a price does not fit the decimal
+/// columns.
+pub fn ticks_record_batch<I>(prices: I) -> Result<RecordBatch, ArrowError>
+where
+    I: IntoIterator,
+    I::Item: Into<TickRow>,
+{
+    let rows: Vec<TickRow> = prices.into_iter().map(Into::into).collect();
+    let decimal = |pick: fn(&TickRow) -> Option<Decimal>| decimals(rows.iter().map(pick));
+    RecordBatch::try_new(
+        tick_schema(),
+        vec![
+            timestamps(rows.iter().map(|r| r.time.as_ref()))?,
+            Arc::new(StringArray::from_iter(
+                rows.iter()
+                    .map(|r| r.instrument.as_ref().map(|i| i.as_str())),
+            )),
+            decimal(|r| r.bid.map(|p| p.0))?,
+            decimal(|r| r.ask.map(|p| p.0))?,
+            decimal(|r| r.bid_liquidity.map(|l| l.0))?,
+            decimal(|r| r.ask_liquidity.map(|l| l.0))?,
+            decimal(|r| r.closeout_bid.map(|p| p.0))?,
+            decimal(|r| r.closeout_ask.map(|p| p.0))?,
+            Arc::new(BooleanArray::from_iter(rows.iter().map(|r| r.tradeable))),
+        ],
+    )
+}
+
+/// Writes record batches sharing `schema` to a Snappy-compressed Parquet
+/// file.
+pub fn write_parquet<W, I>(writer: W, schema: SchemaRef, batches: I) -> Result<(), ParquetError>
+where
+    W: io::Write + Send,
+    I: IntoIterator<Item = RecordBatch>,
+{
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(writer, schema, Some(properties))?;
+    for batch in batches {
+        writer.write(&batch)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+}
+
+fn decimal_type() -> DataType {
+    DataType::Decimal128(DECIMAL_PRECISION, DECIMAL_SCALE)
+}
+
+fn timestamps<'a>(
+    times: impl Iterator<Item = Option<&'a DateTime>>,
+) -> Result<ArrayRef, ArrowError> {
+    let nanos = times
+        .map(|time| {
+            time.map(|time| {
+                time.to_utc()
+                    .and_then(|utc| utc.timestamp_nanos_opt())
+                    .ok_or_else(|| {
+                        ArrowError::InvalidArgumentError(format!(
+                            "cannot convert {time} to nanoseconds since the epoch"
+                        ))
+                    })
+            })
+            .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Arc::new(
+        TimestampNanosecondArray::from(nanos).with_timezone("UTC"),
+    ))
+}
+
+fn decimals(values: impl Iterator<Item = Option<Decimal>>) -> Result<ArrayRef, ArrowError> {
+    let values = values
+        .map(|value| value.map(to_decimal128).transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Arc::new(
+        Decimal128Array::from(values).with_precision_and_scale(DECIMAL_PRECISION, DECIMAL_SCALE)?,
+    ))
+}
+
+/// The unscaled `i128` of `value` at [`DECIMAL_SCALE`], refusing to round.
+fn to_decimal128(value: Decimal) -> Result<i128, ArrowError> {
+    let value = if value.scale() > DECIMAL_SCALE as u32 {
+        value.normalize()
+    } else {
+        value
+    };
+    let max = 10i128.pow(DECIMAL_PRECISION as u32);
+    DECIMAL_SCALE
+        .checked_sub(value.scale() as i8)
+        .filter(|shift| *shift >= 0)
+        .and_then(|shift| value.mantissa().checked_mul(10i128.pow(shift as u32)))
+        .filter(|unscaled| unscaled.abs() < max)
+        .ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!(
+                "{value} does not fit Decimal128({DECIMAL_PRECISION}, {DECIMAL_SCALE})"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dec;
+
+    #[test]
+    fn decimals_are_rescaled_exactly() {
+        assert_eq!(to_decimal128(dec("1.07125")).unwrap(), 10_712_500_000);
+        assert_eq!(to_decimal128(dec("-157.3")).unwrap(), -1_573_000_000_000);
+        // trailing zeros beyond the scale are not significant
+        assert_eq!(to_decimal128(dec("0.500000000000")).unwrap(), 5_000_000_000);
+    }
+
+    #[test]
+    fn decimals_that_would_be_rounded_are_rejected() {
+        assert!(to_decimal128(dec("0.00000000001")).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! CSV writers for the [`export`](super) rows.
+//!
+//! Each writer emits a header row with the row type's `COLUMNS`, even when
+//! there are no records, followed by one line per record. Empty fields
+//! stand for values the record does not carry; decimals are written
+//! exactly as OANDA sent them.
+//!
+//! ```no_run
+//! use oanda_rs::export::csv::write_candles;
+//! use oanda_rs::models::{CandlestickGranularity, PricingComponent};
+//!
+//! # async fn run(client: oanda_rs::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let candles = client
+//!     .candles("EUR_USD")
+//!     .granularity(CandlestickGranularity::H1)
+//!     .price(PricingComponent::BID.with_mid().with_ask())
+//!     .count(500)
+//!     .send()
+//!     .await?;
+//! write_candles(std::fs::File::create("eur_usd_h1.csv")?, &candles.candles)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+
+use serde::Serialize;
+
+use super::{CandleRow, TradeRow, TransactionRow};
+
+pub use ::csv::Error;
+
+/// Writes candlesticks as [`CandleRow`]s.
+pub fn write_candles<W, I>(writer: W, candles: I) -> Result<(), Error>
+where
+    W: io::Write,
+    I: IntoIterator,
+    I::Item: Into<CandleRow>,
+{
+    write_rows(
+        writer,
+        CandleRow::COLUMNS,
+        candles.into_iter().map(Into::into),
+    )
+}
+
+/// Writes transactions of any type as [`TransactionRow`]s.
+pub fn write_transactions<W, I>(writer: W, transactions: I) -> Result<(), Error>
+where
+    W: io::Write,
+    I: IntoIterator,
+    I::Item: Into<TransactionRow>,
+{
+    write_rows(
+        writer,
+        TransactionRow::COLUMNS,
+        transactions.into_iter().map(Into::into),
+    )
+}
+
+/// Writes trades (or trade summaries) as [`TradeRow`]s.
+pub fn write_trades<W, I>(writer: W, trades: I) -> Result<(), Error>
+where
+    W: io::Write,
+    I: IntoIterator,
+    I::Item: Into<TradeRow>,
+{
+    write_rows(
+        writer,
+        TradeRow::COLUMNS,
+        trades.into_iter().map(Into::into),
+    )
+}
+
+fn write_rows<W: io::Write, R: Serialize>(
+    writer: W,
+    columns: &[&str],
+    rows: impl Iterator<Item = R>,
+) -> Result<(), Error> {
+    // The header comes from `columns` rather than from the first record, so
+    // that empty exports still carry it.
+    let mut writer = ::csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    writer.write_record(columns)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,344 @@
+//! Flat, tabular views of candles, transactions and trades.
+//!
+//! The models mirror OANDA's nested JSON. For analysis tools that expect
+//! tidy tables, each row type here flattens one record into a fixed set of
+//! columns:
+//!
+//! - [`CandleRow`]: one candlestick with its bid, mid and ask OHLC;
+//! - [`TransactionRow`]: the accounting fields shared across transaction
+//!   types, left empty where a type does not carry them;
+//! - [`TradeRow`]: a trade with its client extensions inlined;
+//! - [`TickRow`]: a streamed or polled price, top of book only.
+//!
+//! Column names are part of the public API: they are listed in each row's
+//! `COLUMNS` constant and only ever extended at the end. With the `csv`
+//! feature, the `csv` submodule writes rows to any [`std::io::Write`]; with
+//! the `arrow` feature, the `arrow` submodule builds Arrow record batches
+//! and Parquet files from them.
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "csv")]
+pub mod csv;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::models::transaction::Transaction;
+use crate::models::{
+    AccountUnits, Candlestick, CandlestickData, ClientPrice, DateTime, DecimalNumber,
+    InstrumentName, OrderId, PriceValue, Trade, TradeId, TradeState, TradeSummary, TransactionId,
+};
+
+/// A candlestick flattened into a single row.
+///
+/// Price components the candles were not requested with are empty.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct CandleRow {
+    /// Start time of the candle.
+    pub time: Option<DateTime>,
+    /// Whether the candle is complete.
+    pub complete: Option<bool>,
+    /// Number of prices created during the candle.
+    pub volume: Option<i64>,
+    /// Bid open.
+    pub bid_o: Option<PriceValue>,
+    /// Bid high.
+    pub bid_h: Option<PriceValue>,
+    /// Bid low.
+    pub bid_l: Option<PriceValue>,
+    /// Bid close.
+    pub bid_c: Option<PriceValue>,
+    /// Mid open.
+    pub mid_o: Option<PriceValue>,
+    /// Mid high.
+    pub mid_h: Option<PriceValue>,
+    /// Mid low.
+    pub mid_l: Option<PriceValue>,
+    /// Mid close.
+    pub mid_c: Option<PriceValue>,
+    /// Ask open.
+    pub ask_o: Option<PriceValue>,
+    /// Ask high.
+    pub ask_h: Option<PriceValue>,
+    /// Ask low.
+    pub ask_l: Option<PriceValue>,
+    /// Ask close.
+    pub ask_c: Option<PriceValue>,
+}
+
+impl CandleRow {
+    /// The column names, in order.
+    pub const COLUMNS: &'static [&'static str] = &[
+        "time", "complete", "volume", "bid_o", "bid_h", "bid_l", "bid_c", "mid_o", "mid_h",
+        "mid_l", "mid_c", "ask_o", "ask_h", "ask_l", "ask_c",
+    ];
+}
+
+impl From<&Candlestick> for CandleRow {
+    fn from(candle: &Candlestick) -> Self {
+        let ohlc = |data: &Option<CandlestickData>| match data {
+            Some(d) => (d.o, d.h, d.l, d.c),
+            None => (None, None, None, None),
+        };
+        let (bid_o, bid_h, bid_l, bid_c) = ohlc(&candle.bid);
+        let (mid_o, mid_h, mid_l, mid_c) = ohlc(&candle.mid);
+        let (ask_o, ask_h, ask_l, ask_c) = ohlc(&candle.ask);
+        CandleRow {
+            time: candle.time.clone(),
+            complete: candle.complete,
+            volume: candle.volume,
+            bid_o,
+            bid_h,
+            bid_l,
+            bid_c,
+            mid_o,
+            mid_h,
+            mid_l,
+            mid_c,
+            ask_o,
+            ask_h,
+            ask_l,
+            ask_c,
+        }
+    }
+}
+
+/// A transaction of any type flattened into a single row.
+///
+/// Every transaction type maps onto the same columns; fields a type does
+/// not carry (say, `pl` on a `LIMIT_ORDER`) are empty. Transactions
+/// unknown to this SDK are flattened from their raw JSON the same way.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct TransactionRow {
+    /// The transaction's ID.
+    pub id: Option<TransactionId>,
+    /// When the transaction was created.
+    pub time: Option<DateTime>,
+    /// The wire name of the transaction's type, e.g. `ORDER_FILL`.
+    #[serde(rename = "type")]
+    pub type_name: Option<String>,
+    /// The ID of the batch the transaction belongs to.
+    pub batch_id: Option<TransactionId>,
+    /// The order the transaction refers to.
+    pub order_id: Option<OrderId>,
+    /// The instrument traded or financed.
+    pub instrument: Option<InstrumentName>,
+    /// Units ordered or filled; negative for sells.
+    pub units: Option<DecimalNumber>,
+    /// The order's price, or the fill price.
+    pub price: Option<PriceValue>,
+    /// Realized profit/loss in the account's home currency.
+    pub pl: Option<AccountUnits>,
+    /// Financing paid or received.
+    pub financing: Option<AccountUnits>,
+    /// Commission charged.
+    pub commission: Option<AccountUnits>,
+    /// Funds transferred in or out (`TRANSFER_FUNDS`).
+    pub amount: Option<AccountUnits>,
+    /// The account balance after the transaction.
+    pub account_balance: Option<AccountUnits>,
+    /// Why the transaction was created.
+    pub reason: Option<String>,
+}
+
+impl TransactionRow {
+    /// The column names, in order.
+    pub const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "time",
+        "type",
+        "batch_id",
+        "order_id",
+        "instrument",
+        "units",
+        "price",
+        "pl",
+        "financing",
+        "commission",
+        "amount",
+        "account_balance",
+        "reason",
+    ];
+}
+
+impl From<&Transaction> for TransactionRow {
+    fn from(transaction: &Transaction) -> Self {
+        // The variants share OANDA's field names on the wire, so reading
+        // them back from the JSON form covers all of them (and unknown
+        // types) uniformly.
+        let json = match transaction {
+            Transaction::Unknown(value) => value.clone(),
+            known => serde_json::to_value(known).expect("transactions serialize to JSON"),
+        };
+        fn field<T: DeserializeOwned>(json: &serde_json::Value, key: &str) -> Option<T> {
+            json.get(key).and_then(|value| T::deserialize(value).ok())
+        }
+        TransactionRow {
+            id: field(&json, "id"),
+            time: field(&json, "time"),
+            type_name: transaction.type_name().map(str::to_owned),
+            batch_id: field(&json, "batchID"),
+            order_id: field(&json, "orderID"),
+            instrument: field(&json, "instrument"),
+            units: field(&json, "units"),
+            price: field(&json, "price"),
+            pl: field(&json, "pl"),
+            financing: field(&json, "financing"),
+            commission: field(&json, "commission"),
+            amount: field(&json, "amount"),
+            account_balance: field(&json, "accountBalance"),
+            reason: field(&json, "reason"),
+        }
+    }
+}
+
+/// A trade flattened into a single row, from either a [`Trade`] or a
+/// [`TradeSummary`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct TradeRow {
+    /// The trade's ID.
+    pub id: Option<TradeId>,
+    /// The traded instrument.
+    pub instrument: Option<InstrumentName>,
+    /// Whether the trade is open or closed.
+    pub state: Option<TradeState>,
+    /// When the trade was opened.
+    pub open_time: Option<DateTime>,
+    /// When the trade was fully closed.
+    pub close_time: Option<DateTime>,
+    /// The price the trade was opened at.
+    pub price: Option<PriceValue>,
+    /// Units the trade was opened with; negative for shorts.
+    pub initial_units: Option<DecimalNumber>,
+    /// Units still open.
+    pub current_units: Option<DecimalNumber>,
+    /// Average price of the closed units.
+    pub average_close_price: Option<PriceValue>,
+    /// Profit/loss realized by closing units.
+    pub realized_pl: Option<AccountUnits>,
+    /// Profit/loss of the open units.
+    pub unrealized_pl: Option<AccountUnits>,
+    /// Financing paid or received.
+    pub financing: Option<AccountUnits>,
+    /// Margin currently used by the trade.
+    pub margin_used: Option<AccountUnits>,
+    /// The client ID from the trade's client extensions.
+    pub client_id: Option<String>,
+    /// The tag from the trade's client extensions.
+    pub client_tag: Option<String>,
+    /// The comment from the trade's client extensions.
+    pub client_comment: Option<String>,
+}
+
+impl TradeRow {
+    /// The column names, in order.
+    pub const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "instrument",
+        "state",
+        "open_time",
+        "close_time",
+        "price",
+        "initial_units",
+        "current_units",
+        "average_close_price",
+        "realized_pl",
+        "unrealized_pl",
+        "financing",
+        "margin_used",
+        "client_id",
+        "client_tag",
+        "client_comment",
+    ];
+}
+
+macro_rules! trade_row_from {
+    ($ty:ty) => {
+        impl From<&$ty> for TradeRow {
+            fn from(trade: &$ty) -> Self {
+                let extensions = trade.client_extensions.as_ref();
+                TradeRow {
+                    id: trade.id.clone(),
+                    instrument: trade.instrument.clone(),
+                    state: trade.state.clone(),
+                    open_time: trade.open_time.clone(),
+                    close_time: trade.close_time.clone(),
+                    price: trade.price,
+                    initial_units: trade.initial_units,
+                    current_units: trade.current_units,
+                    average_close_price: trade.average_close_price,
+                    realized_pl: trade.realized_pl,
+                    unrealized_pl: trade.unrealized_pl,
+                    financing: trade.financing,
+                    margin_used: trade.margin_used,
+                    client_id: extensions.and_then(|e| e.id.clone()),
+                    client_tag: extensions.and_then(|e| e.tag.clone()),
+                    client_comment: extensions.and_then(|e| e.comment.clone()),
+                }
+            }
+        }
+    };
+}
+
+trade_row_from!(Trade);
+trade_row_from!(TradeSummary);
+
+/// A price flattened into a single row, keeping only the top of book.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct TickRow {
+    /// When the price was created.
+    pub time: Option<DateTime>,
+    /// The priced instrument.
+    pub instrument: Option<InstrumentName>,
+    /// The best bid.
+    pub bid: Option<PriceValue>,
+    /// The best ask.
+    pub ask: Option<PriceValue>,
+    /// Liquidity available at the best bid.
+    pub bid_liquidity: Option<DecimalNumber>,
+    /// Liquidity available at the best ask.
+    pub ask_liquidity: Option<DecimalNumber>,
+    /// The bid a position would be closed out at.
+    pub closeout_bid: Option<PriceValue>,
+    /// The ask a position would be closed out at.
+    pub closeout_ask: Option<PriceValue>,
+    /// Whether the instrument was tradeable at the time.
+    pub tradeable: Option<bool>,
+}
+
+impl TickRow {
+    /// The column names, in order.
+    pub const COLUMNS: &'static [&'static str] = &[
+        "time",
+        "instrument",
+        "bid",
+        "ask",
+        "bid_liquidity",
+        "ask_liquidity",
+        "closeout_bid",
+        "closeout_ask",
+        "tradeable",
+    ];
+}
+
+impl From<&ClientPrice> for TickRow {
+    fn from(price: &ClientPrice) -> Self {
+        let (bid, ask) = (price.bids.first(), price.asks.first());
+        TickRow {
+            time: price.time.clone(),
+            instrument: price.instrument.clone(),
+            bid: bid.and_then(|b| b.price),
+            ask: ask.and_then(|a| a.price),
+            bid_liquidity: bid.and_then(|b| b.liquidity),
+            ask_liquidity: ask.and_then(|a| a.liquidity),
+            closeout_bid: price.closeout_bid,
+            closeout_ask: price.closeout_ask,
+            tradeable: price.tradeable,
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Safety guards checked before orders are sent.
+//!
+//! A [`Client`] built with [`ClientBuilder::guards`](crate::ClientBuilder::guards)
+//! checks every [`create_order`](Client::create_order),
+//! [`replace_order`](Client::replace_order),
+//! [`close_trade`](Client::close_trade) and
+//! [`close_position`](Client::close_position) against its [`Guards`]
+//! first. A violation fails the call with [`Error::Guard`] and nothing is
+//! sent.
+//!
+//! Guards are opt-in. A client built without them is unrestricted: it
+//! sends any order, of any size, to any environment, the live one
+//! included. Only a client with guards refuses live trading until
+//! [`allow_live`](Guards::allow_live) is set.
+//!
+//! ```no_run
+//! use oanda_rs::guard::Guards;
+//! use oanda_rs::{Client, Environment};
+//!
+//! let client = Client::builder()
+//!     .environment(Environment::Live)
+//!     .token("my-token")
+//!     .guards(
+//!         Guards::new()
+//!             .allow_live(true)
+//!             .allow_instruments(["EUR_USD", "USD_JPY"])
+//!             .max_units(10_000)
+//!             .instrument_max_units("USD_JPY", 5_000)
+//!             .max_orders_per_minute(10),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! // The configured limits can be audited through the client's Debug output.
+//! println!("{client:?}");
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use tokio::time::Instant;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::models::{AccountId, InstrumentName, OrderRequest, PriceValue};
+use crate::trading::QuoteConversion;
+
+/// The window [`Guards::max_orders_per_minute`] counts orders in.
+const ORDER_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits a [`Client`] enforces before sending orders.
+///
+/// Nothing is limited by default, except that mutating calls against the
+/// live environment need [`allow_live`](Self::allow_live). These defaults
+/// apply only once the guards are installed with
+/// [`ClientBuilder::guards`](crate::ClientBuilder::guards); a client
+/// without guards is unrestricted.
+///
+/// | Check | `create_order`, `replace_order` | `close_trade` | `close_position` |
+/// |---|---|---|---|
+/// | [`allow_live`](Self::allow_live) | yes | yes | yes |
+/// | [`allow_instruments`](Self::allow_instruments) | orders naming an instrument | no | yes |
+/// | [`max_units`](Self::max_units), [`max_notional`](Self::max_notional) | orders naming an instrument | no | no |
+/// | [`max_orders_per_minute`](Self::max_orders_per_minute) | yes | yes | yes |
+///
+/// Closing only reduces exposure, so size limits do not apply to it; take-
+/// profit, stop-loss and trailing stop-loss orders refer to a trade rather
+/// than an instrument and are likewise not size-checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Guards {
+    allow_live: bool,
+    allowed_instruments: Option<BTreeSet<InstrumentName>>,
+    max_units: Option<Decimal>,
+    max_notional: Option<Decimal>,
+    instrument_limits: BTreeMap<InstrumentName, InstrumentLimits>,
+    max_orders_per_minute: Option<u32>,
+}
+
+/// Per-instrument overrides of the size limits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct InstrumentLimits {
+    max_units: Option<Decimal>,
+    max_notional: Option<Decimal>,
+}
+
+impl Guards {
+    /// Guards limiting nothing and refusing live trading.
+    pub fn new() -> Self {
+        Guards::default()
+    }
+
+    /// Whether mutating calls may be sent to the live environment
+    /// ([`Environment::Live`](crate::Environment::Live), or a custom
+    /// environment whose REST host is neither the practice host nor a
+    /// loopback address, since it may be a proxy to the live one).
+    /// Disabled by default.
+    pub fn allow_live(mut self, allow: bool) -> Self {
+        self.allow_live = allow;
+        self
+    }
+
+    /// Restricts trading to the given instruments; may be called several
+    /// times to add more. Unrestricted by default.
+    pub fn allow_instruments<I>(mut self, instruments: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<InstrumentName>,
+    {
+        self.allowed_instruments
+            .get_or_insert_with(BTreeSet::new)
+            .extend(instruments.into_iter().map(Into::into));
+        self
+    }
+
+    /// The largest order, in units (long or short), for instruments
+    /// without an [`instrument_max_units`](Self::instrument_max_units)
+    /// override.
+    pub fn max_units(mut self, units: impl Into<Decimal>) -> Self {
+        self.max_units = Some(units.into());
+        self
+    }
+
+    /// The largest notional of an order, in the account's home currency,
+    /// for instruments without an
+    /// [`instrument_max_notional`](Self::instrument_max_notional) override.
+    ///
+    /// The notional is units times price, converted from the instrument's
+    /// quote currency with the current `quoteHomeConversionFactors`, which
+    /// costs one pricing request per checked order. Orders are valued at
+    /// their price, or their price bound; market orders without a price
+    /// bound are valued at the current closeout price.
+    pub fn max_notional(mut self, notional: impl Into<Decimal>) -> Self {
+        self.max_notional = Some(notional.into());
+        self
+    }
+
+    /// The largest order, in units, for `instrument`; overrides
+    /// [`max_units`](Self::max_units).
+    pub fn instrument_max_units(
+        mut self,
+        instrument: impl Into<InstrumentName>,
+        units: impl Into<Decimal>,
+    ) -> Self {
+        self.instrument_limits
+            .entry(instrument.into())
+            .or_default()
+            .max_units = Some(units.into());
+        self
+    }
+
+    /// The largest notional of an order for `instrument`, in the account's
+    /// home currency; overrides [`max_notional`](Self::max_notional).
+    pub fn instrument_max_notional(
+        mut self,
+        instrument: impl Into<InstrumentName>,
+        notional: impl Into<Decimal>,
+    ) -> Self {
+        self.instrument_limits
+            .entry(instrument.into())
+            .or_default()
+            .max_notional = Some(notional.into());
+        self
+    }
+
+    /// The most orders (creations, replacements and closes) sent in any
+    /// 60 seconds. Unlimited by default.
+    pub fn max_orders_per_minute(mut self, orders: u32) -> Self {
+        self.max_orders_per_minute = Some(orders);
+        self
+    }
+
+    fn unit_limit(&self, instrument: &InstrumentName) -> Option<Decimal> {
+        self.instrument_limits
+            .get(instrument)
+            .and_then(|limits| limits.max_units)
+            .or(self.max_units)
+    }
+
+    fn notional_limit(&self, instrument: &InstrumentName) -> Option<Decimal> {
+        self.instrument_limits
+            .get(instrument)
+            .and_then(|limits| limits.max_notional)
+            .or(self.max_notional)
+    }
+}
+
+/// Why [`Guards`] refused to send a request, carried by [`Error::Guard`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum GuardViolation {
+    /// The client talks to the live environment and
+    /// [`Guards::allow_live`] is not set.
+    #[error("live trading is not allowed")]
+    LiveTradingNotAllowed,
+    /// The instrument is not in [`Guards::allow_instruments`].
+    #[error("instrument {0} is not allowed")]
+    InstrumentNotAllowed(InstrumentName),
+    /// The order is larger than its units limit.
+    #[error("{units} units of {instrument} exceed the limit of {limit}")]
+    MaxUnits {
+        /// The order's instrument.
+        instrument: InstrumentName,
+        /// The order's size (absolute).
+        units: Decimal,
+        /// The applicable limit.
+        limit: Decimal,
+    },
+    /// The order's notional is larger than its notional limit.
+    #[error("notional {notional} of {instrument} exceeds the limit of {limit}")]
+    MaxNotional {
+        /// The order's instrument.
+        instrument: InstrumentName,
+        /// The order's notional, in the account's home currency.
+        notional: Decimal,
+        /// The applicable limit.
+        limit: Decimal,
+    },
+    /// An order's notional could not be checked because OANDA returned
+    /// no price or home conversion factors for its instrument.
+    #[error("no price to value the order in {0} at")]
+    PriceUnavailable(InstrumentName),
+    /// [`Guards::max_orders_per_minute`] orders were already sent in the
+    /// last 60 seconds.
+    #[error("more than {limit} orders per minute")]
+    OrderRate {
+        /// The applicable limit.
+        limit: u32,
+    },
+}
+
+/// [`Guards`] together with the times of recently sent orders; shared by
+/// all clones of a client.
+pub(crate) struct Guard {
+    guards: Guards,
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl Guard {
+    pub(crate) fn new(guards: Guards) -> Self {
+        Guard {
+            guards,
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn guards(&self) -> &Guards {
+        &self.guards
+    }
+
+    /// Counts an order against the per-minute limit, failing (without
+    /// counting it) when the limit is reached.
+    fn record_order(&self) -> Result<(), GuardViolation> {
+        let Some(limit) = self.guards.max_orders_per_minute else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let mut sent = self.sent.lock().expect("guard state poisoned");
+        while sent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= ORDER_RATE_WINDOW)
+        {
+            sent.pop_front();
+        }
+        if sent.len() >= limit as usize {
+            return Err(GuardViolation::OrderRate { limit });
+        }
+        sent.push_back(now);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.guards.fmt(f)
+    }
+}
+
+impl Client {
+    /// Checks an order about to be created or to replace another against
+    /// the guards.
+    pub(crate) async fn guard_order(
+        &self,
+        account_id: &AccountId,
+        order: &OrderRequest,
+    ) -> Result<(), Error> {
+        let Some(guard) = &self.inner.guard else {
+            return Ok(());
+        };
+        self.guard_live(guard)?;
+        if let Some((instrument, signed_units, price)) = order_size(order) {
+            guard_instrument(guard, instrument)?;
+            let units = signed_units.abs();
+            if let Some(limit) = guard.guards.unit_limit(instrument) {
+                if units > limit {
+                    return Err(GuardViolation::MaxUnits {
+                        instrument: instrument.clone(),
+                        units,
+                        limit,
+                    }
+                    .into());
+                }
+            }
+            if let Some(limit) = guard.guards.notional_limit(instrument) {
+                let (closeout, conversion) =
+                    self.valuation(account_id, instrument, signed_units).await?;
+                let notional = conversion.convert_value(units * price.unwrap_or(closeout));
+                if notional > limit {
+                    return Err(GuardViolation::MaxNotional {
+                        instrument: instrument.clone(),
+                        notional,
+                        limit,
+                    }
+                    .into());
+                }
+            }
+        }
+        Ok(guard.record_order()?)
+    }
+
+    /// Checks a trade or position close against the guards.
+    pub(crate) fn guard_close(&self, instrument: Option<&InstrumentName>) -> Result<(), Error> {
+        let Some(guard) = &self.inner.guard else {
+            return Ok(());
+        };
+        self.guard_live(guard)?;
+        if let Some(instrument) = instrument {
+            guard_instrument(guard, instrument)?;
+        }
+        Ok(guard.record_order()?)
+    }
+
+    fn guard_live(&self, guard: &Guard) -> Result<(), GuardViolation> {
+        if self.inner.live && !guard.guards.allow_live {
+            return Err(GuardViolation::LiveTradingNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// The price an order of `units` would close out at, and the
+    /// conversion of the instrument's quote currency into the account's
+    /// home currency.
+    async fn valuation(
+        &self,
+        account_id: &AccountId,
+        instrument: &InstrumentName,
+        units: Decimal,
+    ) -> Result<(Decimal, QuoteConversion), Error> {
+        let response = self
+            .prices(account_id.clone(), [instrument.clone()])
+            .include_home_conversions(true)
+            .send()
+            .await?;
+        let unavailable = || GuardViolation::PriceUnavailable(instrument.clone());
+        let price = response
+            .prices
+            .iter()
+            .find(|price| price.instrument.as_ref() == Some(instrument))
+            .ok_or_else(unavailable)?;
+        let closeout = if units.is_sign_negative() {
+            price.closeout_bid
+        } else {
+            price.closeout_ask
+        };
+        let conversion = QuoteConversion::from_price(price).or_else(|| {
+            QuoteConversion::from_home_conversions(&response.home_conversions, instrument)
+        });
+        match (closeout, conversion) {
+            (Some(closeout), Some(conversion)) => Ok((closeout.value(), conversion)),
+            _ => Err(unavailable().into()),
+        }
+    }
+}
+
+fn guard_instrument(guard: &Guard, instrument: &InstrumentName) -> Result<(), GuardViolation> {
+    match &guard.guards.allowed_instruments {
+        Some(allowed) if !allowed.contains(instrument) => {
+            Err(GuardViolation::InstrumentNotAllowed(instrument.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The instrument, signed units and price (if known up front) of an order
+/// naming an instrument.
+fn order_size(order: &OrderRequest) -> Option<(&InstrumentName, Decimal, Option<Decimal>)> {
+    let value = |price: &PriceValue| price.value();
+    match order {
+        OrderRequest::Market(o) => Some((
+            &o.instrument,
+            o.units.value(),
+            o.price_bound.as_ref().map(value),
+        )),
+        OrderRequest::Limit(o) => Some((&o.instrument, o.units.value(), Some(o.price.value()))),
+        OrderRequest::Stop(o) => Some((&o.instrument, o.units.value(), Some(o.price.value()))),
+        OrderRequest::MarketIfTouched(o) => {
+            Some((&o.instrument, o.units.value(), Some(o.price.value())))
+        }
+        OrderRequest::TakeProfit(_)
+        | OrderRequest::StopLoss(_)
+        | OrderRequest::TrailingStopLoss(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn order_rate_is_counted_over_a_sliding_minute() {
+        let guard = Guard::new(Guards::new().max_orders_per_minute(2));
+        guard.record_order().unwrap();
+        tokio::time::advance(Duration::from_secs(30)).await;
+        guard.record_order().unwrap();
+        assert_eq!(
+            guard.record_order(),
+            Err(GuardViolation::OrderRate { limit: 2 })
+        );
+        // The first order leaves the window; the refused one was not counted.
+        tokio::time::advance(Duration::from_secs(30)).await;
+        guard.record_order().unwrap();
+        assert!(guard.record_order().is_err());
+    }
+
+    #[test]
+    fn instrument_limits_override_the_defaults() {
+        let guards = Guards::new()
+            .max_units(1_000)
+            .instrument_max_units("USD_JPY", 500)
+            .instrument_max_notional("USD_JPY", 80_000);
+        let eur: InstrumentName = "EUR_USD".into();
+        let jpy: InstrumentName = "USD_JPY".into();
+        assert_eq!(guards.unit_limit(&eur), Some(Decimal::from(1_000)));
+        assert_eq!(guards.unit_limit(&jpy), Some(Decimal::from(500)));
+        assert_eq!(guards.notional_limit(&eur), None);
+        assert_eq!(guards.notional_limit(&jpy), Some(Decimal::from(80_000)));
+    }
+}
@@ -33,15 +33,29 @@
 #![deny(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+mod cassette;
 mod client;
+mod config;
 mod error;
 mod rate_limit;
+mod response;
+#[cfg(test)]
+mod test_util;
 mod transport;
 
+pub mod account;
+pub mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod broker;
 pub mod endpoints;
+pub mod export;
+pub mod guard;
 pub mod models;
 pub mod prelude;
 pub mod streaming;
+pub mod trading;
 
 pub use client::{Client, ClientBuilder, Environment};
-pub use error::{ApiErrorBody, Error};
+pub use error::{ApiErrorBody, Error, ErrorKind};
+pub use response::Response;
@@ -1646,6 +1646,16 @@ impl Order {
         for_each_order!(self, o => o.client_extensions.as_ref(), None)
     }
 
+    /// The ID of the transaction that filled the order, once filled.
+    pub fn filling_transaction_id(&self) -> Option<&TransactionId> {
+        for_each_order!(self, o => o.filling_transaction_id.as_ref(), None)
+    }
+
+    /// The ID of the transaction that cancelled the order, once cancelled.
+    pub fn cancelling_transaction_id(&self) -> Option<&TransactionId> {
+        for_each_order!(self, o => o.cancelling_transaction_id.as_ref(), None)
+    }
+
     /// The wire name of the order's type (e.g. `MARKET`), or the raw
     /// `type` value for unknown orders.
     pub fn type_name(&self) -> Option<&str> {
@@ -1722,6 +1732,54 @@ order_request_from! {
     TrailingStopLossOrderRequest => TrailingStopLoss,
 }
 
+impl OrderRequest {
+    /// The client extensions attached to the order, if any.
+    pub fn client_extensions(&self) -> Option<&ClientExtensions> {
+        match self {
+            OrderRequest::Market(o) => o.client_extensions.as_ref(),
+            OrderRequest::Limit(o) => o.client_extensions.as_ref(),
+            OrderRequest::Stop(o) => o.client_extensions.as_ref(),
+            OrderRequest::MarketIfTouched(o) => o.client_extensions.as_ref(),
+            OrderRequest::TakeProfit(o) => o.client_extensions.as_ref(),
+            OrderRequest::StopLoss(o) => o.client_extensions.as_ref(),
+            OrderRequest::TrailingStopLoss(o) => o.client_extensions.as_ref(),
+        }
+    }
+
+    /// Prepends `prefix` to the tags of the order's client extensions and of
+    /// the trade client extensions it carries.
+    pub(crate) fn prefix_tags(&mut self, prefix: &str) {
+        let trade = match self {
+            OrderRequest::Market(o) => o.trade_client_extensions.as_mut(),
+            OrderRequest::Limit(o) => o.trade_client_extensions.as_mut(),
+            OrderRequest::Stop(o) => o.trade_client_extensions.as_mut(),
+            OrderRequest::MarketIfTouched(o) => o.trade_client_extensions.as_mut(),
+            OrderRequest::TakeProfit(_)
+            | OrderRequest::StopLoss(_)
+            | OrderRequest::TrailingStopLoss(_) => None,
+        };
+        if let Some(extensions) = trade {
+            extensions.prefix_tag(prefix);
+        }
+        if let Some(extensions) = self.client_extensions_mut() {
+            extensions.prefix_tag(prefix);
+        }
+    }
+
+    /// Mutable access to the client extensions attached to the order.
+    pub fn client_extensions_mut(&mut self) -> &mut Option<ClientExtensions> {
+        match self {
+            OrderRequest::Market(o) => &mut o.client_extensions,
+            OrderRequest::Limit(o) => &mut o.client_extensions,
+            OrderRequest::Stop(o) => &mut o.client_extensions,
+            OrderRequest::MarketIfTouched(o) => &mut o.client_extensions,
+            OrderRequest::TakeProfit(o) => &mut o.client_extensions,
+            OrderRequest::StopLoss(o) => &mut o.client_extensions,
+            OrderRequest::TrailingStopLoss(o) => &mut o.client_extensions,
+        }
+    }
+}
+
 /// Generates `with`-style optional-field setters for request builders.
 macro_rules! setters {
     ($($(#[$meta:meta])* $name:ident: $ty:ty,)+) => {
@@ -2365,6 +2423,15 @@ impl ClientExtensions {
         self.comment = Some(comment.into());
         self
     }
+
+    /// Prepends `prefix` to the tag, unless it is unset or already carries it.
+    pub(crate) fn prefix_tag(&mut self, prefix: &str) {
+        if let Some(tag) = &mut self.tag {
+            if !tag.starts_with(prefix) {
+                tag.insert_str(0, prefix);
+            }
+        }
+    }
 }
 
 impl Default for ClientExtensions {
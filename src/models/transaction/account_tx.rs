@@ -240,6 +240,76 @@ string_enum! {
     }
 }
 
+impl TransactionRejectReason {
+    /// Whether the account lacks the margin to take on the requested
+    /// position.
+    pub fn is_insufficient_margin(&self) -> bool {
+        matches!(self, TransactionRejectReason::InsufficientMargin)
+    }
+
+    /// Whether the instrument cannot currently be traded: it is halted,
+    /// has no price, or guaranteed stop-losses are locked while halted.
+    ///
+    /// Such rejections are usually transient; retrying once the market
+    /// reopens may succeed.
+    pub fn is_market_halted(&self) -> bool {
+        matches!(
+            self,
+            TransactionRejectReason::InstrumentNotTradeable
+                | TransactionRejectReason::InstrumentPriceUnknown
+                | TransactionRejectReason::StopLossOrderGuaranteedHaltedCreateViolation
+                | TransactionRejectReason::StopLossOrderGuaranteedHaltedTightenViolation
+        )
+    }
+
+    /// Whether a price, price bound or price distance carried more decimal
+    /// places than the instrument allows (see
+    /// [`Instrument::display_precision`](crate::models::Instrument::display_precision)).
+    pub fn is_price_precision(&self) -> bool {
+        matches!(
+            self,
+            TransactionRejectReason::PricePrecisionExceeded
+                | TransactionRejectReason::PriceDistancePrecisionExceeded
+                | TransactionRejectReason::PriceBoundPrecisionExceeded
+                | TransactionRejectReason::TakeProfitOnFillPricePrecisionExceeded
+                | TransactionRejectReason::StopLossOnFillPricePrecisionExceeded
+                | TransactionRejectReason::StopLossOnFillDistancePrecisionExceeded
+                | TransactionRejectReason::TrailingStopLossOnFillPriceDistancePrecisionExceeded
+        )
+    }
+
+    /// Whether the rejection was caused by the request itself (a missing,
+    /// invalid or out-of-range field, a duplicate client ID, ...), so that
+    /// correcting the request can make it succeed.
+    ///
+    /// Returns `false` for server errors, account locks, halted markets,
+    /// insufficient margin or funds, references to orders, trades or
+    /// positions that do not exist, and reasons unknown to this SDK.
+    pub fn is_client_side_fixable(&self) -> bool {
+        !matches!(
+            self,
+            TransactionRejectReason::InternalServerError
+                | TransactionRejectReason::AccountNotActive
+                | TransactionRejectReason::AccountLocked
+                | TransactionRejectReason::AccountOrderCreationLocked
+                | TransactionRejectReason::AccountConfigurationLocked
+                | TransactionRejectReason::AccountDepositLocked
+                | TransactionRejectReason::AccountWithdrawalLocked
+                | TransactionRejectReason::AccountOrderCancelLocked
+                | TransactionRejectReason::PendingOrdersAllowedExceeded
+                | TransactionRejectReason::OrderDoesntExist
+                | TransactionRejectReason::TradeDoesntExist
+                | TransactionRejectReason::CloseoutPositionDoesntExist
+                | TransactionRejectReason::CloseoutPositionReject
+                | TransactionRejectReason::InsufficientMargin
+                | TransactionRejectReason::InsufficientFunds
+                | TransactionRejectReason::MarginRateWouldTriggerCloseout
+                | TransactionRejectReason::MarginRateWouldTriggerMarginCall
+                | TransactionRejectReason::Other(_)
+        ) && !self.is_market_halted()
+    }
+}
+
 /// A CreateTransaction represents the creation of an Account.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -217,6 +217,26 @@ impl Transaction {
         for_each_transaction!(self, t => t.user_id, None)
     }
 
+    /// The reason a `*_REJECT` transaction was rejected; `None` for every
+    /// other transaction type.
+    pub fn reject_reason(&self) -> Option<&TransactionRejectReason> {
+        match self {
+            Transaction::ClientConfigureReject(t) => t.reject_reason.as_ref(),
+            Transaction::TransferFundsReject(t) => t.reject_reason.as_ref(),
+            Transaction::MarketOrderReject(t) => t.reject_reason.as_ref(),
+            Transaction::LimitOrderReject(t) => t.reject_reason.as_ref(),
+            Transaction::StopOrderReject(t) => t.reject_reason.as_ref(),
+            Transaction::MarketIfTouchedOrderReject(t) => t.reject_reason.as_ref(),
+            Transaction::TakeProfitOrderReject(t) => t.reject_reason.as_ref(),
+            Transaction::StopLossOrderReject(t) => t.reject_reason.as_ref(),
+            Transaction::TrailingStopLossOrderReject(t) => t.reject_reason.as_ref(),
+            Transaction::OrderCancelReject(t) => t.reject_reason.as_ref(),
+            Transaction::OrderClientExtensionsModifyReject(t) => t.reject_reason.as_ref(),
+            Transaction::TradeClientExtensionsModifyReject(t) => t.reject_reason.as_ref(),
+            _ => None,
+        }
+    }
+
     /// The wire name of the transaction's type (e.g. `ORDER_FILL`), or
     /// the raw `type` value for unknown transactions.
     pub fn type_name(&self) -> Option<&str> {
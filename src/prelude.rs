@@ -6,8 +6,9 @@
 //! let client = Client::new(Environment::Practice, "my-token");
 //! ```
 
+pub use crate::account::AccountClient;
 pub use crate::client::{Client, ClientBuilder, Environment};
-pub use crate::error::{ApiErrorBody, Error};
+pub use crate::error::{ApiErrorBody, Error, ErrorKind};
 pub use crate::models::transaction::{Transaction, TransactionStreamItem};
 pub use crate::models::{
     AcceptDatetimeFormat, AccountId, AccountUnits, CandleSpecification, CandlestickGranularity,
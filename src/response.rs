@@ -0,0 +1,72 @@
+//! Response metadata returned by the `send_with_meta()` builder methods
+//! and the `*_with_meta` client methods.
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+
+use crate::models::TransactionId;
+
+/// A decoded response body together with the HTTP metadata of the request
+/// that produced it.
+///
+/// Returned by the `send_with_meta()` counterpart of each REST request
+/// builder's `send()`, and by the `*_with_meta` counterpart of each REST
+/// method without a builder, such as
+/// [`Client::create_order_with_meta`](crate::Client::create_order_with_meta):
+///
+/// ```no_run
+/// # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+/// let response = client.list_trades("101-004-1234567-001").send_with_meta().await?;
+/// println!(
+///     "{} trades, request {:?}, {:?} on the wire after {:?} in the rate limiter",
+///     response.value.trades.len(),
+///     response.request_id,
+///     response.elapsed,
+///     response.rate_limit_wait,
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Response<T> {
+    /// The decoded response body, as returned by `send()`.
+    pub value: T,
+    /// HTTP status code of the response.
+    pub status: StatusCode,
+    /// Value of the `RequestID` response header, which OANDA support asks
+    /// for when investigating a request.
+    pub request_id: Option<String>,
+    /// The `lastTransactionID` reported in the response body, when present.
+    pub last_transaction_id: Option<TransactionId>,
+    /// Value of the `Location` response header, set for requests that
+    /// create a resource.
+    pub location: Option<String>,
+    /// Time from sending the request to having read the full response
+    /// body, excluding [`rate_limit_wait`](Response::rate_limit_wait).
+    /// Zero for responses replayed from a cassette.
+    pub elapsed: Duration,
+    /// Time spent waiting on the client's rate limiter before the request
+    /// was sent.
+    pub rate_limit_wait: Duration,
+    /// All response headers.
+    pub headers: HeaderMap,
+}
+
+impl<T> Response<T> {
+    /// Applies `f` to the body, keeping the metadata.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Response<U> {
+        Response {
+            value: f(self.value),
+            status: self.status,
+            request_id: self.request_id,
+            last_transaction_id: self.last_transaction_id,
+            location: self.location,
+            elapsed: self.elapsed,
+            rate_limit_wait: self.rate_limit_wait,
+            headers: self.headers,
+        }
+    }
+}
@@ -8,19 +8,23 @@ use bytes::{Buf, Bytes, BytesMut};
 use futures_core::Stream;
 use serde::de::DeserializeOwned;
 
+use super::replay::LineRecorder;
 use crate::error::Error;
 
-/// Adapts a stream of byte chunks (as produced by
+/// Adapts a stream of byte chunks (such as
 /// [`reqwest::Response::bytes_stream`]) into a stream of JSON values, one
 /// per newline-terminated line.
 ///
 /// Lines may be split across chunk boundaries and multiple lines may share
 /// a chunk; both are handled. Empty lines are skipped, `\r\n` is accepted,
 /// and a trailing unterminated line is parsed when the inner stream ends.
+/// A [`LineRecorder`] set with [`JsonLines::record`] sees every non-blank
+/// line before it is parsed.
 pub(crate) struct JsonLines<S, T> {
     inner: S,
     buffer: BytesMut,
     ended: bool,
+    recorder: Option<LineRecorder>,
     _item: PhantomData<fn() -> T>,
 }
 
@@ -30,9 +34,15 @@ impl<S, T> JsonLines<S, T> {
             inner,
             buffer: BytesMut::new(),
             ended: false,
+            recorder: None,
             _item: PhantomData,
         }
     }
+
+    /// Tees every line to `recorder` from now on.
+    pub(crate) fn record(&mut self, recorder: Option<LineRecorder>) {
+        self.recorder = recorder;
+    }
 }
 
 impl<S, T> JsonLines<S, T>
@@ -43,7 +53,7 @@ where
     fn next_buffered_line(&mut self) -> Option<Result<T, Error>> {
         while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
             let line = self.buffer.split_to(newline + 1);
-            match parse_line(&line[..newline]) {
+            match self.take_line(&line[..newline]) {
                 Some(result) => return Some(result),
                 None => continue, // blank line
             }
@@ -54,11 +64,19 @@ where
     /// Parses whatever remains in the buffer once the inner stream ended.
     fn final_line(&mut self) -> Option<Result<T, Error>> {
         let rest = self.buffer.split();
-        parse_line(&rest)
+        self.take_line(&rest)
+    }
+
+    fn take_line(&self, line: &[u8]) -> Option<Result<T, Error>> {
+        let parsed = parse_line(line);
+        if let (Some(recorder), Some(_)) = (&self.recorder, &parsed) {
+            recorder.record_line(line);
+        }
+        parsed
     }
 }
 
-fn parse_line<T: DeserializeOwned>(mut line: &[u8]) -> Option<Result<T, Error>> {
+pub(crate) fn parse_line<T: DeserializeOwned>(mut line: &[u8]) -> Option<Result<T, Error>> {
     if line.ends_with(b"\r") {
         line = &line[..line.len() - 1];
     }
@@ -73,9 +91,10 @@ fn parse_line<T: DeserializeOwned>(mut line: &[u8]) -> Option<Result<T, Error>>
     )
 }
 
-impl<S, T> Stream for JsonLines<S, T>
+impl<S, E, T> Stream for JsonLines<S, T>
 where
-    S: Stream<Item = reqwest::Result<Bytes>> + Unpin,
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Error>,
     T: DeserializeOwned,
 {
     type Item = Result<T, Error>;
@@ -93,7 +112,7 @@ where
                 Poll::Ready(Some(Ok(chunk))) => {
                     this.buffer.extend_from_slice(chunk.chunk());
                 }
-                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(Error::Transport(e)))),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
                 Poll::Ready(None) => {
                     this.ended = true;
                 }
@@ -10,11 +10,13 @@ use bytes::Bytes;
 use futures_core::Stream;
 use futures_core::future::BoxFuture;
 use futures_core::stream::BoxStream;
+use serde::Serialize;
 use serde::de::DeserializeOwned;
 use tokio::time::{Instant, Sleep};
 
 use super::StreamConfig;
 use super::json_lines::JsonLines;
+use super::replay::LineRecorder;
 use crate::error::Error;
 
 pub(crate) type ByteStream = BoxStream<'static, reqwest::Result<Bytes>>;
@@ -26,7 +28,7 @@ type Terminal<T> = Option<Poll<Option<Result<T, Error>>>>;
 
 /// Endpoint-specific behaviour plugged into [`ManagedStream`].
 pub(crate) trait StreamKind: Send + Unpin + 'static {
-    type Item: DeserializeOwned + Send + Unpin + 'static;
+    type Item: DeserializeOwned + Serialize + Send + Unpin + 'static;
 
     /// Builds a future that opens the connection (waiting for a
     /// connection-limiter slot, sending the request, and checking the
@@ -83,6 +85,7 @@ pub(crate) struct ManagedStream<K: StreamKind> {
     attempts_since_success: u32,
     current_delay: Duration,
     connected_at: Option<Instant>,
+    recorder: Option<LineRecorder>,
 }
 
 impl<K: StreamKind> ManagedStream<K> {
@@ -102,6 +105,7 @@ impl<K: StreamKind> ManagedStream<K> {
             stats: StreamStats::default(),
             attempts_since_success: 0,
             connected_at: Some(Instant::now()),
+            recorder: None,
         }
     }
 
@@ -109,6 +113,18 @@ impl<K: StreamKind> ManagedStream<K> {
         self.stats
     }
 
+    /// Tees every line received from now on (including those of later
+    /// connections, and back-filled items) to `recorder`.
+    pub(crate) fn record(&mut self, recorder: LineRecorder) {
+        match &mut self.state {
+            State::Backfilling { lines, .. }
+            | State::Draining { lines, .. }
+            | State::Streaming { lines, .. } => lines.record(Some(recorder.clone())),
+            State::Connecting(_) | State::Sleeping(_) | State::Done => {}
+        }
+        self.recorder = Some(recorder);
+    }
+
     /// Handles a broken connection: either schedules a reconnect (returning
     /// `None`) or produces the caller-visible terminal event.
     fn connection_lost(&mut self, error: Option<Error>) -> Terminal<K::Item> {
@@ -165,14 +181,10 @@ impl<K: StreamKind> ManagedStream<K> {
     }
 }
 
-/// Only client-side errors are fatal; transport failures and server errors
-/// are worth retrying.
+/// Only errors that retrying cannot fix are fatal; see
+/// [`ErrorKind::is_retryable`](crate::ErrorKind::is_retryable).
 fn is_fatal(error: &Error) -> bool {
-    match error {
-        Error::Api { status, .. } => status.is_client_error(),
-        Error::Config(_) => true,
-        _ => false,
-    }
+    !error.kind().is_retryable()
 }
 
 /// Applies ±25% pseudo-random jitter so reconnecting clients don't
@@ -188,13 +200,20 @@ impl<K: StreamKind> Stream for ManagedStream<K> {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
+        if let Some(error) = this.recorder.as_ref().and_then(LineRecorder::take_error) {
+            // The recording has stopped; the live stream carries on.
+            return Poll::Ready(Some(Err(Error::Stream(format!(
+                "failed to write the stream recording: {error}"
+            )))));
+        }
         loop {
             match &mut this.state {
                 State::Connecting(future) => match future.as_mut().poll(cx) {
                     Poll::Ready(Ok(bytes)) => {
                         this.stats.reconnects += 1;
                         this.connected_at = Some(Instant::now());
-                        let lines = JsonLines::new(bytes);
+                        let mut lines = JsonLines::new(bytes);
+                        lines.record(this.recorder.clone());
 
                         #[cfg(feature = "tracing")]
                         tracing::debug!(reconnects = this.stats.reconnects, "stream reconnected");
@@ -248,6 +267,9 @@ impl<K: StreamKind> Stream for ManagedStream<K> {
                 State::Draining { pending, .. } => match pending.pop_front() {
                     Some(item) => {
                         if this.kind.filter(&item) {
+                            if let Some(recorder) = &this.recorder {
+                                recorder.record_item(&item);
+                            }
                             return Poll::Ready(Some(Ok(item)));
                         }
                     }
@@ -593,6 +615,101 @@ mod tests {
         assert_eq!(items, vec![3, 4]);
     }
 
+    #[derive(Clone, Default)]
+    struct Recording(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Recording {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recorder_sees_lines_of_every_connection_and_backfilled_items() {
+        let script = vec![Outcome::Chunks(vec![b"{\"n\":4}\n"])];
+        let (mut kind, _) = MockKind::new(script);
+        kind.backfills
+            .push_back(Ok(vec![serde_json::json!({"n": 3})]));
+        let initial = bytes_from(Outcome::Chunks(vec![b"{\"n\":1}\n\n"])).unwrap();
+        let mut stream = ManagedStream::new(kind, StreamConfig::default(), initial);
+        let recording = Recording::default();
+        stream.record(LineRecorder::new(recording.clone()));
+        let items: Vec<_> = stream.take(3).collect().await;
+        assert!(items.iter().all(Result::is_ok));
+
+        let recorded = String::from_utf8(recording.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<String> = recorded
+            .lines()
+            .map(|line| {
+                let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+                entry["line"].as_str().unwrap().to_owned()
+            })
+            .collect();
+        assert_eq!(lines, vec![r#"{"n":1}"#, r#"{"n":3}"#, r#"{"n":4}"#]);
+    }
+
+    struct FullDisk;
+
+    impl std::io::Write for FullDisk {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recording_failure_is_reported_once_and_stream_continues() {
+        let (kind, _) = MockKind::new(vec![]);
+        let initial = bytes_from(Outcome::ChunksThenHang(vec![b"{\"n\":1}\n{\"n\":2}\n"])).unwrap();
+        let mut stream = ManagedStream::new(kind, StreamConfig::default(), initial);
+        let recorder = LineRecorder::new(FullDisk);
+        stream.record(recorder.clone());
+
+        assert_eq!(stream.next().await.unwrap().unwrap()["n"], 1);
+        while !recorder.is_stopped() {
+            std::thread::yield_now();
+        }
+        assert!(
+            matches!(stream.next().await, Some(Err(Error::Stream(msg))) if msg.contains("disk full"))
+        );
+        assert_eq!(stream.next().await.unwrap().unwrap()["n"], 2);
+    }
+
+    /// A writer that blocks until the test lets it through.
+    struct Stalled(std::sync::mpsc::Receiver<()>);
+
+    impl std::io::Write for Stalled {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let _ = self.0.recv();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn recorder_drops_lines_once_it_falls_behind() {
+        let (release, stalled) = std::sync::mpsc::channel();
+        // a rendezvous channel: once the thread is stuck writing, every line is dropped
+        let recorder = LineRecorder::with_backlog(Stalled(stalled), 0);
+        for _ in 0..3 {
+            recorder.record_line(b"{\"n\":1}");
+        }
+        let error = recorder.take_error().expect("a line was dropped");
+        assert!(error.to_string().contains("dropped lines"), "{error}");
+        assert!(recorder.take_error().is_none(), "reported once");
+        drop(release);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn failed_backfill_is_reported_but_stream_continues() {
         let script = vec![Outcome::Chunks(vec![b"{\"n\":7}\n"])];
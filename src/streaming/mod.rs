@@ -21,21 +21,31 @@
 //!   `snapshot=true` so fresh prices arrive immediately.
 //!
 //! Heartbeats are yielded to the caller (useful as a liveness signal);
-//! reconnection is otherwise invisible. Fatal errors (HTTP 4xx on
-//! reconnect) end the stream with a final `Err` item.
+//! reconnection is otherwise invisible. Errors that are not
+//! [retryable](crate::ErrorKind::is_retryable) on reconnect (an HTTP 4xx
+//! other than 408 and 429, or a 401 that persists after refreshing the
+//! token) end the stream with a final `Err` item.
+//!
+//! Either stream can be recorded to an NDJSON file with
+//! [`PricingStream::record`] and replayed later, at the recorded pace or
+//! faster, with [`PricingStream::replay`] (and likewise for
+//! [`TransactionStream`]).
 
 mod json_lines;
 mod managed;
+mod replay;
 
+use std::io::{Read, Write};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
 use futures_core::Stream;
 use futures_util::StreamExt;
-use reqwest::{Method, Url};
+use reqwest::{Method, StatusCode, Url};
 
 pub use managed::StreamStats;
+pub use replay::ReplaySpeed;
 
 use crate::client::Client;
 use crate::error::Error;
@@ -43,6 +53,7 @@ use crate::models::transaction::TransactionStreamItem;
 use crate::models::{AccountId, PriceStreamItem};
 pub(crate) use managed::StreamKind;
 use managed::{ByteStream, ManagedStream};
+use replay::{LineRecorder, Replay};
 
 /// Reconnection policy of a managed stream.
 #[derive(Debug, Clone)]
@@ -68,11 +79,52 @@ impl Default for StreamConfig {
     }
 }
 
+/// Where a stream's items come from: a live connection or a recording.
+enum Source<K: StreamKind> {
+    Live(ManagedStream<K>),
+    Replay(Replay<K::Item>),
+}
+
+impl<K: StreamKind> Source<K> {
+    fn stats(&self) -> StreamStats {
+        match self {
+            Source::Live(stream) => stream.stats(),
+            Source::Replay(_) => StreamStats::default(),
+        }
+    }
+
+    fn record(&mut self, writer: impl Write + Send + 'static) {
+        if let Source::Live(stream) = self {
+            stream.record(LineRecorder::new(writer));
+        }
+    }
+}
+
+impl<K: StreamKind> Stream for Source<K> {
+    type Item = Result<K::Item, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.get_mut() {
+            Source::Live(stream) => Pin::new(stream).poll_next(cx),
+            Source::Replay(replay) => Pin::new(replay).poll_next(cx),
+        }
+    }
+}
+
 /// Opens a streaming connection: waits for a connection-limiter slot,
-/// sends the request, and verifies the response status.
+/// sends the request, and verifies the response status. A 401 is retried
+/// once with a refreshed token.
 pub(crate) async fn open_stream(client: Client, url: Url) -> Result<ByteStream, Error> {
+    let mut request = client.request(Method::GET, url).build()?;
+    let retry = request.try_clone();
+    let token = client.authorize(&mut request, None).await?;
     client.acquire_connection_slot().await;
-    let response = client.request(Method::GET, url).send().await?;
+    let mut response = client.inner.http.execute(request).await?;
+    if let (StatusCode::UNAUTHORIZED, Some(mut retry)) = (response.status(), retry) {
+        client.authorize(&mut retry, Some(token)).await?;
+        client.acquire_connection_slot().await;
+        response = client.inner.http.execute(retry).await?;
+    }
     if !response.status().is_success() {
         return Err(crate::transport::error_from_response(response).await);
     }
@@ -121,17 +173,74 @@ impl StreamKind for PricingKind {
 /// A self-managing pricing stream; see the [module docs](self) for the
 /// reconnection behaviour. Yields [`PriceStreamItem`]s.
 pub struct PricingStream {
-    inner: ManagedStream<PricingKind>,
+    inner: Source<PricingKind>,
 }
 
 impl PricingStream {
     pub(crate) fn new(kind: PricingKind, config: StreamConfig, initial: ByteStream) -> Self {
         PricingStream {
-            inner: ManagedStream::new(kind, config, initial),
+            inner: Source::Live(ManagedStream::new(kind, config, initial)),
         }
     }
 
-    /// A snapshot of the stream's connection statistics.
+    /// Replays a recording made with [`record`](Self::record).
+    ///
+    /// Heartbeats are replayed along with prices; a line that fails to
+    /// parse is yielded as an `Err` item, as it was live. The stream ends
+    /// at the end of the recording. `reader` is read on tokio's blocking
+    /// pool, so a file does not hold up the runtime.
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use oanda_rs::streaming::{PricingStream, ReplaySpeed};
+    ///
+    /// # async fn run() -> Result<(), oanda_rs::Error> {
+    /// let file = std::fs::File::open("eur_usd.ndjson").expect("recording exists");
+    /// let mut prices = PricingStream::replay(file, ReplaySpeed::Scaled(10.0));
+    /// while let Some(item) = prices.next().await {
+    ///     println!("{:?}", item?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replay(reader: impl Read + Send + 'static, speed: ReplaySpeed) -> Self {
+        PricingStream {
+            inner: Source::Replay(Replay::new(reader, speed)),
+        }
+    }
+
+    /// Writes every line received from now on, heartbeats included, to
+    /// `writer` as NDJSON with its receive time, for later
+    /// [`replay`](Self::replay). Lines are written exactly as received,
+    /// before they are parsed.
+    ///
+    /// Lines are written on a separate thread, buffered and flushed
+    /// whenever the writer catches up, so a slow disk never holds up the
+    /// live stream; dropping the stream flushes the rest. The first write
+    /// error stops the recording and is yielded once as
+    /// [`Error::Stream`], after which the live stream carries on. A writer
+    /// that falls too far behind has lines dropped instead, reported the
+    /// same way.
+    /// Recording a replayed stream has no effect.
+    ///
+    /// ```no_run
+    /// # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+    /// let file = std::fs::File::create("eur_usd.ndjson").expect("writable");
+    /// let prices = client
+    ///     .pricing_stream("101-004-1234567-001", ["EUR_USD"])
+    ///     .send()
+    ///     .await?
+    ///     .record(file);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.inner.record(writer);
+        self
+    }
+
+    /// A snapshot of the stream's connection statistics (all zero for a
+    /// replayed stream).
     pub fn stats(&self) -> StreamStats {
         self.inner.stats()
     }
@@ -179,15 +288,7 @@ impl StreamKind for TransactionKind {
     }
 
     fn filter(&mut self, item: &TransactionStreamItem) -> bool {
-        if let TransactionStreamItem::Transaction(tx) = item {
-            if let Some(id) = tx.id().and_then(|id| id.as_str().parse::<u64>().ok()) {
-                if self.last_seen.is_some_and(|seen| id <= seen) {
-                    return false; // already delivered (backfill overlap)
-                }
-                self.last_seen = Some(id);
-            }
-        }
-        true
+        deduplicate(&mut self.last_seen, item)
     }
 
     fn backfill(
@@ -210,21 +311,55 @@ impl StreamKind for TransactionKind {
     }
 }
 
+/// Drops transactions at or below the last ID delivered, updating it.
+fn deduplicate(last_seen: &mut Option<u64>, item: &TransactionStreamItem) -> bool {
+    if let TransactionStreamItem::Transaction(tx) = item {
+        if let Some(id) = tx.id().and_then(|id| id.as_str().parse::<u64>().ok()) {
+            if last_seen.is_some_and(|seen| id <= seen) {
+                return false; // already delivered (backfill overlap)
+            }
+            *last_seen = Some(id);
+        }
+    }
+    true
+}
+
 /// A self-managing transaction stream; see the [module docs](self) for the
 /// reconnection and back-fill behaviour. Yields
 /// [`TransactionStreamItem`]s.
 pub struct TransactionStream {
-    inner: ManagedStream<TransactionKind>,
+    inner: Source<TransactionKind>,
 }
 
 impl TransactionStream {
     pub(crate) fn new(kind: TransactionKind, config: StreamConfig, initial: ByteStream) -> Self {
         TransactionStream {
-            inner: ManagedStream::new(kind, config, initial),
+            inner: Source::Live(ManagedStream::new(kind, config, initial)),
+        }
+    }
+
+    /// Replays a recording made with [`record`](Self::record), dropping
+    /// transactions already replayed as the live stream does. See
+    /// [`PricingStream::replay`].
+    pub fn replay(reader: impl Read + Send + 'static, speed: ReplaySpeed) -> Self {
+        let mut last_seen = None;
+        TransactionStream {
+            inner: Source::Replay(
+                Replay::new(reader, speed).filter(move |item| deduplicate(&mut last_seen, item)),
+            ),
         }
     }
 
-    /// A snapshot of the stream's connection statistics.
+    /// Writes every line received from now on, heartbeats and back-filled
+    /// transactions included, to `writer` as NDJSON with its receive time,
+    /// for later [`replay`](Self::replay). See [`PricingStream::record`].
+    pub fn record(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.inner.record(writer);
+        self
+    }
+
+    /// A snapshot of the stream's connection statistics (all zero for a
+    /// replayed stream).
     pub fn stats(&self) -> StreamStats {
         self.inner.stats()
     }
@@ -0,0 +1,293 @@
+//! Recording streams to NDJSON files and replaying them.
+//!
+//! A recording has one JSON object per received line, holding the time it
+//! was received and the line exactly as it arrived:
+//!
+//! ```json
+//! {"received":"2024-06-14T12:00:00.123456Z","line":"{\"type\":\"HEARTBEAT\",\"time\":\"2024-06-14T12:00:00.000000000Z\"}"}
+//! ```
+
+use std::io::{self, BufWriter, Read, Write};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::Utc;
+use futures_core::Stream;
+use futures_core::stream::BoxStream;
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::time::{Instant, Sleep};
+
+use super::json_lines::{JsonLines, parse_line};
+use crate::error::Error;
+
+/// The size of the chunks a recording is read in.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// How many lines a recording may fall behind the stream before lines are
+/// dropped.
+const RECORD_BACKLOG: usize = 16 * 1024;
+
+/// How fast a recorded stream is replayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum ReplaySpeed {
+    /// At the pace the lines were received.
+    Recorded,
+    /// At a multiple of the recorded pace: `2.0` replays twice as fast,
+    /// `0.5` at half speed.
+    Scaled(f64),
+    /// As fast as the consumer polls, without waiting.
+    Unthrottled,
+}
+
+/// One line of a recording.
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedLine {
+    received: chrono::DateTime<Utc>,
+    line: String,
+}
+
+/// Writes received lines, with their receive time, to a shared writer.
+///
+/// Lines are handed to a dedicated thread that buffers them and flushes
+/// whenever it has caught up, so a slow writer never blocks the stream
+/// being recorded. Up to [`RECORD_BACKLOG`] lines wait for the thread;
+/// beyond that lines are dropped, and each run of dropped lines is kept as
+/// an error for [`take_error`](Self::take_error). The first write error
+/// stops the recording and is kept the same way; neither interrupts the
+/// stream.
+/// Dropping the last clone flushes what is left and waits for the thread.
+#[derive(Clone)]
+pub(crate) struct LineRecorder {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    lines: Option<SyncSender<String>>,
+    thread: Option<JoinHandle<()>>,
+    error: Arc<Mutex<Option<io::Error>>>,
+    /// Whether the last line was dropped because the thread fell behind.
+    dropping: AtomicBool,
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread once it has written the rest.
+        self.lines.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl LineRecorder {
+    pub(crate) fn new(writer: impl Write + Send + 'static) -> Self {
+        Self::with_backlog(writer, RECORD_BACKLOG)
+    }
+
+    pub(super) fn with_backlog(writer: impl Write + Send + 'static, backlog: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(backlog);
+        let error = Arc::new(Mutex::new(None));
+        let failed = Arc::clone(&error);
+        let thread = std::thread::spawn(move || {
+            if let Err(e) = write_lines(&receiver, BufWriter::new(writer)) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %e, "failed to write the stream recording");
+                *failed.lock().unwrap_or_else(PoisonError::into_inner) = Some(e);
+            }
+        });
+        LineRecorder {
+            shared: Arc::new(Shared {
+                lines: Some(sender),
+                thread: Some(thread),
+                error,
+                dropping: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Records one raw line (without its line terminator).
+    pub(crate) fn record_line(&self, line: &[u8]) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let entry = RecordedLine {
+            received: Utc::now(),
+            line: String::from_utf8_lossy(line).into_owned(),
+        };
+        let (Ok(json), Some(lines)) = (serde_json::to_string(&entry), &self.shared.lines) else {
+            return;
+        };
+        match lines.try_send(json) {
+            Ok(()) => self.shared.dropping.store(false, Ordering::Relaxed),
+            Err(TrySendError::Full(_)) => {
+                if !self.shared.dropping.swap(true, Ordering::Relaxed) {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("the stream recording fell behind; dropping lines");
+                    self.shared
+                        .error
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .get_or_insert_with(|| {
+                            io::Error::other("the recording fell behind and dropped lines")
+                        });
+                }
+            }
+            // The thread has stopped on a write error.
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// Records an item that did not arrive as a line (such as a
+    /// transaction back-filled over REST) as if it had.
+    pub(crate) fn record_item<T: Serialize>(&self, item: &T) {
+        if let Ok(line) = serde_json::to_string(item) {
+            self.record_line(line.as_bytes());
+        }
+    }
+
+    /// The write error that stopped the recording, or the lines it
+    /// dropped, the first time it is asked for.
+    pub(crate) fn take_error(&self) -> Option<io::Error> {
+        self.shared
+            .error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    /// Whether the writing thread has finished.
+    #[cfg(test)]
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.shared
+            .thread
+            .as_ref()
+            .is_none_or(JoinHandle::is_finished)
+    }
+}
+
+/// Writes lines until the channel closes, flushing whenever no more are
+/// waiting.
+fn write_lines(lines: &Receiver<String>, mut writer: impl Write) -> io::Result<()> {
+    loop {
+        let line = match lines.try_recv() {
+            Ok(line) => line,
+            Err(TryRecvError::Empty) => {
+                writer.flush()?;
+                match lines.recv() {
+                    Ok(line) => line,
+                    Err(_) => return Ok(()),
+                }
+            }
+            Err(TryRecvError::Disconnected) => return writer.flush(),
+        };
+        writeln!(writer, "{line}")?;
+    }
+}
+
+/// Replays a recording, yielding its lines parsed as `T` at the chosen
+/// speed.
+pub(crate) struct Replay<T> {
+    lines: JsonLines<BoxStream<'static, Result<Bytes, Error>>, RecordedLine>,
+    speed: ReplaySpeed,
+    /// When replay started, and the receive time of the first line.
+    origin: Option<(Instant, chrono::DateTime<Utc>)>,
+    /// A line waiting for its replay time.
+    waiting: Option<(Pin<Box<Sleep>>, String)>,
+    /// Drops items before they are yielded, as the live stream would.
+    filter: Box<dyn FnMut(&T) -> bool + Send>,
+}
+
+impl<T> Replay<T> {
+    pub(crate) fn new(reader: impl Read + Send + 'static, speed: ReplaySpeed) -> Self {
+        // Reads block, so they run on tokio's blocking pool rather than in
+        // the task polling the stream.
+        let chunks = futures_util::stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+            let read = tokio::task::spawn_blocking(move || {
+                let mut buffer = vec![0; READ_CHUNK];
+                let read = reader.read(&mut buffer).map(|read| {
+                    buffer.truncate(read);
+                    buffer
+                });
+                (reader, read)
+            })
+            .await;
+            match read {
+                Ok((_, Ok(buffer))) if buffer.is_empty() => None,
+                Ok((reader, Ok(buffer))) => Some((Ok(Bytes::from(buffer)), Some(reader))),
+                Ok((_, Err(e))) => Some((
+                    Err(Error::Stream(format!("failed to read the recording: {e}"))),
+                    None,
+                )),
+                Err(e) => Some((
+                    Err(Error::Stream(format!("failed to read the recording: {e}"))),
+                    None,
+                )),
+            }
+        });
+        Replay {
+            lines: JsonLines::new(chunks.boxed()),
+            speed,
+            origin: None,
+            waiting: None,
+            filter: Box::new(|_| true),
+        }
+    }
+
+    /// Drops the items `filter` returns `false` for.
+    pub(crate) fn filter(mut self, filter: impl FnMut(&T) -> bool + Send + 'static) -> Self {
+        self.filter = Box::new(filter);
+        self
+    }
+
+    /// How long after the first line `received` is due.
+    fn delay(&self, first: chrono::DateTime<Utc>, received: chrono::DateTime<Utc>) -> Duration {
+        let recorded = (received - first).to_std().unwrap_or_default();
+        match self.speed {
+            ReplaySpeed::Recorded => recorded,
+            ReplaySpeed::Scaled(factor) if factor > 0.0 && factor.is_finite() => {
+                recorded.div_f64(factor)
+            }
+            ReplaySpeed::Scaled(_) | ReplaySpeed::Unthrottled => Duration::ZERO,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Stream for Replay<T> {
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some((sleep, _)) = &mut this.waiting {
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                let (_, line) = this.waiting.take().expect("checked above");
+                match parse_line(line.as_bytes()) {
+                    Some(Ok(item)) if !(this.filter)(&item) => continue,
+                    Some(item) => return Poll::Ready(Some(item)),
+                    None => continue,
+                }
+            }
+            let recorded = match Pin::new(&mut this.lines).poll_next(cx) {
+                Poll::Ready(Some(Ok(recorded))) => recorded,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            let (started, first) = *this
+                .origin
+                .get_or_insert_with(|| (Instant::now(), recorded.received));
+            let due = started + this.delay(first, recorded.received);
+            this.waiting = Some((Box::pin(tokio::time::sleep_until(due)), recorded.line));
+        }
+    }
+}
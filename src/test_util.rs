@@ -0,0 +1,8 @@
+//! Helpers shared by the unit tests.
+
+use rust_decimal::Decimal;
+
+/// Parses a decimal literal.
+pub(crate) fn dec(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
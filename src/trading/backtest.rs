@@ -0,0 +1,371 @@
+//! Replays historical candles or recorded prices through a
+//! [`PaperAccount`].
+
+use chrono::{Duration, TimeDelta, Utc};
+use rust_decimal::Decimal;
+use serde_json::json;
+
+use super::paper::PaperAccount;
+use super::performance::PerformanceReport;
+use crate::error::Error;
+use crate::models::transaction::{OrderFillTransaction, Transaction};
+use crate::models::{
+    AccountId, AccountUnits, Candlestick, CandlestickData, CandlestickGranularity, ClientPrice,
+    DateTime, Instrument, InstrumentCandles, TradeSummary,
+};
+
+/// The UTC hour at which daily financing is charged (17:00 New York
+/// during daylight saving time).
+const ROLLOVER_HOUR: u32 = 21;
+
+/// Runs a strategy over historical prices against a [`PaperAccount`].
+///
+/// The strategy is an async closure called with the account and each
+/// price, after the price has been applied (so pending orders have
+/// already been triggered). Since [`PaperAccount`] implements
+/// [`Broker`](crate::broker::Broker), a strategy written against
+/// `impl Broker` is the same code that runs live.
+///
+/// Candles are replayed as four ticks each (open, low, high, close for
+/// rising candles; open, high, low, close for falling ones) spread over
+/// the candle's interval, taken from the next candle's open time. Bid
+/// and ask candles are used when present; mid-only candles are quoted
+/// with the configured [`spread`](Self::spread). Ticks built from candles
+/// have unlimited liquidity. Daily financing is
+/// charged whenever the replay crosses 21:00 UTC, at the prices before
+/// the crossing.
+///
+/// ```no_run
+/// use oanda_rs::broker::Broker;
+/// use oanda_rs::models::{AccountId, CandlestickGranularity, MarketOrderRequest, PricingComponent};
+/// use oanda_rs::trading::Backtest;
+///
+/// # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+/// let candles = client
+///     .candles("EUR_USD")
+///     .granularity(CandlestickGranularity::H1)
+///     .price(PricingComponent::BID.with_ask())
+///     .count(500)
+///     .send()
+///     .await?;
+///
+/// let account = AccountId::from("backtest");
+/// let report = Backtest::new(account.clone(), 10_000)
+///     .run_candles([&candles], async |broker, price| {
+///         if broker.list_open_trades(&account).await?.trades.is_empty() {
+///             let instrument = price.instrument.clone().unwrap();
+///             broker
+///                 .create_order(&account, MarketOrderRequest::new(instrument, 1000).into())
+///                 .await?;
+///         }
+///         Ok(())
+///     })
+///     .await?;
+/// println!("max drawdown: {}", report.max_drawdown);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backtest {
+    account: PaperAccount,
+    starting_balance: AccountUnits,
+    spread: Decimal,
+}
+
+/// The outcome of a [`Backtest`] run.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct BacktestReport {
+    /// Every transaction the account recorded, oldest first.
+    pub transactions: Vec<Transaction>,
+    /// The order fills, oldest first.
+    pub fills: Vec<OrderFillTransaction>,
+    /// Every trade opened, open and closed, oldest first.
+    pub trades: Vec<TradeSummary>,
+    /// The account's balance and NAV after every price.
+    pub equity_curve: Vec<EquityPoint>,
+    /// The balance the account started with.
+    pub starting_balance: AccountUnits,
+    /// The balance at the end of the run.
+    pub final_balance: AccountUnits,
+    /// The NAV at the end of the run, including open trades.
+    pub final_nav: AccountUnits,
+    /// The total financing charged (negative) or paid (positive).
+    pub financing: AccountUnits,
+    /// The largest fall in NAV from a previous peak, in the home currency.
+    pub max_drawdown: AccountUnits,
+    /// The largest fall in NAV from a previous peak, as a fraction of that
+    /// peak.
+    pub max_drawdown_percent: Decimal,
+}
+
+/// One point of an equity curve.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct EquityPoint {
+    /// The time of the price the point was taken at.
+    pub time: Option<DateTime>,
+    /// The account's balance.
+    pub balance: AccountUnits,
+    /// The account's net asset value.
+    pub nav: AccountUnits,
+    /// Funds deposited (positive) or withdrawn (negative) at this point,
+    /// already included in `balance` and `nav`.
+    pub cash_flow: AccountUnits,
+}
+
+impl BacktestReport {
+    /// Per-trade results and statistics over the run's transactions.
+    pub fn performance(&self) -> PerformanceReport {
+        PerformanceReport::from_transactions(&self.transactions)
+    }
+}
+
+impl Backtest {
+    /// Creates a backtest over a fresh [`PaperAccount`] funded with
+    /// `balance`.
+    pub fn new(account_id: impl Into<AccountId>, balance: impl Into<AccountUnits>) -> Self {
+        let balance = balance.into();
+        Backtest {
+            account: PaperAccount::new(account_id, balance),
+            starting_balance: balance,
+            spread: Decimal::ZERO,
+        }
+    }
+
+    /// The spread to quote mid-only candles with, in price units (default
+    /// zero).
+    pub fn spread(mut self, spread: impl Into<Decimal>) -> Self {
+        self.spread = spread.into();
+        self
+    }
+
+    /// Registers an instrument with the account, for its margin and
+    /// financing rates.
+    pub fn add_instrument(self, instrument: &Instrument) -> Self {
+        self.account.add_instrument(instrument);
+        self
+    }
+
+    /// The simulated account.
+    pub fn account(&self) -> &PaperAccount {
+        &self.account
+    }
+
+    /// Replays candles of one or more instruments, merged in time order.
+    ///
+    /// Incomplete candles are skipped.
+    pub async fn run_candles<'a, S>(
+        &self,
+        candles: impl IntoIterator<Item = &'a InstrumentCandles>,
+        strategy: S,
+    ) -> Result<BacktestReport, Error>
+    where
+        S: AsyncFnMut(&PaperAccount, &ClientPrice) -> Result<(), Error>,
+    {
+        let mut prices: Vec<(chrono::DateTime<Utc>, ClientPrice)> = candles
+            .into_iter()
+            .flat_map(|candles| candle_prices(candles, self.spread))
+            .collect();
+        prices.sort_by_key(|(time, _)| *time);
+        self.run(prices.into_iter().map(|(_, price)| price), strategy)
+            .await
+    }
+
+    /// Replays recorded prices in the order given.
+    ///
+    /// Stops at the first error the strategy returns.
+    pub async fn run<S>(
+        &self,
+        prices: impl IntoIterator<Item = ClientPrice>,
+        mut strategy: S,
+    ) -> Result<BacktestReport, Error>
+    where
+        S: AsyncFnMut(&PaperAccount, &ClientPrice) -> Result<(), Error>,
+    {
+        let mut equity_curve = Vec::new();
+        let mut last_rollover: Option<chrono::NaiveDate> = None;
+        for price in prices {
+            let rollover = price
+                .time
+                .as_ref()
+                .and_then(DateTime::to_utc)
+                .map(rollover_day);
+            if let (Some(last), Some(current)) = (last_rollover, rollover) {
+                for _ in 0..(current - last).num_days() {
+                    self.account.apply_daily_financing();
+                }
+            }
+            last_rollover = rollover.or(last_rollover);
+
+            self.account.update_price(&price);
+            strategy(&self.account, &price).await?;
+            equity_curve.push(EquityPoint {
+                time: price.time.clone(),
+                balance: self.account.balance(),
+                nav: self.account.nav(),
+                cash_flow: AccountUnits::default(),
+            });
+        }
+        Ok(self.report(equity_curve))
+    }
+
+    fn report(&self, equity_curve: Vec<EquityPoint>) -> BacktestReport {
+        let transactions = self.account.transactions();
+        let fills = transactions
+            .iter()
+            .filter_map(|tx| match tx {
+                Transaction::OrderFill(fill) => Some(fill.clone()),
+                _ => None,
+            })
+            .collect();
+        let financing = transactions
+            .iter()
+            .filter_map(|tx| match tx {
+                Transaction::DailyFinancing(financing) => financing.financing,
+                _ => None,
+            })
+            .map(|amount| amount.value())
+            .sum::<Decimal>();
+
+        let mut peak = self.starting_balance.value();
+        let mut max_drawdown = Decimal::ZERO;
+        let mut max_drawdown_percent = Decimal::ZERO;
+        for point in &equity_curve {
+            let nav = point.nav.value();
+            peak = peak.max(nav);
+            max_drawdown = max_drawdown.max(peak - nav);
+            if peak > Decimal::ZERO {
+                max_drawdown_percent = max_drawdown_percent.max((peak - nav) / peak);
+            }
+        }
+
+        BacktestReport {
+            transactions,
+            fills,
+            trades: self.account.trades(),
+            equity_curve,
+            starting_balance: self.starting_balance,
+            final_balance: self.account.balance(),
+            final_nav: self.account.nav(),
+            financing: AccountUnits(financing.normalize()),
+            max_drawdown: AccountUnits(max_drawdown.normalize()),
+            max_drawdown_percent: max_drawdown_percent.normalize(),
+        }
+    }
+}
+
+/// The trading day a time falls in, starting at the rollover hour.
+fn rollover_day(time: chrono::DateTime<Utc>) -> chrono::NaiveDate {
+    (time - Duration::hours(i64::from(ROLLOVER_HOUR))).date_naive()
+}
+
+/// The complete candles of a series with their open times and lengths.
+///
+/// The length comes from the series granularity, falling back to the gap to
+/// the next candle (the last one reusing the previous gap) for weekly and
+/// monthly candles, whose length varies.
+pub(super) fn timed_candles(
+    series: &InstrumentCandles,
+) -> Vec<(chrono::DateTime<Utc>, TimeDelta, &Candlestick)> {
+    let complete: Vec<(chrono::DateTime<Utc>, &Candlestick)> = series
+        .candles
+        .iter()
+        .filter(|c| c.complete != Some(false))
+        .filter_map(|c| Some((c.time.as_ref()?.to_utc()?, c)))
+        .collect();
+    let fixed = series.granularity.as_ref().and_then(granularity_length);
+    let mut gap = TimeDelta::zero();
+    let mut timed = Vec::with_capacity(complete.len());
+    for (index, (open, candle)) in complete.iter().enumerate() {
+        if let Some((next, _)) = complete.get(index + 1) {
+            gap = *next - *open;
+        }
+        timed.push((*open, fixed.unwrap_or(gap), *candle));
+    }
+    timed
+}
+
+/// The length of a candle of a fixed granularity.
+fn granularity_length(granularity: &CandlestickGranularity) -> Option<TimeDelta> {
+    use CandlestickGranularity::*;
+    let seconds = match granularity {
+        S5 => 5,
+        S10 => 10,
+        S15 => 15,
+        S30 => 30,
+        M1 => 60,
+        M2 => 2 * 60,
+        M4 => 4 * 60,
+        M5 => 5 * 60,
+        M10 => 10 * 60,
+        M15 => 15 * 60,
+        M30 => 30 * 60,
+        H1 => 3600,
+        H2 => 2 * 3600,
+        H3 => 3 * 3600,
+        H4 => 4 * 3600,
+        H6 => 6 * 3600,
+        H8 => 8 * 3600,
+        H12 => 12 * 3600,
+        D => 24 * 3600,
+        W | M | Other(_) => return None,
+    };
+    Some(TimeDelta::seconds(seconds))
+}
+
+/// The ticks a set of candles is replayed as, with their times.
+fn candle_prices(
+    candles: &InstrumentCandles,
+    spread: Decimal,
+) -> Vec<(chrono::DateTime<Utc>, ClientPrice)> {
+    let timed = timed_candles(candles);
+    let mut prices = Vec::with_capacity(timed.len() * 4);
+    for (open, interval, candle) in timed {
+        let Some(ticks) = candle_ticks(candle, spread) else {
+            continue;
+        };
+        for (step, (bid, ask)) in ticks.into_iter().enumerate() {
+            let time = open + interval * step as i32 / 4;
+            let price = serde_json::from_value(json!({
+                "instrument": candles.instrument,
+                "time": DateTime::from(time),
+                "tradeable": true,
+                "bids": [{"price": bid.to_string()}],
+                "asks": [{"price": ask.to_string()}],
+                "closeoutBid": bid.to_string(),
+                "closeoutAsk": ask.to_string(),
+            }))
+            .expect("candle ticks are valid prices");
+            prices.push((time, price));
+        }
+    }
+    prices
+}
+
+/// The bid and ask of a candle's four ticks.
+fn candle_ticks(candle: &Candlestick, spread: Decimal) -> Option<[(Decimal, Decimal); 4]> {
+    let path = |data: &CandlestickData, rising: bool| -> Option<[Decimal; 4]> {
+        let (o, h, l, c) = (
+            data.o?.value(),
+            data.h?.value(),
+            data.l?.value(),
+            data.c?.value(),
+        );
+        Some(if rising { [o, l, h, c] } else { [o, h, l, c] })
+    };
+    let rising = |data: &CandlestickData| data.c.zip(data.o).is_some_and(|(c, o)| c >= o);
+    match (&candle.bid, &candle.ask, &candle.mid) {
+        (Some(bid), Some(ask), _) => {
+            let rising = rising(bid);
+            let (bids, asks) = (path(bid, rising)?, path(ask, rising)?);
+            Some(std::array::from_fn(|i| (bids[i], asks[i])))
+        }
+        (_, _, Some(mid)) => {
+            let mids = path(mid, rising(mid))?;
+            let half = spread / Decimal::TWO;
+            Some(mids.map(|m| (m - half, m + half)))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,599 @@
+//! Composition of entry orders with take-profit, stop-loss and trailing
+//! stop-loss exits, and one-cancels-other pairs of pending orders.
+
+use futures_util::future::{Either, select};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::models::{
+    AccountId, ClientExtensions, DecimalNumber, Instrument, InstrumentName, LimitOrderRequest,
+    MarketIfTouchedOrderRequest, MarketOrderRequest, OrderRequest, OrderSpecifier, PriceValue,
+    StopLossDetails, StopOrderRequest, TakeProfitDetails, TrailingStopLossDetails,
+};
+use crate::trading::{OrderStatus, OrderTrackerHandle};
+
+/// Where a [`Bracket`]'s take-profit, stop-loss or trailing stop sits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Exit {
+    /// An absolute price.
+    Price(PriceValue),
+    /// A distance from the entry in pips; needs the instrument's pip
+    /// location (see [`Bracket::instrument_spec`]).
+    Pips(Decimal),
+    /// A distance from the entry in price units.
+    Distance(Decimal),
+    /// Take-profit only: the stop-loss distance times this ratio, e.g.
+    /// `2` for a 1:2 risk:reward.
+    RiskReward(Decimal),
+}
+
+/// How a [`Bracket`] enters the market.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Entry {
+    Market,
+    Limit(PriceValue),
+    Stop(PriceValue),
+    MarketIfTouched(PriceValue),
+}
+
+impl Entry {
+    fn price(self) -> Option<PriceValue> {
+        match self {
+            Entry::Market => None,
+            Entry::Limit(price) | Entry::Stop(price) | Entry::MarketIfTouched(price) => Some(price),
+        }
+    }
+}
+
+/// Builds an entry order with its take-profit, stop-loss and trailing
+/// stop-loss attached as on-fill details.
+///
+/// Exits can be given as absolute prices, in pips, in price units or (for
+/// the take-profit) as a risk:reward multiple of the stop-loss distance.
+/// Stop-loss and trailing-stop distances are passed to OANDA as
+/// distances, so they apply to the actual fill price; a take-profit is
+/// always placed at a price, computed from the entry price (or, for
+/// market entries, the [`reference_price`](Self::reference_price)).
+/// Computed prices are rounded to the instrument's display precision when
+/// it is known.
+///
+/// ```
+/// use oanda_rs::models::{OrderRequest, PriceValue};
+/// use oanda_rs::trading::{Bracket, Exit};
+/// use rust_decimal::Decimal;
+///
+/// let order: OrderRequest = Bracket::limit("EUR_USD", 10_000, "1.07000".parse::<PriceValue>().unwrap())
+///     .pip_location(-4)
+///     .display_precision(5)
+///     .stop_loss(Exit::Pips(Decimal::from(20)))
+///     .take_profit(Exit::RiskReward(Decimal::from(2)))
+///     .build()
+///     .unwrap();
+/// let OrderRequest::Limit(limit) = order else { unreachable!() };
+/// let take_profit = limit.take_profit_on_fill.unwrap().price.unwrap();
+/// assert_eq!(take_profit.to_string(), "1.07400");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bracket {
+    instrument: InstrumentName,
+    units: DecimalNumber,
+    entry: Entry,
+    reference_price: Option<PriceValue>,
+    pip_location: Option<i64>,
+    display_precision: Option<u32>,
+    take_profit: Option<Exit>,
+    stop_loss: Option<Exit>,
+    trailing_stop: Option<Exit>,
+    client_extensions: Option<ClientExtensions>,
+    trade_client_extensions: Option<ClientExtensions>,
+}
+
+impl Bracket {
+    fn new(instrument: InstrumentName, units: DecimalNumber, entry: Entry) -> Self {
+        Bracket {
+            instrument,
+            units,
+            entry,
+            reference_price: None,
+            pip_location: None,
+            display_precision: None,
+            take_profit: None,
+            stop_loss: None,
+            trailing_stop: None,
+            client_extensions: None,
+            trade_client_extensions: None,
+        }
+    }
+
+    /// Enters with a market order. Positive `units` go long, negative
+    /// short.
+    pub fn market(instrument: impl Into<InstrumentName>, units: impl Into<DecimalNumber>) -> Self {
+        Bracket::new(instrument.into(), units.into(), Entry::Market)
+    }
+
+    /// Enters with a limit order at `price` or better.
+    pub fn limit(
+        instrument: impl Into<InstrumentName>,
+        units: impl Into<DecimalNumber>,
+        price: impl Into<PriceValue>,
+    ) -> Self {
+        Bracket::new(instrument.into(), units.into(), Entry::Limit(price.into()))
+    }
+
+    /// Enters with a stop order at `price` or worse.
+    pub fn stop(
+        instrument: impl Into<InstrumentName>,
+        units: impl Into<DecimalNumber>,
+        price: impl Into<PriceValue>,
+    ) -> Self {
+        Bracket::new(instrument.into(), units.into(), Entry::Stop(price.into()))
+    }
+
+    /// Enters with a market-if-touched order triggered at `price`.
+    pub fn market_if_touched(
+        instrument: impl Into<InstrumentName>,
+        units: impl Into<DecimalNumber>,
+        price: impl Into<PriceValue>,
+    ) -> Self {
+        Bracket::new(
+            instrument.into(),
+            units.into(),
+            Entry::MarketIfTouched(price.into()),
+        )
+    }
+
+    /// Sets the take-profit.
+    pub fn take_profit(mut self, exit: Exit) -> Self {
+        self.take_profit = Some(exit);
+        self
+    }
+
+    /// Sets the stop-loss.
+    pub fn stop_loss(mut self, exit: Exit) -> Self {
+        self.stop_loss = Some(exit);
+        self
+    }
+
+    /// Sets a trailing stop-loss; only distances ([`Exit::Pips`],
+    /// [`Exit::Distance`]) are meaningful.
+    pub fn trailing_stop(mut self, exit: Exit) -> Self {
+        self.trailing_stop = Some(exit);
+        self
+    }
+
+    /// The price take-profit distances of a market entry are measured
+    /// from, typically the current ask (long) or bid (short).
+    pub fn reference_price(mut self, price: impl Into<PriceValue>) -> Self {
+        self.reference_price = Some(price.into());
+        self
+    }
+
+    /// Takes the pip location and display precision from the instrument's
+    /// details (see [`Client::account_instruments`]).
+    ///
+    /// [`Client::account_instruments`]: crate::Client::account_instruments
+    pub fn instrument_spec(mut self, instrument: &Instrument) -> Self {
+        self.pip_location = instrument.pip_location.or(self.pip_location);
+        if let Some(precision) = instrument
+            .display_precision
+            .and_then(|p| u32::try_from(p).ok())
+        {
+            self.display_precision = Some(precision);
+        }
+        self
+    }
+
+    /// The instrument's pip location: a pip is `10^location` (e.g. `-4`
+    /// for EUR/USD, `-2` for USD/JPY).
+    pub fn pip_location(mut self, location: i64) -> Self {
+        self.pip_location = Some(location);
+        self
+    }
+
+    /// The number of decimal places computed prices are rounded to.
+    pub fn display_precision(mut self, precision: u32) -> Self {
+        self.display_precision = Some(precision);
+        self
+    }
+
+    /// Attaches client extensions to the entry order.
+    pub fn client_extensions(mut self, extensions: ClientExtensions) -> Self {
+        self.client_extensions = Some(extensions);
+        self
+    }
+
+    /// Attaches client extensions to the trade opened by the entry.
+    pub fn trade_client_extensions(mut self, extensions: ClientExtensions) -> Self {
+        self.trade_client_extensions = Some(extensions);
+        self
+    }
+
+    /// Composes the entry order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidOrder`] when the exits cannot be resolved:
+    /// zero units, a non-positive distance, pips without a pip location, a
+    /// take-profit distance on a market entry without a reference price, a
+    /// risk:reward take-profit without a stop-loss, or an exit on the
+    /// wrong side of the entry. Exits are checked after rounding to the
+    /// display precision, so a distance rounding to zero or a
+    /// take-profit rounding onto the entry is rejected too.
+    pub fn build(&self) -> Result<OrderRequest, Error> {
+        let units = self.units.value();
+        if units.is_zero() {
+            return Err(invalid("units must not be zero"));
+        }
+        // +1 for long entries, -1 for short ones: exits in the trade's
+        // favour lie at entry + distance * direction.
+        let direction = if units.is_sign_positive() {
+            Decimal::ONE
+        } else {
+            Decimal::NEGATIVE_ONE
+        };
+        let entry = self
+            .entry
+            .price()
+            .or(self.reference_price)
+            .map(|p| p.value());
+
+        let (stop_loss, risk) = match self.stop_loss {
+            None => (None, None),
+            Some(Exit::Price(price)) => {
+                let risk = entry.map(|entry| (entry - price.value()) * direction);
+                if risk.is_some_and(|risk| risk <= Decimal::ZERO) {
+                    return Err(invalid("the stop-loss is on the wrong side of the entry"));
+                }
+                (Some(StopLossDetails::at_price(price)), risk)
+            }
+            Some(Exit::RiskReward(_)) => {
+                return Err(invalid("a stop-loss cannot be a risk:reward multiple"));
+            }
+            Some(exit) => {
+                let distance = self.distance(exit)?;
+                (
+                    Some(StopLossDetails::at_distance(distance.into())),
+                    Some(distance),
+                )
+            }
+        };
+
+        let take_profit = match self.take_profit {
+            None => None,
+            Some(exit) => {
+                let price = self.round(match exit {
+                    Exit::Price(price) => price.value(),
+                    Exit::RiskReward(ratio) => {
+                        let risk = risk.ok_or_else(|| {
+                            invalid("a risk:reward take-profit needs a stop-loss")
+                        })?;
+                        if ratio <= Decimal::ZERO {
+                            return Err(invalid("the risk:reward ratio must be positive"));
+                        }
+                        self.entry_for_take_profit(entry)? + risk * ratio * direction
+                    }
+                    exit => self.entry_for_take_profit(entry)? + self.distance(exit)? * direction,
+                });
+                if entry.is_some_and(|entry| (price - entry) * direction <= Decimal::ZERO) {
+                    return Err(invalid("the take-profit is on the wrong side of the entry"));
+                }
+                Some(TakeProfitDetails::at_price(price.into()))
+            }
+        };
+
+        let trailing_stop = match self.trailing_stop {
+            None => None,
+            Some(exit @ (Exit::Pips(_) | Exit::Distance(_))) => Some(
+                TrailingStopLossDetails::at_distance(self.distance(exit)?.into()),
+            ),
+            Some(_) => return Err(invalid("a trailing stop must be given as a distance")),
+        };
+
+        /// Attaches the exits and extensions to one of the entry request
+        /// types, which share the field names.
+        macro_rules! with_exits {
+            ($request:expr) => {{
+                let mut request = $request;
+                request.take_profit_on_fill = take_profit;
+                request.stop_loss_on_fill = stop_loss;
+                request.trailing_stop_loss_on_fill = trailing_stop;
+                request.client_extensions = self.client_extensions.clone();
+                request.trade_client_extensions = self.trade_client_extensions.clone();
+                OrderRequest::from(request)
+            }};
+        }
+
+        let instrument = self.instrument.clone();
+        Ok(match self.entry {
+            Entry::Market => with_exits!(MarketOrderRequest::new(instrument, self.units)),
+            Entry::Limit(price) => {
+                with_exits!(LimitOrderRequest::new(instrument, self.units, price))
+            }
+            Entry::Stop(price) => with_exits!(StopOrderRequest::new(instrument, self.units, price)),
+            Entry::MarketIfTouched(price) => with_exits!(MarketIfTouchedOrderRequest::new(
+                instrument, self.units, price
+            )),
+        })
+    }
+
+    /// The distance an exit describes, in price units, rounded to the
+    /// display precision.
+    fn distance(&self, exit: Exit) -> Result<Decimal, Error> {
+        let distance = match exit {
+            Exit::Distance(distance) => distance,
+            Exit::Pips(pips) => {
+                let location = self
+                    .pip_location
+                    .ok_or_else(|| invalid("pip distances need the instrument's pip location"))?;
+                pips * pip_size(location)
+                    .ok_or_else(|| invalid("the instrument's pip location is out of range"))?
+            }
+            Exit::Price(_) | Exit::RiskReward(_) => unreachable!("not a distance"),
+        };
+        if distance <= Decimal::ZERO {
+            return Err(invalid("exit distances must be positive"));
+        }
+        let distance = self.round(distance);
+        if distance.is_zero() {
+            return Err(invalid(
+                "the exit distance rounds to zero at the instrument's display precision",
+            ));
+        }
+        Ok(distance)
+    }
+
+    fn entry_for_take_profit(&self, entry: Option<Decimal>) -> Result<Decimal, Error> {
+        entry.ok_or_else(|| {
+            invalid("a take-profit distance on a market entry needs a reference price")
+        })
+    }
+
+    fn round(&self, value: Decimal) -> Decimal {
+        match self.display_precision {
+            Some(precision) => value.round_dp(precision),
+            None => value,
+        }
+    }
+}
+
+impl TryFrom<Bracket> for OrderRequest {
+    type Error = Error;
+
+    fn try_from(bracket: Bracket) -> Result<Self, Error> {
+        bracket.build()
+    }
+}
+
+/// The size of one pip for a pip location, e.g. `0.0001` for `-4`; `None`
+/// when the location is outside what a [`Decimal`] can represent.
+pub(crate) fn pip_size(location: i64) -> Option<Decimal> {
+    let exponent = u32::try_from(location.unsigned_abs()).ok()?;
+    if location < 0 {
+        Decimal::try_new(1, exponent).ok()
+    } else {
+        10u64.checked_pow(exponent).map(Decimal::from)
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidOrder(message.to_owned())
+}
+
+/// A one-cancels-other pair of pending orders: once either leg fills, the
+/// other is cancelled.
+///
+/// A leg cancelled by OANDA or another client (expired, cancelled for
+/// margin, replaced) leaves its sibling untouched.
+///
+/// Driven by an [`OrderTracker`](crate::trading::OrderTracker), whose
+/// transaction stream must be consumed while [`run`](Self::run) waits.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), oanda_rs::Error> {
+/// # let client = oanda_rs::Client::new(oanda_rs::Environment::Practice, "token");
+/// use oanda_rs::trading::{Oco, OrderTracker};
+///
+/// let account = "101-004-1234567-001";
+/// let tracker = OrderTracker::new(client.transaction_stream(account).send().await?);
+/// let orders = tracker.handle();
+/// tokio::spawn(tracker.run());
+///
+/// let outcome = Oco::new(&client, account, "6368", "6369").run(&orders).await?;
+/// println!("{:?} resolved first: {:?}", outcome.resolved, outcome.resolved_status);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Oco {
+    client: Client,
+    account_id: AccountId,
+    legs: [OrderSpecifier; 2],
+}
+
+impl Oco {
+    /// Pairs two pending orders, addressed by ID or client ID.
+    pub fn new(
+        client: &Client,
+        account_id: impl Into<AccountId>,
+        first: impl Into<OrderSpecifier>,
+        second: impl Into<OrderSpecifier>,
+    ) -> Self {
+        Oco {
+            client: client.clone(),
+            account_id: account_id.into(),
+            legs: [first.into(), second.into()],
+        }
+    }
+
+    /// Waits for either leg to fill or be cancelled. After a fill, cancels
+    /// the other leg and waits for it to resolve too; after a
+    /// cancellation, returns at once with the other leg still
+    /// [`Pending`](OrderStatus::Pending).
+    ///
+    /// # Errors
+    ///
+    /// Fails when the tracker stops before both legs resolved, or when
+    /// the sibling's cancellation fails for any reason other than the
+    /// order no longer being pending.
+    pub async fn run(self, orders: &OrderTrackerHandle) -> Result<OcoOutcome, Error> {
+        let [first, second] = self.legs.clone();
+        let first_outcome = Box::pin(orders.track(first.clone()).outcome());
+        let second_outcome = Box::pin(orders.track(second.clone()).outcome());
+        let (resolved, resolved_status, sibling, sibling_outcome) =
+            match select(first_outcome, second_outcome).await {
+                Either::Left((status, other)) => (first, status?, second, other),
+                Either::Right((status, other)) => (second, status?, first, other),
+            };
+        if !matches!(resolved_status, OrderStatus::Filled(_)) {
+            return Ok(OcoOutcome {
+                resolved,
+                resolved_status,
+                sibling,
+                sibling_status: OrderStatus::Pending,
+            });
+        }
+
+        match self
+            .client
+            .cancel_order(self.account_id.clone(), sibling.clone())
+            .await
+        {
+            Ok(_) => {}
+            // Already filled or cancelled: the tracker reports which.
+            Err(error) if error.status() == Some(StatusCode::NOT_FOUND) => {}
+            Err(error) => return Err(error),
+        }
+        let sibling_status = sibling_outcome.await?;
+        Ok(OcoOutcome {
+            resolved,
+            resolved_status,
+            sibling,
+            sibling_status,
+        })
+    }
+}
+
+/// Result of [`Oco::run`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct OcoOutcome {
+    /// The leg that filled or was cancelled first.
+    pub resolved: OrderSpecifier,
+    /// Its final status.
+    pub resolved_status: OrderStatus,
+    /// The other leg.
+    pub sibling: OrderSpecifier,
+    /// The sibling's final status: normally cancelled, but filled when it
+    /// filled before the cancellation landed. Still pending when the first
+    /// leg was cancelled rather than filled.
+    pub sibling_status: OrderStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dec;
+
+    fn price(s: &str) -> PriceValue {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn pip_sizes() {
+        assert_eq!(pip_size(-4), Some(dec("0.0001")));
+        assert_eq!(pip_size(-2), Some(dec("0.01")));
+        assert_eq!(pip_size(0), Some(dec("1")));
+        assert_eq!(pip_size(1), Some(dec("10")));
+        assert_eq!(pip_size(-29), None);
+        assert_eq!(pip_size(20), None);
+        assert_eq!(pip_size(i64::MIN), None);
+    }
+
+    #[test]
+    fn short_limit_with_price_exits() {
+        let order = Bracket::limit("EUR_USD", -1000, price("1.08000"))
+            .stop_loss(Exit::Price(price("1.08300")))
+            .take_profit(Exit::RiskReward(dec("1.5")))
+            .trailing_stop(Exit::Distance(dec("0.0025")))
+            .build()
+            .unwrap();
+        let OrderRequest::Limit(limit) = order else {
+            panic!("expected a limit order");
+        };
+        assert_eq!(
+            limit.stop_loss_on_fill.unwrap().price,
+            Some(price("1.08300"))
+        );
+        assert_eq!(
+            limit.take_profit_on_fill.unwrap().price.unwrap().value(),
+            dec("1.07550")
+        );
+        assert_eq!(
+            limit
+                .trailing_stop_loss_on_fill
+                .unwrap()
+                .distance
+                .unwrap()
+                .value(),
+            dec("0.0025")
+        );
+    }
+
+    #[test]
+    fn market_entry_uses_distances_and_reference_price() {
+        let order = Bracket::market("USD_JPY", 500)
+            .pip_location(-2)
+            .display_precision(3)
+            .stop_loss(Exit::Pips(dec("15")))
+            .take_profit(Exit::Pips(dec("30")))
+            .reference_price(price("157.1234"))
+            .build()
+            .unwrap();
+        let OrderRequest::Market(market) = order else {
+            panic!("expected a market order");
+        };
+        let stop_loss = market.stop_loss_on_fill.unwrap();
+        assert_eq!(stop_loss.price, None);
+        assert_eq!(stop_loss.distance.unwrap().value(), dec("0.15"));
+        assert_eq!(
+            market.take_profit_on_fill.unwrap().price.unwrap().value(),
+            dec("157.423")
+        );
+    }
+
+    #[test]
+    fn rejects_unresolvable_exits() {
+        let long = || Bracket::limit("EUR_USD", 100, price("1.07000"));
+        let cases = [
+            long().stop_loss(Exit::Price(price("1.07100"))),
+            long().take_profit(Exit::Price(price("1.06900"))),
+            long().take_profit(Exit::RiskReward(dec("2"))),
+            long().stop_loss(Exit::Pips(dec("10"))),
+            long().pip_location(-40).stop_loss(Exit::Pips(dec("10"))),
+            long().pip_location(25).stop_loss(Exit::Pips(dec("10"))),
+            long().stop_loss(Exit::Distance(dec("-0.001"))),
+            long().trailing_stop(Exit::Price(price("1.06000"))),
+            Bracket::market("EUR_USD", 100).take_profit(Exit::Distance(dec("0.001"))),
+            Bracket::market("EUR_USD", 0),
+            // Exits collapsing when rounded to the display precision.
+            long()
+                .display_precision(4)
+                .stop_loss(Exit::Distance(dec("0.00004"))),
+            long()
+                .display_precision(4)
+                .trailing_stop(Exit::Distance(dec("0.00004"))),
+            long()
+                .display_precision(4)
+                .take_profit(Exit::Price(price("1.07004"))),
+        ];
+        for bracket in cases {
+            assert!(
+                matches!(bracket.build(), Err(Error::InvalidOrder(_))),
+                "{bracket:?}"
+            );
+        }
+    }
+}
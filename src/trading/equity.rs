@@ -0,0 +1,357 @@
+//! Balance and NAV timelines reconstructed from transaction history.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+
+use super::backtest::{EquityPoint, timed_candles};
+use crate::export::TransactionRow;
+use crate::models::transaction::{OrderFillTransaction, Transaction};
+use crate::models::{
+    AccountUnits, CandlestickData, DateTime, InstrumentCandles, InstrumentName, TradeId,
+};
+
+/// An account's balance over time, optionally marked to market.
+///
+/// [`from_transactions`](Self::from_transactions) takes a point from every
+/// transaction that reports the resulting `accountBalance`. Deposits and
+/// withdrawals (`TRANSFER_FUNDS`) are recorded as the point's
+/// [`cash_flow`](EquityPoint::cash_flow), so they can be told apart from
+/// trading P/L; everything else that moves the balance (realized P/L,
+/// financing, commissions, dividends) counts as trading.
+///
+/// Until the curve is [marked to market](Self::mark_to_market), each
+/// point's NAV is its balance. Marking adds the unrealized P/L of the
+/// trades open at each point, valued at historical candle closes, and a
+/// point at the close of every candle.
+///
+/// ```no_run
+/// use oanda_rs::models::CandlestickGranularity;
+/// use oanda_rs::trading::EquityCurve;
+///
+/// # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+/// let history = client.transactions_since_id("101-004-1234567-001", "1").await?;
+/// let candles = client
+///     .candles("EUR_USD")
+///     .granularity(CandlestickGranularity::D)
+///     .from("2024-06-01T00:00:00Z")
+///     .send()
+///     .await?;
+/// let curve = EquityCurve::from_transactions(&history.transactions).mark_to_market([&candles]);
+/// let june = curve.between("2024-06-01T00:00:00Z", "2024-07-01T00:00:00Z");
+/// println!("June: {:?} time-weighted", june.time_weighted_return());
+/// println!("trading P/L {}, net deposits {}", june.trading_pl(), june.cash_flows());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EquityCurve {
+    points: Vec<EquityPoint>,
+    exposures: Vec<Exposure>,
+}
+
+/// A trade opened within the history, for marking to market.
+#[derive(Debug, Clone, PartialEq)]
+struct Exposure {
+    instrument: InstrumentName,
+    price: Decimal,
+    gain_factor: Decimal,
+    loss_factor: Decimal,
+    /// The trade's units after each change, oldest first.
+    units: Vec<(chrono::DateTime<Utc>, Decimal)>,
+}
+
+impl Exposure {
+    fn units_at(&self, time: chrono::DateTime<Utc>) -> Decimal {
+        let changes = self.units.partition_point(|(at, _)| *at <= time);
+        changes
+            .checked_sub(1)
+            .map_or(Decimal::ZERO, |i| self.units[i].1)
+    }
+}
+
+/// A candle close: its time, and the bid, mid and ask closes.
+type Mark = (
+    chrono::DateTime<Utc>,
+    Option<Decimal>,
+    Option<Decimal>,
+    Option<Decimal>,
+);
+
+impl EquityCurve {
+    /// Builds the balance curve from transactions in the order OANDA
+    /// created them.
+    ///
+    /// Transactions without a time or an `accountBalance` contribute no
+    /// point.
+    pub fn from_transactions<'a, I>(transactions: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Transaction>,
+    {
+        let mut curve = EquityCurve::default();
+        let mut open: HashMap<TradeId, usize> = HashMap::new();
+        for transaction in transactions {
+            let row = TransactionRow::from(transaction);
+            if let Transaction::OrderFill(fill) = transaction {
+                curve.track(fill, &mut open);
+            }
+            let Some(balance) = row.account_balance else {
+                continue;
+            };
+            if row.time.is_none() {
+                continue;
+            }
+            let cash_flow = match transaction {
+                Transaction::TransferFunds(transfer) => transfer.amount.unwrap_or_default(),
+                _ => AccountUnits::default(),
+            };
+            curve.points.push(EquityPoint {
+                time: row.time,
+                balance,
+                nav: balance,
+                cash_flow,
+            });
+        }
+        curve
+    }
+
+    fn track(&mut self, fill: &OrderFillTransaction, open: &mut HashMap<TradeId, usize>) {
+        let Some(time) = fill.time.as_ref().and_then(DateTime::to_utc) else {
+            return;
+        };
+        let mut reduce = |id: Option<&TradeId>, by: Option<Decimal>, close: bool| {
+            let Some(&i) = id.and_then(|id| open.get(id)) else {
+                return;
+            };
+            let exposure = &mut self.exposures[i];
+            let current = exposure.units_at(time);
+            let remaining = if close {
+                Decimal::ZERO
+            } else {
+                // the reduction's sign varies; it always shrinks the trade
+                let by = by.unwrap_or_default().abs();
+                let remaining = (current.abs() - by).max(Decimal::ZERO);
+                if current.is_sign_negative() {
+                    -remaining
+                } else {
+                    remaining
+                }
+            };
+            exposure.units.push((time, remaining));
+        };
+        for closed in &fill.trades_closed {
+            reduce(closed.trade_id.as_ref(), None, true);
+        }
+        if let Some(reduced) = &fill.trade_reduced {
+            reduce(reduced.trade_id.as_ref(), reduced.units.map(|u| u.0), false);
+        }
+        let opened = fill.trade_opened.as_ref();
+        let trade =
+            opened.and_then(|o| Some((o.trade_id.clone()?, o.units?, o.price.or(fill.price)?)));
+        if let (Some((id, units, price)), Some(instrument)) = (trade, &fill.instrument) {
+            open.insert(id, self.exposures.len());
+            self.exposures.push(Exposure {
+                instrument: instrument.clone(),
+                price: price.0,
+                gain_factor: fill
+                    .gain_quote_home_conversion_factor
+                    .map_or(Decimal::ONE, |f| f.0),
+                loss_factor: fill
+                    .loss_quote_home_conversion_factor
+                    .map_or(Decimal::ONE, |f| f.0),
+                units: vec![(time, units.0)],
+            });
+        }
+    }
+
+    /// Marks the curve to market with historical candles.
+    ///
+    /// Every point's NAV becomes its balance plus the unrealized P/L of the
+    /// trades open at that time, and a point is added at the close of each
+    /// complete candle. Trades are valued at the latest close at or before
+    /// the point (long units at the bid, short units at the ask, falling
+    /// back to the mid) and converted into the home currency with the
+    /// conversion factors of their opening fill. This is approximate:
+    /// trades opened before the first transaction, and instruments without
+    /// candles, are left out, and conversion factors are not updated over
+    /// time.
+    pub fn mark_to_market<'a, I>(mut self, candles: I) -> Self
+    where
+        I: IntoIterator<Item = &'a InstrumentCandles>,
+    {
+        let mut marks: HashMap<InstrumentName, Vec<Mark>> = HashMap::new();
+        for series in candles {
+            let Some(instrument) = &series.instrument else {
+                continue;
+            };
+            marks
+                .entry(instrument.clone())
+                .or_default()
+                .extend(closes(series));
+        }
+        for series in marks.values_mut() {
+            series.sort_by_key(|mark| mark.0);
+        }
+
+        // the balance carries forward to the candle points
+        let mut points = std::mem::take(&mut self.points);
+        let mut candle_times: Vec<_> = marks.values().flatten().map(|mark| mark.0).collect();
+        candle_times.sort();
+        candle_times.dedup();
+        for time in candle_times {
+            let index = points
+                .partition_point(|p| p.time.as_ref().and_then(DateTime::to_utc) <= Some(time));
+            let balance = index
+                .checked_sub(1)
+                .map_or_else(AccountUnits::default, |i| points[i].balance);
+            points.insert(
+                index,
+                EquityPoint {
+                    time: Some(DateTime::from(time)),
+                    balance,
+                    nav: balance,
+                    cash_flow: AccountUnits::default(),
+                },
+            );
+        }
+
+        for point in &mut points {
+            let Some(time) = point.time.as_ref().and_then(DateTime::to_utc) else {
+                continue;
+            };
+            let unrealized: Decimal = self
+                .exposures
+                .iter()
+                .filter_map(|exposure| {
+                    unrealized_pl(exposure, marks.get(&exposure.instrument)?, time)
+                })
+                .sum();
+            point.nav = AccountUnits((point.balance.0 + unrealized).normalize());
+        }
+        self.points = points;
+        self
+    }
+
+    /// The points, oldest first.
+    pub fn points(&self) -> &[EquityPoint] {
+        &self.points
+    }
+
+    /// The part of the curve from `from` (inclusive) to `to` (exclusive),
+    /// e.g. a calendar month.
+    pub fn between(&self, from: impl Into<DateTime>, to: impl Into<DateTime>) -> EquityCurve {
+        let (from, to) = (from.into().to_utc(), to.into().to_utc());
+        EquityCurve {
+            points: self
+                .points
+                .iter()
+                .filter(|point| {
+                    let time = point.time.as_ref().and_then(DateTime::to_utc);
+                    matches!((time, from, to), (Some(t), Some(from), Some(to)) if from <= t && t < to)
+                })
+                .cloned()
+                .collect(),
+            exposures: self.exposures.clone(),
+        }
+    }
+
+    /// The net deposits (positive) or withdrawals (negative) after the
+    /// first point.
+    pub fn cash_flows(&self) -> AccountUnits {
+        let flows = self
+            .points
+            .iter()
+            .skip(1)
+            .map(|p| p.cash_flow.0)
+            .sum::<Decimal>();
+        AccountUnits(flows.normalize())
+    }
+
+    /// The change in NAV from the first point to the last, less the
+    /// [`cash_flows`](Self::cash_flows) in between.
+    pub fn trading_pl(&self) -> AccountUnits {
+        match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => {
+                AccountUnits((last.nav.0 - first.nav.0 - self.cash_flows().0).normalize())
+            }
+            _ => AccountUnits::default(),
+        }
+    }
+
+    /// The time-weighted return from the first point to the last, as a
+    /// fraction.
+    ///
+    /// The curve is split at every point; each period's return is its
+    /// closing NAV, less the cash flow at its end, over its opening NAV, so
+    /// deposits and withdrawals do not count as performance. Periods that
+    /// open with a NAV of zero or less (an unfunded account) are skipped.
+    /// `None` when no period qualifies.
+    pub fn time_weighted_return(&self) -> Option<Decimal> {
+        let mut growth = Decimal::ONE;
+        let mut periods = 0;
+        for pair in self.points.windows(2) {
+            let (start, end) = (pair[0].nav.0, pair[1].nav.0);
+            if start <= Decimal::ZERO {
+                continue;
+            }
+            growth *= (end - pair[1].cash_flow.0) / start;
+            periods += 1;
+        }
+        (periods > 0).then(|| (growth - Decimal::ONE).normalize())
+    }
+}
+
+impl From<Vec<EquityPoint>> for EquityCurve {
+    /// Wraps existing points, such as a
+    /// [`BacktestReport`](super::BacktestReport)'s equity curve.
+    fn from(points: Vec<EquityPoint>) -> Self {
+        EquityCurve {
+            points,
+            exposures: Vec::new(),
+        }
+    }
+}
+
+/// The close of each complete candle, timed at the candle's close.
+fn closes(series: &InstrumentCandles) -> Vec<Mark> {
+    let close = |data: &Option<CandlestickData>| data.as_ref().and_then(|d| d.c).map(|c| c.0);
+    timed_candles(series)
+        .into_iter()
+        .map(|(open, interval, candle)| {
+            (
+                open + interval,
+                close(&candle.bid),
+                close(&candle.mid),
+                close(&candle.ask),
+            )
+        })
+        .collect()
+}
+
+fn unrealized_pl(
+    exposure: &Exposure,
+    marks: &[Mark],
+    time: chrono::DateTime<Utc>,
+) -> Option<Decimal> {
+    let units = exposure.units_at(time);
+    if units.is_zero() {
+        return None;
+    }
+    let latest = marks
+        .partition_point(|mark| mark.0 <= time)
+        .checked_sub(1)?;
+    let (_, bid, mid, ask) = marks[latest];
+    let price = if units > Decimal::ZERO {
+        bid.or(mid)?
+    } else {
+        ask.or(mid)?
+    };
+    let pl = units * (price - exposure.price);
+    let factor = if pl >= Decimal::ZERO {
+        exposure.gain_factor
+    } else {
+        exposure.loss_factor
+    };
+    Some(pl * factor)
+}
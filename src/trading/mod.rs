@@ -0,0 +1,37 @@
+//! Higher-level trading workflows built on top of the raw endpoints.
+//!
+//! - [`OrderTracker`] follows orders through their lifecycle by watching
+//!   the transaction stream.
+//! - [`Bracket`] composes an entry order with its take-profit, stop-loss
+//!   and trailing stop; [`Oco`] pairs two pending orders so that one
+//!   cancels the other.
+//! - [`size_for_risk`] and [`margin_required`] size positions from the
+//!   account's NAV and compute the margin they tie up;
+//!   [`PnlCalculator`] marks open trades and positions to market between
+//!   account polls.
+//! - [`PaperAccount`] simulates an account locally, filling orders against
+//!   the prices it is fed, for strategies written against
+//!   [`Broker`](crate::broker::Broker); [`Backtest`] replays historical
+//!   candles or recorded prices through one.
+//! - [`PerformanceReport`] computes per-trade results, win rate, profit
+//!   factor and drawdown from a transaction history; [`EquityCurve`]
+//!   rebuilds the balance and NAV over time from it, for time-weighted
+//!   returns.
+
+mod backtest;
+mod bracket;
+mod equity;
+mod paper;
+mod performance;
+mod pnl;
+mod sizing;
+mod tracker;
+
+pub use backtest::{Backtest, BacktestReport, EquityPoint};
+pub use bracket::{Bracket, Exit, Oco, OcoOutcome};
+pub use equity::EquityCurve;
+pub use paper::PaperAccount;
+pub use performance::{PerformanceReport, PerformanceStats, TradePerformance};
+pub use pnl::PnlCalculator;
+pub use sizing::{QuoteConversion, margin_required, size_for_risk};
+pub use tracker::{OrderStatus, OrderTracker, OrderTrackerHandle, OrderWatch};
@@ -0,0 +1,2131 @@
+//! A local paper-trading account that fills orders against the prices it
+//! is fed.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use futures_core::Stream;
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use serde_json::{Map, Value, json};
+use tokio::sync::broadcast;
+
+use super::sizing::{Quote, QuoteConversion};
+use crate::broker::Broker;
+use crate::endpoints::accounts::{
+    AccountInstrumentsResponse, AccountResponse, AccountSummaryResponse,
+};
+use crate::endpoints::orders::{
+    CancelOrderResponse, CreateOrderResponse, ListOrdersResponse, OrderResponse,
+    ReplaceOrderResponse,
+};
+use crate::endpoints::positions::{ClosePositionResponse, ListPositionsResponse, PositionResponse};
+use crate::endpoints::pricing::PricesResponse;
+use crate::endpoints::trades::{CloseTradeResponse, ListTradesResponse, TradeResponse};
+use crate::endpoints::transactions::{TransactionResponse, TransactionsResponse};
+use crate::error::{ApiErrorBody, Error};
+use crate::models::transaction::{
+    MarketOrderTransaction, OrderCancelTransaction, OrderFillTransaction, Transaction,
+    TransactionStreamItem,
+};
+use crate::models::{
+    AccountId, AccountUnits, ClientId, ClientPrice, DateTime, DecimalNumber, Instrument,
+    InstrumentName, Order, OrderRequest, OrderSpecifier, PriceStreamItem, TradeSpecifier,
+    TradeSummary, TransactionId,
+};
+
+/// How many prices and transactions a slow stream subscriber may fall
+/// behind before it lags.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// A simulated account that keeps its state locally and fills orders
+/// against the [`ClientPrice`]s it is fed.
+///
+/// It implements [`Broker`], so strategy code written against the trait
+/// runs unchanged against it, and every state change is recorded as the
+/// same [`Transaction`] OANDA would emit (`MARKET_ORDER`, `ORDER_FILL`,
+/// `ORDER_CANCEL`, `DAILY_FINANCING`, ...). Transaction IDs double as
+/// order and trade IDs, as they do on the server.
+///
+/// Fill rules:
+///
+/// - buys fill against the asks and sells against the bids, walking the
+///   [`PriceBucket`](crate::models::PriceBucket)s at their VWAP; buckets
+///   without liquidity (and prices with only closeout prices) are treated
+///   as unlimited;
+/// - market orders fill immediately; `FOK` (the default) is cancelled
+///   with `INSUFFICIENT_LIQUIDITY` when the book cannot absorb the units,
+///   `IOC` fills what it can. A `priceBound` cancels with
+///   `BOUNDS_VIOLATION`;
+/// - limit, stop and market-if-touched orders, and take-profit,
+///   stop-loss and trailing stop-loss orders (attached on fill or
+///   created for a trade), trigger on the top of the book and fill in
+///   full once the book is deep enough. `GTD` orders expire at their
+///   `gtdTime`; other time-in-force values behave like `GTC`;
+/// - fills reduce opposing trades first-in first-out before opening a
+///   new trade (`positionFill` `OPEN_ONLY` and `REDUCE_ONLY` are
+///   honoured), and closing a trade cancels its dependent orders.
+///
+/// [`Broker`] operations only answer for the account ID the account was
+/// created with; any other ID fails with HTTP 404, as OANDA does.
+///
+/// Home-currency amounts use the price's quote home conversion factors
+/// (1 when absent) and are rounded to 4 decimal places. Prices set the
+/// account's clock: transactions are stamped with the time of the latest
+/// price.
+///
+/// ```no_run
+/// use oanda_rs::broker::Broker;
+/// use oanda_rs::models::{AccountId, ClientPrice, MarketOrderRequest};
+/// use oanda_rs::trading::PaperAccount;
+///
+/// # async fn run() -> Result<(), oanda_rs::Error> {
+/// let account = PaperAccount::new("paper-001", 10_000);
+/// let price: ClientPrice = serde_json::from_str(
+///     r#"{"instrument": "EUR_USD", "time": "2024-06-14T12:00:00Z",
+///         "bids": [{"price": "1.07000", "liquidity": 1000000}],
+///         "asks": [{"price": "1.07010", "liquidity": 1000000}]}"#,
+/// )
+/// .unwrap();
+/// account.update_price(&price);
+///
+/// let id = AccountId::from("paper-001");
+/// let response = account
+///     .create_order(&id, MarketOrderRequest::new("EUR_USD", 1000).into())
+///     .await?;
+/// assert_eq!(response.order_fill_transaction.unwrap().price.unwrap().to_string(), "1.0701");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PaperAccount {
+    account_id: AccountId,
+    ledger: Arc<Mutex<Ledger>>,
+    prices: broadcast::Sender<ClientPrice>,
+    transactions: broadcast::Sender<Transaction>,
+}
+
+impl PaperAccount {
+    /// Creates an account funded with `balance`, recorded as an initial
+    /// `TRANSFER_FUNDS` transaction.
+    pub fn new(account_id: impl Into<AccountId>, balance: impl Into<AccountUnits>) -> Self {
+        let account_id = account_id.into();
+        let mut ledger = Ledger {
+            account_id: account_id.clone(),
+            currency: None,
+            balance: Decimal::ZERO,
+            pl: Decimal::ZERO,
+            financing: Decimal::ZERO,
+            last_id: 0,
+            batch_id: None,
+            time: None,
+            prices: HashMap::new(),
+            instruments: HashMap::new(),
+            orders: Vec::new(),
+            trades: Vec::new(),
+            log: Vec::new(),
+            outbox: Vec::new(),
+        };
+        let amount = balance.into().value();
+        ledger.balance = amount;
+        ledger.record(
+            "TRANSFER_FUNDS",
+            object(json!({
+                "amount": num(amount),
+                "fundingReason": "CLIENT_FUNDING",
+                "accountBalance": num(amount),
+            })),
+        );
+        ledger.outbox.clear();
+        PaperAccount {
+            account_id,
+            ledger: Arc::new(Mutex::new(ledger)),
+            prices: broadcast::channel(CHANNEL_CAPACITY).0,
+            transactions: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Sets the account's home currency, as reported in its summary.
+    pub fn currency(self, currency: impl Into<String>) -> Self {
+        self.with_ledger(|ledger| ledger.currency = Some(currency.into()));
+        self
+    }
+
+    /// Registers an instrument: it is then listed by
+    /// [`Broker::account_instruments`], and its margin rate and financing
+    /// rates are used for margin and [daily
+    /// financing](Self::apply_daily_financing).
+    pub fn add_instrument(&self, instrument: &Instrument) -> &Self {
+        if let Some(name) = &instrument.name {
+            self.with_ledger(|ledger| {
+                ledger.instruments.insert(name.clone(), instrument.clone());
+            });
+        }
+        self
+    }
+
+    /// Records a new price, triggers the pending orders of its instrument
+    /// and returns the transactions this created.
+    ///
+    /// Prices without an instrument are ignored.
+    pub fn update_price(&self, price: &ClientPrice) -> Vec<Transaction> {
+        self.with_ledger(|ledger| {
+            ledger.on_price(price);
+            let _ = self.prices.send(price.clone());
+            ledger.outbox.clone()
+        })
+    }
+
+    /// Feeds every price of a pricing stream (live, recorded or
+    /// synthetic) into the account until the stream ends.
+    ///
+    /// Error items do not stop the account; the last one is returned once
+    /// the stream has ended.
+    pub async fn run<S>(&self, prices: S) -> Result<(), Error>
+    where
+        S: Stream<Item = Result<PriceStreamItem, Error>>,
+    {
+        let mut prices = std::pin::pin!(prices);
+        let mut last_error = None;
+        while let Some(item) = prices.next().await {
+            match item {
+                Ok(PriceStreamItem::Price(price)) => {
+                    self.update_price(&price);
+                }
+                Ok(_) => {}
+                Err(error) => last_error = Some(error),
+            }
+        }
+        last_error.map_or(Ok(()), Err)
+    }
+
+    /// Charges (or pays) one day of financing on every open trade, using
+    /// the long/short rates of its [registered
+    /// instrument](Self::add_instrument) at the current mid price, and
+    /// returns the `DAILY_FINANCING` transaction.
+    ///
+    /// Returns `None` when no open trade has a known rate and price.
+    pub fn apply_daily_financing(&self) -> Option<Transaction> {
+        self.with_ledger(Ledger::apply_daily_financing)
+    }
+
+    /// The account's balance.
+    pub fn balance(&self) -> AccountUnits {
+        self.with_ledger(|ledger| AccountUnits(ledger.balance.normalize()))
+    }
+
+    /// The account's net asset value: its balance plus the unrealized P/L
+    /// of its open trades at the latest prices.
+    pub fn nav(&self) -> AccountUnits {
+        self.with_ledger(|ledger| AccountUnits((ledger.balance + ledger.unrealized()).normalize()))
+    }
+
+    /// Every transaction recorded so far, oldest first.
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.with_ledger(|ledger| ledger.log.clone())
+    }
+
+    /// Every trade the account has opened, open and closed, oldest first.
+    pub fn trades(&self) -> Vec<TradeSummary> {
+        self.with_ledger(|ledger| {
+            ledger
+                .trades
+                .iter()
+                .filter_map(|t| decode(ledger.trade_json(t)).ok())
+                .collect()
+        })
+    }
+
+    /// Fails like OANDA (HTTP 404) for any account but this one, so that a
+    /// strategy addressing the wrong account does not trade this one.
+    fn check_account(&self, account_id: &AccountId) -> Result<(), Error> {
+        if *account_id == self.account_id {
+            Ok(())
+        } else {
+            Err(api_error(
+                StatusCode::NOT_FOUND,
+                "NO_SUCH_ACCOUNT",
+                &format!("The account {account_id} does not exist"),
+            ))
+        }
+    }
+
+    /// Runs `f` on the ledger and publishes the transactions it recorded,
+    /// in order, before releasing the lock.
+    fn with_ledger<T>(&self, f: impl FnOnce(&mut Ledger) -> T) -> T {
+        let mut ledger = self.ledger.lock().unwrap_or_else(PoisonError::into_inner);
+        ledger.batch_id = None;
+        let value = f(&mut ledger);
+        for transaction in ledger.outbox.drain(..) {
+            let _ = self.transactions.send(transaction);
+        }
+        value
+    }
+}
+
+impl Broker for PaperAccount {
+    type PriceStream = BoxStream<'static, Result<PriceStreamItem, Error>>;
+    type TransactionStream = BoxStream<'static, Result<TransactionStreamItem, Error>>;
+
+    async fn account(&self, account_id: &AccountId) -> Result<AccountResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let mut account = ledger.summary();
+            account.insert("trades".into(), ledger.open_trades().collect());
+            account.insert("positions".into(), ledger.open_positions().collect());
+            account.insert(
+                "orders".into(),
+                ledger
+                    .pending_orders()
+                    .map(|o| ledger.order_json(o))
+                    .collect(),
+            );
+            Ok(AccountResponse {
+                account: decode(Value::Object(account))?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn account_summary(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<AccountSummaryResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            Ok(AccountSummaryResponse {
+                account: decode(Value::Object(ledger.summary()))?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn account_instruments(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> Result<AccountInstrumentsResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            Ok(AccountInstrumentsResponse {
+                instruments: ledger
+                    .instruments
+                    .iter()
+                    .filter(|(name, _)| instruments.is_empty() || instruments.contains(name))
+                    .map(|(_, instrument)| instrument.clone())
+                    .collect(),
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn create_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderRequest,
+    ) -> Result<CreateOrderResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let prepared = ledger.prepare(&order, None)?;
+            let first = ledger.last_id + 1;
+            let placed = ledger.place(prepared, None);
+            Ok(CreateOrderResponse {
+                order_create_transaction: placed.create,
+                order_fill_transaction: placed.outcome.fill,
+                order_cancel_transaction: placed.outcome.cancel,
+                order_reissue_transaction: None,
+                order_reissue_reject_transaction: None,
+                related_transaction_ids: ledger.ids_since(first),
+                last_transaction_id: ledger.last_transaction_id(),
+                location: None,
+            })
+        })
+    }
+
+    async fn list_pending_orders(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<ListOrdersResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            Ok(ListOrdersResponse {
+                orders: ledger
+                    .pending_orders()
+                    .rev()
+                    .map(|o| decode_order(ledger.order_json(o)))
+                    .collect::<Result<_, _>>()?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+    ) -> Result<OrderResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let index = ledger.find_order(&order).ok_or_else(|| {
+                api_error(
+                    StatusCode::NOT_FOUND,
+                    "NO_SUCH_ORDER",
+                    "The order specified does not exist",
+                )
+            })?;
+            Ok(OrderResponse {
+                order: decode_order(ledger.order_json(&ledger.orders[index]))?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn replace_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+        replacement: OrderRequest,
+    ) -> Result<ReplaceOrderResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let index = ledger.find_pending(&order)?;
+            let replaced = ledger.orders[index].id;
+            let prepared = ledger.prepare(&replacement, Some(replaced))?;
+            let first = ledger.last_id + 1;
+            // the replacing order is created right after the cancellation
+            let cancel = ledger.cancel(
+                index,
+                "CLIENT_REQUEST_REPLACED",
+                json!({"replacedByOrderID": (first + 1).to_string()}),
+            );
+            let placed = ledger.place(prepared, Some(replaced));
+            Ok(ReplaceOrderResponse {
+                order_cancel_transaction: as_cancel(cancel),
+                order_create_transaction: Some(placed.create),
+                order_fill_transaction: placed.outcome.fill,
+                order_reissue_transaction: None,
+                order_reissue_reject_transaction: None,
+                replacing_order_cancel_transaction: placed.outcome.cancel,
+                related_transaction_ids: ledger.ids_since(first),
+                last_transaction_id: ledger.last_transaction_id(),
+                location: None,
+            })
+        })
+    }
+
+    async fn cancel_order(
+        &self,
+        account_id: &AccountId,
+        order: OrderSpecifier,
+    ) -> Result<CancelOrderResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let index = ledger.find_pending(&order)?;
+            let first = ledger.last_id + 1;
+            let cancel = ledger.cancel(index, "CLIENT_REQUEST", json!({}));
+            Ok(CancelOrderResponse {
+                order_cancel_transaction: as_cancel(cancel),
+                related_transaction_ids: ledger.ids_since(first),
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn list_open_trades(&self, account_id: &AccountId) -> Result<ListTradesResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let mut trades: Vec<_> = ledger.open_trades().collect();
+            trades.reverse();
+            Ok(ListTradesResponse {
+                trades: decode(Value::Array(trades))?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn trade(
+        &self,
+        account_id: &AccountId,
+        trade: TradeSpecifier,
+    ) -> Result<TradeResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let index = ledger.find_trade(&trade).ok_or_else(|| {
+                api_error(
+                    StatusCode::NOT_FOUND,
+                    "NO_SUCH_TRADE",
+                    "The trade specified does not exist",
+                )
+            })?;
+            Ok(TradeResponse {
+                trade: decode(ledger.trade_json(&ledger.trades[index]))?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn close_trade(
+        &self,
+        account_id: &AccountId,
+        trade: TradeSpecifier,
+        units: Option<DecimalNumber>,
+    ) -> Result<CloseTradeResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let index = ledger
+                .find_trade(&trade)
+                .filter(|&i| ledger.trades[i].is_open())
+                .ok_or_else(|| {
+                    api_error(
+                        StatusCode::NOT_FOUND,
+                        "TRADE_DOESNT_EXIST",
+                        "The trade specified does not exist",
+                    )
+                })?;
+            let open = ledger.trades[index].current_units;
+            let closing = units.map_or(open.abs(), |u| u.value().abs());
+            if closing > open.abs() || closing.is_zero() {
+                return Err(api_error(
+                    StatusCode::BAD_REQUEST,
+                    "CLOSE_TRADE_UNITS_EXCEED_TRADE_SIZE",
+                    "The units to close exceed the size of the trade",
+                ));
+            }
+            let trade = &ledger.trades[index];
+            let (instrument, order_units) = (trade.instrument.clone(), -closing * sign(open));
+            let fields = object(json!({
+                "timeInForce": "FOK",
+                "positionFill": "REDUCE_ONLY",
+                "tradeClose": {
+                    "tradeID": trade.id.to_string(),
+                    "units": units.map_or("ALL".to_owned(), |_| num_string(closing)),
+                },
+            }));
+            let trade_id = trade.id;
+            let first = ledger.last_id + 1;
+            let outcome = ledger.market_close(
+                instrument,
+                order_units,
+                fields,
+                "TRADE_CLOSE",
+                FillMode::Trade(trade_id),
+            );
+            Ok(CloseTradeResponse {
+                order_create_transaction: as_market_order(outcome.create),
+                order_fill_transaction: outcome.outcome.fill,
+                order_cancel_transaction: outcome.outcome.cancel,
+                related_transaction_ids: ledger.ids_since(first),
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn list_open_positions(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<ListPositionsResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            Ok(ListPositionsResponse {
+                positions: decode(Value::Array(ledger.open_positions().collect()))?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn position(
+        &self,
+        account_id: &AccountId,
+        instrument: InstrumentName,
+    ) -> Result<PositionResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            Ok(PositionResponse {
+                position: decode(ledger.position_json(&instrument))?,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn close_position(
+        &self,
+        account_id: &AccountId,
+        instrument: InstrumentName,
+    ) -> Result<ClosePositionResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let side = |long: bool| -> Decimal {
+                ledger
+                    .trades
+                    .iter()
+                    .filter(|t| t.is_open() && t.instrument == instrument)
+                    .map(|t| t.current_units)
+                    .filter(|u| u.is_sign_positive() == long)
+                    .sum()
+            };
+            let (long, short) = (side(true), side(false));
+            if long.is_zero() && short.is_zero() {
+                return Err(api_error(
+                    StatusCode::BAD_REQUEST,
+                    "CLOSEOUT_POSITION_DOESNT_EXIST",
+                    "The position requested to be closed out does not exist",
+                ));
+            }
+            let first = ledger.last_id + 1;
+            let mut close = |units: Decimal, closeout: &str| {
+                if units.is_zero() {
+                    return None;
+                }
+                let fields = object(json!({
+                    "timeInForce": "FOK",
+                    "positionFill": "REDUCE_ONLY",
+                    closeout: {"instrument": instrument, "units": "ALL"},
+                }));
+                Some(ledger.market_close(
+                    instrument.clone(),
+                    -units,
+                    fields,
+                    "POSITION_CLOSEOUT",
+                    FillMode::ReduceOnly,
+                ))
+            };
+            let long = close(long, "longPositionCloseout");
+            let short = close(short, "shortPositionCloseout");
+            let (long, short) = (long.map(Placed::split), short.map(Placed::split));
+            let (long_create, long_fill, long_cancel) = long.unwrap_or_default();
+            let (short_create, short_fill, short_cancel) = short.unwrap_or_default();
+            Ok(ClosePositionResponse {
+                long_order_create_transaction: long_create,
+                long_order_fill_transaction: long_fill,
+                long_order_cancel_transaction: long_cancel,
+                short_order_create_transaction: short_create,
+                short_order_fill_transaction: short_fill,
+                short_order_cancel_transaction: short_cancel,
+                related_transaction_ids: ledger.ids_since(first),
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn prices(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> Result<PricesResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            Ok(PricesResponse {
+                prices: instruments
+                    .iter()
+                    .filter_map(|i| ledger.prices.get(i).cloned())
+                    .collect(),
+                home_conversions: Vec::new(),
+                time: ledger.time.clone(),
+            })
+        })
+    }
+
+    async fn pricing_stream(
+        &self,
+        account_id: &AccountId,
+        instruments: Vec<InstrumentName>,
+    ) -> Result<Self::PriceStream, Error> {
+        self.check_account(account_id)?;
+        let prices = futures_util::stream::unfold(self.prices.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(price) => return Some((price, rx)),
+                    // a slow consumer only needs the latest prices
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(prices
+            .filter(move |price: &ClientPrice| {
+                let wanted = price
+                    .instrument
+                    .as_ref()
+                    .is_some_and(|i| instruments.contains(i));
+                std::future::ready(wanted)
+            })
+            .map(|price| Ok(PriceStreamItem::Price(price)))
+            .boxed())
+    }
+
+    async fn transaction(
+        &self,
+        account_id: &AccountId,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionResponse, Error> {
+        self.check_account(account_id)?;
+        self.with_ledger(|ledger| {
+            let transaction = ledger
+                .log
+                .iter()
+                .find(|tx| tx.id() == Some(&transaction_id))
+                .cloned()
+                .ok_or_else(|| {
+                    api_error(
+                        StatusCode::NOT_FOUND,
+                        "NO_SUCH_TRANSACTION",
+                        "The transaction specified does not exist",
+                    )
+                })?;
+            Ok(TransactionResponse {
+                transaction,
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn transactions_since_id(
+        &self,
+        account_id: &AccountId,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionsResponse, Error> {
+        self.check_account(account_id)?;
+        let since: u64 = transaction_id.as_str().parse().unwrap_or(0);
+        self.with_ledger(|ledger| {
+            Ok(TransactionsResponse {
+                transactions: ledger
+                    .log
+                    .get(since as usize..)
+                    .unwrap_or_default()
+                    .to_vec(),
+                last_transaction_id: ledger.last_transaction_id(),
+            })
+        })
+    }
+
+    async fn transaction_stream(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Self::TransactionStream, Error> {
+        self.check_account(account_id)?;
+        let transactions =
+            futures_util::stream::unfold(self.transactions.subscribe(), |mut rx| async move {
+                let item = match rx.recv().await {
+                    Ok(tx) => Ok(TransactionStreamItem::Transaction(tx)),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => Err(Error::Stream(
+                        format!("paper transaction stream lagged by {missed} transactions"),
+                    )),
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                };
+                Some((item, rx))
+            });
+        Ok(transactions.boxed())
+    }
+}
+
+/// The mutable state of a [`PaperAccount`].
+#[derive(Debug)]
+struct Ledger {
+    account_id: AccountId,
+    currency: Option<String>,
+    balance: Decimal,
+    pl: Decimal,
+    financing: Decimal,
+    last_id: u64,
+    batch_id: Option<u64>,
+    time: Option<DateTime>,
+    prices: HashMap<InstrumentName, ClientPrice>,
+    instruments: HashMap<InstrumentName, Instrument>,
+    orders: Vec<PaperOrder>,
+    trades: Vec<PaperTrade>,
+    log: Vec<Transaction>,
+    outbox: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone)]
+struct PaperOrder {
+    id: u64,
+    kind: OrderKind,
+    /// The request's fields, as they appear on the wire.
+    fields: Map<String, Value>,
+    instrument: InstrumentName,
+    /// Signed units of an entry order; dependent orders close their trade.
+    units: Decimal,
+    trade_id: Option<u64>,
+    trigger: Trigger,
+    expires: Option<chrono::DateTime<chrono::Utc>>,
+    create_time: Option<DateTime>,
+    state: PaperOrderState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OrderKind {
+    Market,
+    Limit,
+    Stop,
+    MarketIfTouched,
+    TakeProfit,
+    StopLoss,
+    TrailingStopLoss,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Trigger {
+    Immediate,
+    /// Buys when the ask falls to, sells when the bid rises to the price.
+    AtOrBetter(Decimal),
+    /// Buys when the ask rises to, sells when the bid falls to the price.
+    AtOrWorse(Decimal),
+    /// Trails `distance` behind the best price seen so far.
+    Trailing {
+        distance: Decimal,
+        stop: Option<Decimal>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum PaperOrderState {
+    Pending,
+    Filled {
+        transaction: u64,
+        time: Option<DateTime>,
+        trade_opened: Option<u64>,
+    },
+    Cancelled {
+        transaction: u64,
+        time: Option<DateTime>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct PaperTrade {
+    id: u64,
+    instrument: InstrumentName,
+    price: Decimal,
+    open_time: Option<DateTime>,
+    initial_units: Decimal,
+    current_units: Decimal,
+    realized_pl: Decimal,
+    financing: Decimal,
+    client_extensions: Option<Value>,
+    closing_transaction_ids: Vec<u64>,
+    close_time: Option<DateTime>,
+}
+
+/// Which trades a fill may reduce, and whether it may open one.
+#[derive(Debug, Clone, Copy)]
+enum FillMode {
+    Default,
+    OpenOnly,
+    ReduceOnly,
+    Trade(u64),
+}
+
+/// The order an operation created and what became of it.
+struct Placed {
+    create: Transaction,
+    outcome: Outcome,
+}
+
+impl Placed {
+    #[allow(clippy::type_complexity)]
+    fn split(
+        self,
+    ) -> (
+        Option<MarketOrderTransaction>,
+        Option<OrderFillTransaction>,
+        Option<OrderCancelTransaction>,
+    ) {
+        (
+            as_market_order(self.create),
+            self.outcome.fill,
+            self.outcome.cancel,
+        )
+    }
+}
+
+#[derive(Default)]
+struct Outcome {
+    fill: Option<OrderFillTransaction>,
+    cancel: Option<OrderCancelTransaction>,
+}
+
+/// A validated order request, ready to be placed.
+struct Prepared {
+    kind: OrderKind,
+    fields: Map<String, Value>,
+    instrument: InstrumentName,
+    units: Decimal,
+    trade_id: Option<u64>,
+    trigger: Trigger,
+}
+
+impl OrderKind {
+    fn wire(self) -> &'static str {
+        match self {
+            OrderKind::Market => "MARKET",
+            OrderKind::Limit => "LIMIT",
+            OrderKind::Stop => "STOP",
+            OrderKind::MarketIfTouched => "MARKET_IF_TOUCHED",
+            OrderKind::TakeProfit => "TAKE_PROFIT",
+            OrderKind::StopLoss => "STOP_LOSS",
+            OrderKind::TrailingStopLoss => "TRAILING_STOP_LOSS",
+        }
+    }
+
+    fn is_dependent(self) -> bool {
+        matches!(
+            self,
+            OrderKind::TakeProfit | OrderKind::StopLoss | OrderKind::TrailingStopLoss
+        )
+    }
+}
+
+impl PaperOrder {
+    fn is_pending(&self) -> bool {
+        matches!(self.state, PaperOrderState::Pending)
+    }
+
+    fn client_id(&self) -> Option<&str> {
+        self.fields.get("clientExtensions")?.get("id")?.as_str()
+    }
+}
+
+impl PaperTrade {
+    fn is_open(&self) -> bool {
+        !self.current_units.is_zero()
+    }
+
+    fn client_id(&self) -> Option<&str> {
+        self.client_extensions.as_ref()?.get("id")?.as_str()
+    }
+}
+
+impl Ledger {
+    fn last_transaction_id(&self) -> Option<TransactionId> {
+        (self.last_id > 0).then(|| TransactionId(self.last_id.to_string()))
+    }
+
+    fn ids_since(&self, first: u64) -> Vec<TransactionId> {
+        (first..=self.last_id)
+            .map(|id| TransactionId(id.to_string()))
+            .collect()
+    }
+
+    /// Appends a transaction of type `kind`, stamping its ID, account,
+    /// batch and time.
+    fn record(&mut self, kind: &str, mut fields: Map<String, Value>) -> Transaction {
+        self.last_id += 1;
+        let id = self.last_id;
+        let batch = *self.batch_id.get_or_insert(id);
+        fields.insert("type".into(), kind.into());
+        fields.insert("id".into(), id.to_string().into());
+        fields.insert("accountID".into(), self.account_id.as_str().into());
+        fields.insert("batchID".into(), batch.to_string().into());
+        if let Some(time) = &self.time {
+            fields.insert("time".into(), time.as_str().into());
+        }
+        let transaction: Transaction = serde_json::from_value(Value::Object(fields))
+            .expect("undecodable transactions fall back to Transaction::Unknown");
+        // Falling back would hide a mistyped field of a known type.
+        debug_assert!(
+            !matches!(transaction, Transaction::Unknown(_)),
+            "paper {kind} transaction does not decode as its type: {transaction:?}"
+        );
+        self.log.push(transaction.clone());
+        self.outbox.push(transaction.clone());
+        transaction
+    }
+
+    fn on_price(&mut self, price: &ClientPrice) {
+        let Some(instrument) = price.instrument.clone() else {
+            return;
+        };
+        if price.time.is_some() {
+            self.time = price.time.clone();
+        }
+        self.prices.insert(instrument.clone(), price.clone());
+        let pending: Vec<u64> = self
+            .pending_orders()
+            .filter(|o| o.instrument == instrument)
+            .map(|o| o.id)
+            .collect();
+        for id in pending {
+            // every triggered order is a batch of its own
+            self.batch_id = None;
+            self.evaluate(id);
+        }
+    }
+
+    fn pending_orders(&self) -> impl DoubleEndedIterator<Item = &PaperOrder> {
+        self.orders.iter().filter(|o| o.is_pending())
+    }
+
+    fn find_order(&self, spec: &OrderSpecifier) -> Option<usize> {
+        match spec.as_str().strip_prefix('@') {
+            Some(client_id) => self
+                .orders
+                .iter()
+                .rposition(|o| o.client_id() == Some(client_id)),
+            None => self
+                .orders
+                .iter()
+                .position(|o| o.id.to_string() == spec.as_str()),
+        }
+    }
+
+    fn find_pending(&self, spec: &OrderSpecifier) -> Result<usize, Error> {
+        self.find_order(spec)
+            .filter(|&i| self.orders[i].is_pending())
+            .ok_or_else(|| {
+                api_error(
+                    StatusCode::NOT_FOUND,
+                    "ORDER_DOESNT_EXIST",
+                    "The order specified does not exist or is not pending",
+                )
+            })
+    }
+
+    fn find_trade(&self, spec: &TradeSpecifier) -> Option<usize> {
+        match spec.as_str().strip_prefix('@') {
+            Some(client_id) => self
+                .trades
+                .iter()
+                .rposition(|t| t.client_id() == Some(client_id)),
+            None => self
+                .trades
+                .iter()
+                .position(|t| t.id.to_string() == spec.as_str()),
+        }
+    }
+
+    /// Validates an order request against the account without changing
+    /// it. `replacing` is the order the request is about to replace.
+    fn prepare(&self, request: &OrderRequest, replacing: Option<u64>) -> Result<Prepared, Error> {
+        if let Some(client_id) = request.client_extensions().and_then(|c| c.id.as_deref()) {
+            if self
+                .orders
+                .iter()
+                .any(|o| o.client_id() == Some(client_id) && Some(o.id) != replacing)
+            {
+                return Err(reject(
+                    "CLIENT_ORDER_ID_ALREADY_EXISTS",
+                    "The client order ID specified is already assigned to another order",
+                ));
+            }
+        }
+        let mut fields = object(serde_json::to_value(request).unwrap_or_default());
+        fields.remove("type");
+        let entry = |kind, instrument: &InstrumentName, units: Decimal, trigger| {
+            if units.is_zero() {
+                return Err(reject("UNITS_INVALID", "The order units are invalid"));
+            }
+            Ok((kind, instrument.clone(), units, None, trigger))
+        };
+        let (kind, instrument, units, trade_id, trigger) = match request {
+            OrderRequest::Market(o) => entry(
+                OrderKind::Market,
+                &o.instrument,
+                o.units.value(),
+                Trigger::Immediate,
+            )?,
+            OrderRequest::Limit(o) => entry(
+                OrderKind::Limit,
+                &o.instrument,
+                o.units.value(),
+                Trigger::AtOrBetter(o.price.value()),
+            )?,
+            OrderRequest::Stop(o) => entry(
+                OrderKind::Stop,
+                &o.instrument,
+                o.units.value(),
+                Trigger::AtOrWorse(o.price.value()),
+            )?,
+            OrderRequest::MarketIfTouched(o) => {
+                let price = o.price.value();
+                let buying = o.units.value().is_sign_positive();
+                // touching from above behaves like a limit, from below like a stop
+                let above = self
+                    .quote(&o.instrument)
+                    .is_some_and(|q| if buying { q.ask } else { q.bid } > price);
+                let trigger = if above == buying {
+                    Trigger::AtOrBetter(price)
+                } else {
+                    Trigger::AtOrWorse(price)
+                };
+                entry(
+                    OrderKind::MarketIfTouched,
+                    &o.instrument,
+                    o.units.value(),
+                    trigger,
+                )?
+            }
+            OrderRequest::TakeProfit(o) => {
+                let trade = self.dependent_trade(
+                    &o.trade_id,
+                    o.client_trade_id.as_ref().map(ClientId::as_str),
+                    OrderKind::TakeProfit,
+                    replacing,
+                )?;
+                (
+                    OrderKind::TakeProfit,
+                    trade.instrument.clone(),
+                    Decimal::ZERO,
+                    Some(trade.id),
+                    Trigger::AtOrBetter(o.price.value()),
+                )
+            }
+            OrderRequest::StopLoss(o) => {
+                let trade = self.dependent_trade(
+                    &o.trade_id,
+                    o.client_trade_id.as_ref().map(ClientId::as_str),
+                    OrderKind::StopLoss,
+                    replacing,
+                )?;
+                let price = match (o.price, o.distance) {
+                    (Some(price), None) => price.value(),
+                    (None, Some(distance)) => {
+                        // the distance is measured from the price the trade closes at
+                        let long = trade.current_units.is_sign_positive();
+                        let market = self
+                            .quote(&trade.instrument)
+                            .map_or(trade.price, |q| if long { q.bid } else { q.ask });
+                        if long {
+                            market - distance.value()
+                        } else {
+                            market + distance.value()
+                        }
+                    }
+                    (None, None) => {
+                        return Err(reject(
+                            "STOP_LOSS_ORDER_PRICE_AND_DISTANCE_BOTH_MISSING",
+                            "A stop loss order needs a price or a distance",
+                        ));
+                    }
+                    (Some(_), Some(_)) => {
+                        return Err(reject(
+                            "STOP_LOSS_ORDER_PRICE_AND_DISTANCE_BOTH_SPECIFIED",
+                            "A stop loss order takes a price or a distance, not both",
+                        ));
+                    }
+                };
+                (
+                    OrderKind::StopLoss,
+                    trade.instrument.clone(),
+                    Decimal::ZERO,
+                    Some(trade.id),
+                    Trigger::AtOrWorse(price),
+                )
+            }
+            OrderRequest::TrailingStopLoss(o) => {
+                let trade = self.dependent_trade(
+                    &o.trade_id,
+                    o.client_trade_id.as_ref().map(ClientId::as_str),
+                    OrderKind::TrailingStopLoss,
+                    replacing,
+                )?;
+                (
+                    OrderKind::TrailingStopLoss,
+                    trade.instrument.clone(),
+                    Decimal::ZERO,
+                    Some(trade.id),
+                    Trigger::Trailing {
+                        distance: o.distance.value(),
+                        stop: None,
+                    },
+                )
+            }
+        };
+        Ok(Prepared {
+            kind,
+            fields,
+            instrument,
+            units,
+            trade_id,
+            trigger,
+        })
+    }
+
+    /// The open trade a dependent order is created for, provided it has
+    /// no order of that kind yet.
+    fn dependent_trade(
+        &self,
+        trade_id: &crate::models::TradeId,
+        client_trade_id: Option<&str>,
+        kind: OrderKind,
+        replacing: Option<u64>,
+    ) -> Result<&PaperTrade, Error> {
+        let spec = match client_trade_id {
+            Some(client_id) if trade_id.as_str().is_empty() => {
+                TradeSpecifier(format!("@{client_id}"))
+            }
+            _ => TradeSpecifier(trade_id.as_str().to_owned()),
+        };
+        let trade = self
+            .find_trade(&spec)
+            .map(|i| &self.trades[i])
+            .filter(|t| t.is_open())
+            .ok_or_else(|| reject("TRADE_DOESNT_EXIST", "The trade specified does not exist"))?;
+        if self
+            .pending_orders()
+            .any(|o| o.trade_id == Some(trade.id) && o.kind == kind && Some(o.id) != replacing)
+        {
+            return Err(reject(
+                match kind {
+                    OrderKind::TakeProfit => "TAKE_PROFIT_ORDER_ALREADY_EXISTS",
+                    OrderKind::StopLoss => "STOP_LOSS_ORDER_ALREADY_EXISTS",
+                    _ => "TRAILING_STOP_LOSS_ORDER_ALREADY_EXISTS",
+                },
+                "The trade already has an order of this type",
+            ));
+        }
+        Ok(trade)
+    }
+
+    /// Records the creation of a prepared order and evaluates it against
+    /// the current price.
+    fn place(&mut self, prepared: Prepared, replaces: Option<u64>) -> Placed {
+        let mut fields = prepared.fields;
+        fields.insert("reason".into(), "CLIENT_ORDER".into());
+        if let Some(replaces) = replaces {
+            fields.insert("replacesOrderID".into(), replaces.to_string().into());
+        }
+        let (id, create) = self.open_order(
+            prepared.kind,
+            fields,
+            prepared.instrument,
+            prepared.units,
+            prepared.trade_id,
+            prepared.trigger,
+        );
+        let outcome = self.evaluate(id);
+        Placed { create, outcome }
+    }
+
+    /// Places and executes a market order closing a trade or a position
+    /// side.
+    fn market_close(
+        &mut self,
+        instrument: InstrumentName,
+        units: Decimal,
+        mut fields: Map<String, Value>,
+        reason: &str,
+        reduce: FillMode,
+    ) -> Placed {
+        fields.insert("instrument".into(), instrument.as_str().into());
+        fields.insert("units".into(), num(units));
+        fields.insert("reason".into(), reason.into());
+        let (id, create) = self.open_order(
+            OrderKind::Market,
+            fields,
+            instrument,
+            units,
+            None,
+            Trigger::Immediate,
+        );
+        let index = self.orders.len() - 1;
+        let fill_reason = match reduce {
+            FillMode::Trade(_) => "MARKET_ORDER_TRADE_CLOSE",
+            _ => "MARKET_ORDER_POSITION_CLOSEOUT",
+        };
+        debug_assert_eq!(self.orders[index].id, id);
+        let outcome = self.execute(index, reduce, fill_reason);
+        Placed { create, outcome }
+    }
+
+    /// Records an order-creation transaction and adds the order to the
+    /// book.
+    fn open_order(
+        &mut self,
+        kind: OrderKind,
+        fields: Map<String, Value>,
+        instrument: InstrumentName,
+        units: Decimal,
+        trade_id: Option<u64>,
+        trigger: Trigger,
+    ) -> (u64, Transaction) {
+        let expires = (fields.get("timeInForce").and_then(Value::as_str) == Some("GTD"))
+            .then(|| {
+                fields
+                    .get("gtdTime")?
+                    .as_str()
+                    .map(DateTime::from)?
+                    .to_utc()
+            })
+            .flatten();
+        let create = self.record(&format!("{}_ORDER", kind.wire()), fields.clone());
+        let mut fields = fields;
+        fields.remove("reason");
+        self.orders.push(PaperOrder {
+            id: self.last_id,
+            kind,
+            fields,
+            instrument,
+            units,
+            trade_id,
+            trigger,
+            expires,
+            create_time: self.time.clone(),
+            state: PaperOrderState::Pending,
+        });
+        (self.last_id, create)
+    }
+
+    fn quote(&self, instrument: &InstrumentName) -> Option<Quote> {
+        Quote::from_price(self.prices.get(instrument)?)
+    }
+
+    /// Checks a pending order's expiry and trigger, and executes it when
+    /// triggered.
+    fn evaluate(&mut self, id: u64) -> Outcome {
+        let Some(index) = self
+            .orders
+            .iter()
+            .position(|o| o.id == id && o.is_pending())
+        else {
+            return Outcome::default();
+        };
+        let order = &self.orders[index];
+        let now = self.time.as_ref().and_then(DateTime::to_utc);
+        if let (Some(expires), Some(now)) = (order.expires, now) {
+            if now >= expires {
+                let cancel = self.cancel(index, "TIME_IN_FORCE_EXPIRED", json!({}));
+                return Outcome {
+                    fill: None,
+                    cancel: as_cancel(cancel),
+                };
+            }
+        }
+        let reduce = match order.trade_id {
+            Some(trade_id) => FillMode::Trade(trade_id),
+            None => match order.fields.get("positionFill").and_then(Value::as_str) {
+                Some("OPEN_ONLY") => FillMode::OpenOnly,
+                Some("REDUCE_ONLY") => FillMode::ReduceOnly,
+                _ => FillMode::Default,
+            },
+        };
+        let fill_reason = match order.kind {
+            OrderKind::Market => "MARKET_ORDER",
+            OrderKind::Limit => "LIMIT_ORDER",
+            OrderKind::Stop => "STOP_ORDER",
+            OrderKind::MarketIfTouched => "MARKET_IF_TOUCHED_ORDER",
+            OrderKind::TakeProfit => "TAKE_PROFIT_ORDER",
+            OrderKind::StopLoss => "STOP_LOSS_ORDER",
+            OrderKind::TrailingStopLoss => "TRAILING_STOP_LOSS_ORDER",
+        };
+        if !matches!(order.trigger, Trigger::Immediate) {
+            let Some(quote) = self.quote(&order.instrument) else {
+                return Outcome::default();
+            };
+            let buying = self.order_units(order).is_sign_positive();
+            let market = if buying { quote.ask } else { quote.bid };
+            let triggered = match &mut self.orders[index].trigger {
+                Trigger::Immediate => true,
+                Trigger::AtOrBetter(price) => {
+                    if buying {
+                        market <= *price
+                    } else {
+                        market >= *price
+                    }
+                }
+                Trigger::AtOrWorse(price) => {
+                    if buying {
+                        market >= *price
+                    } else {
+                        market <= *price
+                    }
+                }
+                Trigger::Trailing { distance, stop } => {
+                    let trailed = if buying {
+                        let candidate = market + *distance;
+                        stop.map_or(candidate, |s| s.min(candidate))
+                    } else {
+                        let candidate = market - *distance;
+                        stop.map_or(candidate, |s| s.max(candidate))
+                    };
+                    *stop = Some(trailed);
+                    if buying {
+                        market >= trailed
+                    } else {
+                        market <= trailed
+                    }
+                }
+            };
+            if !triggered {
+                return Outcome::default();
+            }
+        }
+        self.execute(index, reduce, fill_reason)
+    }
+
+    /// The signed units an order would fill: dependent orders close their
+    /// trade.
+    fn order_units(&self, order: &PaperOrder) -> Decimal {
+        match order.trade_id {
+            Some(trade_id) => self
+                .trades
+                .iter()
+                .find(|t| t.id == trade_id)
+                .map_or(Decimal::ZERO, |t| -t.current_units),
+            None => order.units,
+        }
+    }
+
+    /// Fills a triggered order, or cancels it when it cannot be filled.
+    /// Pending orders the book cannot absorb stay pending.
+    fn execute(&mut self, index: usize, reduce: FillMode, fill_reason: &'static str) -> Outcome {
+        let order = self.orders[index].clone();
+        // mark the order as filling, so closing its own trade does not
+        // cancel it
+        self.orders[index].state = PaperOrderState::Filled {
+            transaction: self.last_id + 1,
+            time: self.time.clone(),
+            trade_opened: None,
+        };
+        let fill = self.fill(FillRequest {
+            order_id: order.id,
+            client_order_id: order.client_id().map(str::to_owned),
+            instrument: order.instrument.clone(),
+            units: self.order_units(&order),
+            reason: fill_reason,
+            reduce,
+            price_bound: decimal(order.fields.get("priceBound")),
+            partial: order.fields.get("timeInForce").and_then(Value::as_str) == Some("IOC"),
+            trade_client_extensions: order.fields.get("tradeClientExtensions").cloned(),
+        });
+        match fill {
+            Ok((transaction, opened, price)) => {
+                if let PaperOrderState::Filled { trade_opened, .. } = &mut self.orders[index].state
+                {
+                    *trade_opened = opened;
+                }
+                if let Some(trade_id) = opened {
+                    self.attach_dependents(&order, trade_id, price);
+                }
+                Outcome {
+                    fill: match transaction {
+                        Transaction::OrderFill(fill) => Some(fill),
+                        _ => None,
+                    },
+                    cancel: None,
+                }
+            }
+            Err("INSUFFICIENT_LIQUIDITY") if order.kind != OrderKind::Market => {
+                self.orders[index].state = PaperOrderState::Pending;
+                Outcome::default()
+            }
+            Err(reason) => {
+                self.orders[index].state = PaperOrderState::Pending;
+                let cancel = self.cancel(index, reason, json!({}));
+                Outcome {
+                    fill: None,
+                    cancel: as_cancel(cancel),
+                }
+            }
+        }
+    }
+
+    /// Records the cancellation of a pending order.
+    fn cancel(&mut self, index: usize, reason: &str, extra: Value) -> Transaction {
+        let order = &self.orders[index];
+        let mut fields = object(json!({
+            "orderID": order.id.to_string(),
+            "reason": reason,
+        }));
+        if let Some(client_id) = order.client_id() {
+            fields.insert("clientOrderID".into(), client_id.into());
+        }
+        fields.extend(object(extra));
+        let transaction = self.record("ORDER_CANCEL", fields);
+        self.orders[index].state = PaperOrderState::Cancelled {
+            transaction: self.last_id,
+            time: self.time.clone(),
+        };
+        transaction
+    }
+
+    /// Creates the take-profit, stop-loss and trailing stop-loss orders an
+    /// entry order asked for on the trade its fill opened.
+    fn attach_dependents(&mut self, order: &PaperOrder, trade_id: u64, fill_price: Decimal) {
+        let long = order.units.is_sign_positive();
+        for (key, kind) in [
+            ("takeProfitOnFill", OrderKind::TakeProfit),
+            ("stopLossOnFill", OrderKind::StopLoss),
+            ("trailingStopLossOnFill", OrderKind::TrailingStopLoss),
+        ] {
+            let Some(Value::Object(details)) = order.fields.get(key) else {
+                continue;
+            };
+            let price = decimal(details.get("price"));
+            let distance = decimal(details.get("distance"));
+            let trigger = match kind {
+                OrderKind::TakeProfit => price.map(Trigger::AtOrBetter),
+                OrderKind::StopLoss => price
+                    .or_else(|| {
+                        distance.map(|d| if long { fill_price - d } else { fill_price + d })
+                    })
+                    .map(Trigger::AtOrWorse),
+                _ => distance.map(|distance| Trigger::Trailing {
+                    distance,
+                    stop: None,
+                }),
+            };
+            let Some(trigger) = trigger else {
+                continue;
+            };
+            let mut fields = details.clone();
+            fields.insert("tradeID".into(), trade_id.to_string().into());
+            fields.insert("reason".into(), "ON_FILL".into());
+            let (id, _) = self.open_order(
+                kind,
+                fields,
+                order.instrument.clone(),
+                Decimal::ZERO,
+                Some(trade_id),
+                trigger,
+            );
+            self.evaluate(id);
+        }
+    }
+
+    /// Fills `units` against the book, reducing and opening trades.
+    ///
+    /// Nothing changes when the fill fails; the error is the reason to
+    /// cancel the order with. On success, returns the fill, the trade it
+    /// opened and its price.
+    fn fill(
+        &mut self,
+        request: FillRequest,
+    ) -> Result<(Transaction, Option<u64>, Decimal), &'static str> {
+        let price = self
+            .prices
+            .get(&request.instrument)
+            .filter(|p| p.tradeable != Some(false))
+            .cloned()
+            .ok_or("MARKET_HALTED")?;
+        let conversion =
+            QuoteConversion::from_price(&price).unwrap_or_else(QuoteConversion::identity);
+        let buying = request.units.is_sign_positive();
+        let opposing: Vec<usize> = match request.reduce {
+            FillMode::OpenOnly => Vec::new(),
+            FillMode::Trade(id) => self
+                .trades
+                .iter()
+                .position(|t| t.id == id && t.is_open())
+                .into_iter()
+                .collect(),
+            FillMode::Default | FillMode::ReduceOnly => self
+                .trades
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| {
+                    t.is_open()
+                        && t.instrument == request.instrument
+                        && t.current_units.is_sign_positive() != buying
+                })
+                .map(|(i, _)| i)
+                .collect(),
+        };
+        let mut requested = request.units.abs();
+        if matches!(request.reduce, FillMode::ReduceOnly | FillMode::Trade(_)) {
+            let reducible: Decimal = opposing
+                .iter()
+                .map(|&i| self.trades[i].current_units.abs())
+                .sum();
+            requested = requested.min(reducible);
+        }
+        if requested.is_zero() {
+            return Err("POSITION_CLOSEOUT_FAILED");
+        }
+        let (units, vwap) = walk_book(&price, buying, requested, request.partial)?;
+        if let Some(bound) = request.price_bound {
+            if (buying && vwap > bound) || (!buying && vwap < bound) {
+                return Err("BOUNDS_VIOLATION");
+            }
+        }
+
+        let fill_id = self.last_id + 1;
+        let mut remaining = units;
+        let mut pl = Decimal::ZERO;
+        let mut quote_pl = Decimal::ZERO;
+        let mut closed = Vec::new();
+        let mut closed_ids = Vec::new();
+        let mut reduced = None;
+        for index in opposing {
+            if remaining.is_zero() {
+                break;
+            }
+            let trade = &mut self.trades[index];
+            let take = remaining.min(trade.current_units.abs());
+            let closing = take * sign(trade.current_units);
+            let trade_quote_pl = (vwap - trade.price) * closing;
+            let trade_pl = conversion.convert(trade_quote_pl).round_dp(4);
+            trade.current_units -= closing;
+            trade.realized_pl += trade_pl;
+            trade.closing_transaction_ids.push(fill_id);
+            remaining -= take;
+            pl += trade_pl;
+            quote_pl += trade_quote_pl;
+            let entry = json!({
+                "tradeID": trade.id.to_string(),
+                "units": num(-closing),
+                "price": num(vwap),
+                "realizedPL": num(trade_pl),
+                "financing": "0",
+            });
+            if trade.current_units.is_zero() {
+                trade.close_time = self.time.clone();
+                closed.push(entry);
+                closed_ids.push(trade.id);
+            } else {
+                reduced = Some(entry);
+            }
+        }
+        let mut opened = None;
+        if !remaining.is_zero() {
+            let units = if buying { remaining } else { -remaining };
+            self.trades.push(PaperTrade {
+                id: fill_id,
+                instrument: request.instrument.clone(),
+                price: vwap,
+                open_time: self.time.clone(),
+                initial_units: units,
+                current_units: units,
+                realized_pl: Decimal::ZERO,
+                financing: Decimal::ZERO,
+                client_extensions: request.trade_client_extensions.clone(),
+                closing_transaction_ids: Vec::new(),
+                close_time: None,
+            });
+            let mut trade_opened = object(json!({
+                "tradeID": fill_id.to_string(),
+                "units": num(units),
+                "price": num(vwap),
+            }));
+            if let Some(extensions) = &request.trade_client_extensions {
+                trade_opened.insert("clientExtensions".into(), extensions.clone());
+            }
+            opened = Some(Value::Object(trade_opened));
+        }
+        self.balance += pl;
+        self.pl += pl;
+
+        let mut fields = object(json!({
+            "orderID": request.order_id.to_string(),
+            "instrument": request.instrument,
+            "units": num(if buying { units } else { -units }),
+            "requestedUnits": num(request.units),
+            "price": num(vwap),
+            "fullVWAP": num(vwap),
+            "fullPrice": price,
+            "reason": request.reason,
+            "pl": num(pl),
+            "quotePL": num(quote_pl),
+            "financing": "0",
+            "commission": "0",
+            "accountBalance": num(self.balance),
+            "gainQuoteHomeConversionFactor": num(conversion.gain),
+            "lossQuoteHomeConversionFactor": num(conversion.loss),
+            "tradesClosed": closed,
+        }));
+        if let Some(client_order_id) = request.client_order_id {
+            fields.insert("clientOrderID".into(), client_order_id.into());
+        }
+        if let Some(opened) = opened {
+            fields.insert("tradeOpened".into(), opened);
+        }
+        if let Some(reduced) = reduced {
+            fields.insert("tradeReduced".into(), reduced);
+        }
+        let transaction = self.record("ORDER_FILL", fields);
+
+        for trade_id in closed_ids {
+            let dependents: Vec<usize> = (0..self.orders.len())
+                .filter(|&i| {
+                    self.orders[i].is_pending() && self.orders[i].trade_id == Some(trade_id)
+                })
+                .collect();
+            for index in dependents {
+                self.cancel(
+                    index,
+                    "LINKED_TRADE_CLOSED",
+                    json!({
+                        "closedTradeID": trade_id.to_string(),
+                        "tradeCloseTransactionID": fill_id.to_string(),
+                    }),
+                );
+            }
+        }
+        let opened = (!remaining.is_zero()).then_some(fill_id);
+        Ok((transaction, opened, vwap))
+    }
+
+    fn apply_daily_financing(&mut self) -> Option<Transaction> {
+        let mut by_instrument: Vec<(InstrumentName, Decimal, Vec<Value>)> = Vec::new();
+        let mut total = Decimal::ZERO;
+        for index in 0..self.trades.len() {
+            let trade = &self.trades[index];
+            if !trade.is_open() {
+                continue;
+            }
+            let Some(financing) = self
+                .instruments
+                .get(&trade.instrument)
+                .and_then(|i| i.financing.as_ref())
+            else {
+                continue;
+            };
+            let rate = if trade.current_units.is_sign_positive() {
+                financing.long_rate
+            } else {
+                financing.short_rate
+            };
+            let (Some(rate), Some(price)) = (rate, self.prices.get(&trade.instrument)) else {
+                continue;
+            };
+            let (Some(quote), conversion) = (
+                Quote::from_price(price),
+                QuoteConversion::from_price(price).unwrap_or_else(QuoteConversion::identity),
+            ) else {
+                continue;
+            };
+            let quote_amount =
+                trade.current_units.abs() * quote.mid() * rate.value() / Decimal::from(365);
+            let amount = conversion.convert(quote_amount).round_dp(4);
+            let entry = json!({
+                "tradeID": trade.id.to_string(),
+                "financing": num(amount),
+                "financingRate": num(rate.value()),
+            });
+            let instrument = trade.instrument.clone();
+            let trade = &mut self.trades[index];
+            trade.financing += amount;
+            total += amount;
+            match by_instrument.iter_mut().find(|(i, _, _)| *i == instrument) {
+                Some((_, sum, trades)) => {
+                    *sum += amount;
+                    trades.push(entry);
+                }
+                None => by_instrument.push((instrument, amount, vec![entry])),
+            }
+        }
+        if by_instrument.is_empty() {
+            return None;
+        }
+        self.balance += total;
+        self.financing += total;
+        let positions: Vec<Value> = by_instrument
+            .into_iter()
+            .map(|(instrument, financing, trades)| {
+                json!({
+                    "instrument": instrument,
+                    "financing": num(financing),
+                    "openTradeFinancings": trades,
+                })
+            })
+            .collect();
+        Some(self.record(
+            "DAILY_FINANCING",
+            object(json!({
+                "financing": num(total),
+                "accountBalance": num(self.balance),
+                "accountFinancingMode": "DAILY",
+                "positionFinancings": positions,
+            })),
+        ))
+    }
+
+    /// The unrealized P/L and margin of an open trade at the latest price.
+    fn mark(&self, trade: &PaperTrade) -> (Decimal, Option<Decimal>) {
+        let Some(price) = self.prices.get(&trade.instrument) else {
+            return (Decimal::ZERO, None);
+        };
+        let Some(quote) = Quote::from_price(price) else {
+            return (Decimal::ZERO, None);
+        };
+        let conversion =
+            QuoteConversion::from_price(price).unwrap_or_else(QuoteConversion::identity);
+        let exit = if trade.current_units.is_sign_positive() {
+            quote.bid
+        } else {
+            quote.ask
+        };
+        let unrealized = conversion
+            .convert((exit - trade.price) * trade.current_units)
+            .round_dp(4);
+        let margin = self
+            .instruments
+            .get(&trade.instrument)
+            .and_then(|i| i.margin_rate)
+            .map(|rate| {
+                (conversion.convert_value(trade.current_units.abs() * quote.mid()) * rate.value())
+                    .round_dp(4)
+            });
+        (unrealized, margin)
+    }
+
+    fn order_json(&self, order: &PaperOrder) -> Value {
+        let mut fields = order.fields.clone();
+        fields.insert("type".into(), order.kind.wire().into());
+        fields.insert("id".into(), order.id.to_string().into());
+        if let Some(time) = &order.create_time {
+            fields.insert("createTime".into(), time.as_str().into());
+        }
+        match order.trigger {
+            Trigger::AtOrWorse(price) | Trigger::AtOrBetter(price) if order.kind.is_dependent() => {
+                fields.insert("price".into(), num(price));
+            }
+            Trigger::Trailing {
+                stop: Some(stop), ..
+            } => {
+                fields.insert("trailingStopValue".into(), num(stop));
+            }
+            _ => {}
+        }
+        let state = match &order.state {
+            PaperOrderState::Pending => "PENDING",
+            PaperOrderState::Filled {
+                transaction,
+                time,
+                trade_opened,
+            } => {
+                fields.insert(
+                    "fillingTransactionID".into(),
+                    transaction.to_string().into(),
+                );
+                if let Some(time) = time {
+                    fields.insert("filledTime".into(), time.as_str().into());
+                }
+                if let Some(trade) = trade_opened {
+                    fields.insert("tradeOpenedID".into(), trade.to_string().into());
+                }
+                "FILLED"
+            }
+            PaperOrderState::Cancelled { transaction, time } => {
+                fields.insert(
+                    "cancellingTransactionID".into(),
+                    transaction.to_string().into(),
+                );
+                if let Some(time) = time {
+                    fields.insert("cancelledTime".into(), time.as_str().into());
+                }
+                "CANCELLED"
+            }
+        };
+        fields.insert("state".into(), state.into());
+        Value::Object(fields)
+    }
+
+    /// A trade as both a `Trade` and a `TradeSummary`.
+    fn trade_json(&self, trade: &PaperTrade) -> Value {
+        let mut fields = object(json!({
+            "id": trade.id.to_string(),
+            "instrument": trade.instrument,
+            "price": num(trade.price),
+            "state": if trade.is_open() { "OPEN" } else { "CLOSED" },
+            "initialUnits": num(trade.initial_units),
+            "currentUnits": num(trade.current_units),
+            "realizedPL": num(trade.realized_pl),
+            "financing": num(trade.financing),
+            "closingTransactionIDs": trade
+                .closing_transaction_ids
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>(),
+        }));
+        if let Some(time) = &trade.open_time {
+            fields.insert("openTime".into(), time.as_str().into());
+        }
+        if let Some(time) = &trade.close_time {
+            fields.insert("closeTime".into(), time.as_str().into());
+        }
+        if let Some(extensions) = &trade.client_extensions {
+            fields.insert("clientExtensions".into(), extensions.clone());
+        }
+        if trade.is_open() {
+            let (unrealized, margin) = self.mark(trade);
+            fields.insert("unrealizedPL".into(), num(unrealized));
+            if let Some(margin) = margin {
+                fields.insert("marginUsed".into(), num(margin));
+            }
+        }
+        for order in self
+            .pending_orders()
+            .filter(|o| o.trade_id == Some(trade.id))
+        {
+            let (key, id_key) = match order.kind {
+                OrderKind::TakeProfit => ("takeProfitOrder", "takeProfitOrderID"),
+                OrderKind::StopLoss => ("stopLossOrder", "stopLossOrderID"),
+                _ => ("trailingStopLossOrder", "trailingStopLossOrderID"),
+            };
+            fields.insert(key.into(), self.order_json(order));
+            fields.insert(id_key.into(), order.id.to_string().into());
+        }
+        Value::Object(fields)
+    }
+
+    fn open_trades(&self) -> impl Iterator<Item = Value> + '_ {
+        self.trades
+            .iter()
+            .filter(|t| t.is_open())
+            .map(|t| self.trade_json(t))
+    }
+
+    fn position_json(&self, instrument: &InstrumentName) -> Value {
+        let trades: Vec<&PaperTrade> = self
+            .trades
+            .iter()
+            .filter(|t| &t.instrument == instrument)
+            .collect();
+        let mut margins = (Decimal::ZERO, Decimal::ZERO);
+        let mut side = |long: bool| {
+            let mut units = Decimal::ZERO;
+            let mut cost = Decimal::ZERO;
+            let mut unrealized = Decimal::ZERO;
+            let mut margin = Decimal::ZERO;
+            let mut ids = Vec::new();
+            let mut pl = Decimal::ZERO;
+            let mut financing = Decimal::ZERO;
+            for trade in &trades {
+                if trade.initial_units.is_sign_positive() != long {
+                    continue;
+                }
+                pl += trade.realized_pl;
+                financing += trade.financing;
+                if trade.is_open() {
+                    let (u, m) = self.mark(trade);
+                    units += trade.current_units;
+                    cost += trade.current_units * trade.price;
+                    unrealized += u;
+                    margin += m.unwrap_or_default();
+                    ids.push(trade.id.to_string());
+                }
+            }
+            if long {
+                margins.0 = margin;
+            } else {
+                margins.1 = margin;
+            }
+            let mut side = object(json!({
+                "units": num(units),
+                "tradeIDs": ids,
+                "pl": num(pl),
+                "unrealizedPL": num(unrealized),
+                "financing": num(financing),
+            }));
+            if !units.is_zero() {
+                side.insert("averagePrice".into(), num(cost / units));
+            }
+            (Value::Object(side), pl, unrealized, financing)
+        };
+        let (long, long_pl, long_unrealized, long_financing) = side(true);
+        let (short, short_pl, short_unrealized, short_financing) = side(false);
+        json!({
+            "instrument": instrument,
+            "pl": num(long_pl + short_pl),
+            "unrealizedPL": num(long_unrealized + short_unrealized),
+            "marginUsed": num(margins.0.max(margins.1)),
+            "financing": num(long_financing + short_financing),
+            "commission": "0",
+            "long": long,
+            "short": short,
+        })
+    }
+
+    fn open_positions(&self) -> impl Iterator<Item = Value> + '_ {
+        let mut instruments: Vec<&InstrumentName> = Vec::new();
+        for trade in self.trades.iter().filter(|t| t.is_open()) {
+            if !instruments.contains(&&trade.instrument) {
+                instruments.push(&trade.instrument);
+            }
+        }
+        instruments.into_iter().map(|i| self.position_json(i))
+    }
+
+    fn unrealized(&self) -> Decimal {
+        self.trades
+            .iter()
+            .filter(|t| t.is_open())
+            .map(|t| self.mark(t).0)
+            .sum()
+    }
+
+    fn summary(&self) -> Map<String, Value> {
+        let open: Vec<&PaperTrade> = self.trades.iter().filter(|t| t.is_open()).collect();
+        let unrealized = self.unrealized();
+        let mut position_value = Decimal::ZERO;
+        for trade in &open {
+            if let Some(price) = self.prices.get(&trade.instrument) {
+                if let Some(quote) = Quote::from_price(price) {
+                    let conversion = QuoteConversion::from_price(price)
+                        .unwrap_or_else(QuoteConversion::identity);
+                    position_value +=
+                        conversion.convert_value(trade.current_units.abs() * quote.mid());
+                }
+            }
+        }
+        let margin_used: Decimal = self
+            .open_positions()
+            .filter_map(|p| decimal(p.get("marginUsed")))
+            .sum();
+        let nav = self.balance + unrealized;
+        let positions = self.open_positions().count();
+        let mut summary = object(json!({
+            "id": self.account_id,
+            "balance": num(self.balance),
+            "NAV": num(nav),
+            "unrealizedPL": num(unrealized),
+            "pl": num(self.pl),
+            "financing": num(self.financing),
+            "commission": "0",
+            "marginUsed": num(margin_used),
+            "marginAvailable": num((nav - margin_used).max(Decimal::ZERO)),
+            "positionValue": num(position_value.round_dp(4)),
+            "openTradeCount": open.len(),
+            "openPositionCount": positions,
+            "pendingOrderCount": self.pending_orders().count(),
+            "hedgingEnabled": false,
+            "lastTransactionID": self.last_id.to_string(),
+        }));
+        if let Some(currency) = &self.currency {
+            summary.insert("currency".into(), currency.as_str().into());
+        }
+        summary
+    }
+}
+
+struct FillRequest {
+    order_id: u64,
+    client_order_id: Option<String>,
+    instrument: InstrumentName,
+    units: Decimal,
+    reason: &'static str,
+    reduce: FillMode,
+    price_bound: Option<Decimal>,
+    partial: bool,
+    trade_client_extensions: Option<Value>,
+}
+
+/// Walks the asks (buying) or bids (selling) for `units`, returning the
+/// units filled and their VWAP.
+fn walk_book(
+    price: &ClientPrice,
+    buying: bool,
+    units: Decimal,
+    partial: bool,
+) -> Result<(Decimal, Decimal), &'static str> {
+    let (book, closeout) = if buying {
+        (&price.asks, price.closeout_ask)
+    } else {
+        (&price.bids, price.closeout_bid)
+    };
+    if book.is_empty() {
+        let closeout = closeout.ok_or("MARKET_HALTED")?;
+        return Ok((units, closeout.value()));
+    }
+    let mut remaining = units;
+    let mut cost = Decimal::ZERO;
+    for bucket in book {
+        let Some(bucket_price) = bucket.price else {
+            continue;
+        };
+        let available = bucket
+            .liquidity
+            .map_or(remaining, |l| l.value().min(remaining));
+        cost += available * bucket_price.value();
+        remaining -= available;
+        if remaining.is_zero() {
+            break;
+        }
+    }
+    let filled = units - remaining;
+    if filled.is_zero() || (!remaining.is_zero() && !partial) {
+        return Err("INSUFFICIENT_LIQUIDITY");
+    }
+    Ok((filled, (cost / filled).normalize()))
+}
+
+fn object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+fn num_string(value: Decimal) -> String {
+    value.normalize().to_string()
+}
+
+fn num(value: Decimal) -> Value {
+    Value::String(num_string(value))
+}
+
+fn decimal(value: Option<&Value>) -> Option<Decimal> {
+    value?.as_str()?.parse().ok()
+}
+
+fn decode<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, Error> {
+    serde_json::from_value(value.clone()).map_err(|source| Error::Decode {
+        source,
+        body: value.to_string(),
+    })
+}
+
+/// Decodes an order, failing rather than falling back to
+/// [`Order::Unknown`] when it does not match its type.
+fn decode_order(value: Value) -> Result<Order, Error> {
+    match decode(value)? {
+        Order::Unknown(value) => Err(Error::Decode {
+            source: serde::de::Error::custom("the order does not decode as its type"),
+            body: value.to_string(),
+        }),
+        order => Ok(order),
+    }
+}
+
+fn as_cancel(transaction: Transaction) -> Option<OrderCancelTransaction> {
+    match transaction {
+        Transaction::OrderCancel(cancel) => Some(cancel),
+        _ => None,
+    }
+}
+
+fn as_market_order(transaction: Transaction) -> Option<MarketOrderTransaction> {
+    match transaction {
+        Transaction::MarketOrder(order) => Some(order),
+        _ => None,
+    }
+}
+
+fn api_error(status: StatusCode, code: &str, message: &str) -> Error {
+    Error::Api {
+        status,
+        request_id: None,
+        body: ApiErrorBody {
+            error_message: message.to_owned(),
+            error_code: Some(code.to_owned()),
+            reject_reason: None,
+            extra: Map::new(),
+        },
+    }
+}
+
+/// A 400 order rejection, as OANDA reports it.
+fn reject(reason: &str, message: &str) -> Error {
+    Error::Api {
+        status: StatusCode::BAD_REQUEST,
+        request_id: None,
+        body: ApiErrorBody {
+            error_message: message.to_owned(),
+            error_code: Some(reason.to_owned()),
+            reject_reason: Some(reason.into()),
+            extra: Map::new(),
+        },
+    }
+}
+
+/// `1` for positive and `-1` for negative units.
+fn sign(units: Decimal) -> Decimal {
+    if units.is_sign_negative() {
+        Decimal::NEGATIVE_ONE
+    } else {
+        Decimal::ONE
+    }
+}
@@ -0,0 +1,339 @@
+//! Trading performance statistics computed from an account's transaction
+//! history.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::TimeDelta;
+use rust_decimal::Decimal;
+
+use crate::models::transaction::{OrderFillTransaction, Transaction};
+use crate::models::{
+    AccountUnits, DateTime, DecimalNumber, InstrumentName, PriceValue, TradeId, TradeReduce,
+};
+
+/// Per-trade results and aggregate statistics over a transaction history.
+///
+/// Built by walking the transactions oldest first:
+///
+/// - `ORDER_FILL`s open trades (`tradeOpened`), realize P/L and financing
+///   on them (`tradeReduced`, `tradesClosed`) and close them;
+/// - `DAILY_FINANCING`s and `DIVIDEND_ADJUSTMENT`s credit or charge the
+///   open trades they list;
+/// - `TRADE_CLIENT_EXTENSIONS_MODIFY`s retag trades.
+///
+/// Trades opened before the first transaction are still tracked from
+/// their first reduction or financing, without an open time or tag. The
+/// statistics cover closed trades only, judged by their
+/// [`net_pl`](TradePerformance::net_pl).
+///
+/// ```no_run
+/// use oanda_rs::trading::PerformanceReport;
+///
+/// # async fn run(client: oanda_rs::Client) -> Result<(), oanda_rs::Error> {
+/// let history = client.transactions_since_id("101-004-1234567-001", "1").await?;
+/// let report = PerformanceReport::from_transactions(&history.transactions);
+/// println!("win rate: {:?}", report.overall.win_rate);
+/// for (tag, stats) in &report.by_tag {
+///     println!("{tag}: {} over {} trades", stats.net_pl, stats.trades);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct PerformanceReport {
+    /// Every trade seen, in the order it first appeared.
+    pub trades: Vec<TradePerformance>,
+    /// Statistics over all closed trades.
+    pub overall: PerformanceStats,
+    /// Statistics over the closed trades of each instrument.
+    pub by_instrument: BTreeMap<InstrumentName, PerformanceStats>,
+    /// Statistics over the closed trades of each client extensions tag;
+    /// untagged trades are only counted in [`overall`](Self::overall).
+    pub by_tag: BTreeMap<String, PerformanceStats>,
+}
+
+/// What a single trade made or lost.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct TradePerformance {
+    /// The trade's ID.
+    pub trade_id: TradeId,
+    /// The traded instrument.
+    pub instrument: Option<InstrumentName>,
+    /// The trade's client extensions tag.
+    pub tag: Option<String>,
+    /// The units the trade was opened with; negative for shorts.
+    pub units: Option<DecimalNumber>,
+    /// The price the trade was opened at.
+    pub open_price: Option<PriceValue>,
+    /// When the trade was opened.
+    pub open_time: Option<DateTime>,
+    /// When the trade was fully closed.
+    pub close_time: Option<DateTime>,
+    /// Whether the trade has been fully closed.
+    pub closed: bool,
+    /// The P/L realized by reducing and closing the trade.
+    pub realized_pl: AccountUnits,
+    /// The financing paid (negative) or received while the trade was open.
+    pub financing: AccountUnits,
+    /// The dividend adjustments applied to the trade.
+    pub dividend_adjustment: AccountUnits,
+}
+
+impl TradePerformance {
+    /// Realized P/L plus financing and dividend adjustments.
+    pub fn net_pl(&self) -> AccountUnits {
+        AccountUnits(self.realized_pl.0 + self.financing.0 + self.dividend_adjustment.0)
+    }
+
+    /// How long the trade was open, once closed.
+    pub fn holding_time(&self) -> Option<TimeDelta> {
+        Some(self.close_time.as_ref()?.to_utc()? - self.open_time.as_ref()?.to_utc()?)
+    }
+}
+
+/// Aggregate statistics over a set of closed trades.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct PerformanceStats {
+    /// The number of closed trades.
+    pub trades: usize,
+    /// Trades with a positive net P/L.
+    pub wins: usize,
+    /// Trades with a negative net P/L.
+    pub losses: usize,
+    /// The total net P/L.
+    pub net_pl: AccountUnits,
+    /// The sum of the winning trades' net P/L.
+    pub gross_profit: AccountUnits,
+    /// The sum of the losing trades' net P/L, as a positive amount.
+    pub gross_loss: AccountUnits,
+    /// The financing included in the net P/L.
+    pub financing: AccountUnits,
+    /// `wins / trades`; `None` without trades.
+    pub win_rate: Option<Decimal>,
+    /// `gross_profit / gross_loss`; `None` without losses.
+    pub profit_factor: Option<Decimal>,
+    /// The average net P/L per trade; `None` without trades.
+    pub expectancy: Option<AccountUnits>,
+    /// The average net P/L of the winning trades.
+    pub average_win: Option<AccountUnits>,
+    /// The average net P/L of the losing trades.
+    pub average_loss: Option<AccountUnits>,
+    /// The largest fall of the cumulative net P/L from a previous peak,
+    /// taking trades in the order they closed.
+    pub max_drawdown: AccountUnits,
+    /// The average time trades were held, over those with known open and
+    /// close times.
+    pub average_holding_time: Option<TimeDelta>,
+}
+
+impl PerformanceReport {
+    /// Computes the report from transactions in the order OANDA created
+    /// them.
+    pub fn from_transactions<'a, I>(transactions: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Transaction>,
+    {
+        let mut ledger = Ledger::default();
+        for transaction in transactions {
+            ledger.apply(transaction);
+        }
+
+        let closed: Vec<&TradePerformance> =
+            ledger.closed.iter().map(|&i| &ledger.trades[i]).collect();
+        let mut by_instrument: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        let mut by_tag: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for &trade in &closed {
+            if let Some(instrument) = &trade.instrument {
+                by_instrument
+                    .entry(instrument.clone())
+                    .or_default()
+                    .push(trade);
+            }
+            if let Some(tag) = &trade.tag {
+                by_tag.entry(tag.clone()).or_default().push(trade);
+            }
+        }
+
+        PerformanceReport {
+            overall: stats(&closed),
+            by_instrument: by_instrument
+                .into_iter()
+                .map(|(instrument, trades)| (instrument, stats(&trades)))
+                .collect(),
+            by_tag: by_tag
+                .into_iter()
+                .map(|(tag, trades)| (tag, stats(&trades)))
+                .collect(),
+            trades: ledger.trades,
+        }
+    }
+}
+
+/// The trades seen so far and the order they closed in.
+#[derive(Default)]
+struct Ledger {
+    trades: Vec<TradePerformance>,
+    index: HashMap<TradeId, usize>,
+    closed: Vec<usize>,
+}
+
+impl Ledger {
+    fn apply(&mut self, transaction: &Transaction) {
+        match transaction {
+            Transaction::OrderFill(fill) => self.fill(fill),
+            Transaction::DailyFinancing(financing) => {
+                for position in &financing.position_financings {
+                    for open in &position.open_trade_financings {
+                        if let Some(id) = &open.trade_id {
+                            let trade = self.trade(id, position.instrument.as_ref());
+                            add(&mut trade.financing, open.financing.map(|f| f.0));
+                        }
+                    }
+                }
+            }
+            Transaction::DividendAdjustment(dividend) => {
+                for open in &dividend.open_trade_dividend_adjustments {
+                    if let Some(id) = &open.trade_id {
+                        let trade = self.trade(&TradeId(id.clone()), dividend.instrument.as_ref());
+                        add(
+                            &mut trade.dividend_adjustment,
+                            open.dividend_adjustment.map(|d| d.0),
+                        );
+                    }
+                }
+            }
+            Transaction::TradeClientExtensionsModify(modify) => {
+                let tag = modify
+                    .trade_client_extensions_modify
+                    .as_ref()
+                    .and_then(|e| e.tag.clone());
+                if let (Some(id), Some(tag)) = (&modify.trade_id, tag) {
+                    if let Some(&i) = self.index.get(id) {
+                        self.trades[i].tag = Some(tag);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fill(&mut self, fill: &OrderFillTransaction) {
+        for closed in &fill.trades_closed {
+            if let Some(i) = self.reduce(fill, closed) {
+                let trade = &mut self.trades[i];
+                if !trade.closed {
+                    trade.closed = true;
+                    trade.close_time = fill.time.clone();
+                    self.closed.push(i);
+                }
+            }
+        }
+        if let Some(reduced) = &fill.trade_reduced {
+            self.reduce(fill, reduced);
+        }
+        if let Some(opened) = &fill.trade_opened {
+            if let Some(id) = &opened.trade_id {
+                let trade = self.trade(id, fill.instrument.as_ref());
+                trade.tag = opened
+                    .client_extensions
+                    .as_ref()
+                    .and_then(|e| e.tag.clone());
+                trade.units = opened.units;
+                trade.open_price = opened.price;
+                trade.open_time = fill.time.clone();
+            }
+        }
+    }
+
+    fn reduce(&mut self, fill: &OrderFillTransaction, reduce: &TradeReduce) -> Option<usize> {
+        let id = reduce.trade_id.as_ref()?;
+        self.trade(id, fill.instrument.as_ref());
+        let i = self.index[id];
+        let trade = &mut self.trades[i];
+        add(&mut trade.realized_pl, reduce.realized_pl.map(|pl| pl.0));
+        add(&mut trade.financing, reduce.financing.map(|f| f.0));
+        Some(i)
+    }
+
+    /// The trade with `id`, tracked from now on if it was not yet.
+    fn trade(
+        &mut self,
+        id: &TradeId,
+        instrument: Option<&InstrumentName>,
+    ) -> &mut TradePerformance {
+        let i = *self.index.entry(id.clone()).or_insert_with(|| {
+            self.trades.push(TradePerformance {
+                trade_id: id.clone(),
+                instrument: instrument.cloned(),
+                tag: None,
+                units: None,
+                open_price: None,
+                open_time: None,
+                close_time: None,
+                closed: false,
+                realized_pl: AccountUnits::default(),
+                financing: AccountUnits::default(),
+                dividend_adjustment: AccountUnits::default(),
+            });
+            self.trades.len() - 1
+        });
+        &mut self.trades[i]
+    }
+}
+
+fn add(total: &mut AccountUnits, amount: Option<Decimal>) {
+    if let Some(amount) = amount {
+        total.0 += amount;
+    }
+}
+
+/// Statistics over closed trades, given in the order they closed.
+fn stats(trades: &[&TradePerformance]) -> PerformanceStats {
+    let mut wins = Vec::new();
+    let mut losses = Vec::new();
+    let (mut net, mut financing) = (Decimal::ZERO, Decimal::ZERO);
+    let (mut peak, mut max_drawdown) = (Decimal::ZERO, Decimal::ZERO);
+    let mut holding = (TimeDelta::zero(), 0i32);
+    for trade in trades {
+        let pl = trade.net_pl().0;
+        if pl > Decimal::ZERO {
+            wins.push(pl);
+        } else if pl < Decimal::ZERO {
+            losses.push(-pl);
+        }
+        net += pl;
+        financing += trade.financing.0;
+        peak = peak.max(net);
+        max_drawdown = max_drawdown.max(peak - net);
+        if let Some(time) = trade.holding_time() {
+            holding = (holding.0 + time, holding.1 + 1);
+        }
+    }
+
+    let gross_profit: Decimal = wins.iter().sum();
+    let gross_loss: Decimal = losses.iter().sum();
+    let average = |total: Decimal, count: usize| {
+        (count > 0).then(|| AccountUnits((total / Decimal::from(count)).normalize()))
+    };
+    PerformanceStats {
+        trades: trades.len(),
+        wins: wins.len(),
+        losses: losses.len(),
+        net_pl: AccountUnits(net.normalize()),
+        gross_profit: AccountUnits(gross_profit.normalize()),
+        gross_loss: AccountUnits(gross_loss.normalize()),
+        financing: AccountUnits(financing.normalize()),
+        win_rate: (!trades.is_empty())
+            .then(|| (Decimal::from(wins.len()) / Decimal::from(trades.len())).normalize()),
+        profit_factor: (gross_loss > Decimal::ZERO)
+            .then(|| (gross_profit / gross_loss).normalize()),
+        expectancy: average(net, trades.len()),
+        average_win: average(gross_profit, wins.len()),
+        average_loss: average(gross_loss, losses.len()).map(|loss| AccountUnits(-loss.0)),
+        max_drawdown: AccountUnits(max_drawdown.normalize()),
+        average_holding_time: (holding.1 > 0).then(|| holding.0 / holding.1),
+    }
+}
@@ -0,0 +1,430 @@
+//! Client-side mark-to-market of open trades and positions.
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use super::sizing::{Quote, QuoteConversion, effective_margin_rate};
+use crate::models::{
+    AccountSummary, AccountUnits, CalculatedAccountState, CalculatedPositionState,
+    CalculatedTradeState, ClientPrice, DecimalNumber, HomeConversions, Instrument, InstrumentName,
+    Position, PositionSide, TradeSummary,
+};
+
+/// Marks open trades and positions to market from the latest prices.
+///
+/// Feed it every [`ClientPrice`] from a pricing stream with
+/// [`update_price`](Self::update_price); between account polls it then
+/// computes the same [`CalculatedTradeState`], [`CalculatedPositionState`]
+/// and [`CalculatedAccountState`] the server reports:
+///
+/// - long units are valued at the bid and short units at the ask (the
+///   closeout bid/ask for the `marginCloseout*` fields);
+/// - P/L is converted into the home currency with the price's gain or
+///   loss factor, position value and margin at their midpoint;
+/// - the margin rate is the instrument's, or the account's `marginRate`
+///   override when that is higher.
+///
+/// ```
+/// use oanda_rs::models::{ClientPrice, Instrument, TradeSummary};
+/// use oanda_rs::trading::PnlCalculator;
+///
+/// let instrument: Instrument =
+///     serde_json::from_str(r#"{"name": "EUR_USD", "marginRate": "0.0333"}"#).unwrap();
+/// let price: ClientPrice = serde_json::from_str(
+///     r#"{"instrument": "EUR_USD", "closeoutBid": "1.07100", "closeoutAsk": "1.07110",
+///         "quoteHomeConversionFactors": {"positiveUnits": "1", "negativeUnits": "1"}}"#,
+/// )
+/// .unwrap();
+/// let trade: TradeSummary = serde_json::from_str(
+///     r#"{"id": "6368", "instrument": "EUR_USD", "price": "1.07000", "currentUnits": "1000"}"#,
+/// )
+/// .unwrap();
+///
+/// let mut calculator = PnlCalculator::new();
+/// calculator.add_instrument(&instrument);
+/// calculator.update_price(&price);
+///
+/// let state = calculator.trade_state(&trade).unwrap();
+/// assert_eq!(state.unrealized_pl.unwrap().to_string(), "1.00000");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PnlCalculator {
+    prices: HashMap<InstrumentName, (Quote, Option<QuoteConversion>)>,
+    home_conversions: HashMap<String, QuoteConversion>,
+    margin_rates: HashMap<InstrumentName, Decimal>,
+    account_margin_rate: Option<Decimal>,
+}
+
+impl PnlCalculator {
+    /// Creates an empty calculator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an instrument's margin rate.
+    pub fn add_instrument(&mut self, instrument: &Instrument) -> &mut Self {
+        if let (Some(name), Some(rate)) = (&instrument.name, instrument.margin_rate) {
+            self.margin_rates.insert(name.clone(), rate.value());
+        }
+        self
+    }
+
+    /// Records the account's `marginRate` override for
+    /// [`trade_state`](Self::trade_state) and
+    /// [`position_state`](Self::position_state).
+    pub fn set_account(&mut self, account: &AccountSummary) -> &mut Self {
+        self.account_margin_rate = account.margin_rate.map(|r| r.value());
+        self
+    }
+
+    /// Records the latest price of its instrument, together with its quote
+    /// home conversion factors when present. Prices without an instrument
+    /// or a bid/ask are ignored.
+    pub fn update_price(&mut self, price: &ClientPrice) -> &mut Self {
+        if let (Some(instrument), Some(quote)) = (&price.instrument, Quote::from_price(price)) {
+            self.prices.insert(
+                instrument.clone(),
+                (quote, QuoteConversion::from_price(price)),
+            );
+        }
+        self
+    }
+
+    /// Records home conversions (as returned alongside prices) for
+    /// instruments whose prices carry no conversion factors of their own.
+    pub fn update_home_conversions(&mut self, conversions: &[HomeConversions]) -> &mut Self {
+        for conversion in conversions {
+            if let (Some(currency), Some(gain), Some(loss)) = (
+                &conversion.currency,
+                conversion.account_gain,
+                conversion.account_loss,
+            ) {
+                self.home_conversions.insert(
+                    currency.as_str().to_owned(),
+                    QuoteConversion {
+                        gain: gain.value(),
+                        loss: loss.value(),
+                    },
+                );
+            }
+        }
+        self
+    }
+
+    /// The unrealized P/L and margin used of an open trade, or `None` when
+    /// its instrument's price, conversion or margin rate is not known.
+    pub fn trade_state(&self, trade: &TradeSummary) -> Option<CalculatedTradeState> {
+        let market = self.market(trade.instrument.as_ref()?, self.account_margin_rate)?;
+        let units = trade.current_units?.value();
+        let entry = trade.price?.value();
+        Some(CalculatedTradeState {
+            id: trade.id.clone(),
+            unrealized_pl: Some(AccountUnits(market.pl(market.quote, units, entry))),
+            margin_used: Some(AccountUnits(market.margin(market.quote, units))),
+        })
+    }
+
+    /// The unrealized P/L of each side of a position and its margin used,
+    /// or `None` when its instrument's price, conversion or margin rate is
+    /// not known.
+    ///
+    /// The margin of a position holding both long and short units is that
+    /// of its larger side.
+    pub fn position_state(&self, position: &Position) -> Option<CalculatedPositionState> {
+        let valuation = self.value_position(position, false, self.account_margin_rate)?;
+        Some(CalculatedPositionState {
+            instrument: position.instrument.clone(),
+            net_unrealized_pl: Some(AccountUnits(valuation.long_pl + valuation.short_pl)),
+            long_unrealized_pl: Some(AccountUnits(valuation.long_pl)),
+            short_unrealized_pl: Some(AccountUnits(valuation.short_pl)),
+            margin_used: Some(AccountUnits(valuation.margin)),
+        })
+    }
+
+    /// The account's NAV, margin and closeout figures given its summary
+    /// (for the balance) and its open positions, or `None` when the
+    /// summary has no balance or any position cannot be valued.
+    ///
+    /// The account's `marginRate` override is taken from `account`; call
+    /// [`set_account`](Self::set_account) with the same summary for trade
+    /// and position states to use it too.
+    pub fn account_state(
+        &self,
+        account: &AccountSummary,
+        positions: &[Position],
+    ) -> Option<CalculatedAccountState> {
+        let account_margin_rate = account.margin_rate.map(|r| r.value());
+        let balance = account.balance?.value();
+        let mut live = Valuation::default();
+        let mut closeout = Valuation::default();
+        for position in positions {
+            live += self.value_position(position, false, account_margin_rate)?;
+            closeout += self.value_position(position, true, account_margin_rate)?;
+        }
+
+        let unrealized_pl = live.long_pl + live.short_pl;
+        let nav = balance + unrealized_pl;
+        let closeout_unrealized_pl = closeout.long_pl + closeout.short_pl;
+        let closeout_nav = balance + closeout_unrealized_pl;
+        let percent = |margin: Decimal, nav: Decimal| {
+            if nav > Decimal::ZERO {
+                Some(DecimalNumber(margin / nav))
+            } else if margin.is_zero() {
+                Some(DecimalNumber(Decimal::ZERO))
+            } else {
+                // the account is below zero NAV with positions open
+                None
+            }
+        };
+
+        Some(CalculatedAccountState {
+            unrealized_pl: Some(AccountUnits(unrealized_pl)),
+            nav: Some(AccountUnits(nav)),
+            margin_used: Some(AccountUnits(live.margin)),
+            margin_available: Some(AccountUnits((nav - live.margin).max(Decimal::ZERO))),
+            position_value: Some(AccountUnits(live.value)),
+            margin_closeout_unrealized_pl: Some(AccountUnits(closeout_unrealized_pl)),
+            margin_closeout_nav: Some(AccountUnits(closeout_nav)),
+            margin_closeout_margin_used: Some(AccountUnits(closeout.margin)),
+            margin_closeout_percent: percent(closeout.margin / Decimal::TWO, closeout_nav),
+            margin_closeout_position_value: Some(DecimalNumber(closeout.value)),
+            withdrawal_limit: None,
+            margin_call_margin_used: Some(AccountUnits(live.margin)),
+            margin_call_percent: percent(live.margin, nav),
+            dividend_adjustment: None,
+            true_nav: None,
+            true_unrealized_pl: None,
+            last_dividend_adjustment_timestamps: Vec::new(),
+        })
+    }
+
+    fn market(
+        &self,
+        instrument: &InstrumentName,
+        account_margin_rate: Option<Decimal>,
+    ) -> Option<Market> {
+        let (quote, conversion) = self.prices.get(instrument)?;
+        let conversion = conversion.or_else(|| {
+            let (_, currency) = instrument.as_str().split_once('_')?;
+            self.home_conversions.get(currency).copied()
+        })?;
+        let margin_rate = effective_margin_rate(
+            self.margin_rates.get(instrument).copied(),
+            account_margin_rate,
+        )?;
+        Some(Market {
+            quote: *quote,
+            conversion,
+            margin_rate,
+        })
+    }
+
+    fn value_position(
+        &self,
+        position: &Position,
+        closeout: bool,
+        account_margin_rate: Option<Decimal>,
+    ) -> Option<Valuation> {
+        let market = self.market(position.instrument.as_ref()?, account_margin_rate)?;
+        let quote = if closeout {
+            market.quote.closeout()
+        } else {
+            market.quote
+        };
+        let side = |side: Option<&PositionSide>| -> Option<(Decimal, Decimal, Decimal)> {
+            let Some(side) = side else {
+                return Some(Default::default());
+            };
+            let units = side.units.map(|u| u.value()).unwrap_or_default();
+            if units.is_zero() {
+                return Some(Default::default());
+            }
+            let entry = side.average_price?.value();
+            Some((
+                market.pl(quote, units, entry),
+                market.margin(quote, units),
+                market.conversion.convert_value(units.abs() * quote.mid()),
+            ))
+        };
+        let (long_pl, long_margin, long_value) = side(position.long.as_ref())?;
+        let (short_pl, short_margin, short_value) = side(position.short.as_ref())?;
+        Some(Valuation {
+            long_pl,
+            short_pl,
+            margin: long_margin.max(short_margin),
+            value: long_value + short_value,
+        })
+    }
+}
+
+/// An instrument's latest quote with its conversion and margin rate.
+#[derive(Debug, Clone, Copy)]
+struct Market {
+    quote: Quote,
+    conversion: QuoteConversion,
+    margin_rate: Decimal,
+}
+
+impl Market {
+    /// Home-currency P/L of `units` opened at `entry`: longs close at the
+    /// bid, shorts at the ask.
+    fn pl(&self, quote: Quote, units: Decimal, entry: Decimal) -> Decimal {
+        let exit = if units.is_sign_negative() {
+            quote.ask
+        } else {
+            quote.bid
+        };
+        self.conversion.convert((exit - entry) * units)
+    }
+
+    fn margin(&self, quote: Quote, units: Decimal) -> Decimal {
+        self.conversion.convert_value(units.abs() * quote.mid()) * self.margin_rate
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Valuation {
+    long_pl: Decimal,
+    short_pl: Decimal,
+    margin: Decimal,
+    value: Decimal,
+}
+
+impl std::ops::AddAssign for Valuation {
+    fn add_assign(&mut self, other: Valuation) {
+        self.long_pl += other.long_pl;
+        self.short_pl += other.short_pl;
+        self.margin += other.margin;
+        self.value += other.value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::dec;
+
+    fn calculator() -> PnlCalculator {
+        let mut calculator = PnlCalculator::new();
+        for instrument in [
+            json!({"name": "EUR_USD", "marginRate": "0.05"}),
+            json!({"name": "USD_JPY", "marginRate": "0.04"}),
+        ] {
+            calculator.add_instrument(&serde_json::from_value(instrument).unwrap());
+        }
+        calculator.update_price(
+            &serde_json::from_value(json!({
+                "instrument": "EUR_USD",
+                "bids": [{"price": "1.10000", "liquidity": 1000000}],
+                "asks": [{"price": "1.10020", "liquidity": 1000000}],
+                "closeoutBid": "1.09990", "closeoutAsk": "1.10030",
+                "quoteHomeConversionFactors": {"positiveUnits": "1", "negativeUnits": "1"}
+            }))
+            .unwrap(),
+        );
+        calculator.update_price(
+            &serde_json::from_value(json!({
+                "instrument": "USD_JPY",
+                "bids": [{"price": "150.000", "liquidity": 1000000}],
+                "asks": [{"price": "150.020", "liquidity": 1000000}],
+                "closeoutBid": "149.990", "closeoutAsk": "150.030"
+            }))
+            .unwrap(),
+        );
+        calculator.update_home_conversions(
+            &serde_json::from_value::<Vec<HomeConversions>>(json!([
+                {"currency": "JPY", "accountGain": "0.0066", "accountLoss": "0.0067"}
+            ]))
+            .unwrap(),
+        );
+        calculator
+    }
+
+    #[test]
+    fn trade_marks_long_at_bid_and_short_at_ask() {
+        let calculator = calculator();
+        let long: TradeSummary = serde_json::from_value(json!({
+            "id": "1", "instrument": "EUR_USD", "price": "1.09000", "currentUnits": "10000"
+        }))
+        .unwrap();
+        let state = calculator.trade_state(&long).unwrap();
+        assert_eq!(state.id.unwrap().as_str(), "1");
+        assert_eq!(state.unrealized_pl.unwrap().value(), dec("100"));
+        assert_eq!(state.margin_used.unwrap().value(), dec("550.05"));
+
+        // a losing JPY short converts at the loss factor
+        let short: TradeSummary = serde_json::from_value(json!({
+            "id": "2", "instrument": "USD_JPY", "price": "149.000", "currentUnits": "-1000"
+        }))
+        .unwrap();
+        let state = calculator.trade_state(&short).unwrap();
+        assert_eq!(
+            state.unrealized_pl.unwrap().value(),
+            dec("-1020") * dec("0.0067")
+        );
+    }
+
+    #[test]
+    fn unknown_instrument_cannot_be_valued() {
+        let trade: TradeSummary = serde_json::from_value(json!({
+            "instrument": "GBP_USD", "price": "1.25000", "currentUnits": "1"
+        }))
+        .unwrap();
+        assert!(calculator().trade_state(&trade).is_none());
+    }
+
+    #[test]
+    fn hedged_position_uses_larger_side_for_margin() {
+        let position: Position = serde_json::from_value(json!({
+            "instrument": "EUR_USD",
+            "long": {"units": "10000", "averagePrice": "1.09000"},
+            "short": {"units": "-4000", "averagePrice": "1.11000"}
+        }))
+        .unwrap();
+        let state = calculator().position_state(&position).unwrap();
+        assert_eq!(state.long_unrealized_pl.unwrap().value(), dec("100"));
+        assert_eq!(state.short_unrealized_pl.unwrap().value(), dec("39.2"));
+        assert_eq!(state.net_unrealized_pl.unwrap().value(), dec("139.2"));
+        assert_eq!(state.margin_used.unwrap().value(), dec("550.05"));
+    }
+
+    #[test]
+    fn account_state_matches_server_fields() {
+        let account: AccountSummary =
+            serde_json::from_value(json!({"balance": "1000", "marginRate": "0.1"})).unwrap();
+        let positions: Vec<Position> = serde_json::from_value(json!([{
+            "instrument": "EUR_USD",
+            "long": {"units": "10000", "averagePrice": "1.10100"}
+        }]))
+        .unwrap();
+        let expected: CalculatedAccountState = serde_json::from_value(json!({
+            "unrealizedPL": "-10",
+            "NAV": "990",
+            "marginUsed": "1100.1",
+            "marginAvailable": "0",
+            "positionValue": "11001",
+            "marginCloseoutUnrealizedPL": "-11",
+            "marginCloseoutNAV": "989",
+            "marginCloseoutMarginUsed": "1100.1",
+            "marginCloseoutPercent": "0.5561678463094034378159757331",
+            "marginCloseoutPositionValue": "11001",
+            "marginCallMarginUsed": "1100.1",
+            "marginCallPercent": "1.1112121212121212121212121212"
+        }))
+        .unwrap();
+
+        let mut calculator = calculator();
+        let state = calculator.account_state(&account, &positions).unwrap();
+        assert_eq!(state, expected);
+        // Once recorded, the override applies to position states too:
+        // 11001 * 0.1.
+        let position = calculator
+            .set_account(&account)
+            .position_state(&positions[0])
+            .unwrap();
+        assert_eq!(position.margin_used, state.margin_used);
+    }
+}
@@ -0,0 +1,377 @@
+//! Position sizing and margin requirements from instrument, price and
+//! account data.
+
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::error::Error;
+use crate::models::{
+    AccountSummary, AccountUnits, ClientPrice, DecimalNumber, HomeConversionFactors,
+    HomeConversions, Instrument, InstrumentName,
+};
+
+/// Factors converting amounts in an instrument's quote currency into the
+/// account's home currency.
+///
+/// OANDA quotes separate factors for gains and losses (they differ by the
+/// spread of the conversion pair); values such as margin use their
+/// midpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuoteConversion {
+    /// The factor applied to positive amounts (gains).
+    pub gain: Decimal,
+    /// The factor applied to negative amounts (losses).
+    pub loss: Decimal,
+}
+
+impl QuoteConversion {
+    /// The conversion of an instrument quoted in the home currency.
+    pub fn identity() -> Self {
+        QuoteConversion {
+            gain: Decimal::ONE,
+            loss: Decimal::ONE,
+        }
+    }
+
+    /// The factors carried by a price's `quoteHomeConversionFactors`.
+    pub fn from_price(price: &ClientPrice) -> Option<Self> {
+        let factors = price.quote_home_conversion_factors.as_ref()?;
+        Some(QuoteConversion {
+            gain: factors.positive_units?.value(),
+            loss: factors.negative_units?.value(),
+        })
+    }
+
+    /// The quote factors of a fill's [`HomeConversionFactors`].
+    pub fn from_factors(factors: &HomeConversionFactors) -> Option<Self> {
+        Some(QuoteConversion {
+            gain: factors.gain_quote_home.as_ref()?.factor?.value(),
+            loss: factors.loss_quote_home.as_ref()?.factor?.value(),
+        })
+    }
+
+    /// The factors for `instrument`'s quote currency among the
+    /// [`HomeConversions`] returned by
+    /// [`PricesRequest::include_home_conversions`](crate::endpoints::pricing::PricesRequest::include_home_conversions).
+    pub fn from_home_conversions(
+        conversions: &[HomeConversions],
+        instrument: &InstrumentName,
+    ) -> Option<Self> {
+        let (_, quote) = instrument.as_str().split_once('_')?;
+        let conversion = conversions
+            .iter()
+            .find(|c| c.currency.as_ref().is_some_and(|c| c.as_str() == quote))?;
+        Some(QuoteConversion {
+            gain: conversion.account_gain?.value(),
+            loss: conversion.account_loss?.value(),
+        })
+    }
+
+    /// Converts a quote-currency amount, applying the gain factor to
+    /// positive and the loss factor to negative amounts.
+    pub fn convert(&self, amount: Decimal) -> Decimal {
+        if amount.is_sign_negative() {
+            amount * self.loss
+        } else {
+            amount * self.gain
+        }
+    }
+
+    /// Converts a quote-currency value (a position value or margin) at
+    /// the midpoint of the two factors.
+    pub fn convert_value(&self, amount: Decimal) -> Decimal {
+        amount * (self.gain + self.loss) / Decimal::TWO
+    }
+}
+
+/// The bid/ask snapshot of a [`ClientPrice`] used by the calculators.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Quote {
+    pub(crate) bid: Decimal,
+    pub(crate) ask: Decimal,
+    pub(crate) closeout_bid: Decimal,
+    pub(crate) closeout_ask: Decimal,
+}
+
+impl Quote {
+    /// Best bid/ask, falling back to the closeout prices (and vice versa).
+    pub(crate) fn from_price(price: &ClientPrice) -> Option<Quote> {
+        let best = |buckets: &[crate::models::PriceBucket]| {
+            buckets.first().and_then(|b| b.price).map(|p| p.value())
+        };
+        let closeout_bid = price.closeout_bid.map(|p| p.value());
+        let closeout_ask = price.closeout_ask.map(|p| p.value());
+        let bid = best(&price.bids).or(closeout_bid)?;
+        let ask = best(&price.asks).or(closeout_ask)?;
+        Some(Quote {
+            bid,
+            ask,
+            closeout_bid: closeout_bid.unwrap_or(bid),
+            closeout_ask: closeout_ask.unwrap_or(ask),
+        })
+    }
+
+    pub(crate) fn mid(&self) -> Decimal {
+        (self.bid + self.ask) / Decimal::TWO
+    }
+
+    /// The quote used for margin closeout calculations.
+    pub(crate) fn closeout(&self) -> Quote {
+        Quote {
+            bid: self.closeout_bid,
+            ask: self.closeout_ask,
+            ..*self
+        }
+    }
+}
+
+/// The margin rate applying to `instrument`: the instrument's rate, or
+/// the account's margin-rate override when that is stricter.
+pub(crate) fn effective_margin_rate(
+    instrument_rate: Option<Decimal>,
+    account_rate: Option<Decimal>,
+) -> Option<Decimal> {
+    match (instrument_rate, account_rate) {
+        (Some(instrument), Some(account)) => Some(instrument.max(account)),
+        (rate, None) | (None, rate) => rate,
+    }
+}
+
+/// The number of units to trade so that hitting a stop `stop_distance`
+/// (in price units) away loses `risk_fraction` of the account's NAV.
+///
+/// The loss per unit is converted into the home currency with the
+/// price's loss conversion factor. The result is positive (pick the sign
+/// for the direction), rounded down to the instrument's
+/// `tradeUnitsPrecision` so the risk budget is never exceeded, capped at
+/// `maximumOrderUnits`, and zero when the budget does not cover the
+/// `minimumTradeSize`.
+///
+/// ```
+/// use oanda_rs::models::{AccountSummary, ClientPrice, Instrument};
+/// use oanda_rs::trading::size_for_risk;
+/// use rust_decimal::Decimal;
+///
+/// let account: AccountSummary = serde_json::from_str(r#"{"NAV": "10000"}"#).unwrap();
+/// let instrument: Instrument =
+///     serde_json::from_str(r#"{"name": "EUR_USD", "tradeUnitsPrecision": 0}"#).unwrap();
+/// let price: ClientPrice = serde_json::from_str(
+///     r#"{"closeoutBid": "1.07000", "closeoutAsk": "1.07010",
+///         "quoteHomeConversionFactors": {"positiveUnits": "1", "negativeUnits": "1"}}"#,
+/// )
+/// .unwrap();
+///
+/// // 1% of 10,000 USD with a 20-pip stop: 100 / 0.0020 = 50,000 units.
+/// let units = size_for_risk(&account, &instrument, &price, "0.0020".parse().unwrap(), Decimal::new(1, 2))
+///     .unwrap();
+/// assert_eq!(units.to_string(), "50000");
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidOrder`] when the account has no NAV, the price
+/// carries no quote home conversion factors (or a loss factor that is not
+/// positive), the stop distance or risk fraction is not positive, or the
+/// size is too large to represent.
+pub fn size_for_risk(
+    account: &AccountSummary,
+    instrument: &Instrument,
+    price: &ClientPrice,
+    stop_distance: Decimal,
+    risk_fraction: Decimal,
+) -> Result<DecimalNumber, Error> {
+    if stop_distance <= Decimal::ZERO {
+        return Err(invalid("the stop distance must be positive"));
+    }
+    if risk_fraction <= Decimal::ZERO {
+        return Err(invalid("the risk fraction must be positive"));
+    }
+    let nav = account
+        .nav
+        .ok_or_else(|| invalid("the account summary carries no NAV"))?
+        .value();
+    let conversion = QuoteConversion::from_price(price)
+        .ok_or_else(|| invalid("the price carries no quote home conversion factors"))?;
+
+    let budget = nav
+        .checked_mul(risk_fraction)
+        .ok_or_else(|| invalid("the risk budget is out of range"))?;
+    let loss_per_unit = -conversion.convert(-stop_distance);
+    if loss_per_unit <= Decimal::ZERO {
+        return Err(invalid(
+            "the quote home conversion factor for losses must be positive",
+        ));
+    }
+    let precision = instrument
+        .trade_units_precision
+        .and_then(|p| u32::try_from(p).ok())
+        .unwrap_or(0);
+    let mut units = budget
+        .checked_div(loss_per_unit)
+        .ok_or_else(|| invalid("the position size is out of range"))?
+        .round_dp_with_strategy(precision, RoundingStrategy::ToZero)
+        .normalize();
+    if let Some(maximum) = instrument.maximum_order_units {
+        units = units.min(maximum.value());
+    }
+    if instrument
+        .minimum_trade_size
+        .is_some_and(|minimum| units < minimum.value())
+    {
+        units = Decimal::ZERO;
+    }
+    Ok(DecimalNumber(units))
+}
+
+/// The margin (in the home currency) a position of `units` requires at
+/// the current price.
+///
+/// The position value is `|units|` at the mid price, converted at the
+/// midpoint of the price's conversion factors; the margin rate is the
+/// instrument's, or the account's `marginRate` override when that is
+/// higher.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidOrder`] when the price has no bid/ask or no
+/// quote home conversion factors, or no margin rate is known.
+pub fn margin_required(
+    units: impl Into<DecimalNumber>,
+    price: &ClientPrice,
+    instrument: &Instrument,
+    account: &AccountSummary,
+) -> Result<AccountUnits, Error> {
+    let quote = Quote::from_price(price).ok_or_else(|| invalid("the price has no bid/ask"))?;
+    let conversion = QuoteConversion::from_price(price)
+        .ok_or_else(|| invalid("the price carries no quote home conversion factors"))?;
+    let rate = effective_margin_rate(
+        instrument.margin_rate.map(|r| r.value()),
+        account.margin_rate.map(|r| r.value()),
+    )
+    .ok_or_else(|| invalid("neither the instrument nor the account has a margin rate"))?;
+    let value = conversion.convert_value(units.into().value().abs() * quote.mid());
+    Ok(AccountUnits(value * rate))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidOrder(message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::dec;
+
+    fn price(bid: &str, ask: &str, gain: &str, loss: &str) -> ClientPrice {
+        serde_json::from_value(json!({
+            "instrument": "EUR_GBP",
+            "bids": [{"price": bid, "liquidity": 1000000}],
+            "asks": [{"price": ask, "liquidity": 1000000}],
+            "closeoutBid": bid,
+            "closeoutAsk": ask,
+            "quoteHomeConversionFactors": {"positiveUnits": gain, "negativeUnits": loss}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn conversion_applies_gain_and_loss_factors() {
+        let conversion = QuoteConversion {
+            gain: dec("1.25"),
+            loss: dec("1.27"),
+        };
+        assert_eq!(conversion.convert(dec("10")), dec("12.5"));
+        assert_eq!(conversion.convert(dec("-10")), dec("-12.7"));
+        assert_eq!(conversion.convert_value(dec("10")), dec("12.6"));
+    }
+
+    #[test]
+    fn conversion_from_home_conversions_uses_quote_currency() {
+        let conversions: Vec<HomeConversions> = serde_json::from_value(json!([
+            {"currency": "EUR", "accountGain": "1.07", "accountLoss": "1.08"},
+            {"currency": "JPY", "accountGain": "0.0063", "accountLoss": "0.0064"}
+        ]))
+        .unwrap();
+        let conversion =
+            QuoteConversion::from_home_conversions(&conversions, &InstrumentName::UsdJpy).unwrap();
+        assert_eq!(conversion.gain, dec("0.0063"));
+        assert_eq!(conversion.loss, dec("0.0064"));
+    }
+
+    #[test]
+    fn sizes_in_home_currency_and_rounds_down() {
+        let account: AccountSummary = serde_json::from_value(json!({"NAV": "25000"})).unwrap();
+        let instrument: Instrument = serde_json::from_value(json!({
+            "name": "EUR_GBP", "tradeUnitsPrecision": 0, "minimumTradeSize": "1",
+            "maximumOrderUnits": "100000000"
+        }))
+        .unwrap();
+        // 0.5% of 25,000 = 125 home; 30-pip stop costs 0.0030 * 1.27 per unit.
+        let units = size_for_risk(
+            &account,
+            &instrument,
+            &price("0.85000", "0.85010", "1.25", "1.27"),
+            dec("0.0030"),
+            dec("0.005"),
+        )
+        .unwrap();
+        assert_eq!(units.value(), dec("32808"));
+    }
+
+    #[test]
+    fn sizing_respects_order_limits() {
+        let account: AccountSummary = serde_json::from_value(json!({"NAV": "1000"})).unwrap();
+        let capped: Instrument =
+            serde_json::from_value(json!({"maximumOrderUnits": "1000"})).unwrap();
+        let price = price("1.0", "1.0", "1", "1");
+        let units = size_for_risk(&account, &capped, &price, dec("0.01"), dec("0.5")).unwrap();
+        assert_eq!(units.value(), dec("1000"));
+
+        let minimum: Instrument =
+            serde_json::from_value(json!({"minimumTradeSize": "1000"})).unwrap();
+        let units = size_for_risk(&account, &minimum, &price, dec("1"), dec("0.01")).unwrap();
+        assert!(units.value().is_zero());
+
+        assert!(size_for_risk(&account, &minimum, &price, dec("0"), dec("0.01")).is_err());
+    }
+
+    #[test]
+    fn sizing_rejects_degenerate_values_instead_of_panicking() {
+        let account: AccountSummary = serde_json::from_value(json!({"NAV": "1000"})).unwrap();
+        let instrument: Instrument = serde_json::from_value(json!({})).unwrap();
+        let free = price("1.0", "1.0", "1", "0");
+        assert!(matches!(
+            size_for_risk(&account, &instrument, &free, dec("0.01"), dec("0.01")),
+            Err(Error::InvalidOrder(_))
+        ));
+
+        let tiny = price("1.0", "1.0", "1", "1");
+        assert!(matches!(
+            size_for_risk(
+                &account,
+                &instrument,
+                &tiny,
+                dec("0.0000000000000000000000000001"),
+                dec("1")
+            ),
+            Err(Error::InvalidOrder(_))
+        ));
+    }
+
+    #[test]
+    fn margin_uses_stricter_account_rate() {
+        let price = price("0.85000", "0.85010", "1.25", "1.27");
+        let instrument: Instrument =
+            serde_json::from_value(json!({"marginRate": "0.0333"})).unwrap();
+        let plain: AccountSummary = serde_json::from_value(json!({})).unwrap();
+        let overridden: AccountSummary =
+            serde_json::from_value(json!({"marginRate": "0.05"})).unwrap();
+
+        // 10,000 * 0.85005 mid * 1.26 mid factor = 10710.63 home.
+        let margin = margin_required(-10_000, &price, &instrument, &plain).unwrap();
+        assert_eq!(margin.value(), dec("10710.63") * dec("0.0333"));
+        let margin = margin_required(10_000, &price, &instrument, &overridden).unwrap();
+        assert_eq!(margin.value(), dec("10710.63") * dec("0.05"));
+    }
+}
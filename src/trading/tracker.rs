@@ -0,0 +1,392 @@
+//! Order lifecycle tracking driven by the transaction stream.
+
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use futures_util::StreamExt;
+use tokio::sync::watch;
+
+use crate::endpoints::orders::CreateOrderResponse;
+use crate::error::Error;
+use crate::models::OrderSpecifier;
+use crate::models::transaction::{
+    OrderCancelReason, OrderCancelTransaction, OrderFillTransaction, Transaction,
+    TransactionStreamItem,
+};
+use crate::streaming::TransactionStream;
+
+/// How many resolved orders are remembered, so that an order registered
+/// just after its fill went past still resolves.
+const RESOLVED_HISTORY: usize = 1024;
+
+/// The lifecycle state of a tracked order.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+#[allow(clippy::large_enum_variant)] // variants mirror the wire format; boxing would hurt ergonomics
+pub enum OrderStatus {
+    /// Neither a fill nor a cancellation has been observed yet.
+    Pending,
+    /// The order was filled; the first fill resolves the order.
+    Filled(OrderFillTransaction),
+    /// The order was cancelled.
+    Cancelled {
+        /// Why OANDA cancelled the order.
+        reason: Option<OrderCancelReason>,
+        /// The full cancellation transaction.
+        transaction: OrderCancelTransaction,
+    },
+}
+
+impl OrderStatus {
+    /// Whether the order has reached a final state (filled or cancelled).
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, OrderStatus::Pending)
+    }
+}
+
+/// Registration key: an order is addressed either by its OANDA ID or by
+/// the client ID assigned through its client extensions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Id(String),
+    Client(String),
+}
+
+impl Key {
+    fn from_specifier(order: &OrderSpecifier) -> Key {
+        match order.as_str().strip_prefix('@') {
+            Some(client_id) => Key::Client(client_id.to_owned()),
+            None => Key::Id(order.as_str().to_owned()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    watching: HashMap<Key, watch::Sender<OrderStatus>>,
+    resolved: HashMap<Key, OrderStatus>,
+    resolved_order: VecDeque<Key>,
+    closed: bool,
+}
+
+impl Registry {
+    fn subscribe(&mut self, key: Key) -> watch::Receiver<OrderStatus> {
+        if let Some(state) = self.resolved.get(&key) {
+            return watch::channel(state.clone()).1;
+        }
+        if self.closed {
+            // Nothing will ever resolve it; the dropped sender says so.
+            return watch::channel(OrderStatus::Pending).1;
+        }
+        // Forget orders nobody is waiting for any more.
+        self.watching
+            .retain(|_, sender| sender.receiver_count() > 0);
+        self.watching
+            .entry(key)
+            .or_insert_with(|| watch::channel(OrderStatus::Pending).0)
+            .subscribe()
+    }
+
+    fn observe(&mut self, transaction: &Transaction) {
+        match transaction {
+            Transaction::OrderFill(fill) => self.observe_fill(fill),
+            Transaction::OrderCancel(cancel) => self.observe_cancel(cancel),
+            _ => {}
+        }
+    }
+
+    fn observe_fill(&mut self, fill: &OrderFillTransaction) {
+        let keys = [
+            fill.order_id.as_ref().map(|id| Key::Id(id.0.clone())),
+            fill.client_order_id.clone().map(Key::Client),
+        ];
+        self.resolve(keys, OrderStatus::Filled(fill.clone()));
+    }
+
+    fn observe_cancel(&mut self, cancel: &OrderCancelTransaction) {
+        let keys = [
+            cancel.order_id.as_ref().map(|id| Key::Id(id.0.clone())),
+            cancel
+                .client_order_id
+                .as_ref()
+                .map(|id| Key::Client(id.0.clone())),
+        ];
+        let state = OrderStatus::Cancelled {
+            reason: cancel.reason.clone(),
+            transaction: cancel.clone(),
+        };
+        self.resolve(keys, state);
+    }
+
+    /// Stops tracking: pending watches observe the dropped senders.
+    fn close(&mut self) {
+        self.closed = true;
+        self.watching.clear();
+    }
+
+    fn resolve(&mut self, keys: [Option<Key>; 2], state: OrderStatus) {
+        for key in keys.into_iter().flatten() {
+            if self.resolved.contains_key(&key) {
+                continue; // a later fill of a reissued order; the first one wins
+            }
+            if let Some(sender) = self.watching.remove(&key) {
+                sender.send_replace(state.clone());
+            }
+            self.resolved.insert(key.clone(), state.clone());
+            self.resolved_order.push_back(key);
+            while self.resolved_order.len() > RESOLVED_HISTORY {
+                if let Some(oldest) = self.resolved_order.pop_front() {
+                    self.resolved.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+/// Follows orders from creation to fill or cancellation by watching a
+/// transaction stream.
+///
+/// The tracker wraps a [`TransactionStream`] (or any stream of
+/// transaction items) and is itself a pass-through [`Stream`]: every item
+/// is yielded unchanged, and fills and cancellations are matched against
+/// the registered orders by `orderID` and `clientOrderID` as they go by.
+/// If nothing else consumes the transactions, drive it with
+/// [`run`](Self::run) on a task of its own.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), oanda_rs::Error> {
+/// # let client = oanda_rs::Client::new(oanda_rs::Environment::Practice, "token");
+/// use oanda_rs::models::{ClientExtensions, LimitOrderRequest, PriceValue};
+/// use oanda_rs::trading::{OrderStatus, OrderTracker};
+///
+/// let account = "101-004-1234567-001";
+/// let tracker = OrderTracker::new(client.transaction_stream(account).send().await?);
+/// let handle = tracker.handle();
+/// tokio::spawn(tracker.run());
+///
+/// let order = LimitOrderRequest::new("EUR_USD", 100, "1.0700".parse::<PriceValue>().unwrap())
+///     .client_extensions(ClientExtensions::new().id("entry-1"));
+/// let response = client.create_order(account, order).await?;
+/// match handle.track_created(&response).outcome().await? {
+///     OrderStatus::Filled(fill) => println!("filled at {:?}", fill.price),
+///     OrderStatus::Cancelled { reason, .. } => println!("cancelled: {reason:?}"),
+///     _ => {}
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct OrderTracker<S = TransactionStream> {
+    stream: S,
+    handle: OrderTrackerHandle,
+}
+
+impl<S> OrderTracker<S> {
+    /// Wraps a transaction stream.
+    pub fn new(stream: S) -> Self {
+        OrderTracker {
+            stream,
+            handle: OrderTrackerHandle {
+                registry: Arc::new(Mutex::new(Registry::default())),
+            },
+        }
+    }
+
+    /// A cloneable handle for registering orders while the tracker itself
+    /// is being driven elsewhere.
+    pub fn handle(&self) -> OrderTrackerHandle {
+        self.handle.clone()
+    }
+
+    /// Registers an order; see [`OrderTrackerHandle::track`].
+    pub fn track(&self, order: impl Into<OrderSpecifier>) -> OrderWatch {
+        self.handle.track(order)
+    }
+
+    /// Registers a freshly created order; see
+    /// [`OrderTrackerHandle::track_created`].
+    pub fn track_created(&self, response: &CreateOrderResponse) -> OrderWatch {
+        self.handle.track_created(response)
+    }
+}
+
+impl<S> OrderTracker<S>
+where
+    S: Stream<Item = Result<TransactionStreamItem, Error>> + Unpin,
+{
+    /// Consumes the stream until it ends, resolving registered orders on
+    /// the way.
+    ///
+    /// # Errors
+    ///
+    /// Returns the stream's final error when it ended with one (e.g. a
+    /// fatal reconnect failure). Pending [`OrderWatch`]es then fail as
+    /// well.
+    pub async fn run(mut self) -> Result<(), Error> {
+        let mut last_error = None;
+        while let Some(item) = self.next().await {
+            last_error = item.err();
+        }
+        last_error.map_or(Ok(()), Err)
+    }
+}
+
+impl<S> Stream for OrderTracker<S>
+where
+    S: Stream<Item = Result<TransactionStreamItem, Error>> + Unpin,
+{
+    type Item = Result<TransactionStreamItem, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.stream).poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(TransactionStreamItem::Transaction(tx)))) => {
+                self.handle.registry().observe(tx);
+            }
+            Poll::Ready(None) => self.handle.registry().close(),
+            _ => {}
+        }
+        poll
+    }
+}
+
+impl<S> Drop for OrderTracker<S> {
+    fn drop(&mut self) {
+        self.handle.registry().close();
+    }
+}
+
+impl<S> std::fmt::Debug for OrderTracker<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderTracker")
+            .field("handle", &self.handle)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A cloneable handle registering orders with an [`OrderTracker`].
+#[derive(Clone)]
+pub struct OrderTrackerHandle {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl OrderTrackerHandle {
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        // The registry holds no invariants a panicking holder could break.
+        self.registry
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Registers an order by ID, or by client ID via
+    /// [`OrderSpecifier::from_client_id`], and returns a watch on its
+    /// state.
+    ///
+    /// Orders resolved shortly before registration (the last 1024 fills
+    /// and cancellations seen) resolve immediately.
+    pub fn track(&self, order: impl Into<OrderSpecifier>) -> OrderWatch {
+        let key = Key::from_specifier(&order.into());
+        OrderWatch {
+            rx: self.registry().subscribe(key),
+        }
+    }
+
+    /// Registers the order created by [`Client::create_order`]: market
+    /// orders that were filled or cancelled immediately resolve at once,
+    /// anything else is tracked under the created order's ID.
+    ///
+    /// [`Client::create_order`]: crate::Client::create_order
+    pub fn track_created(&self, response: &CreateOrderResponse) -> OrderWatch {
+        let mut registry = self.registry();
+        if let Some(fill) = &response.order_fill_transaction {
+            registry.observe_fill(fill);
+        }
+        if let Some(cancel) = &response.order_cancel_transaction {
+            registry.observe_cancel(cancel);
+        }
+        // An order's ID is the ID of the transaction that created it.
+        let rx = match response.order_create_transaction.id() {
+            Some(id) => registry.subscribe(Key::Id(id.0.clone())),
+            None => watch::channel(OrderStatus::Pending).1,
+        };
+        OrderWatch { rx }
+    }
+}
+
+impl std::fmt::Debug for OrderTrackerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let registry = self.registry();
+        f.debug_struct("OrderTrackerHandle")
+            .field("watching", &registry.watching.len())
+            .field("resolved", &registry.resolved.len())
+            .finish()
+    }
+}
+
+/// A watch on one tracked order's [`OrderStatus`].
+#[derive(Debug, Clone)]
+pub struct OrderWatch {
+    rx: watch::Receiver<OrderStatus>,
+}
+
+impl OrderWatch {
+    /// The order's current state.
+    pub fn state(&self) -> OrderStatus {
+        self.rx.borrow().clone()
+    }
+
+    /// Waits for the next state change and returns the new state.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Stream`] when the tracker stopped before the
+    /// state changed.
+    pub async fn changed(&mut self) -> Result<OrderStatus, Error> {
+        self.rx.changed().await.map_err(|_| stopped())?;
+        Ok(self.rx.borrow_and_update().clone())
+    }
+
+    /// Waits until the order is filled or cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Stream`] when the tracker stopped before the order
+    /// resolved.
+    pub async fn outcome(mut self) -> Result<OrderStatus, Error> {
+        loop {
+            let state = self.rx.borrow_and_update().clone();
+            if state.is_terminal() {
+                return Ok(state);
+            }
+            if self.rx.changed().await.is_err() {
+                let state = self.rx.borrow().clone();
+                return if state.is_terminal() {
+                    Ok(state)
+                } else {
+                    Err(stopped())
+                };
+            }
+        }
+    }
+
+    /// The order's states as a stream: the current state first, then
+    /// every change, ending after the final state (or when the tracker
+    /// stops).
+    pub fn into_stream(self) -> impl Stream<Item = OrderStatus> + Send + 'static {
+        futures_util::stream::unfold(Some((self.rx, true)), |next| async move {
+            let (mut rx, first) = next?;
+            if !first && rx.changed().await.is_err() {
+                return None;
+            }
+            let state = rx.borrow_and_update().clone();
+            let next = (!state.is_terminal()).then_some((rx, false));
+            Some((state, next))
+        })
+    }
+}
+
+fn stopped() -> Error {
+    Error::Stream("order tracker stopped before the order resolved".to_owned())
+}
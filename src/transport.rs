@@ -2,15 +2,24 @@
 //! and error mapping. All endpoint modules funnel through this file, which
 //! is also where `tracing` instrumentation lives.
 
-use reqwest::header::HeaderMap;
-use reqwest::{Method, RequestBuilder, Response, Url};
+use std::time::Duration;
+
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use reqwest::{Method, Request, RequestBuilder, Response, StatusCode, Url};
+use serde::Deserialize;
 use serde::de::DeserializeOwned;
+use tokio::time::Instant;
 
+use crate::auth::Token;
+use crate::cassette::RawResponse;
 use crate::client::Client;
-use crate::error::{ApiErrorBody, Error};
+use crate::error::{ApiErrorBody, Error, ErrorKind};
+use crate::models::TransactionId;
 
 /// The response header carrying OANDA's request identifier.
 const REQUEST_ID_HEADER: &str = "RequestID";
+/// The response header carrying the URL of a created resource.
+const LOCATION_HEADER: &str = "Location";
 const DATETIME_FORMAT_HEADER: &str = "Accept-Datetime-Format";
 
 impl Client {
@@ -25,16 +34,13 @@ impl Client {
         join_segments(&self.inner.stream_base, segments)
     }
 
-    /// Starts a request with authentication and datetime-format headers set.
+    /// Starts a request with the datetime-format header set; the bearer
+    /// token is added by [`authorize`](Self::authorize) when it is sent.
     pub(crate) fn request(&self, method: Method, url: Url) -> RequestBuilder {
-        self.inner
-            .http
-            .request(method, url)
-            .bearer_auth(&self.inner.token)
-            .header(
-                DATETIME_FORMAT_HEADER,
-                self.inner.datetime_format.as_header_value(),
-            )
+        self.inner.http.request(method, url).header(
+            DATETIME_FORMAT_HEADER,
+            self.inner.datetime_format.as_header_value(),
+        )
     }
 
     /// Convenience for `request(Method::GET, self.rest_url(segments))`.
@@ -64,25 +70,166 @@ impl Client {
         }
     }
 
+    /// Sets the `Authorization` header from the token provider, returning
+    /// the token used. After an HTTP 401, pass the `rejected` token to ask
+    /// the provider for a fresh one.
+    pub(crate) async fn authorize(
+        &self,
+        request: &mut Request,
+        rejected: Option<Token>,
+    ) -> Result<Token, Error> {
+        let token = match rejected {
+            Some(rejected) => self.inner.token.refresh(rejected).await?,
+            None => self.inner.token.token().await?,
+        };
+        let mut value = HeaderValue::try_from(format!("Bearer {}", token.expose()))
+            .map_err(|_| Error::Config("the API token is not a valid header value".into()))?;
+        value.set_sensitive(true);
+        request.headers_mut().insert(AUTHORIZATION, value);
+        Ok(token)
+    }
+
     /// Executes a REST request and decodes the JSON response body.
     pub(crate) async fn execute<T: DeserializeOwned>(
         &self,
         request: RequestBuilder,
     ) -> Result<T, Error> {
-        let (value, _headers) = self.execute_with_headers(request).await?;
-        Ok(value)
+        // Timing is only reported through `execute_with_meta`.
+        let (mut rate_limit_wait, mut elapsed) = (Duration::ZERO, Duration::ZERO);
+        let response = self
+            .fetch(request, &mut rate_limit_wait, &mut elapsed)
+            .await?;
+        decode(&response.body)
     }
 
     /// Executes a REST request, returning the decoded body together with the
-    /// response headers (for endpoints that surface `Location`/`Link`).
-    pub(crate) async fn execute_with_headers<T: DeserializeOwned>(
+    /// response metadata (headers such as `Location`/`Link`, timing).
+    pub(crate) async fn execute_with_meta<T: DeserializeOwned>(
         &self,
         request: RequestBuilder,
-    ) -> Result<(T, HeaderMap), Error> {
+    ) -> Result<crate::Response<T>, Error> {
+        let mut rate_limit_wait = Duration::ZERO;
+        let mut elapsed = Duration::ZERO;
+        let response = self
+            .fetch(request, &mut rate_limit_wait, &mut elapsed)
+            .await?;
+        let value = decode(&response.body)?;
+        let last_transaction_id = serde_json::from_str::<LastTransactionId>(&response.body)
+            .ok()
+            .and_then(|body| body.last_transaction_id);
+        Ok(crate::Response {
+            value,
+            status: response.status,
+            request_id: header_str(&response.headers, REQUEST_ID_HEADER).map(str::to_owned),
+            last_transaction_id,
+            location: header_str(&response.headers, LOCATION_HEADER).map(str::to_owned),
+            elapsed,
+            rate_limit_wait,
+            headers: response.headers,
+        })
+    }
+
+    /// Gets a successful response to a REST request, from the cassette when
+    /// it holds one and over the network otherwise.
+    async fn fetch(
+        &self,
+        request: RequestBuilder,
+        rate_limit_wait: &mut Duration,
+        elapsed: &mut Duration,
+    ) -> Result<RawResponse, Error> {
+        let request = request.build()?;
+        let response = match self
+            .inner
+            .cassette
+            .as_ref()
+            .and_then(|c| c.replay(&request))
+        {
+            Some(replayed) => replayed?,
+            None => self.dispatch(request, rate_limit_wait, elapsed).await?,
+        };
+        if !response.status.is_success() {
+            return Err(api_error(response.status, &response.headers, response.body));
+        }
+        Ok(response)
+    }
+
+    /// Sends a REST request over the network: authorizes it, retries once
+    /// with a refreshed token after a 401, retries GET requests after
+    /// transient failures, and records the final response to the cassette.
+    async fn dispatch(
+        &self,
+        mut request: Request,
+        rate_limit_wait: &mut Duration,
+        elapsed: &mut Duration,
+    ) -> Result<RawResponse, Error> {
+        // The token first sent; taken once it has been refreshed.
+        let mut sent = Some(self.authorize(&mut request, None).await?);
+        *request.timeout_mut() = self.inner.timeout;
+        let recorded = self
+            .inner
+            .cassette
+            .is_some()
+            .then(|| request.try_clone())
+            .flatten();
+        let retry = self.inner.retry;
+        let max_retries = if request.method() == Method::GET {
+            retry.max_retries
+        } else {
+            0
+        };
+        let mut retries = 0;
+        let response = loop {
+            let next = request.try_clone();
+            let result = self.send(request, rate_limit_wait, elapsed).await;
+            let Some(mut next) = next else {
+                break result?;
+            };
+            let retryable = match &result {
+                // The token may have been rotated: retry once with a fresh one.
+                Ok(response) if response.status == StatusCode::UNAUTHORIZED && sent.is_some() => {
+                    self.authorize(&mut next, sent.take()).await?;
+                    request = next;
+                    continue;
+                }
+                Ok(response) => {
+                    !response.status.is_success()
+                        && ErrorKind::from_status(response.status).is_retryable()
+                }
+                Err(error) => error.kind().is_retryable(),
+            };
+            if !retryable || retries >= max_retries {
+                break result?;
+            }
+            tokio::time::sleep(retry.backoff.saturating_mul(1 << retries.min(16))).await;
+            retries += 1;
+            request = next;
+        };
+        if let (Some(cassette), Some(request)) = (&self.inner.cassette, recorded) {
+            // The request was sent: failing to record it must not turn its
+            // outcome (e.g. a placed order) into an error.
+            if let Err(_error) = cassette.record(&request, &response) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_error, "failed to record the exchange to the cassette");
+            }
+        }
+        Ok(response)
+    }
+
+    /// Sends a REST request over the network (through the rate limiter) and
+    /// reads its body, adding to the time spent waiting and in flight.
+    async fn send(
+        &self,
+        request: Request,
+        rate_limit_wait: &mut Duration,
+        elapsed: &mut Duration,
+    ) -> Result<RawResponse, Error> {
         if let Some(limiter) = &self.inner.rest_limiter {
+            let waiting = Instant::now();
             limiter.acquire().await;
+            *rate_limit_wait += waiting.elapsed();
         }
-        let response = request.send().await?;
+        let sent = Instant::now();
+        let response = self.inner.http.execute(request).await?;
 
         #[cfg(feature = "tracing")]
         tracing::debug!(
@@ -92,27 +239,49 @@ impl Client {
             "oanda response"
         );
 
-        if !response.status().is_success() {
-            return Err(error_from_response(response).await);
-        }
+        let status = response.status();
         let headers = response.headers().clone();
         let body = response.text().await?;
-        match serde_json::from_str(&body) {
-            Ok(value) => Ok((value, headers)),
-            Err(source) => Err(Error::Decode { source, body }),
-        }
+        *elapsed += sent.elapsed();
+        Ok(RawResponse {
+            status,
+            headers,
+            body,
+        })
     }
 }
 
+/// Decodes a JSON response body, keeping the body in the error when it
+/// does not match `T`.
+fn decode<T: DeserializeOwned>(body: &str) -> Result<T, Error> {
+    serde_json::from_str(body).map_err(|source| Error::Decode {
+        source,
+        body: body.to_owned(),
+    })
+}
+
+/// The `lastTransactionID` most response bodies carry, read for
+/// [`Response::last_transaction_id`](crate::Response::last_transaction_id).
+#[derive(Deserialize)]
+struct LastTransactionId {
+    #[serde(rename = "lastTransactionID")]
+    last_transaction_id: Option<TransactionId>,
+}
+
 /// Converts a non-success response into [`Error::Api`], keeping the raw body
 /// when it is not valid JSON.
 pub(crate) async fn error_from_response(response: Response) -> Error {
     let status = response.status();
-    let request_id = header_str(response.headers(), REQUEST_ID_HEADER).map(str::to_owned);
-    let text = match response.text().await {
-        Ok(text) => text,
-        Err(e) => return Error::Transport(e),
-    };
+    let headers = response.headers().clone();
+    match response.text().await {
+        Ok(text) => api_error(status, &headers, text),
+        Err(e) => Error::Transport(e),
+    }
+}
+
+/// Builds [`Error::Api`] from a non-success status, its headers and body.
+fn api_error(status: StatusCode, headers: &HeaderMap, text: String) -> Error {
+    let request_id = header_str(headers, REQUEST_ID_HEADER).map(str::to_owned);
     let body = serde_json::from_str::<ApiErrorBody>(&text)
         .unwrap_or_else(|_| ApiErrorBody::from_text(text));
     Error::Api {
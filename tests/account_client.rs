@@ -0,0 +1,281 @@
+//! Tests for the account-scoped client handle.
+
+mod common;
+
+use common::{ACCOUNT_ID, TOKEN, mock_client, standard_headers};
+use oanda_rs::account::MultiAccountClient;
+use oanda_rs::models::{
+    AccountId, CandleSpecification, CandlestickGranularity, ClientExtensions, MarketOrderRequest,
+    PricingComponent, TradeId,
+};
+use oanda_rs::{Environment, Error};
+use rust_decimal::Decimal;
+use serde_json::json;
+use wiremock::matchers::{body_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const OTHER_ACCOUNT_ID: &str = "101-004-7654321-001";
+const OTHER_TOKEN: &str = "other-token";
+
+/// Two accounts on the mock server: [`ACCOUNT_ID`] with the default token
+/// and [`OTHER_ACCOUNT_ID`] with [`OTHER_TOKEN`].
+async fn multi_account_client() -> (MockServer, MultiAccountClient) {
+    let (server, client) = mock_client().await;
+    let url: reqwest::Url = server.uri().parse().unwrap();
+    let environment = Environment::Custom {
+        rest: url.clone(),
+        stream: url,
+    };
+    let accounts = MultiAccountClient::new(client)
+        .account(ACCOUNT_ID)
+        .account_with_credentials(OTHER_ACCOUNT_ID, environment, OTHER_TOKEN)
+        .unwrap();
+    (server, accounts)
+}
+
+/// Mounts `GET /accounts/{account}{suffix}` answering `body`, expecting the
+/// account's own token.
+async fn mount_get(
+    server: &MockServer,
+    account: &str,
+    token: &str,
+    suffix: &str,
+    body: serde_json::Value,
+) {
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{account}{suffix}")))
+        .and(header("Authorization", format!("Bearer {token}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+fn open_trade(id: &str) -> serde_json::Value {
+    json!({
+        "id": id,
+        "instrument": "EUR_USD",
+        "price": "1.10423",
+        "openTime": "2024-06-14T12:00:00.000000000Z",
+        "state": "OPEN",
+        "initialUnits": "100",
+        "currentUnits": "100"
+    })
+}
+
+#[tokio::test]
+async fn create_order_prefixes_order_and_trade_tags() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("POST"))
+            .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+            .and(body_json(json!({
+                "order": {
+                    "type": "MARKET",
+                    "instrument": "EUR_USD",
+                    "units": "100",
+                    "clientExtensions": {"id": "entry-1", "tag": "mr/entry"},
+                    "tradeClientExtensions": {"tag": "mr/swing"}
+                }
+            }))),
+    )
+    .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+        "orderCreateTransaction": {
+            "type": "MARKET_ORDER",
+            "id": "6900",
+            "instrument": "EUR_USD",
+            "units": "100"
+        },
+        "relatedTransactionIDs": ["6900"],
+        "lastTransactionID": "6900"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    let account = client.for_account(ACCOUNT_ID).tag_prefix("mr/");
+    assert_eq!(account.account_id().as_str(), ACCOUNT_ID);
+    account
+        .orders()
+        .create(
+            MarketOrderRequest::new("EUR_USD", 100)
+                .client_extensions(ClientExtensions::new().id("entry-1").tag("entry"))
+                .trade_client_extensions(ClientExtensions::new().tag("mr/swing")),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn set_trade_client_extensions_prefixes_tag() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("PUT"))
+            .and(path(format!(
+                "/accounts/{ACCOUNT_ID}/trades/6543/clientExtensions"
+            )))
+            .and(body_json(json!({"clientExtensions": {"tag": "mr/exit"}}))),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "relatedTransactionIDs": ["6902"],
+        "lastTransactionID": "6902"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    client
+        .for_account(ACCOUNT_ID)
+        .tag_prefix("mr/")
+        .trades()
+        .set_client_extensions(TradeId::from("6543"), ClientExtensions::new().tag("exit"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn candles_use_the_default_price_component() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/accounts/{ACCOUNT_ID}/instruments/EUR_USD/candles"
+            )))
+            .and(query_param("price", "BA")),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "instrument": "EUR_USD",
+        "granularity": "H1",
+        "candles": []
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+    standard_headers(
+        Mock::given(method("GET"))
+            .and(path(format!("/accounts/{ACCOUNT_ID}/candles/latest")))
+            .and(query_param(
+                "candleSpecifications",
+                "EUR_USD:H1:BA,EUR_USD:M1:M",
+            )),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({"latestCandles": []})))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    let account = client
+        .for_account(ACCOUNT_ID)
+        .price_component(PricingComponent::BID.with_ask());
+    account
+        .candles()
+        .get("EUR_USD")
+        .granularity(CandlestickGranularity::H1)
+        .send()
+        .await
+        .unwrap();
+    account
+        .candles()
+        .latest([
+            CandleSpecification::new("EUR_USD", CandlestickGranularity::H1),
+            CandleSpecification::new("EUR_USD", CandlestickGranularity::M1)
+                .price(PricingComponent::MID),
+        ])
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn multi_account_routes_each_account_to_its_token() {
+    let (server, accounts) = multi_account_client().await;
+    mount_get(
+        &server,
+        ACCOUNT_ID,
+        TOKEN,
+        "/openTrades",
+        json!({"trades": [open_trade("1"), open_trade("2")], "lastTransactionID": "9"}),
+    )
+    .await;
+    mount_get(
+        &server,
+        OTHER_ACCOUNT_ID,
+        OTHER_TOKEN,
+        "/openTrades",
+        json!({"trades": [open_trade("7")], "lastTransactionID": "9"}),
+    )
+    .await;
+
+    assert_eq!(accounts.accounts().count(), 2);
+    let trades: Vec<_> = accounts
+        .list_all_open_trades()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(account, trade)| (account.as_str().to_owned(), trade.id.unwrap()))
+        .collect();
+    assert_eq!(
+        trades,
+        [
+            (ACCOUNT_ID.to_owned(), TradeId::from("1")),
+            (ACCOUNT_ID.to_owned(), TradeId::from("2")),
+            (OTHER_ACCOUNT_ID.to_owned(), TradeId::from("7")),
+        ]
+    );
+
+    assert!(matches!(
+        accounts.for_account("101-004-0000000-001"),
+        Err(Error::Config(_))
+    ));
+    assert!(
+        accounts
+            .client(&AccountId::from(OTHER_ACCOUNT_ID))
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn total_nav_converts_other_home_currencies() {
+    let (server, accounts) = multi_account_client().await;
+    mount_get(
+        &server,
+        ACCOUNT_ID,
+        TOKEN,
+        "/summary",
+        json!({"account": {"id": ACCOUNT_ID, "currency": "USD", "NAV": "1000.00"}}),
+    )
+    .await;
+    mount_get(
+        &server,
+        OTHER_ACCOUNT_ID,
+        OTHER_TOKEN,
+        "/summary",
+        json!({"account": {"id": OTHER_ACCOUNT_ID, "currency": "EUR", "NAV": "500.00"}}),
+    )
+    .await;
+    mount_get(
+        &server,
+        OTHER_ACCOUNT_ID,
+        OTHER_TOKEN,
+        "/instruments",
+        json!({"instruments": [{"name": "EUR_GBP"}, {"name": "EUR_USD"}]}),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{OTHER_ACCOUNT_ID}/pricing")))
+        .and(query_param("instruments", "EUR_USD"))
+        .and(query_param("includeHomeConversions", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "prices": [],
+            "homeConversions": [
+                {"currency": "EUR", "accountGain": "1", "accountLoss": "1", "positionValue": "1"},
+                {"currency": "USD", "accountGain": "0.8", "accountLoss": "0.8", "positionValue": "0.8"}
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let nav = accounts.total_nav("USD").await.unwrap();
+    assert_eq!(nav.value(), Decimal::new(162500, 2));
+}
@@ -0,0 +1,118 @@
+//! Tests for the Arrow/Parquet export (requires the `arrow` feature).
+#![cfg(feature = "arrow")]
+
+use arrow_array::Array;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Decimal128Type, TimestampNanosecondType};
+use oanda_rs::export::arrow::{
+    RecordBatch, candle_schema, candles_record_batch, tick_schema, ticks_record_batch,
+    write_parquet,
+};
+use oanda_rs::models::{Candlestick, ClientPrice};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde_json::json;
+
+fn candles() -> Vec<Candlestick> {
+    serde_json::from_value(json!([
+        {"time": "2024-06-14T11:00:00.123456789Z", "complete": true, "volume": 10,
+         "bid": {"o": "1.07000", "h": "1.07100", "l": "1.06900", "c": "1.07050"},
+         "ask": {"o": "1.07010", "h": "1.07110", "l": "1.06910", "c": "1.07060"}},
+        {"time": "1718366400.000000000", "complete": false, "volume": 3,
+         "bid": {"o": "157.123", "h": "157.200", "l": "157.001", "c": "157.150"}}
+    ]))
+    .unwrap()
+}
+
+fn read_parquet(bytes: Vec<u8>) -> Vec<RecordBatch> {
+    ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(bytes))
+        .unwrap()
+        .build()
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn candles_keep_exact_decimals_and_nanosecond_times() {
+    let batch = candles_record_batch(&candles()).unwrap();
+    assert_eq!(batch.schema(), candle_schema());
+    assert_eq!(batch.num_rows(), 2);
+
+    let time = batch
+        .column_by_name("time")
+        .unwrap()
+        .as_primitive::<TimestampNanosecondType>();
+    assert_eq!(time.value(0), 1_718_362_800_123_456_789);
+    assert_eq!(time.value(1), 1_718_366_400_000_000_000);
+
+    let bid_o = batch
+        .column_by_name("bid_o")
+        .unwrap()
+        .as_primitive::<Decimal128Type>();
+    assert_eq!(bid_o.value_as_string(0), "1.0700000000");
+    assert_eq!(bid_o.value_as_string(1), "157.1230000000");
+
+    let ask_c = batch
+        .column_by_name("ask_c")
+        .unwrap()
+        .as_primitive::<Decimal128Type>();
+    assert!(ask_c.is_valid(0));
+    assert!(ask_c.is_null(1));
+    assert_eq!(batch.column_by_name("mid_o").unwrap().null_count(), 2);
+}
+
+#[test]
+fn batches_round_trip_through_parquet() {
+    let first = candles_record_batch(&candles()).unwrap();
+    let second = candles_record_batch(&candles()[..1]).unwrap();
+
+    let mut file = Vec::new();
+    write_parquet(&mut file, candle_schema(), [first.clone(), second.clone()]).unwrap();
+
+    let read = read_parquet(file);
+    let rows: usize = read.iter().map(RecordBatch::num_rows).sum();
+    assert_eq!(rows, 3);
+    assert_eq!(read[0].schema(), candle_schema());
+    assert_eq!(read[0].slice(0, 2), first);
+}
+
+#[test]
+fn ticks_keep_the_top_of_book() {
+    let prices: Vec<ClientPrice> = serde_json::from_value(json!([
+        {"type": "PRICE", "instrument": "EUR_USD", "time": "2024-06-14T12:00:00.000000000Z",
+         "tradeable": true,
+         "bids": [{"price": "1.07128", "liquidity": 1000000}, {"price": "1.07127", "liquidity": 5000000}],
+         "asks": [{"price": "1.07149", "liquidity": 1000000}],
+         "closeoutBid": "1.07113", "closeoutAsk": "1.07164"}
+    ]))
+    .unwrap();
+
+    let batch = ticks_record_batch(&prices).unwrap();
+    assert_eq!(batch.schema(), tick_schema());
+    assert_eq!(
+        batch
+            .column_by_name("instrument")
+            .unwrap()
+            .as_string::<i32>()
+            .value(0),
+        "EUR_USD"
+    );
+    let decimal = |name: &str| {
+        batch
+            .column_by_name(name)
+            .unwrap()
+            .as_primitive::<Decimal128Type>()
+            .value_as_string(0)
+    };
+    assert_eq!(decimal("bid"), "1.0712800000");
+    assert_eq!(decimal("ask"), "1.0714900000");
+    assert_eq!(decimal("bid_liquidity"), "1000000.0000000000");
+    assert_eq!(decimal("closeout_ask"), "1.0716400000");
+}
+
+#[test]
+fn unparseable_times_are_errors_not_nulls() {
+    let candles: Vec<Candlestick> =
+        serde_json::from_value(json!([{"time": "yesterday", "volume": 1}])).unwrap();
+    assert!(candles_record_batch(&candles).is_err());
+}
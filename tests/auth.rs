@@ -0,0 +1,96 @@
+//! Tests for token providers and the refresh-and-retry on HTTP 401.
+
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use common::{ACCOUNT_ID, mock_client_with};
+use futures_util::StreamExt;
+use oanda_rs::auth::{CallbackToken, Token};
+use oanda_rs::models::PriceStreamItem;
+use oanda_rs::{Client, Error};
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A client whose token provider answers `token-0`, then `token-1` after
+/// the first refresh, and so on; returns the number of fetches made.
+async fn rotating_client() -> (MockServer, Client, Arc<AtomicU32>) {
+    let fetches = Arc::new(AtomicU32::new(0));
+    let counter = fetches.clone();
+    let (server, client) = mock_client_with(|builder| {
+        builder.token_provider(CallbackToken::new(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(Token::new(format!("token-{n}"))) }
+        }))
+    })
+    .await;
+    (server, client, fetches)
+}
+
+async fn mount_unauthorized(server: &MockServer, route: String, token: &str) {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .and(header("Authorization", format!("Bearer {token}")))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "errorMessage": "Insufficient authorization to perform request."
+        })))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn rest_request_is_retried_once_with_a_refreshed_token() {
+    let (server, client, fetches) = rotating_client().await;
+    mount_unauthorized(&server, "/accounts".into(), "token-0").await;
+    Mock::given(method("GET"))
+        .and(path("/accounts"))
+        .and(header("Authorization", "Bearer token-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"accounts": []})))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    client.list_accounts().await.unwrap();
+    // The refreshed token is cached for later requests.
+    client.list_accounts().await.unwrap();
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn persistent_401_is_returned_after_one_retry() {
+    let (server, client, fetches) = rotating_client().await;
+    mount_unauthorized(&server, "/accounts".into(), "token-0").await;
+    mount_unauthorized(&server, "/accounts".into(), "token-1").await;
+
+    let error = client.list_accounts().await.unwrap_err();
+    assert!(matches!(&error, Error::Api { status, .. } if status.as_u16() == 401));
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn stream_connect_uses_the_refreshed_token() {
+    let (server, client, _) = rotating_client().await;
+    let route = format!("/accounts/{ACCOUNT_ID}/pricing/stream");
+    mount_unauthorized(&server, route.clone(), "token-0").await;
+    Mock::given(method("GET"))
+        .and(path(route))
+        .and(header("Authorization", "Bearer token-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            r#"{"type":"HEARTBEAT","time":"2024-06-14T12:00:00.000000000Z"}"#,
+            "\n"
+        )))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut stream = client
+        .pricing_stream(ACCOUNT_ID, ["EUR_USD"])
+        .send()
+        .await
+        .unwrap();
+    let item = stream.next().await.unwrap().unwrap();
+    assert!(matches!(item, PriceStreamItem::Heartbeat(_)));
+}
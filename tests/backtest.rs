@@ -0,0 +1,211 @@
+//! Tests for the candle and tick backtest runner.
+
+use oanda_rs::Error;
+use oanda_rs::broker::Broker;
+use oanda_rs::models::transaction::{OrderFillReason, Transaction};
+use oanda_rs::models::{
+    AccountId, ClientPrice, Instrument, InstrumentCandles, MarketOrderRequest, TakeProfitDetails,
+};
+use oanda_rs::trading::{Backtest, PaperAccount};
+use serde_json::{Value, json};
+
+fn candle(time: &str, o: &str, h: &str, l: &str, c: &str) -> Value {
+    json!({"time": time, "complete": true, "volume": 10, "mid": {"o": o, "h": h, "l": l, "c": c}})
+}
+
+fn candles(candles: Vec<Value>) -> InstrumentCandles {
+    serde_json::from_value(json!({
+        "instrument": "EUR_USD",
+        "granularity": "H4",
+        "candles": candles
+    }))
+    .unwrap()
+}
+
+/// Buys 1,000 units with a take-profit whenever the account is flat.
+async fn buy_when_flat(
+    broker: &impl Broker,
+    account: &AccountId,
+    price: &ClientPrice,
+) -> Result<(), Error> {
+    if broker.list_open_trades(account).await?.trades.is_empty() {
+        let close = price.closeout_ask.unwrap().value();
+        let take_profit = close + rust_decimal::Decimal::new(20, 4);
+        broker
+            .create_order(
+                account,
+                MarketOrderRequest::new("EUR_USD", 1000)
+                    .take_profit_on_fill(TakeProfitDetails::at_price(take_profit.into()))
+                    .into(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn replays_candles_through_the_strategy() {
+    let history = candles(vec![
+        candle(
+            "2024-06-10T12:00:00Z",
+            "1.07000",
+            "1.07100",
+            "1.06900",
+            "1.07050",
+        ),
+        candle(
+            "2024-06-10T16:00:00Z",
+            "1.07050",
+            "1.07400",
+            "1.07000",
+            "1.07300",
+        ),
+        candle(
+            "2024-06-10T20:00:00Z",
+            "1.07300",
+            "1.07350",
+            "1.06800",
+            "1.06900",
+        ),
+        candle(
+            "2024-06-11T00:00:00Z",
+            "1.06900",
+            "1.07000",
+            "1.06850",
+            "1.06950",
+        ),
+        // incomplete candles are skipped
+        json!({"time": "2024-06-11T04:00:00Z", "complete": false,
+               "mid": {"o": "1.06950", "h": "1.06950", "l": "1.06950", "c": "1.06950"}}),
+    ]);
+    let account = AccountId::from("backtest");
+    let instrument: Instrument = serde_json::from_value(json!({
+        "name": "EUR_USD",
+        "marginRate": "0.02",
+        "financing": {"longRate": "-0.0365", "shortRate": "0.01"}
+    }))
+    .unwrap();
+
+    let report = Backtest::new(account.clone(), 10_000)
+        .spread(rust_decimal::Decimal::new(2, 4))
+        .add_instrument(&instrument)
+        .run_candles(
+            [&history],
+            async |broker: &PaperAccount, price: &ClientPrice| {
+                buy_when_flat(broker, &account, price).await
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(report.equity_curve.len(), 16);
+    assert_eq!(
+        report.equity_curve[1].time.as_ref().unwrap().to_utc(),
+        "2024-06-10T13:00:00Z".parse().ok()
+    );
+    // entry at the first open's ask (1.07010); the second candle gaps
+    // through the take-profit to its high (bid 1.07390), where the
+    // strategy re-enters and stays open to the end
+    assert_eq!(report.trades.len(), 2);
+    let reasons: Vec<_> = report
+        .fills
+        .iter()
+        .map(|fill| fill.reason.clone())
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![
+            Some(OrderFillReason::MarketOrder),
+            Some(OrderFillReason::TakeProfitOrder),
+            Some(OrderFillReason::MarketOrder),
+        ]
+    );
+    assert_eq!(report.fills[1].pl.unwrap().to_string(), "3.8");
+    // one rollover (21:00 UTC on the 10th) with the second trade open
+    assert_eq!(
+        report
+            .transactions
+            .iter()
+            .filter(|tx| matches!(tx, Transaction::DailyFinancing(_)))
+            .count(),
+        1
+    );
+    assert!(report.financing.value() < rust_decimal::Decimal::ZERO);
+    assert!(report.max_drawdown.value() > rust_decimal::Decimal::ZERO);
+    assert!(report.max_drawdown_percent < rust_decimal::Decimal::ONE);
+    assert_eq!(
+        report.final_nav,
+        report.equity_curve.last().unwrap().nav,
+        "the report ends where the curve does"
+    );
+
+    let performance = report.performance();
+    assert_eq!(performance.trades.len(), 2);
+    assert_eq!(performance.overall.trades, 1);
+    assert_eq!(performance.overall.wins, 1);
+    assert_eq!(performance.overall.net_pl.to_string(), "3.8");
+    assert!(performance.trades[1].financing.value() < rust_decimal::Decimal::ZERO);
+}
+
+#[tokio::test]
+async fn strategy_errors_stop_the_run() {
+    let history = candles(vec![candle(
+        "2024-06-10T12:00:00Z",
+        "1.07000",
+        "1.07100",
+        "1.06900",
+        "1.07050",
+    )]);
+    let mut calls = 0;
+    let err = Backtest::new("backtest", 10_000)
+        .run_candles([&history], async |_: &PaperAccount, _: &ClientPrice| {
+            calls += 1;
+            Err(Error::Config("stop".into()))
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Config(_)));
+    assert_eq!(calls, 1);
+}
+
+#[tokio::test]
+async fn candle_ticks_span_the_granularity_across_the_weekend() {
+    let mut history = candles(vec![
+        candle(
+            "2024-06-14T20:00:00Z",
+            "1.07000",
+            "1.07100",
+            "1.06900",
+            "1.07050",
+        ),
+        candle(
+            "2024-06-16T21:00:00Z",
+            "1.07050",
+            "1.07100",
+            "1.07000",
+            "1.07080",
+        ),
+    ]);
+    history.granularity = Some("H1".into());
+    let report = Backtest::new("backtest", 10_000)
+        .run_candles([&history], async |_: &PaperAccount, _: &ClientPrice| Ok(()))
+        .await
+        .unwrap();
+
+    let times: Vec<_> = report
+        .equity_curve
+        .iter()
+        .map(|point| point.time.as_ref().unwrap().to_utc().unwrap().to_rfc3339())
+        .collect();
+    // Friday's last hour is replayed within that hour, not over the weekend
+    assert_eq!(
+        times[..4],
+        [
+            "2024-06-14T20:00:00+00:00",
+            "2024-06-14T20:15:00+00:00",
+            "2024-06-14T20:30:00+00:00",
+            "2024-06-14T20:45:00+00:00",
+        ]
+    );
+    assert_eq!(times[7], "2024-06-16T21:45:00+00:00");
+}
@@ -0,0 +1,104 @@
+//! Tests for the blocking client (requires the `blocking` feature).
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use common::{ACCOUNT_ID, mock_client, standard_headers};
+use oanda_rs::models::{CandlestickGranularity, PriceStreamItem};
+use oanda_rs::{Error, blocking};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Starts a wiremock server from a separate runtime (the blocking client
+/// must not run inside one) and a blocking client pointed at it.
+fn mock_blocking_client(runtime: &tokio::runtime::Runtime) -> (MockServer, blocking::Client) {
+    let (server, client) = runtime.block_on(mock_client());
+    (server, blocking::Client::from_client(client).unwrap())
+}
+
+#[test]
+fn plain_and_builder_requests_block_until_the_response() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (server, client) = mock_blocking_client(&runtime);
+    runtime.block_on(async {
+        standard_headers(
+            Mock::given(method("GET")).and(path(format!("/accounts/{ACCOUNT_ID}/summary"))),
+        )
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "account": {"id": ACCOUNT_ID, "balance": "10000.0000", "currency": "USD"},
+            "lastTransactionID": "6789"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+        standard_headers(
+            Mock::given(method("GET"))
+                .and(path("/instruments/EUR_USD/candles"))
+                .and(query_param("granularity", "H1"))
+                .and(query_param("count", "2")),
+        )
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "instrument": "EUR_USD",
+            "granularity": "H1",
+            "candles": [
+                {"time": "2024-06-14T11:00:00Z", "complete": true, "volume": 10,
+                 "mid": {"o": "1.07000", "h": "1.07100", "l": "1.06900", "c": "1.07050"}},
+                {"time": "2024-06-14T12:00:00Z", "complete": false, "volume": 3,
+                 "mid": {"o": "1.07050", "h": "1.07060", "l": "1.07040", "c": "1.07055"}}
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    });
+
+    let summary = client.account_summary(ACCOUNT_ID).unwrap();
+    assert_eq!(summary.account.balance.unwrap().to_string(), "10000.0000");
+
+    let candles = client
+        .candles("EUR_USD")
+        .granularity(CandlestickGranularity::H1)
+        .count(2)
+        .send()
+        .unwrap();
+    assert_eq!(candles.candles.len(), 2);
+
+    // clones share the runtime and keep working across threads
+    let clone = client.clone();
+    let err = std::thread::spawn(move || clone.account_summary("unknown").unwrap_err())
+        .join()
+        .unwrap();
+    assert!(matches!(err, Error::Api { status, .. } if status.as_u16() == 404));
+}
+
+#[test]
+fn pricing_stream_is_an_iterator() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (server, client) = mock_blocking_client(&runtime);
+    let body = concat!(
+        r#"{"type":"PRICE","instrument":"EUR_USD","closeoutBid":"1.07128","closeoutAsk":"1.07149","tradeable":true}"#,
+        "\n",
+        r#"{"type":"HEARTBEAT","time":"2024-06-14T12:00:05.000000000Z"}"#,
+        "\n",
+    );
+    runtime.block_on(
+        Mock::given(method("GET"))
+            .and(path(format!("/accounts/{ACCOUNT_ID}/pricing/stream")))
+            .and(query_param("snapshot", "false"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/octet-stream"))
+            .mount(&server),
+    );
+
+    let stream = client
+        .pricing_stream(ACCOUNT_ID, ["EUR_USD"])
+        .snapshot(false)
+        .auto_reconnect(false)
+        .send()
+        .unwrap();
+    assert_eq!(stream.stats().reconnects, 0);
+    let items: Vec<_> = stream.map(Result::unwrap).collect();
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[0], PriceStreamItem::Price(_)));
+    assert!(matches!(items[1], PriceStreamItem::Heartbeat(_)));
+}
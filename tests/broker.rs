@@ -0,0 +1,97 @@
+//! Tests for the `Broker` implementation of `Client`, driven through
+//! generic strategy code.
+
+mod common;
+
+use common::{ACCOUNT_ID, mock_client, standard_headers};
+use oanda_rs::Error;
+use oanda_rs::broker::Broker;
+use oanda_rs::models::AccountId;
+use serde_json::json;
+use wiremock::matchers::{body_json, method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Closes every open trade, the way strategy code would against any
+/// backend.
+async fn flatten(broker: &impl Broker, account: &AccountId) -> Result<usize, Error> {
+    let mut closed = 0;
+    for trade in broker.list_open_trades(account).await?.trades {
+        if let Some(id) = trade.id {
+            broker.close_trade(account, id.into(), None).await?;
+            closed += 1;
+        }
+    }
+    Ok(closed)
+}
+
+#[tokio::test]
+async fn generic_code_runs_against_client() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("GET")).and(path(format!("/accounts/{ACCOUNT_ID}/openTrades"))),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "trades": [
+            {"id": "6543", "instrument": "EUR_USD", "currentUnits": "100"},
+            {"id": "6544", "instrument": "USD_JPY", "currentUnits": "-100"}
+        ],
+        "lastTransactionID": "6900"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+    for id in ["6543", "6544"] {
+        standard_headers(
+            Mock::given(method("PUT"))
+                .and(path(format!("/accounts/{ACCOUNT_ID}/trades/{id}/close")))
+                .and(body_json(json!({}))),
+        )
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "lastTransactionID": "6901"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    }
+
+    let closed = flatten(&client, &AccountId::from(ACCOUNT_ID))
+        .await
+        .unwrap();
+    assert_eq!(closed, 2);
+}
+
+#[tokio::test]
+async fn optional_arguments_map_onto_builders() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("PUT"))
+            .and(path(format!("/accounts/{ACCOUNT_ID}/trades/6543/close")))
+            .and(body_json(json!({"units": "50"}))),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({"lastTransactionID": "6901"})))
+    .expect(1)
+    .mount(&server)
+    .await;
+    standard_headers(
+        Mock::given(method("GET"))
+            .and(path(format!("/accounts/{ACCOUNT_ID}/instruments")))
+            .and(query_param("instruments", "EUR_USD,USD_JPY")),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "instruments": [{"name": "EUR_USD"}, {"name": "USD_JPY"}],
+        "lastTransactionID": "6901"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    let account = AccountId::from(ACCOUNT_ID);
+    Broker::close_trade(&client, &account, "6543".into(), Some(50.into()))
+        .await
+        .unwrap();
+    let instruments =
+        Broker::account_instruments(&client, &account, vec!["EUR_USD".into(), "USD_JPY".into()])
+            .await
+            .unwrap();
+    assert_eq!(instruments.instruments.len(), 2);
+}
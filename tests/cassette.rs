@@ -0,0 +1,172 @@
+//! Tests for recording REST exchanges to a cassette and replaying them
+//! without a server.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{ACCOUNT_ID, TOKEN, mock_client, standard_headers};
+use oanda_rs::models::CandlestickGranularity;
+use oanda_rs::{Client, Environment, Error};
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("oanda-rs-{name}-{}.ndjson", std::process::id()))
+}
+
+fn client_for(server: &MockServer) -> oanda_rs::ClientBuilder {
+    let url: reqwest::Url = server.uri().parse().unwrap();
+    Client::builder()
+        .environment(Environment::Custom {
+            rest: url.clone(),
+            stream: url,
+        })
+        .token(TOKEN)
+}
+
+#[tokio::test]
+async fn records_then_replays_without_the_server() {
+    let cassette = cassette_path("replay");
+    let (server, _) = mock_client().await;
+    standard_headers(
+        Mock::given(method("GET")).and(path(format!("/accounts/{ACCOUNT_ID}/summary"))),
+    )
+    .respond_with(
+        ResponseTemplate::new(200)
+            .insert_header("RequestID", "24644537")
+            .set_body_json(json!({
+                "account": {"id": ACCOUNT_ID, "balance": "10000.0000"},
+                "lastTransactionID": "6900"
+            })),
+    )
+    .expect(1)
+    .mount(&server)
+    .await;
+    standard_headers(
+        Mock::given(method("GET"))
+            .and(path("/instruments/EUR_USD/candles"))
+            .and(query_param("granularity", "H1"))
+            .and(query_param("count", "2")),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "instrument": "EUR_USD", "granularity": "H1", "candles": []
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+    standard_headers(
+        Mock::given(method("GET")).and(path(format!("/accounts/{ACCOUNT_ID}/trades/9"))),
+    )
+    .respond_with(
+        ResponseTemplate::new(404)
+            .insert_header("RequestID", "24644538")
+            .set_body_json(json!({"errorMessage": "The trade ID specified does not exist"})),
+    )
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    let recorder = client_for(&server).record_to(&cassette).build().unwrap();
+    recorder.account_summary(ACCOUNT_ID).await.unwrap();
+    recorder
+        .candles("EUR_USD")
+        .granularity(CandlestickGranularity::H1)
+        .count(2)
+        .send()
+        .await
+        .unwrap();
+    recorder.trade(ACCOUNT_ID, "9").await.unwrap_err();
+
+    let recorded = std::fs::read_to_string(&cassette).unwrap();
+    assert!(!recorded.contains(TOKEN), "the token must be redacted");
+    let lines: Vec<Value> = recorded
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["method"], "GET");
+    assert_eq!(lines[0]["response"]["headers"]["requestid"], "24644537");
+    assert_eq!(
+        lines[0]["request"]["headers"]["authorization"],
+        "Bearer <redacted>"
+    );
+    assert_eq!(lines[0]["response"]["body"]["lastTransactionID"], "6900");
+
+    // the server is gone; every answer comes from the cassette
+    let url = server.uri();
+    drop(server);
+    let url: reqwest::Url = url.parse().unwrap();
+    let replayer = Client::builder()
+        .environment(Environment::Custom {
+            rest: url.clone(),
+            stream: url,
+        })
+        .token("another-token")
+        .replay_from(&cassette)
+        .build()
+        .unwrap();
+    let summary = replayer.account_summary(ACCOUNT_ID).await.unwrap();
+    assert_eq!(summary.account.balance.unwrap().to_string(), "10000.0000");
+    // query parameters match in any order
+    let candles = replayer
+        .candles("EUR_USD")
+        .count(2)
+        .granularity(CandlestickGranularity::H1)
+        .send()
+        .await
+        .unwrap();
+    assert!(candles.candles.is_empty());
+    match replayer.trade(ACCOUNT_ID, "9").await.unwrap_err() {
+        Error::Api {
+            status, request_id, ..
+        } => {
+            assert_eq!(status, 404);
+            assert_eq!(request_id.as_deref(), Some("24644538"));
+        }
+        other => panic!("expected an API error, got {other:?}"),
+    }
+    // each recording answers once
+    assert!(matches!(
+        replayer.account_summary(ACCOUNT_ID).await,
+        Err(Error::Config(_))
+    ));
+    std::fs::remove_file(&cassette).unwrap();
+}
+
+#[tokio::test]
+async fn missing_cassette_fails_to_build() {
+    let err = Client::builder()
+        .token(TOKEN)
+        .replay_from(cassette_path("missing"))
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::Config(_)));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn failing_to_record_does_not_fail_the_request() {
+    let (server, _) = mock_client().await;
+    standard_headers(
+        Mock::given(method("POST")).and(path(format!("/accounts/{ACCOUNT_ID}/orders"))),
+    )
+    .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+        "orderCreateTransaction": {"type": "MARKET_ORDER", "id": "6789"},
+        "lastTransactionID": "6789"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+    // Every write to /dev/full fails with "no space left on device".
+    let client = client_for(&server).record_to("/dev/full").build().unwrap();
+    let response = client
+        .create_order(
+            ACCOUNT_ID,
+            oanda_rs::models::MarketOrderRequest::new("EUR_USD", 100),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.last_transaction_id.unwrap().as_str(), "6789");
+}
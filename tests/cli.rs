@@ -0,0 +1,272 @@
+//! Tests for the `oanda` binary (requires the `cli` feature).
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::{ACCOUNT_ID, TOKEN, mock_client, standard_headers};
+use serde_json::{Value, json};
+use wiremock::matchers::{body_json, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Runs the binary against the mock server with a clean environment.
+fn oanda(server: &MockServer, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_oanda"))
+        .args(args)
+        .env_clear()
+        .env("OANDA_TOKEN", TOKEN)
+        .env("OANDA_REST_URL", server.uri())
+        .env("OANDA_STREAM_URL", server.uri())
+        .output()
+        .expect("the binary runs")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[tokio::test]
+async fn summary_uses_the_only_account() {
+    let (server, _) = mock_client().await;
+    standard_headers(Mock::given(method("GET")).and(path("/accounts")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "accounts": [{"id": ACCOUNT_ID, "tags": []}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    standard_headers(
+        Mock::given(method("GET")).and(path(format!("/accounts/{ACCOUNT_ID}/summary"))),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "account": {"id": ACCOUNT_ID, "balance": "10000.0000"},
+        "lastTransactionID": "6789"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    let output = oanda(&server, &["summary"]);
+    let summary: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(summary["account"]["balance"], "10000.0000");
+}
+
+#[tokio::test]
+async fn candles_as_csv() {
+    let (server, _) = mock_client().await;
+    Mock::given(method("GET"))
+        .and(path("/instruments/EUR_USD/candles"))
+        .and(query_param("granularity", "M5"))
+        .and(query_param("price", "BA"))
+        .and(query_param("count", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "instrument": "EUR_USD",
+            "granularity": "M5",
+            "candles": [{
+                "time": "2024-06-14T12:00:00.000000000Z", "complete": true, "volume": 12,
+                "bid": {"o": "1.07000", "h": "1.07100", "l": "1.06900", "c": "1.07050"},
+                "ask": {"o": "1.07010", "h": "1.07110", "l": "1.06910", "c": "1.07060"}
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = oanda(
+        &server,
+        &[
+            "candles",
+            "EUR_USD",
+            "--granularity",
+            "M5",
+            "--price",
+            "BA",
+            "--count",
+            "1",
+            "--format",
+            "csv",
+        ],
+    );
+    let csv = stdout(&output);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "time,complete,volume,bid_o,bid_h,bid_l,bid_c,mid_o,mid_h,mid_l,mid_c,ask_o,ask_h,ask_l,ask_c",
+            "2024-06-14T12:00:00.000000000Z,true,12,1.07000,1.07100,1.06900,1.07050,,,,,1.07010,1.07110,1.06910,1.07060",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn close_position_all_closes_only_open_sides() {
+    let (server, _) = mock_client().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/openPositions")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "positions": [
+                {"instrument": "EUR_USD", "long": {"units": "100"}, "short": {"units": "0"}},
+                {"instrument": "USD_JPY", "long": {"units": "0"}, "short": {"units": "-50"}}
+            ],
+            "lastTransactionID": "6789"
+        })))
+        .mount(&server)
+        .await;
+    for (instrument, long, short) in [("EUR_USD", "ALL", "NONE"), ("USD_JPY", "NONE", "ALL")] {
+        Mock::given(method("PUT"))
+            .and(path(format!(
+                "/accounts/{ACCOUNT_ID}/positions/{instrument}/close"
+            )))
+            .and(body_json(json!({"longUnits": long, "shortUnits": short})))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"lastTransactionID": "6790"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let output = oanda(
+        &server,
+        &["--account", ACCOUNT_ID, "close-position", "--all"],
+    );
+    stdout(&output);
+}
+
+#[tokio::test]
+async fn close_position_all_attempts_every_position_before_failing() {
+    let (server, _) = mock_client().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/openPositions")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "positions": [
+                {"instrument": "EUR_USD", "long": {"units": "100"}, "short": {"units": "0"}},
+                {"instrument": "USD_JPY", "long": {"units": "0"}, "short": {"units": "-50"}}
+            ],
+            "lastTransactionID": "6789"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path(format!(
+            "/accounts/{ACCOUNT_ID}/positions/EUR_USD/close"
+        )))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "errorMessage": "Market halted",
+            "errorCode": "MARKET_HALTED"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path(format!(
+            "/accounts/{ACCOUNT_ID}/positions/USD_JPY/close"
+        )))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({"lastTransactionID": "6790"})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = oanda(
+        &server,
+        &["--account", ACCOUNT_ID, "close-position", "--all"],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot close EUR_USD"), "{stderr}");
+    assert!(stderr.contains("MARKET_HALTED"), "{stderr}");
+    let closed: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(closed["lastTransactionID"], "6790");
+}
+
+#[tokio::test]
+async fn api_errors_fail_with_the_reject_body() {
+    let (server, _) = mock_client().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+        .and(body_json(json!({"order": {
+            "type": "MARKET", "instrument": "EUR_USD", "units": "-100",
+            "stopLossOnFill": {"price": "1.08000"}
+        }})))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "errorMessage": "Insufficient margin",
+            "errorCode": "INSUFFICIENT_MARGIN"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = oanda(
+        &server,
+        &[
+            "order",
+            "market",
+            "EUR_USD",
+            "-100",
+            "--stop-loss",
+            "1.08000",
+            "--account",
+            ACCOUNT_ID,
+        ],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("INSUFFICIENT_MARGIN"), "{stderr}");
+}
+
+#[tokio::test]
+async fn config_file_uses_the_library_schema_and_flags_override_it() {
+    let (server, _) = mock_client().await;
+    standard_headers(
+        Mock::given(method("GET")).and(path(format!("/accounts/{ACCOUNT_ID}/summary"))),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "account": {"id": ACCOUNT_ID, "balance": "10000.0000"},
+        "lastTransactionID": "6789"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+    let config = std::env::temp_dir().join(format!("oanda-rs-cli-{}.json", std::process::id()));
+    std::fs::write(
+        &config,
+        json!({
+            "token": TOKEN,
+            "environment": "live",
+            "account_id": "001-001-0000000-001"
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_oanda"))
+        .args(["summary", "--config"])
+        .arg(&config)
+        .args(["--rest-url", &server.uri(), "--stream-url", &server.uri()])
+        .args(["--account", ACCOUNT_ID])
+        .env_clear()
+        .output()
+        .unwrap();
+    std::fs::remove_file(&config).unwrap();
+    let summary: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(summary["account"]["id"], ACCOUNT_ID);
+}
+
+#[test]
+fn missing_token_is_a_configuration_error() {
+    let output = Command::new(env!("CARGO_BIN_EXE_oanda"))
+        .args(["accounts"])
+        .env_clear()
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("OANDA_TOKEN"));
+}
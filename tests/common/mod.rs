@@ -1,6 +1,10 @@
 //! Shared helpers for endpoint integration tests.
 
-use oanda_rs::{Client, Environment};
+// Each test crate uses only some of the helpers.
+#![allow(dead_code)]
+
+use oanda_rs::{Client, ClientBuilder, Environment};
+use rust_decimal::Decimal;
 use wiremock::MockServer;
 
 pub const TOKEN: &str = "test-token";
@@ -9,16 +13,23 @@ pub const ACCOUNT_ID: &str = "101-004-1234567-001";
 /// Starts a wiremock server and a client pointed at it (both REST and
 /// stream hosts).
 pub async fn mock_client() -> (MockServer, Client) {
+    mock_client_with(|builder| builder).await
+}
+
+/// Like [`mock_client`], with the builder (already holding [`TOKEN`])
+/// adjusted by `configure` before the client is built.
+pub async fn mock_client_with(
+    configure: impl FnOnce(ClientBuilder) -> ClientBuilder,
+) -> (MockServer, Client) {
     let server = MockServer::start().await;
     let url: reqwest::Url = server.uri().parse().unwrap();
-    let client = Client::builder()
+    let builder = Client::builder()
         .environment(Environment::Custom {
             rest: url.clone(),
             stream: url,
         })
-        .token(TOKEN)
-        .build()
-        .unwrap();
+        .token(TOKEN);
+    let client = configure(builder).build().unwrap();
     (server, client)
 }
 
@@ -28,3 +39,8 @@ pub fn standard_headers(mock: wiremock::MockBuilder) -> wiremock::MockBuilder {
     mock.and(header("Authorization", format!("Bearer {TOKEN}")))
         .and(header("Accept-Datetime-Format", "RFC3339"))
 }
+
+/// Parses a decimal literal.
+pub fn dec(s: &str) -> Decimal {
+    s.parse().unwrap()
+}
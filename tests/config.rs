@@ -0,0 +1,145 @@
+//! Tests for client configuration files and the retry and timeout
+//! settings they cover.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use oanda_rs::{ClientBuilder, Error};
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Writes `contents` to a uniquely named temporary file with `extension`.
+fn config_file(name: &str, extension: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "oanda-config-{}-{name}.{extension}",
+        std::process::id()
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn json_config_builds_a_working_client() {
+    let server = MockServer::start().await;
+    let file = config_file(
+        "json",
+        "json",
+        &json!({
+            "rest_url": server.uri(),
+            "stream_url": server.uri(),
+            "token": "file-token",
+            "account_id": "101-004-1234567-001",
+            "datetime_format": "UNIX",
+            "max_retries": 2,
+            "retry_backoff": 0.01
+        })
+        .to_string(),
+    );
+    Mock::given(method("GET"))
+        .and(path("/accounts"))
+        .and(header("Authorization", "Bearer file-token"))
+        .and(header("Accept-Datetime-Format", "UNIX"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/accounts"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"accounts": []})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = ClientBuilder::from_config(&file).unwrap().build().unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(
+        client.default_account().map(|id| id.as_str()),
+        Some("101-004-1234567-001")
+    );
+    // Two 503s are retried; the third attempt succeeds.
+    client.list_accounts().await.unwrap();
+}
+
+#[tokio::test]
+async fn timeout_fails_slow_requests_and_retries_stop_at_the_limit() {
+    let server = MockServer::start().await;
+    let url: reqwest::Url = server.uri().parse().unwrap();
+    Mock::given(method("GET"))
+        .and(path("/accounts"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"accounts": []}))
+                .set_delay(Duration::from_secs(5)),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = oanda_rs::Client::builder()
+        .environment(oanda_rs::Environment::Custom {
+            rest: url.clone(),
+            stream: url,
+        })
+        .token("t")
+        .timeout(Duration::from_millis(50))
+        .max_retries(1)
+        .retry_backoff(Duration::from_millis(1))
+        .build()
+        .unwrap();
+    let error = client.list_accounts().await.unwrap_err();
+    assert!(matches!(&error, Error::Transport(e) if e.is_timeout()));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn toml_config_is_parsed() {
+    let file = config_file(
+        "toml",
+        "toml",
+        r#"
+environment = "live"
+token = "t"
+account_id = "001-001-1234567-001"
+rest_rate_limit = 50
+stream_heartbeat_timeout = 20
+"#,
+    );
+    let client = ClientBuilder::from_config(&file).unwrap().build().unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(
+        client.default_account().map(|id| id.as_str()),
+        Some("001-001-1234567-001")
+    );
+}
+
+#[test]
+fn config_errors_name_every_bad_key() {
+    let file = config_file(
+        "invalid",
+        "json",
+        r#"{"environment": "demo", "max_retries": -1, "proxy": "x", "colour": "blue"}"#,
+    );
+    let error = ClientBuilder::from_config(&file).unwrap_err();
+    std::fs::remove_file(&file).unwrap();
+    let Error::Config(message) = error else {
+        panic!("expected Error::Config, got {error:?}");
+    };
+    for key in [
+        "`environment`",
+        "`max_retries`",
+        "unknown key `proxy`",
+        "unknown key `colour`",
+        "`token`",
+    ] {
+        assert!(message.contains(key), "{key} not in {message:?}");
+    }
+
+    let file = config_file("extension", "yaml", "token: t");
+    assert!(matches!(
+        ClientBuilder::from_config(&file),
+        Err(Error::Config(_))
+    ));
+    std::fs::remove_file(&file).unwrap();
+}
@@ -0,0 +1,170 @@
+//! Tests for `EquityCurve`.
+
+mod common;
+
+use common::dec;
+use oanda_rs::models::InstrumentCandles;
+use oanda_rs::models::transaction::Transaction;
+use oanda_rs::trading::EquityCurve;
+use rust_decimal::Decimal;
+use serde_json::json;
+
+fn history() -> Vec<Transaction> {
+    serde_json::from_value(json!([
+        {"id": "1", "time": "2024-06-03T00:00:00Z", "type": "TRANSFER_FUNDS",
+         "amount": "10000.0000", "accountBalance": "10000.0000", "fundingReason": "CLIENT_FUNDING"},
+        {"id": "2", "time": "2024-06-03T12:00:00Z", "type": "MARKET_ORDER",
+         "instrument": "EUR_USD", "units": "10000"},
+        {"id": "3", "time": "2024-06-03T12:00:00Z", "type": "ORDER_FILL", "orderID": "2",
+         "instrument": "EUR_USD", "units": "10000", "price": "1.07000",
+         "gainQuoteHomeConversionFactor": "1", "lossQuoteHomeConversionFactor": "1",
+         "tradeOpened": {"tradeID": "3", "units": "10000", "price": "1.07000"},
+         "accountBalance": "10000.0000"},
+        {"id": "4", "time": "2024-06-04T12:00:00Z", "type": "ORDER_FILL",
+         "instrument": "EUR_USD", "units": "-5000", "price": "1.07100",
+         "tradeReduced": {"tradeID": "3", "units": "-5000", "realizedPL": "0.0000"},
+         "accountBalance": "10000.0000"},
+        {"id": "5", "time": "2024-06-05T12:00:00Z", "type": "TRANSFER_FUNDS",
+         "amount": "1000.0000", "accountBalance": "11000.0000", "fundingReason": "CLIENT_FUNDING"},
+        {"id": "6", "time": "2024-06-05T18:00:00Z", "type": "ORDER_FILL",
+         "instrument": "EUR_USD", "units": "-5000", "price": "1.07400",
+         "tradesClosed": [{"tradeID": "3", "units": "-5000", "realizedPL": "20.0000"}],
+         "pl": "20.0000", "accountBalance": "11020.0000"}
+    ]))
+    .unwrap()
+}
+
+fn candles() -> InstrumentCandles {
+    serde_json::from_value(json!({
+        "instrument": "EUR_USD",
+        "granularity": "D",
+        "candles": [
+            {"time": "2024-06-03T00:00:00Z", "complete": true, "volume": 1,
+             "bid": {"o": "1.07000", "h": "1.07200", "l": "1.06900", "c": "1.07100"},
+             "ask": {"o": "1.07010", "h": "1.07210", "l": "1.06910", "c": "1.07110"}},
+            {"time": "2024-06-04T00:00:00Z", "complete": true, "volume": 1,
+             "bid": {"o": "1.07100", "h": "1.07200", "l": "1.06800", "c": "1.06900"},
+             "ask": {"o": "1.07110", "h": "1.07210", "l": "1.06810", "c": "1.06910"}},
+            {"time": "2024-06-05T00:00:00Z", "complete": true, "volume": 1,
+             "bid": {"o": "1.06900", "h": "1.07500", "l": "1.06800", "c": "1.07300"},
+             "ask": {"o": "1.06910", "h": "1.07510", "l": "1.06810", "c": "1.07310"}},
+            {"time": "2024-06-06T00:00:00Z", "complete": false, "volume": 1,
+             "bid": {"o": "1.07300", "h": "1.07500", "l": "1.06800", "c": "1.09000"}}
+        ]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn balance_points_flag_transfers() {
+    let curve = EquityCurve::from_transactions(&history());
+    let points: Vec<_> = curve
+        .points()
+        .iter()
+        .map(|p| (p.balance.value(), p.nav.value(), p.cash_flow.value()))
+        .collect();
+    assert_eq!(
+        points,
+        [
+            (dec("10000"), dec("10000"), dec("10000")),
+            (dec("10000"), dec("10000"), Decimal::ZERO),
+            (dec("10000"), dec("10000"), Decimal::ZERO),
+            (dec("11000"), dec("11000"), dec("1000")),
+            (dec("11020"), dec("11020"), Decimal::ZERO),
+        ]
+    );
+    assert_eq!(curve.cash_flows().value(), dec("1000"));
+    assert_eq!(curve.trading_pl().value(), dec("20"));
+}
+
+#[test]
+fn marking_to_market_values_open_units_at_candle_closes() {
+    let curve = EquityCurve::from_transactions(&history()).mark_to_market([&candles()]);
+    let points: Vec<_> = curve
+        .points()
+        .iter()
+        .map(|p| {
+            (
+                p.time.as_ref().unwrap().to_utc().unwrap().to_rfc3339(),
+                p.nav.value(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        points,
+        [
+            ("2024-06-03T00:00:00+00:00".to_owned(), dec("10000")),
+            // opened before any candle has closed
+            ("2024-06-03T12:00:00+00:00".to_owned(), dec("10000")),
+            // 10000 long units at bid 1.07100
+            ("2024-06-04T00:00:00+00:00".to_owned(), dec("10010")),
+            // reduced to 5000 units, still at the 1.07100 close
+            ("2024-06-04T12:00:00+00:00".to_owned(), dec("10005")),
+            // 5000 units at bid 1.06900
+            ("2024-06-05T00:00:00+00:00".to_owned(), dec("9995")),
+            ("2024-06-05T12:00:00+00:00".to_owned(), dec("10995")),
+            ("2024-06-05T18:00:00+00:00".to_owned(), dec("11020")),
+            // the incomplete candle is ignored
+            ("2024-06-06T00:00:00+00:00".to_owned(), dec("11020")),
+        ]
+    );
+    assert_eq!(curve.trading_pl().value(), dec("20"));
+}
+
+#[test]
+fn time_weighted_return_excludes_cash_flows() {
+    let curve = EquityCurve::from_transactions(&history()).mark_to_market([&candles()]);
+    // the sub-period returns telescope to the two stretches between flows
+    let expected = dec("9995") / dec("10000") * (dec("11020") / dec("10995")) - Decimal::ONE;
+    let twr = curve.time_weighted_return().unwrap();
+    assert!((twr - expected).abs() < dec("0.000000000001"), "{twr}");
+
+    let june_5 = curve.between("2024-06-05T00:00:00Z", "2024-06-06T00:00:00Z");
+    assert_eq!(june_5.points().len(), 3);
+    assert_eq!(june_5.cash_flows().value(), dec("1000"));
+    assert_eq!(june_5.trading_pl().value(), dec("25"));
+
+    assert_eq!(EquityCurve::default().time_weighted_return(), None);
+}
+
+#[test]
+fn candle_closes_follow_the_granularity_across_the_weekend() {
+    let history: Vec<Transaction> = serde_json::from_value(json!([
+        {"id": "1", "time": "2024-06-07T19:00:00Z", "type": "TRANSFER_FUNDS",
+         "amount": "10000.0000", "accountBalance": "10000.0000", "fundingReason": "CLIENT_FUNDING"},
+        {"id": "2", "time": "2024-06-07T20:30:00Z", "type": "ORDER_FILL",
+         "instrument": "EUR_USD", "units": "10000", "price": "1.07000",
+         "gainQuoteHomeConversionFactor": "1", "lossQuoteHomeConversionFactor": "1",
+         "tradeOpened": {"tradeID": "2", "units": "10000", "price": "1.07000"},
+         "accountBalance": "10000.0000"}
+    ]))
+    .unwrap();
+    let candles: InstrumentCandles = serde_json::from_value(json!({
+        "instrument": "EUR_USD",
+        "granularity": "H1",
+        "candles": [
+            {"time": "2024-06-07T20:00:00Z", "complete": true, "volume": 1,
+             "bid": {"o": "1.07000", "h": "1.07200", "l": "1.06900", "c": "1.07100"}},
+            {"time": "2024-06-09T21:00:00Z", "complete": true, "volume": 1,
+             "bid": {"o": "1.07100", "h": "1.07200", "l": "1.06800", "c": "1.06900"}}
+        ]
+    }))
+    .unwrap();
+    let curve = EquityCurve::from_transactions(&history).mark_to_market([&candles]);
+    let times: Vec<_> = curve
+        .points()
+        .iter()
+        .map(|p| p.time.as_ref().unwrap().to_utc().unwrap().to_rfc3339())
+        .collect();
+    // Friday's last hour closes at 21:00, not at Sunday's open
+    assert_eq!(
+        times,
+        [
+            "2024-06-07T19:00:00+00:00",
+            "2024-06-07T20:30:00+00:00",
+            "2024-06-07T21:00:00+00:00",
+            "2024-06-09T22:00:00+00:00",
+        ]
+    );
+    assert_eq!(curve.points()[2].nav.value(), dec("10010"));
+}
@@ -0,0 +1,144 @@
+//! Tests for the CSV export (requires the `csv` feature).
+#![cfg(feature = "csv")]
+
+use oanda_rs::export::csv::{write_candles, write_trades, write_transactions};
+use oanda_rs::models::transaction::Transaction;
+use oanda_rs::models::{Candlestick, Trade, TradeSummary};
+use serde_json::{Value, json};
+
+fn csv_lines(write: impl FnOnce(&mut Vec<u8>)) -> Vec<String> {
+    let mut out = Vec::new();
+    write(&mut out);
+    String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn column_names_are_stable_and_written_without_records() {
+    let candles = csv_lines(|out| write_candles(out, Vec::<&Candlestick>::new()).unwrap());
+    assert_eq!(
+        candles,
+        [
+            "time,complete,volume,bid_o,bid_h,bid_l,bid_c,mid_o,mid_h,mid_l,mid_c,ask_o,ask_h,ask_l,ask_c"
+        ]
+    );
+    let transactions =
+        csv_lines(|out| write_transactions(out, Vec::<&Transaction>::new()).unwrap());
+    assert_eq!(
+        transactions,
+        [
+            "id,time,type,batch_id,order_id,instrument,units,price,pl,financing,commission,amount,account_balance,reason"
+        ]
+    );
+    let trades = csv_lines(|out| write_trades(out, Vec::<&Trade>::new()).unwrap());
+    assert_eq!(
+        trades,
+        [
+            "id,instrument,state,open_time,close_time,price,initial_units,current_units,average_close_price,realized_pl,unrealized_pl,financing,margin_used,client_id,client_tag,client_comment"
+        ]
+    );
+}
+
+#[test]
+fn candles_flatten_each_price_component() {
+    let candles: Vec<Candlestick> = serde_json::from_value(json!([
+        {"time": "2024-06-14T11:00:00.000000000Z", "complete": true, "volume": 10,
+         "bid": {"o": "1.07000", "h": "1.07100", "l": "1.06900", "c": "1.07050"},
+         "mid": {"o": "1.07005", "h": "1.07105", "l": "1.06905", "c": "1.07055"}},
+        {"time": "2024-06-14T12:00:00.000000000Z", "complete": false, "volume": 3,
+         "mid": {"o": "1.07055", "h": "1.07060", "l": "1.07040", "c": "1.07050"}}
+    ]))
+    .unwrap();
+
+    let lines = csv_lines(|out| write_candles(out, &candles).unwrap());
+    assert_eq!(
+        &lines[1..],
+        [
+            "2024-06-14T11:00:00.000000000Z,true,10,1.07000,1.07100,1.06900,1.07050,1.07005,1.07105,1.06905,1.07055,,,,",
+            "2024-06-14T12:00:00.000000000Z,false,3,,,,,1.07055,1.07060,1.07040,1.07050,,,,",
+        ]
+    );
+}
+
+#[test]
+fn transactions_share_one_set_of_columns() {
+    let transactions: Vec<Transaction> = serde_json::from_value(json!([
+        {"id": "100", "time": "2024-06-14T12:00:00.000000000Z", "type": "TRANSFER_FUNDS",
+         "batchID": "100", "amount": "10000.0000", "accountBalance": "10000.0000",
+         "fundingReason": "CLIENT_FUNDING"},
+        {"id": "101", "time": "2024-06-14T12:01:00.000000000Z", "type": "LIMIT_ORDER",
+         "batchID": "101", "instrument": "EUR_USD", "units": "1000", "price": "1.07000",
+         "reason": "CLIENT_ORDER", "timeInForce": "GTC"},
+        {"id": "102", "time": "2024-06-14T12:02:00.000000000Z", "type": "ORDER_FILL",
+         "batchID": "102", "orderID": "101", "instrument": "EUR_USD", "units": "-1000",
+         "price": "1.07100", "pl": "1.0000", "financing": "-0.0500", "commission": "0.0000",
+         "accountBalance": "10000.9500", "reason": "LIMIT_ORDER"},
+        {"id": "103", "time": "2024-06-15T21:00:00.000000000Z", "type": "DAILY_FINANCING",
+         "batchID": "103", "financing": "-0.1200", "accountBalance": "10000.8300"},
+        {"id": "104", "type": "SOMETHING_NEW", "instrument": "EUR_USD", "units": "5"}
+    ]))
+    .unwrap();
+
+    let lines = csv_lines(|out| write_transactions(out, &transactions).unwrap());
+    assert_eq!(
+        &lines[1..],
+        [
+            "100,2024-06-14T12:00:00.000000000Z,TRANSFER_FUNDS,100,,,,,,,,10000.0000,10000.0000,",
+            "101,2024-06-14T12:01:00.000000000Z,LIMIT_ORDER,101,,EUR_USD,1000,1.07000,,,,,,CLIENT_ORDER",
+            "102,2024-06-14T12:02:00.000000000Z,ORDER_FILL,102,101,EUR_USD,-1000,1.07100,1.0000,-0.0500,0.0000,,10000.9500,LIMIT_ORDER",
+            "103,2024-06-15T21:00:00.000000000Z,DAILY_FINANCING,103,,,,,,-0.1200,,,10000.8300,",
+            "104,,SOMETHING_NEW,,,EUR_USD,5,,,,,,,",
+        ]
+    );
+}
+
+#[test]
+fn every_transaction_type_exports_one_row() {
+    let fixtures: Vec<Value> =
+        serde_json::from_str(include_str!("fixtures/transactions.json")).unwrap();
+    let transactions: Vec<Transaction> = fixtures
+        .into_iter()
+        .map(|value| serde_json::from_value(value).unwrap())
+        .collect();
+
+    let lines = csv_lines(|out| write_transactions(out, &transactions).unwrap());
+    assert_eq!(lines.len(), transactions.len() + 1);
+    for (line, transaction) in lines[1..].iter().zip(&transactions) {
+        let type_name = line.split(',').nth(2).unwrap();
+        assert_eq!(Some(type_name), transaction.type_name());
+    }
+}
+
+#[test]
+fn trades_and_summaries_inline_client_extensions() {
+    let trade: Trade = serde_json::from_value(json!({
+        "id": "6368", "instrument": "EUR_USD", "state": "CLOSED",
+        "openTime": "2024-06-14T12:00:00.000000000Z", "closeTime": "2024-06-14T13:00:00.000000000Z",
+        "price": "1.07000", "initialUnits": "1000", "currentUnits": "0",
+        "averageClosePrice": "1.07100", "realizedPL": "1.0000", "financing": "-0.0100",
+        "clientExtensions": {"id": "my-trade", "tag": "breakout", "comment": "a, quoted \"note\""}
+    }))
+    .unwrap();
+    let summary: TradeSummary = serde_json::from_value(json!({
+        "id": "6369", "instrument": "USD_JPY", "state": "OPEN", "price": "157.000",
+        "initialUnits": "-500", "currentUnits": "-500", "unrealizedPL": "-0.3100",
+        "marginUsed": "16.6500"
+    }))
+    .unwrap();
+
+    let lines = csv_lines(|out| {
+        write_trades(&mut *out, [&trade]).unwrap();
+        write_trades(&mut *out, [&summary]).unwrap();
+    });
+    assert_eq!(
+        lines[1],
+        r#"6368,EUR_USD,CLOSED,2024-06-14T12:00:00.000000000Z,2024-06-14T13:00:00.000000000Z,1.07000,1000,0,1.07100,1.0000,,-0.0100,,my-trade,breakout,"a, quoted ""note""""#
+    );
+    assert_eq!(
+        lines[3],
+        "6369,USD_JPY,OPEN,,,157.000,-500,-500,,,-0.3100,,16.6500,,,"
+    );
+}
@@ -0,0 +1,290 @@
+//! Tests for the live-trading guards checked before orders are sent.
+
+mod common;
+
+use common::{ACCOUNT_ID, TOKEN, dec, mock_client_with};
+use oanda_rs::guard::{GuardViolation, Guards};
+use oanda_rs::models::{InstrumentName, LimitOrderRequest, MarketOrderRequest, OrderRequest};
+use oanda_rs::{Client, Environment, Error};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn guarded_client(guards: Guards) -> (MockServer, Client) {
+    mock_client_with(|builder| builder.guards(guards)).await
+}
+
+/// Answers `expected` pricing requests for `instrument` with the given
+/// closeout prices and quote-to-home conversion factors.
+async fn mount_price(
+    server: &MockServer,
+    instrument: &str,
+    closeout: (&str, &str),
+    conversion: (&str, &str),
+    expected: u64,
+) {
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/pricing")))
+        .and(query_param("instruments", instrument))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "prices": [{
+                "instrument": instrument,
+                "closeoutBid": closeout.0,
+                "closeoutAsk": closeout.1,
+                "quoteHomeConversionFactors": {
+                    "positiveUnits": conversion.0,
+                    "negativeUnits": conversion.1
+                }
+            }]
+        })))
+        .expect(expected)
+        .mount(server)
+        .await;
+}
+
+/// Accepts `expected` order creations.
+async fn mount_create(server: &MockServer, expected: u64) {
+    Mock::given(method("POST"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+            "orderCreateTransaction": {"type": "MARKET_ORDER", "id": "6789"},
+            "lastTransactionID": "6789"
+        })))
+        .expect(expected)
+        .mount(server)
+        .await;
+}
+
+fn violation(result: Result<impl std::fmt::Debug, Error>) -> GuardViolation {
+    match result {
+        Err(Error::Guard(violation)) => violation,
+        other => panic!("expected a guard violation, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn live_mutations_need_an_explicit_opt_in() {
+    let client = Client::builder()
+        .environment(Environment::Live)
+        .token(TOKEN)
+        .guards(Guards::new())
+        .build()
+        .unwrap();
+
+    let order = MarketOrderRequest::new("EUR_USD", 10_000_000);
+    assert_eq!(
+        violation(client.create_order(ACCOUNT_ID, order).await),
+        GuardViolation::LiveTradingNotAllowed
+    );
+    assert_eq!(
+        violation(client.close_trade(ACCOUNT_ID, "6543").send().await),
+        GuardViolation::LiveTradingNotAllowed
+    );
+    assert_eq!(
+        violation(
+            client
+                .close_position(ACCOUNT_ID, "EUR_USD")
+                .long_units("ALL")
+                .send()
+                .await
+        ),
+        GuardViolation::LiveTradingNotAllowed
+    );
+}
+
+#[tokio::test]
+async fn size_and_instrument_violations_send_nothing() {
+    let (server, client) = guarded_client(
+        Guards::new()
+            .allow_instruments(["EUR_USD", "USD_JPY"])
+            .max_units(1_000)
+            .instrument_max_units("USD_JPY", 500)
+            .max_notional(1_200),
+    )
+    .await;
+    // Only the orders passing the other checks are valued.
+    mount_price(&server, "EUR_USD", ("1.10", "1.12"), ("1", "1"), 2).await;
+    mount_create(&server, 1).await;
+
+    let create = |order: OrderRequest| client.create_order(ACCOUNT_ID, order);
+    assert_eq!(
+        violation(create(MarketOrderRequest::new("GBP_USD", 10).into()).await),
+        GuardViolation::InstrumentNotAllowed(InstrumentName::from("GBP_USD"))
+    );
+    assert_eq!(
+        violation(create(MarketOrderRequest::new("EUR_USD", -1_500).into()).await),
+        GuardViolation::MaxUnits {
+            instrument: "EUR_USD".into(),
+            units: dec("1500"),
+            limit: dec("1000"),
+        }
+    );
+    assert_eq!(
+        violation(create(LimitOrderRequest::new("USD_JPY", 600, dec("150.0")).into()).await),
+        GuardViolation::MaxUnits {
+            instrument: "USD_JPY".into(),
+            units: dec("600"),
+            limit: dec("500"),
+        }
+    );
+    assert_eq!(
+        violation(create(LimitOrderRequest::new("EUR_USD", 1_000, dec("1.25")).into()).await),
+        GuardViolation::MaxNotional {
+            instrument: "EUR_USD".into(),
+            notional: dec("1250"),
+            limit: dec("1200"),
+        }
+    );
+    assert_eq!(
+        violation(
+            client
+                .close_position(ACCOUNT_ID, "GBP_USD")
+                .long_units("ALL")
+                .send()
+                .await
+        ),
+        GuardViolation::InstrumentNotAllowed(InstrumentName::from("GBP_USD"))
+    );
+
+    create(LimitOrderRequest::new("EUR_USD", 1_000, dec("1.10")).into())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn market_orders_are_valued_at_the_closeout_price() {
+    let (server, client) = guarded_client(Guards::new().max_notional(1_200)).await;
+    mount_price(&server, "EUR_USD", ("1.10", "1.30"), ("1", "1"), 2).await;
+    mount_create(&server, 1).await;
+
+    // Buying is valued at the ask, selling at the bid.
+    assert_eq!(
+        violation(
+            client
+                .create_order(ACCOUNT_ID, MarketOrderRequest::new("EUR_USD", 1_000))
+                .await
+        ),
+        GuardViolation::MaxNotional {
+            instrument: "EUR_USD".into(),
+            notional: dec("1300"),
+            limit: dec("1200"),
+        }
+    );
+    client
+        .create_order(ACCOUNT_ID, MarketOrderRequest::new("EUR_USD", -1_000))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn notional_is_converted_to_the_home_currency() {
+    let (server, client) = guarded_client(
+        Guards::new()
+            .max_notional(900)
+            .instrument_max_notional("EUR_JPY", 2_000),
+    )
+    .await;
+    mount_price(
+        &server,
+        "USD_JPY",
+        ("149.9", "150.1"),
+        ("0.0064", "0.0066"),
+        1,
+    )
+    .await;
+    mount_price(
+        &server,
+        "EUR_JPY",
+        ("161.9", "162.1"),
+        ("0.0064", "0.0066"),
+        1,
+    )
+    .await;
+    mount_create(&server, 1).await;
+
+    // 150,000 JPY at the midpoint factor of 0.0065 is 975 in the home
+    // currency, over the global limit.
+    assert_eq!(
+        violation(
+            client
+                .create_order(
+                    ACCOUNT_ID,
+                    LimitOrderRequest::new("USD_JPY", 1_000, dec("150"))
+                )
+                .await
+        ),
+        GuardViolation::MaxNotional {
+            instrument: "USD_JPY".into(),
+            notional: dec("975.0000"),
+            limit: dec("900"),
+        }
+    );
+    // 162,100 JPY is about 1,054 in the home currency, under its own limit.
+    client
+        .create_order(ACCOUNT_ID, MarketOrderRequest::new("EUR_JPY", 1_000))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn custom_hosts_other_than_practice_or_loopback_count_as_live() {
+    let proxy: reqwest::Url = "https://oanda-proxy.example.com/v3".parse().unwrap();
+    let client = Client::builder()
+        .environment(Environment::Custom {
+            rest: proxy.clone(),
+            stream: proxy,
+        })
+        .token(TOKEN)
+        .guards(Guards::new())
+        .build()
+        .unwrap();
+
+    let order = MarketOrderRequest::new("EUR_USD", 100);
+    assert_eq!(
+        violation(client.create_order(ACCOUNT_ID, order).await),
+        GuardViolation::LiveTradingNotAllowed
+    );
+}
+
+#[tokio::test]
+async fn orders_per_minute_include_closes() {
+    let (server, client) = guarded_client(Guards::new().max_orders_per_minute(1)).await;
+    mount_create(&server, 1).await;
+
+    client
+        .create_order(ACCOUNT_ID, MarketOrderRequest::new("EUR_USD", 100))
+        .await
+        .unwrap();
+    assert_eq!(
+        violation(client.close_trade(ACCOUNT_ID, "6543").send().await),
+        GuardViolation::OrderRate { limit: 1 }
+    );
+}
+
+#[test]
+fn guards_are_auditable_through_debug() {
+    let client = Client::builder()
+        .token(TOKEN)
+        .guards(
+            Guards::new()
+                .allow_instruments(["EUR_USD"])
+                .instrument_max_units("EUR_USD", 5_000),
+        )
+        .build()
+        .unwrap();
+    let debug = format!("{client:?}");
+    assert!(debug.contains("allow_live: false"), "{debug}");
+    assert!(
+        debug.contains("allowed_instruments: Some({EurUsd})"),
+        "{debug}"
+    );
+    assert!(debug.contains("max_units: Some(5000)"), "{debug}");
+    assert_eq!(
+        client.guards(),
+        Some(
+            &Guards::new()
+                .allow_instruments(["EUR_USD"])
+                .instrument_max_units("EUR_USD", 5_000)
+        )
+    );
+}
@@ -3,9 +3,10 @@
 mod common;
 
 use common::{ACCOUNT_ID, mock_client, standard_headers};
+use futures_util::TryStreamExt;
 use oanda_rs::Error;
 use oanda_rs::endpoints::orders::CreateOrderRejectBody;
-use oanda_rs::models::transaction::Transaction;
+use oanda_rs::models::transaction::{Transaction, TransactionRejectReason};
 use oanda_rs::models::{
     ClientExtensions, LimitOrderRequest, MarketOrderRequest, Order, OrderId, OrderSpecifier,
     OrderStateFilter, StopLossDetails, TakeProfitDetails,
@@ -82,6 +83,43 @@ async fn create_market_order_pins_body_and_reads_location() {
     );
 }
 
+#[tokio::test]
+async fn create_order_with_meta_keeps_the_response_metadata() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("POST")).and(path(format!("/accounts/{ACCOUNT_ID}/orders"))),
+    )
+    .respond_with(
+        ResponseTemplate::new(201)
+            .insert_header("Location", "/v3/accounts/x/orders/6789")
+            .insert_header("RequestID", "42359180358046734")
+            .set_body_json(json!({
+                "orderCreateTransaction": {"type": "MARKET_ORDER", "id": "6789"},
+                "relatedTransactionIDs": ["6789"],
+                "lastTransactionID": "6789"
+            })),
+    )
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    let response = client
+        .create_order_with_meta(ACCOUNT_ID, MarketOrderRequest::new("EUR_USD", 100))
+        .await
+        .unwrap();
+    assert_eq!(response.status.as_u16(), 201);
+    assert_eq!(response.request_id.as_deref(), Some("42359180358046734"));
+    assert_eq!(
+        response.last_transaction_id.as_ref().map(|id| id.as_str()),
+        Some("6789")
+    );
+    assert_eq!(response.location, response.value.location);
+    assert_eq!(
+        response.location.as_deref(),
+        Some("/v3/accounts/x/orders/6789")
+    );
+}
+
 #[tokio::test]
 async fn create_order_reject_carries_typed_details() {
     let (server, client) = mock_client().await;
@@ -111,7 +149,12 @@ async fn create_order_reject_carries_typed_details() {
         panic!("expected Error::Api, got {error:?}");
     };
     assert_eq!(status.as_u16(), 400);
+    let reason = error.reject_reason().unwrap();
+    assert_eq!(reason, TransactionRejectReason::InstrumentNotTradeable);
+    assert!(reason.is_market_halted());
+    assert!(!reason.is_client_side_fixable());
     let details: CreateOrderRejectBody = body.details().unwrap();
+    assert_eq!(details.reject_reason(), Some(&reason));
     let Some(Transaction::MarketOrderReject(reject)) = details.order_reject_transaction else {
         panic!("expected MarketOrderReject");
     };
@@ -121,6 +164,73 @@ async fn create_order_reject_carries_typed_details() {
     );
 }
 
+#[tokio::test]
+async fn list_orders_into_stream_stops_before_the_time_window() {
+    let (server, client) = mock_client().await;
+    let order = |id: &str, time: &str| {
+        json!({
+            "id": id, "createTime": time, "type": "LIMIT", "state": "CANCELLED",
+            "instrument": "EUR_USD", "units": "100", "price": "1.08000"
+        })
+    };
+    let orders_path = format!("/accounts/{ACCOUNT_ID}/orders");
+    Mock::given(method("GET"))
+        .and(path(orders_path.as_str()))
+        .and(query_param("count", "2"))
+        .and(query_param("beforeID", "31"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "orders": [order("31", "2024-06-03T10:00:00Z"), order("30", "2024-06-02T10:00:00Z")]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(orders_path.as_str()))
+        .and(query_param("beforeID", "30"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "orders": [order("30", "2024-06-02T10:00:00Z"), order("29", "2024-05-31T10:00:00Z")]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // order 29 predates the window, so older pages are never requested
+    Mock::given(method("GET"))
+        .and(path(orders_path))
+        .and(query_param("beforeID", "29"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"orders": []})))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let orders: Vec<Order> = client
+        .list_orders(ACCOUNT_ID)
+        .state(OrderStateFilter::All)
+        .count(2)
+        .before_id("31")
+        .into_stream()
+        .between("2024-06-01T00:00:00Z", "2024-06-03T00:00:00Z")
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let ids: Vec<_> = orders
+        .iter()
+        .map(|order| order.id().unwrap().as_str())
+        .collect();
+    assert_eq!(ids, ["30"]);
+}
+
+#[tokio::test]
+async fn list_orders_into_stream_rejects_an_unparseable_time_bound() {
+    let (_server, client) = mock_client().await;
+    let err = client
+        .list_orders(ACCOUNT_ID)
+        .into_stream()
+        .between("2024-06-01T00:00:00Z", "next tuesday")
+        .unwrap_err();
+    assert!(matches!(err, Error::Config(msg) if msg.contains("next tuesday")));
+}
+
 #[tokio::test]
 async fn list_orders_with_filters() {
     let (server, client) = mock_client().await;
@@ -355,6 +465,10 @@ async fn cancel_order_reject_carries_typed_details() {
         panic!("expected Error::Api");
     };
     let details: oanda_rs::endpoints::orders::CancelOrderRejectBody = body.details().unwrap();
+    assert_eq!(
+        details.reject_reason(),
+        Some(&TransactionRejectReason::OrderDoesntExist)
+    );
     let reject = details.order_cancel_reject_transaction.unwrap();
     assert_eq!(reject.order_id.unwrap().as_str(), "9999");
     assert_eq!(details.last_transaction_id.unwrap().as_str(), "7100");
@@ -396,3 +510,260 @@ async fn set_order_trade_client_extensions() {
             .is_some()
     );
 }
+
+#[tokio::test]
+async fn idempotent_create_recovers_order_after_server_error() {
+    let (server, client) = mock_client().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+        .and(body_json(json!({
+            "order": {
+                "type": "MARKET",
+                "instrument": "EUR_USD",
+                "units": "100",
+                "clientExtensions": {"id": "entry-1", "tag": "breakout"}
+            }
+        })))
+        .respond_with(ResponseTemplate::new(504).set_body_string("gateway timeout"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders/@entry-1")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "order": {
+                "type": "MARKET", "id": "6789", "state": "FILLED",
+                "instrument": "EUR_USD", "units": "100",
+                "clientExtensions": {"id": "entry-1", "tag": "breakout"},
+                "fillingTransactionID": "6790"
+            },
+            "lastTransactionID": "6790"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/transactions/6789")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "transaction": {
+                "type": "MARKET_ORDER", "id": "6789", "instrument": "EUR_USD", "units": "100",
+                "clientExtensions": {"id": "entry-1", "tag": "breakout"}
+            },
+            "lastTransactionID": "6790"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/transactions/6790")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "transaction": {
+                "type": "ORDER_FILL", "id": "6790", "orderID": "6789",
+                "clientOrderID": "entry-1", "instrument": "EUR_USD", "units": "100",
+                "price": "1.10423"
+            },
+            "lastTransactionID": "6790"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = client
+        .create_order_idempotent(
+            ACCOUNT_ID,
+            MarketOrderRequest::new("EUR_USD", 100)
+                .client_extensions(ClientExtensions::new().tag("breakout")),
+            "entry-1",
+        )
+        .settle_delay(std::time::Duration::ZERO)
+        .send()
+        .await
+        .unwrap();
+    assert!(result.recovered);
+    assert_eq!(result.attempts, 1);
+    let response = result.response;
+    assert_eq!(
+        response.order_create_transaction.id().unwrap().as_str(),
+        "6789"
+    );
+    let fill = response.order_fill_transaction.unwrap();
+    assert_eq!(fill.price.unwrap().to_string(), "1.10423");
+    assert_eq!(response.related_transaction_ids.len(), 2);
+}
+
+#[tokio::test]
+async fn idempotent_create_resubmits_when_order_is_not_found() {
+    let (server, client) = mock_client().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+        .respond_with(ResponseTemplate::new(503).set_body_string("unavailable"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+            "orderCreateTransaction": {
+                "type": "LIMIT_ORDER", "id": "6801", "instrument": "EUR_USD", "units": "100",
+                "price": "1.07000", "clientExtensions": {"id": "entry-2"}
+            },
+            "lastTransactionID": "6801"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders/@entry-2")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "errorMessage": "The Order specified does not exist",
+            "lastTransactionID": "6800"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/summary")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "account": {"id": ACCOUNT_ID, "currency": "USD"},
+            "lastTransactionID": "6800"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/transactions/idrange")))
+        .and(query_param("from", "6791"))
+        .and(query_param("to", "6800"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "transactions": [
+                {"type": "LIMIT_ORDER", "id": "6799", "instrument": "USD_JPY", "units": "10",
+                 "price": "157.000", "clientExtensions": {"id": "other"}}
+            ],
+            "lastTransactionID": "6800"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = client
+        .create_order_idempotent(
+            ACCOUNT_ID,
+            LimitOrderRequest::new(
+                "EUR_USD",
+                100,
+                "1.07000".parse::<oanda_rs::models::PriceValue>().unwrap(),
+            ),
+            "entry-2",
+        )
+        .settle_delay(std::time::Duration::ZERO)
+        .lookback(10)
+        .send()
+        .await
+        .unwrap();
+    assert!(!result.recovered);
+    assert_eq!(result.attempts, 2);
+    assert_eq!(
+        result
+            .response
+            .order_create_transaction
+            .id()
+            .unwrap()
+            .as_str(),
+        "6801"
+    );
+}
+
+#[tokio::test]
+async fn idempotent_create_finds_order_in_recent_transactions() {
+    let (server, client) = mock_client().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+        .respond_with(ResponseTemplate::new(502))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders/@entry-3")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "errorMessage": "The Order specified does not exist"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/summary")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "account": {"id": ACCOUNT_ID},
+            "lastTransactionID": "6812"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/transactions/idrange")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "transactions": [
+                {"type": "MARKET_ORDER", "id": "6810", "instrument": "EUR_USD", "units": "100",
+                 "clientExtensions": {"id": "entry-3"}},
+                {"type": "ORDER_FILL", "id": "6811", "orderID": "6810", "instrument": "EUR_USD",
+                 "units": "100", "price": "1.10423"},
+                {"type": "ORDER_FILL", "id": "6812", "orderID": "6799", "instrument": "USD_JPY",
+                 "units": "10", "price": "157.000"}
+            ],
+            "lastTransactionID": "6812"
+        })))
+        .mount(&server)
+        .await;
+
+    let result = client
+        .create_order_idempotent(
+            ACCOUNT_ID,
+            MarketOrderRequest::new("EUR_USD", 100),
+            "entry-3",
+        )
+        .settle_delay(std::time::Duration::ZERO)
+        .send()
+        .await
+        .unwrap();
+    assert!(result.recovered);
+    let response = result.response;
+    assert_eq!(
+        response
+            .order_fill_transaction
+            .unwrap()
+            .id
+            .unwrap()
+            .as_str(),
+        "6811"
+    );
+    assert_eq!(response.last_transaction_id.unwrap().as_str(), "6812");
+}
+
+#[tokio::test]
+async fn idempotent_create_returns_definite_rejections_without_lookup() {
+    let (server, client) = mock_client().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/orders")))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "errorCode": "INSUFFICIENT_MARGIN",
+            "errorMessage": "Insufficient margin"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let error = client
+        .create_order_idempotent(
+            ACCOUNT_ID,
+            MarketOrderRequest::new("EUR_USD", 100),
+            "entry-4",
+        )
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(error.status().map(|s| s.as_u16()), Some(400));
+}
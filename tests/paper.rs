@@ -0,0 +1,505 @@
+//! Tests for the paper-trading account: fills against supplied prices,
+//! dependent orders, financing and the `Broker` operations.
+
+use oanda_rs::Error;
+use oanda_rs::broker::Broker;
+use oanda_rs::models::transaction::{OrderCancelReason, OrderFillReason, Transaction};
+use oanda_rs::models::{
+    AccountId, ClientPrice, Instrument, LimitOrderRequest, MarketIfTouchedOrderRequest,
+    MarketOrderRequest, MarketOrderTimeInForce, Order, PriceValue, StopLossDetails,
+    StopOrderRequest, TakeProfitDetails, TrailingStopLossDetails, TrailingStopLossOrderRequest,
+};
+use oanda_rs::trading::PaperAccount;
+use serde_json::json;
+
+const ACCOUNT: &str = "paper-001";
+
+fn account_id() -> AccountId {
+    AccountId::from(ACCOUNT)
+}
+
+/// A EUR_USD price with two buckets of 1,000 units on each side.
+fn price(time: &str, bid: &str, ask: &str) -> ClientPrice {
+    let step = rust_decimal::Decimal::new(1, 5);
+    let deeper = |price: &str, by: rust_decimal::Decimal| {
+        (price.parse::<rust_decimal::Decimal>().unwrap() + by).to_string()
+    };
+    serde_json::from_value(json!({
+        "instrument": "EUR_USD",
+        "time": time,
+        "tradeable": true,
+        "bids": [
+            {"price": bid, "liquidity": 1000},
+            {"price": deeper(bid, -step), "liquidity": 1000}
+        ],
+        "asks": [
+            {"price": ask, "liquidity": 1000},
+            {"price": deeper(ask, step), "liquidity": 1000}
+        ],
+        "closeoutBid": bid,
+        "closeoutAsk": ask
+    }))
+    .unwrap()
+}
+
+fn account() -> PaperAccount {
+    let account = PaperAccount::new(ACCOUNT, 10_000).currency("USD");
+    account.update_price(&price("2024-06-14T12:00:00Z", "1.07000", "1.07010"));
+    account
+}
+
+fn fills(
+    transactions: &[Transaction],
+) -> Vec<&oanda_rs::models::transaction::OrderFillTransaction> {
+    transactions
+        .iter()
+        .filter_map(|tx| match tx {
+            Transaction::OrderFill(fill) => Some(fill),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn market_order_walks_the_asks() {
+    let account = account();
+    let response = account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", 1500).into(),
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        response.order_create_transaction,
+        Transaction::MarketOrder(_)
+    ));
+    let fill = response.order_fill_transaction.unwrap();
+    // 1,000 at 1.07010 and 500 at 1.07011
+    assert_eq!(
+        fill.price.unwrap().to_string(),
+        "1.0701033333333333333333333333"
+    );
+    assert_eq!(fill.units.unwrap().to_string(), "1500");
+    assert_eq!(fill.reason, Some(OrderFillReason::MarketOrder));
+    let opened = fill.trade_opened.unwrap();
+    assert_eq!(opened.trade_id.unwrap().as_str(), fill.id.unwrap().as_str());
+    assert!(matches!(
+        account.transactions().first(),
+        Some(Transaction::TransferFunds(_))
+    ));
+}
+
+#[tokio::test]
+async fn fill_or_kill_is_cancelled_without_liquidity() {
+    let account = account();
+    let response = account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", 5000).into(),
+        )
+        .await
+        .unwrap();
+    assert!(response.order_fill_transaction.is_none());
+    let cancel = response.order_cancel_transaction.unwrap();
+    assert_eq!(
+        cancel.reason,
+        Some(OrderCancelReason::InsufficientLiquidity)
+    );
+
+    let partial = account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", -5000)
+                .time_in_force(MarketOrderTimeInForce::Ioc)
+                .into(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        partial
+            .order_fill_transaction
+            .unwrap()
+            .units
+            .unwrap()
+            .to_string(),
+        "-2000"
+    );
+}
+
+#[tokio::test]
+async fn limit_order_fills_when_the_price_crosses() {
+    let account = account();
+    let response = account
+        .create_order(
+            &account_id(),
+            LimitOrderRequest::new(
+                "EUR_USD",
+                1000,
+                "1.06950".parse::<oanda_rs::models::PriceValue>().unwrap(),
+            )
+            .into(),
+        )
+        .await
+        .unwrap();
+    assert!(response.order_fill_transaction.is_none());
+    assert_eq!(
+        account
+            .list_pending_orders(&account_id())
+            .await
+            .unwrap()
+            .orders
+            .len(),
+        1
+    );
+
+    let created = account.update_price(&price("2024-06-14T12:00:01Z", "1.06960", "1.06970"));
+    assert!(created.is_empty());
+    let created = account.update_price(&price("2024-06-14T12:00:02Z", "1.06940", "1.06950"));
+    let fill = fills(&created)[0];
+    assert_eq!(fill.reason, Some(OrderFillReason::LimitOrder));
+    assert_eq!(fill.price.unwrap().to_string(), "1.0695");
+    assert!(
+        account
+            .list_pending_orders(&account_id())
+            .await
+            .unwrap()
+            .orders
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn take_profit_closes_the_trade_and_cancels_the_stop_loss() {
+    let account = account();
+    account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", 1000)
+                .take_profit_on_fill(TakeProfitDetails::at_price("1.07100".parse().unwrap()))
+                .stop_loss_on_fill(StopLossDetails::at_distance("0.00050".parse().unwrap()))
+                .into(),
+        )
+        .await
+        .unwrap();
+    let trades = account
+        .list_open_trades(&account_id())
+        .await
+        .unwrap()
+        .trades;
+    assert_eq!(trades.len(), 1);
+    assert_eq!(
+        account
+            .list_pending_orders(&account_id())
+            .await
+            .unwrap()
+            .orders
+            .len(),
+        2
+    );
+
+    let created = account.update_price(&price("2024-06-14T12:01:00Z", "1.07100", "1.07110"));
+    let fill = fills(&created)[0];
+    assert_eq!(fill.reason, Some(OrderFillReason::TakeProfitOrder));
+    assert_eq!(fill.pl.unwrap().to_string(), "0.9");
+    assert!(created.iter().any(|tx| matches!(
+        tx,
+        Transaction::OrderCancel(cancel) if cancel.reason == Some(OrderCancelReason::LinkedTradeClosed)
+    )));
+    assert!(
+        account
+            .list_open_trades(&account_id())
+            .await
+            .unwrap()
+            .trades
+            .is_empty()
+    );
+    assert!(
+        account
+            .list_pending_orders(&account_id())
+            .await
+            .unwrap()
+            .orders
+            .is_empty()
+    );
+    assert_eq!(account.balance().to_string(), "10000.9");
+}
+
+#[tokio::test]
+async fn trailing_stop_follows_the_market() {
+    let account = account();
+    let trade_id = account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", 1000).into(),
+        )
+        .await
+        .unwrap()
+        .order_fill_transaction
+        .unwrap()
+        .trade_opened
+        .unwrap()
+        .trade_id
+        .unwrap();
+    account
+        .create_order(
+            &account_id(),
+            TrailingStopLossOrderRequest::new(
+                trade_id,
+                "0.00050".parse::<rust_decimal::Decimal>().unwrap(),
+            )
+            .into(),
+        )
+        .await
+        .unwrap();
+
+    // the stop trails up to 1.07150 and does not come back down
+    assert!(
+        account
+            .update_price(&price("2024-06-14T12:01:00Z", "1.07200", "1.07210"))
+            .is_empty()
+    );
+    assert!(
+        account
+            .update_price(&price("2024-06-14T12:02:00Z", "1.07160", "1.07170"))
+            .is_empty()
+    );
+    let created = account.update_price(&price("2024-06-14T12:03:00Z", "1.07150", "1.07160"));
+    let fill = fills(&created)[0];
+    assert_eq!(fill.reason, Some(OrderFillReason::TrailingStopLossOrder));
+    assert_eq!(fill.units.unwrap().to_string(), "-1000");
+}
+
+#[tokio::test]
+async fn daily_financing_charges_open_trades() {
+    let account = account();
+    let instrument: Instrument = serde_json::from_value(json!({
+        "name": "EUR_USD",
+        "marginRate": "0.0333",
+        "financing": {"longRate": "-0.0365", "shortRate": "0.0100"}
+    }))
+    .unwrap();
+    account.add_instrument(&instrument);
+    assert!(account.apply_daily_financing().is_none());
+
+    account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", 1000).into(),
+        )
+        .await
+        .unwrap();
+    let Some(Transaction::DailyFinancing(financing)) = account.apply_daily_financing() else {
+        panic!("expected a daily financing transaction");
+    };
+    // 1,000 × 1.07005 × -3.65% / 365
+    assert_eq!(financing.financing.unwrap().to_string(), "-0.107");
+    assert_eq!(account.balance().to_string(), "9999.893");
+}
+
+#[tokio::test]
+async fn close_trade_and_lookup_errors() {
+    let account = account();
+    let fill = account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", -1000).into(),
+        )
+        .await
+        .unwrap()
+        .order_fill_transaction
+        .unwrap();
+    let trade_id = fill.trade_opened.unwrap().trade_id.unwrap();
+
+    let closed = account
+        .close_trade(&account_id(), trade_id.clone().into(), Some(400.into()))
+        .await
+        .unwrap();
+    assert!(closed.order_create_transaction.is_some());
+    let fill = closed.order_fill_transaction.unwrap();
+    assert_eq!(fill.reason, Some(OrderFillReason::MarketOrderTradeClose));
+    assert_eq!(fill.units.unwrap().to_string(), "400");
+    let trade = account
+        .trade(&account_id(), trade_id.clone().into())
+        .await
+        .unwrap()
+        .trade;
+    assert_eq!(trade.current_units.unwrap().to_string(), "-600");
+
+    let err = account
+        .close_trade(&account_id(), trade_id.into(), Some(1000.into()))
+        .await
+        .unwrap_err();
+    assert!(matches!(&err, Error::Api { body, .. }
+        if body.error_code.as_deref() == Some("CLOSE_TRADE_UNITS_EXCEED_TRADE_SIZE")));
+    let err = account
+        .cancel_order(&account_id(), "999".into())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Api { status, .. } if status == 404));
+}
+
+#[tokio::test]
+async fn other_accounts_do_not_exist() {
+    let account = account();
+    let other = AccountId::from("paper-002");
+    let err = account
+        .create_order(&other, MarketOrderRequest::new("EUR_USD", 1000).into())
+        .await
+        .unwrap_err();
+    assert!(matches!(&err, Error::Api { status, body, .. }
+        if *status == 404 && body.error_code.as_deref() == Some("NO_SUCH_ACCOUNT")));
+    assert!(account.account_summary(&other).await.is_err());
+    assert!(account.transaction_stream(&other).await.is_err());
+    // Nothing reached the ledger.
+    assert!(
+        account
+            .list_open_trades(&account_id())
+            .await
+            .unwrap()
+            .trades
+            .is_empty()
+    );
+}
+
+/// Closes every open trade, the way strategy code would against any
+/// backend.
+async fn flatten(broker: &impl Broker, account: &AccountId) -> Result<usize, Error> {
+    let mut closed = 0;
+    for trade in broker.list_open_trades(account).await?.trades {
+        if let Some(id) = trade.id {
+            broker.close_trade(account, id.into(), None).await?;
+            closed += 1;
+        }
+    }
+    Ok(closed)
+}
+
+#[tokio::test]
+async fn generic_code_runs_against_paper_account() {
+    let account = account();
+    account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", 100)
+                .position_fill(oanda_rs::models::OrderPositionFill::OpenOnly)
+                .into(),
+        )
+        .await
+        .unwrap();
+    account
+        .create_order(
+            &account_id(),
+            MarketOrderRequest::new("EUR_USD", 200)
+                .position_fill(oanda_rs::models::OrderPositionFill::OpenOnly)
+                .into(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(flatten(&account, &account_id()).await.unwrap(), 2);
+    let summary = account
+        .account_summary(&account_id())
+        .await
+        .unwrap()
+        .account;
+    assert_eq!(summary.open_trade_count, Some(0));
+    // two spreads of 0.0001 on 300 units
+    assert_eq!(summary.balance.unwrap().to_string(), "9999.97");
+}
+
+#[tokio::test]
+async fn every_emitted_transaction_and_order_decodes_as_its_type() {
+    let account = account();
+    let instrument: Instrument = serde_json::from_value(json!({
+        "name": "EUR_USD",
+        "marginRate": "0.0333",
+        "financing": {"longRate": "-0.0365", "shortRate": "0.0100"}
+    }))
+    .unwrap();
+    account.add_instrument(&instrument);
+    let id = account_id();
+    let decimal = |s: &str| s.parse::<rust_decimal::Decimal>().unwrap();
+
+    let trade_id = account
+        .create_order(
+            &id,
+            MarketOrderRequest::new("EUR_USD", 1000)
+                .take_profit_on_fill(TakeProfitDetails::at_price("1.08000".parse().unwrap()))
+                .stop_loss_on_fill(StopLossDetails::at_distance("0.00500".parse().unwrap()))
+                .trailing_stop_loss_on_fill(TrailingStopLossDetails::at_distance(
+                    "0.00600".parse().unwrap(),
+                ))
+                .into(),
+        )
+        .await
+        .unwrap()
+        .order_fill_transaction
+        .unwrap()
+        .trade_opened
+        .unwrap()
+        .trade_id
+        .unwrap();
+    for order in [
+        LimitOrderRequest::new("EUR_USD", 100, "1.06000".parse::<PriceValue>().unwrap()).into(),
+        StopOrderRequest::new("EUR_USD", 100, "1.08000".parse::<PriceValue>().unwrap()).into(),
+        MarketIfTouchedOrderRequest::new("EUR_USD", 100, "1.06000".parse::<PriceValue>().unwrap())
+            .into(),
+    ] {
+        account.create_order(&id, order).await.unwrap();
+    }
+    let pending = account.list_pending_orders(&id).await.unwrap().orders;
+    assert_eq!(pending.len(), 6);
+    assert!(
+        !pending
+            .iter()
+            .any(|order| matches!(order, Order::Unknown(_)))
+    );
+
+    let limit = pending
+        .iter()
+        .find(|order| matches!(order, Order::Limit(_)))
+        .and_then(|order| order.id().cloned())
+        .unwrap();
+    account
+        .cancel_order(&id, limit.as_str().into())
+        .await
+        .unwrap();
+    account.apply_daily_financing().unwrap();
+    account
+        .close_trade(&id, trade_id.as_str().into(), Some(decimal("500").into()))
+        .await
+        .unwrap();
+    account.close_position(&id, "EUR_USD".into()).await.unwrap();
+
+    let transactions = account.transactions();
+    assert!(
+        !transactions
+            .iter()
+            .any(|tx| matches!(tx, Transaction::Unknown(_))),
+        "{transactions:?}"
+    );
+    let mut kinds: Vec<&str> = transactions
+        .iter()
+        .filter_map(Transaction::type_name)
+        .collect();
+    kinds.sort_unstable();
+    kinds.dedup();
+    assert_eq!(
+        kinds,
+        [
+            "DAILY_FINANCING",
+            "LIMIT_ORDER",
+            "MARKET_IF_TOUCHED_ORDER",
+            "MARKET_ORDER",
+            "ORDER_CANCEL",
+            "ORDER_FILL",
+            "STOP_LOSS_ORDER",
+            "STOP_ORDER",
+            "TAKE_PROFIT_ORDER",
+            "TRAILING_STOP_LOSS_ORDER",
+            "TRANSFER_FUNDS",
+        ]
+    );
+}
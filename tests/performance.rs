@@ -0,0 +1,158 @@
+//! Tests for `PerformanceReport`.
+
+mod common;
+
+use chrono::TimeDelta;
+use common::dec;
+use oanda_rs::models::transaction::Transaction;
+use oanda_rs::trading::PerformanceReport;
+use rust_decimal::Decimal;
+use serde_json::{Value, json};
+
+fn fill(id: &str, time: &str, instrument: &str, extra: Value) -> Value {
+    let mut fill = json!({
+        "id": id, "time": time, "type": "ORDER_FILL", "instrument": instrument,
+        "reason": "MARKET_ORDER"
+    });
+    fill.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    fill
+}
+
+fn open(trade: &str, units: &str, tag: Option<&str>) -> Value {
+    let mut opened = json!({"tradeID": trade, "units": units, "price": "1.00000"});
+    if let Some(tag) = tag {
+        opened["clientExtensions"] = json!({"tag": tag});
+    }
+    json!({"tradeOpened": opened})
+}
+
+fn close(trade: &str, pl: &str) -> Value {
+    json!({"tradesClosed": [{"tradeID": trade, "realizedPL": pl, "financing": "0.0000"}]})
+}
+
+fn history() -> Vec<Transaction> {
+    serde_json::from_value(json!([
+        fill("10", "2024-06-10T12:00:00Z", "EUR_USD", open("10", "1000", Some("breakout"))),
+        fill("11", "2024-06-10T13:00:00Z", "USD_JPY", open("11", "-500", Some("meanrev"))),
+        fill("12", "2024-06-10T14:00:00Z", "EUR_USD", open("12", "2000", None)),
+        fill("13", "2024-06-10T15:00:00Z", "EUR_USD", open("13", "100", Some("breakout"))),
+        // opened before the history starts
+        fill("14", "2024-06-11T09:00:00Z", "EUR_USD", close("2", "3.0000")),
+        fill("15", "2024-06-11T10:00:00Z", "EUR_USD", json!({
+            "tradeReduced": {"tradeID": "10", "units": "-500", "realizedPL": "5.0000",
+                             "financing": "0.0000"}
+        })),
+        {"id": "16", "time": "2024-06-11T21:00:00Z", "type": "DAILY_FINANCING",
+         "financing": "-0.6000", "positionFinancings": [
+            {"instrument": "EUR_USD", "financing": "-0.6000", "openTradeFinancings": [
+                {"tradeID": "10", "financing": "-0.5000"},
+                {"tradeID": "12", "financing": "-0.1000"}
+            ]}
+        ]},
+        {"id": "17", "time": "2024-06-12T08:00:00Z", "type": "TRADE_CLIENT_EXTENSIONS_MODIFY",
+         "tradeID": "12", "tradeClientExtensionsModify": {"tag": "breakout"}},
+        fill("18", "2024-06-12T09:00:00Z", "USD_JPY", close("11", "-4.0000")),
+        fill("19", "2024-06-12T12:00:00Z", "EUR_USD", close("10", "10.0000")),
+        fill("20", "2024-06-12T14:00:00Z", "EUR_USD", close("12", "-1.9000")),
+    ]))
+    .unwrap()
+}
+
+#[test]
+fn trades_accumulate_pl_financing_and_holding_time() {
+    let report = PerformanceReport::from_transactions(&history());
+    let ids: Vec<_> = report.trades.iter().map(|t| t.trade_id.as_str()).collect();
+    assert_eq!(ids, ["10", "11", "12", "13", "2"]);
+
+    let first = &report.trades[0];
+    assert!(first.closed);
+    assert_eq!(first.realized_pl.value(), dec("15"));
+    assert_eq!(first.financing.value(), dec("-0.5"));
+    assert_eq!(first.net_pl().value(), dec("14.5"));
+    assert_eq!(first.holding_time(), Some(TimeDelta::days(2)));
+
+    let retagged = &report.trades[2];
+    assert_eq!(retagged.tag.as_deref(), Some("breakout"));
+    assert_eq!(retagged.net_pl().value(), dec("-2"));
+
+    let still_open = &report.trades[3];
+    assert!(!still_open.closed);
+    assert_eq!(still_open.holding_time(), None);
+
+    let before_history = &report.trades[4];
+    assert!(before_history.closed);
+    assert_eq!(
+        before_history.instrument.as_ref().unwrap().as_str(),
+        "EUR_USD"
+    );
+    assert_eq!(before_history.open_time, None);
+}
+
+#[test]
+fn overall_statistics_cover_closed_trades() {
+    let report = PerformanceReport::from_transactions(&history());
+    let overall = &report.overall;
+    // closed in order: +3, -4, +14.5, -2
+    assert_eq!((overall.trades, overall.wins, overall.losses), (4, 2, 2));
+    assert_eq!(overall.net_pl.value(), dec("11.5"));
+    assert_eq!(overall.gross_profit.value(), dec("17.5"));
+    assert_eq!(overall.gross_loss.value(), dec("6"));
+    assert_eq!(overall.financing.value(), dec("-0.6"));
+    assert_eq!(overall.win_rate, Some(dec("0.5")));
+    assert_eq!(
+        overall.profit_factor,
+        Some((dec("17.5") / dec("6")).normalize())
+    );
+    assert_eq!(overall.expectancy.unwrap().value(), dec("2.875"));
+    assert_eq!(overall.average_win.unwrap().value(), dec("8.75"));
+    assert_eq!(overall.average_loss.unwrap().value(), dec("-3"));
+    // from the +3 peak down to -1
+    assert_eq!(overall.max_drawdown.value(), dec("4"));
+    // only trades 10, 11 and 12 have both ends: 48h, 44h and 48h
+    assert_eq!(
+        overall.average_holding_time,
+        Some(TimeDelta::hours(140) / 3)
+    );
+}
+
+#[test]
+fn breakdowns_by_instrument_and_tag() {
+    let report = PerformanceReport::from_transactions(&history());
+
+    let instruments: Vec<_> = report
+        .by_instrument
+        .iter()
+        .map(|(name, stats)| (name.as_str(), stats.trades, stats.net_pl.value()))
+        .collect();
+    assert_eq!(
+        instruments,
+        [("EUR_USD", 3, dec("15.5")), ("USD_JPY", 1, dec("-4"))]
+    );
+
+    let tags: Vec<_> = report
+        .by_tag
+        .iter()
+        .map(|(tag, stats)| (tag.as_str(), stats.trades, stats.net_pl.value()))
+        .collect();
+    assert_eq!(
+        tags,
+        [("breakout", 2, dec("12.5")), ("meanrev", 1, dec("-4"))]
+    );
+    let meanrev = &report.by_tag["meanrev"];
+    assert_eq!(meanrev.win_rate, Some(Decimal::ZERO));
+    assert_eq!(meanrev.profit_factor, Some(Decimal::ZERO));
+    assert_eq!(meanrev.average_win, None);
+}
+
+#[test]
+fn empty_history_has_no_ratios() {
+    let report = PerformanceReport::from_transactions(&[]);
+    assert!(report.trades.is_empty());
+    assert_eq!(report.overall.trades, 0);
+    assert_eq!(report.overall.win_rate, None);
+    assert_eq!(report.overall.profit_factor, None);
+    assert_eq!(report.overall.expectancy, None);
+    assert_eq!(report.overall.max_drawdown.value(), Decimal::ZERO);
+}
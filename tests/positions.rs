@@ -209,4 +209,8 @@ async fn close_position_reject_carries_typed_details() {
     };
     let details: oanda_rs::endpoints::positions::ClosePositionRejectBody = body.details().unwrap();
     assert!(details.long_order_reject_transaction.is_some());
+    assert_eq!(
+        details.reject_reason().map(|r| r.as_str()),
+        Some("CLOSEOUT_POSITION_DOESNT_EXIST")
+    );
 }
@@ -2,6 +2,8 @@
 
 mod common;
 
+use std::io::Write;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use common::{ACCOUNT_ID, mock_client, standard_headers};
@@ -9,6 +11,7 @@ use futures_util::StreamExt;
 use oanda_rs::Error;
 use oanda_rs::models::transaction::{Transaction, TransactionStreamItem};
 use oanda_rs::models::{InstrumentName, PriceStreamItem};
+use oanda_rs::streaming::{PricingStream, ReplaySpeed, TransactionStream};
 use serde_json::json;
 use wiremock::matchers::{method, path, query_param};
 use wiremock::{Mock, ResponseTemplate};
@@ -317,3 +320,130 @@ async fn prices_since_parameter() {
         .unwrap();
     assert!(response.prices.is_empty());
 }
+
+/// A recording target the test can read back.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn recorded_pricing_stream_replays_identically() {
+    let (server, client) = mock_client().await;
+    let body = concat!(
+        r#"{"type":"PRICE","instrument":"EUR_USD","closeoutBid":"1.07128","closeoutAsk":"1.07149","tradeable":true}"#,
+        "\r\n",
+        r#"{"type":"HEARTBEAT","time":"2024-06-14T12:00:05.000000000Z"}"#,
+        "\n\n",
+        "garbage line\n",
+        r#"{"type":"PRICE","instrument":"EUR_USD","closeoutBid":"1.07131","closeoutAsk":"1.07152","tradeable":true}"#,
+        "\n",
+    );
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/pricing/stream")))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/octet-stream"))
+        .mount(&server)
+        .await;
+
+    let recording = SharedBuffer::default();
+    let live: Vec<_> = client
+        .pricing_stream(ACCOUNT_ID, ["EUR_USD"])
+        .auto_reconnect(false)
+        .send()
+        .await
+        .unwrap()
+        .record(recording.clone())
+        .collect()
+        .await;
+    assert_eq!(live.len(), 4);
+
+    let recorded = recording.0.lock().unwrap().clone();
+    let lines: Vec<serde_json::Value> = recorded
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).unwrap())
+        .collect();
+    // blank lines are dropped, everything else is kept verbatim
+    assert_eq!(lines.len(), 4);
+    assert!(lines.iter().all(|line| line["received"].is_string()));
+    assert_eq!(lines[2]["line"], "garbage line");
+    assert_eq!(
+        lines[1]["line"],
+        r#"{"type":"HEARTBEAT","time":"2024-06-14T12:00:05.000000000Z"}"#
+    );
+
+    let replayed: Vec<_> =
+        PricingStream::replay(std::io::Cursor::new(recorded), ReplaySpeed::Unthrottled)
+            .collect()
+            .await;
+    assert_eq!(replayed.len(), 4);
+    assert_eq!(replayed[0].as_ref().unwrap(), live[0].as_ref().unwrap());
+    assert!(matches!(replayed[1], Ok(PriceStreamItem::Heartbeat(_))));
+    assert!(matches!(replayed[2], Err(Error::Decode { .. })));
+    assert_eq!(replayed[3].as_ref().unwrap(), live[3].as_ref().unwrap());
+}
+
+#[tokio::test(start_paused = true)]
+async fn replay_follows_the_recorded_pace() {
+    let recording = concat!(
+        r#"{"received":"2024-06-14T12:00:00Z","line":"{\"type\":\"HEARTBEAT\"}"}"#,
+        "\n",
+        r#"{"received":"2024-06-14T12:00:05Z","line":"{\"type\":\"HEARTBEAT\"}"}"#,
+        "\n",
+        r#"{"received":"2024-06-14T12:00:10Z","line":"{\"type\":\"HEARTBEAT\"}"}"#,
+        "\n",
+    );
+    for (speed, expected) in [
+        (ReplaySpeed::Recorded, Duration::from_secs(10)),
+        (ReplaySpeed::Scaled(5.0), Duration::from_secs(2)),
+        (ReplaySpeed::Unthrottled, Duration::ZERO),
+    ] {
+        let start = tokio::time::Instant::now();
+        let items: Vec<_> = PricingStream::replay(recording.as_bytes(), speed)
+            .collect()
+            .await;
+        assert_eq!(items.len(), 3);
+        assert_eq!(start.elapsed(), expected, "{speed:?}");
+    }
+}
+
+#[tokio::test]
+async fn replayed_transactions_are_deduplicated() {
+    let recording = [
+        r#"{"type":"ORDER_FILL","id":"6790","orderID":"6789"}"#,
+        r#"{"type":"HEARTBEAT","lastTransactionID":"6790"}"#,
+        // a back-fill overlapping the live stream, recorded as received
+        r#"{"type":"ORDER_FILL","id":"6790","orderID":"6789"}"#,
+        r#"{"type":"ORDER_CANCEL","id":"6791","orderID":"6788"}"#,
+    ]
+    .iter()
+    .map(|line| json!({"received": "2024-06-14T12:00:00Z", "line": line}).to_string() + "\n")
+    .collect::<String>();
+
+    let items: Vec<_> = TransactionStream::replay(
+        std::io::Cursor::new(recording.into_bytes()),
+        ReplaySpeed::Recorded,
+    )
+    .map(Result::unwrap)
+    .collect()
+    .await;
+    assert_eq!(items.len(), 3);
+    assert!(matches!(
+        &items[2],
+        TransactionStreamItem::Transaction(Transaction::OrderCancel(_))
+    ));
+    assert_eq!(
+        TransactionStream::replay(std::io::empty(), ReplaySpeed::Recorded)
+            .stats()
+            .reconnects,
+        0
+    );
+}
@@ -0,0 +1,217 @@
+//! Tests for the order lifecycle tracker and the OCO helper over a
+//! scripted transaction stream.
+
+mod common;
+
+use common::{ACCOUNT_ID, mock_client, standard_headers};
+use futures_util::StreamExt;
+use oanda_rs::Error;
+use oanda_rs::endpoints::orders::CreateOrderResponse;
+use oanda_rs::models::OrderSpecifier;
+use oanda_rs::models::transaction::{OrderCancelReason, TransactionStreamItem};
+use oanda_rs::trading::{Oco, OrderStatus, OrderTracker};
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn items(lines: Vec<Value>) -> Vec<Result<TransactionStreamItem, Error>> {
+    lines
+        .into_iter()
+        .map(|line| serde_json::from_value(line).unwrap())
+        .map(Ok)
+        .collect()
+}
+
+fn limit_order(id: &str, client_id: &str) -> Value {
+    json!({
+        "type": "LIMIT_ORDER", "id": id, "instrument": "EUR_USD", "units": "100",
+        "price": "1.07000", "clientExtensions": {"id": client_id}
+    })
+}
+
+fn fill(id: &str, order_id: &str, client_id: &str) -> Value {
+    json!({
+        "type": "ORDER_FILL", "id": id, "orderID": order_id, "clientOrderID": client_id,
+        "instrument": "EUR_USD", "units": "100", "price": "1.07000"
+    })
+}
+
+#[tokio::test]
+async fn resolves_fill_by_order_id_and_passes_items_through() {
+    let stream = futures_util::stream::iter(items(vec![
+        limit_order("6368", "entry-1"),
+        json!({"type": "HEARTBEAT", "time": "2024-06-14T12:00:05.000000000Z"}),
+        fill("6369", "6368", "entry-1"),
+    ]));
+    let mut tracker = OrderTracker::new(stream);
+    let watch = tracker.track("6368");
+    assert_eq!(watch.state(), OrderStatus::Pending);
+
+    let mut seen = 0;
+    while let Some(item) = tracker.next().await {
+        item.unwrap();
+        seen += 1;
+    }
+    assert_eq!(seen, 3);
+
+    match watch.outcome().await.unwrap() {
+        OrderStatus::Filled(fill) => assert_eq!(fill.price.unwrap().to_string(), "1.07000"),
+        other => panic!("unexpected state: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn resolves_cancel_by_client_id_while_running() {
+    let stream = futures_util::stream::iter(items(vec![
+        limit_order("6368", "entry-1"),
+        json!({
+            "type": "ORDER_CANCEL", "id": "6370", "orderID": "6368",
+            "clientOrderID": "entry-1", "reason": "MARKET_HALTED"
+        }),
+    ]));
+    let tracker = OrderTracker::new(stream);
+    let handle = tracker.handle();
+    let states = handle
+        .track(OrderSpecifier::from_client_id("entry-1"))
+        .into_stream();
+    tokio::spawn(tracker.run());
+
+    let states: Vec<_> = states.collect().await;
+    assert_eq!(states.len(), 2);
+    assert_eq!(states[0], OrderStatus::Pending);
+    match &states[1] {
+        OrderStatus::Cancelled {
+            reason,
+            transaction,
+        } => {
+            assert_eq!(reason, &Some(OrderCancelReason::MarketHalted));
+            assert_eq!(transaction.id.as_ref().unwrap().as_str(), "6370");
+        }
+        other => panic!("unexpected state: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn late_registration_resolves_from_history() {
+    let stream = futures_util::stream::iter(items(vec![fill("6369", "6368", "entry-1")]));
+    let mut tracker = OrderTracker::new(stream);
+    let handle = tracker.handle();
+    // consume without finishing, so the tracker is still open
+    tracker.next().await.unwrap().unwrap();
+
+    assert!(handle.track("6368").state().is_terminal());
+    assert!(
+        handle
+            .track(OrderSpecifier::from_client_id("entry-1"))
+            .state()
+            .is_terminal()
+    );
+}
+
+#[tokio::test]
+async fn immediate_fill_in_create_response_resolves_at_once() {
+    let response: CreateOrderResponse = serde_json::from_value(json!({
+        "orderCreateTransaction": {"type": "MARKET_ORDER", "id": "6368", "instrument": "EUR_USD", "units": "100"},
+        "orderFillTransaction": fill("6369", "6368", "entry-1"),
+        "lastTransactionID": "6369"
+    }))
+    .unwrap();
+    let tracker = OrderTracker::new(futures_util::stream::pending::<
+        Result<TransactionStreamItem, Error>,
+    >());
+
+    let state = tracker.track_created(&response).outcome().await.unwrap();
+    assert!(matches!(state, OrderStatus::Filled(_)));
+}
+
+#[tokio::test]
+async fn pending_order_fails_when_tracker_stops() {
+    let stream = futures_util::stream::iter(items(vec![limit_order("6368", "entry-1")]));
+    let tracker = OrderTracker::new(stream);
+    let watch = tracker.track("6368");
+    tracker.run().await.unwrap();
+
+    assert!(matches!(watch.outcome().await, Err(Error::Stream(_))));
+}
+
+#[tokio::test]
+async fn run_returns_final_stream_error() {
+    let stream = futures_util::stream::iter(vec![Err(Error::Stream("gone".into()))]);
+    let tracker = OrderTracker::new(stream);
+
+    assert!(matches!(tracker.run().await, Err(Error::Stream(_))));
+}
+
+#[tokio::test]
+async fn oco_cancels_sibling_when_one_leg_fills() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("PUT")).and(path(format!("/accounts/{ACCOUNT_ID}/orders/6369/cancel"))),
+    )
+    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "orderCancelTransaction": {"type": "ORDER_CANCEL", "id": "6371", "orderID": "6369"},
+        "lastTransactionID": "6371"
+    })))
+    .expect(1)
+    .mount(&server)
+    .await;
+
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    let tracker = OrderTracker::new(rx);
+    let orders = tracker.handle();
+    tokio::spawn(tracker.run());
+
+    let oco = tokio::spawn({
+        let client = client.clone();
+        async move {
+            Oco::new(&client, ACCOUNT_ID, "6368", "6369")
+                .run(&orders)
+                .await
+        }
+    });
+    tokio::task::yield_now().await;
+    for line in items(vec![
+        fill("6370", "6368", "take-profit"),
+        json!({"type": "ORDER_CANCEL", "id": "6371", "orderID": "6369", "reason": "CLIENT_REQUEST"}),
+    ]) {
+        tx.unbounded_send(line).unwrap();
+    }
+
+    let outcome = oco.await.unwrap().unwrap();
+    assert_eq!(outcome.resolved.as_str(), "6368");
+    assert!(matches!(outcome.resolved_status, OrderStatus::Filled(_)));
+    assert_eq!(outcome.sibling.as_str(), "6369");
+    assert!(matches!(
+        outcome.sibling_status,
+        OrderStatus::Cancelled { .. }
+    ));
+}
+
+#[tokio::test]
+async fn oco_leaves_sibling_alone_when_one_leg_is_cancelled() {
+    let (server, client) = mock_client().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let stream = futures_util::stream::iter(items(vec![json!({
+        "type": "ORDER_CANCEL", "id": "6370", "orderID": "6368", "reason": "TIME_IN_FORCE_EXPIRED"
+    })]))
+    .chain(futures_util::stream::pending());
+    let tracker = OrderTracker::new(stream);
+    let orders = tracker.handle();
+    tokio::spawn(tracker.run());
+
+    let outcome = Oco::new(&client, ACCOUNT_ID, "6368", "6369")
+        .run(&orders)
+        .await
+        .unwrap();
+    assert_eq!(outcome.resolved.as_str(), "6368");
+    assert!(matches!(
+        outcome.resolved_status,
+        OrderStatus::Cancelled { .. }
+    ));
+    assert_eq!(outcome.sibling_status, OrderStatus::Pending);
+}
@@ -2,12 +2,15 @@
 
 mod common;
 
+use std::time::Duration;
+
 use common::{ACCOUNT_ID, mock_client, standard_headers};
+use futures_util::StreamExt;
 use oanda_rs::models::{
     ClientExtensions, StopLossDetails, TakeProfitDetails, TradeId, TradeStateFilter,
 };
 use serde_json::json;
-use wiremock::matchers::{body_json, method, path, query_param};
+use wiremock::matchers::{body_json, method, path, query_param, query_param_is_missing};
 use wiremock::{Mock, ResponseTemplate};
 
 fn trade_body() -> serde_json::Value {
@@ -55,6 +58,129 @@ async fn list_trades_with_filters() {
     assert_eq!(response.trades[0].current_units.unwrap().to_string(), "100");
 }
 
+#[tokio::test]
+async fn list_trades_with_meta_exposes_headers_and_timing() {
+    let (server, client) = mock_client().await;
+    standard_headers(
+        Mock::given(method("GET")).and(path(format!("/accounts/{ACCOUNT_ID}/trades"))),
+    )
+    .respond_with(
+        ResponseTemplate::new(200)
+            .insert_header("RequestID", "24912345678901234")
+            .set_body_json(json!({"trades": [trade_body()], "lastTransactionID": "6790"}))
+            .set_delay(Duration::from_millis(50)),
+    )
+    .mount(&server)
+    .await;
+
+    let response = client
+        .list_trades(ACCOUNT_ID)
+        .send_with_meta()
+        .await
+        .unwrap();
+    assert_eq!(response.value.trades.len(), 1);
+    assert_eq!(response.status.as_u16(), 200);
+    assert_eq!(response.request_id.as_deref(), Some("24912345678901234"));
+    assert_eq!(
+        response.last_transaction_id.as_ref().map(|id| id.as_str()),
+        Some("6790")
+    );
+    assert_eq!(response.location, None);
+    assert!(response.elapsed >= Duration::from_millis(50));
+    assert!(response.rate_limit_wait < Duration::from_millis(50));
+    assert_eq!(response.headers["RequestID"], "24912345678901234");
+}
+
+#[tokio::test]
+async fn send_with_meta_separates_rate_limit_wait() {
+    let (server, _) = mock_client().await;
+    let url: reqwest::Url = server.uri().parse().unwrap();
+    let client = oanda_rs::Client::builder()
+        .environment(oanda_rs::Environment::Custom {
+            rest: url.clone(),
+            stream: url,
+        })
+        .token(common::TOKEN)
+        .rest_rate_limit(4)
+        .build()
+        .unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("/accounts/{ACCOUNT_ID}/trades")))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"trades": [], "lastTransactionID": "6790"})),
+        )
+        .mount(&server)
+        .await;
+
+    // the first four requests use up the burst; the fifth waits ~250ms
+    let mut waits = Vec::new();
+    for _ in 0..5 {
+        let response = client
+            .list_trades(ACCOUNT_ID)
+            .send_with_meta()
+            .await
+            .unwrap();
+        waits.push(response.rate_limit_wait);
+    }
+    assert!(waits[4] >= Duration::from_millis(150), "{waits:?}");
+    assert!(waits[4] > waits[0]);
+}
+
+#[tokio::test]
+async fn list_trades_into_stream_pages_backwards_and_filters_by_tag() {
+    let (server, client) = mock_client().await;
+    let trade = |id: &str, tag: &str| {
+        let mut trade = trade_body();
+        trade["id"] = json!(id);
+        trade["clientExtensions"] = json!({"tag": tag});
+        trade
+    };
+    let trades_path = format!("/accounts/{ACCOUNT_ID}/trades");
+    Mock::given(method("GET"))
+        .and(path(trades_path.as_str()))
+        .and(query_param("state", "CLOSED"))
+        .and(query_param("count", "2"))
+        .and(query_param_is_missing("beforeID"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "trades": [trade("10", "breakout"), trade("9", "meanrev")]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // `beforeID` is inclusive, so each page repeats the previous oldest trade
+    Mock::given(method("GET"))
+        .and(path(trades_path.as_str()))
+        .and(query_param("state", "CLOSED"))
+        .and(query_param("beforeID", "9"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "trades": [trade("9", "meanrev"), trade("8", "breakout")]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(trades_path))
+        .and(query_param("beforeID", "8"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({"trades": [trade("8", "breakout")]})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let ids: Vec<_> = client
+        .list_trades(ACCOUNT_ID)
+        .state(TradeStateFilter::Closed)
+        .count(2)
+        .into_stream()
+        .tag("breakout")
+        .map(|trade| trade.unwrap().id.unwrap().as_str().to_owned())
+        .collect()
+        .await;
+    assert_eq!(ids, ["10", "8"]);
+}
+
 #[tokio::test]
 async fn open_trades_and_get_trade() {
     let (server, client) = mock_client().await;